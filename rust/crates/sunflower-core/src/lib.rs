@@ -4,13 +4,19 @@
 //! through `sunflower-bridge`; the Rust server calls the same services through
 //! `sunflower-server`. Storage details live behind repository traits.
 
+pub mod lyrics;
 pub mod models;
+pub mod playlist;
+pub mod playlist_file;
 pub mod queue;
 pub mod recommendation;
 pub mod repository;
 pub mod wire;
 
+pub use lyrics::*;
 pub use models::*;
+pub use playlist::*;
+pub use playlist_file::*;
 pub use queue::*;
 pub use recommendation::*;
 pub use repository::*;
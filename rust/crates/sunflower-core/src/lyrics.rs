@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+
+pub const LYRICS_SOURCE_LRC: &str = "lrc";
+pub const LYRICS_SOURCE_EMBEDDED: &str = "embedded";
+pub const LYRICS_SOURCE_YOUTUBE: &str = "youtube";
+/// Stored for a `yt:` song InnerTube had no lyrics for, so repeat requests
+/// skip the lookup until the entry expires.
+pub const LYRICS_SOURCE_NONE: &str = "none";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LyricsLine {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_ms: Option<i64>,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lyrics {
+    pub source: String,
+    pub lines: Vec<LyricsLine>,
+}
+
+impl Lyrics {
+    /// Lyrics are synced when every line carries a start timestamp.
+    pub fn is_synced(&self) -> bool {
+        !self.lines.is_empty() && self.lines.iter().all(|line| line.start_ms.is_some())
+    }
+}
+
+/// Parses LRC text (`[mm:ss.xx]line`). Text without any timestamp tags is
+/// returned as plain, untimed lines so the same parser handles USLT bodies.
+pub fn parse_lrc(raw: &str) -> Vec<LyricsLine> {
+    let mut offset_ms = 0_i64;
+    let mut timed = Vec::new();
+    let mut plain = Vec::new();
+    for line in raw.lines() {
+        let mut rest = line.trim();
+        let mut stamps = Vec::new();
+        while let Some(tag_end) = rest.strip_prefix('[').and_then(|tail| tail.find(']')) {
+            let tag = &rest[1..tag_end + 1];
+            if let Some(ms) = parse_lrc_timestamp(tag) {
+                stamps.push(ms);
+            } else if let Some(value) = tag.strip_prefix("offset:") {
+                offset_ms = value.trim().parse().unwrap_or(offset_ms);
+            } else if stamps.is_empty() && tag.contains(':') {
+                // ID tags such as [ar:Artist] carry no lyric text.
+                rest = "";
+                break;
+            } else {
+                break;
+            }
+            rest = rest[tag_end + 2..].trim_start();
+        }
+        let text = rest.trim().to_string();
+        if stamps.is_empty() {
+            if !text.is_empty() || !plain.is_empty() {
+                plain.push(LyricsLine {
+                    start_ms: None,
+                    text,
+                });
+            }
+            continue;
+        }
+        for start_ms in stamps {
+            timed.push(LyricsLine {
+                start_ms: Some(start_ms),
+                text: text.clone(),
+            });
+        }
+    }
+
+    if timed.is_empty() {
+        while plain.last().is_some_and(|line| line.text.is_empty()) {
+            plain.pop();
+        }
+        return plain;
+    }
+    // The LRC offset tag shifts lyrics earlier when positive.
+    for line in &mut timed {
+        line.start_ms = line.start_ms.map(|ms| (ms - offset_ms).max(0));
+    }
+    timed.sort_by_key(|line| line.start_ms);
+    timed
+}
+
+fn parse_lrc_timestamp(tag: &str) -> Option<i64> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    if minutes.is_empty() || !minutes.chars().all(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
+    let (whole, fraction) = seconds.split_once(['.', ':']).unwrap_or((seconds, ""));
+    if whole.is_empty()
+        || !whole.chars().all(|ch| ch.is_ascii_digit())
+        || !fraction.chars().all(|ch| ch.is_ascii_digit())
+    {
+        return None;
+    }
+    let minutes: i64 = minutes.parse().ok()?;
+    let whole: i64 = whole.parse().ok()?;
+    let fraction_ms = match fraction.len() {
+        0 => 0,
+        1 => fraction.parse::<i64>().ok()? * 100,
+        2 => fraction.parse::<i64>().ok()? * 10,
+        _ => fraction[..3].parse::<i64>().ok()?,
+    };
+    Some(minutes * 60_000 + whole * 1_000 + fraction_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_lrc_reads_timestamps_and_skips_id_tags() {
+        let lines = parse_lrc(
+            "[ar:Artist]\n[ti:Title]\n[00:12.34]First line\n[00:05.5]Intro\n[01:02.003]Third\n",
+        );
+        assert_eq!(
+            lines,
+            vec![
+                LyricsLine {
+                    start_ms: Some(5_500),
+                    text: "Intro".into()
+                },
+                LyricsLine {
+                    start_ms: Some(12_340),
+                    text: "First line".into()
+                },
+                LyricsLine {
+                    start_ms: Some(62_003),
+                    text: "Third".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_lrc_expands_repeated_timestamps_and_applies_offset() {
+        let lines = parse_lrc("[offset:+500]\n[00:10.00][00:20.00]Chorus\n[00:15.00]\n");
+        assert_eq!(
+            lines
+                .iter()
+                .map(|line| (line.start_ms, line.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some(9_500), "Chorus"),
+                (Some(14_500), ""),
+                (Some(19_500), "Chorus"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_lrc_falls_back_to_plain_lines() {
+        let lyrics = Lyrics {
+            source: LYRICS_SOURCE_EMBEDDED.into(),
+            lines: parse_lrc("\nVerse one\n\nVerse two\n\n"),
+        };
+        assert_eq!(
+            lyrics
+                .lines
+                .iter()
+                .map(|line| line.text.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Verse one", "", "Verse two"]
+        );
+        assert!(lyrics.lines.iter().all(|line| line.start_ms.is_none()));
+        assert!(!lyrics.is_synced());
+    }
+}
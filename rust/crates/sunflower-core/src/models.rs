@@ -1,3 +1,4 @@
+use crate::QueueRules;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -41,6 +42,13 @@ pub struct QueueItem {
     pub title: String,
     pub artists: Vec<String>,
     pub duration_ms: i32,
+    /// Set once `/next` has skipped the item because it no longer resolves.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub unavailable: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -51,6 +59,50 @@ pub struct QueueSession {
     pub title: String,
     pub version: i64,
     pub items: Vec<QueueItem>,
+    /// Cursor for fetching more radio items once `items` runs low.
+    pub continuation: Option<String>,
+    /// Seed of the shuffled play order; `None` plays `items` front to back.
+    pub shuffle_seed: Option<u64>,
+    /// The shuffled play order as indexes into `items`, kept in step with
+    /// edits; empty for a queue that is not shuffled.
+    #[serde(default)]
+    pub shuffle_order: Vec<usize>,
+    pub repeat: RepeatMode,
+    /// The rules the queue was seeded with, applied again to items appended
+    /// from its radio continuation.
+    #[serde(default)]
+    pub rules: QueueRules,
+}
+
+/// What a queue plays once the current item ends.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    /// Plays the current item again.
+    One,
+    /// Wraps around to the start of the play order after the last item.
+    All,
+}
+
+impl RepeatMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::One => "one",
+            Self::All => "all",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(Self::Off),
+            "one" => Some(Self::One),
+            "all" => Some(Self::All),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -72,10 +124,15 @@ pub struct NextDecision {
     pub position: usize,
     pub current: Option<ResolvedStream>,
     pub lookahead: Vec<QueueItem>,
+    /// Queue position of each `lookahead` item, which differs from the
+    /// following positions once the queue is shuffled or repeating.
+    pub lookahead_positions: Vec<usize>,
     pub continuation: Option<String>,
     pub automix: Vec<QueueItem>,
     pub has_more: bool,
     pub queue_version: i64,
+    pub shuffle: bool,
+    pub repeat: RepeatMode,
     pub recommender_source: RecommendationSource,
 }
 
@@ -0,0 +1,234 @@
+use std::collections::HashSet;
+
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::MediaId;
+
+/// One playlist row: a song at a place in the playlist, named by its own id
+/// so that repeated songs can be told apart.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaylistEntry {
+    pub item_id: Uuid,
+    pub media_id: MediaId,
+}
+
+/// One edit to a playlist's items, applied by [`apply_playlist_edit`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlaylistEdit {
+    /// Inserts the songs before `position`, or appends them when it is
+    /// `None`. Without `allow_duplicates`, songs already in the playlist or
+    /// earlier in `media_ids` are left out.
+    Insert {
+        position: Option<usize>,
+        media_ids: Vec<MediaId>,
+        allow_duplicates: bool,
+    },
+    /// Moves one item so that it ends up at index `to`.
+    Move {
+        item_id: Uuid,
+        to: usize,
+    },
+    Remove {
+        item_ids: Vec<Uuid>,
+    },
+}
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum PlaylistEditError {
+    #[error("position {position} is outside playlist length {len}")]
+    PositionOutOfRange { position: usize, len: usize },
+    #[error("playlist has no item {0}")]
+    UnknownItem(Uuid),
+}
+
+/// Applies `edit` to `entries`, taking ids for inserted items from
+/// `new_item_id`. Returns whether anything changed; on error `entries` is left
+/// untouched.
+pub fn apply_playlist_edit(
+    entries: &mut Vec<PlaylistEntry>,
+    edit: PlaylistEdit,
+    mut new_item_id: impl FnMut() -> Uuid,
+) -> Result<bool, PlaylistEditError> {
+    let len = entries.len();
+    let index_of = |entries: &[PlaylistEntry], item_id: Uuid| {
+        entries
+            .iter()
+            .position(|entry| entry.item_id == item_id)
+            .ok_or(PlaylistEditError::UnknownItem(item_id))
+    };
+    match edit {
+        PlaylistEdit::Insert {
+            position,
+            media_ids,
+            allow_duplicates,
+        } => {
+            let position = position.unwrap_or(len);
+            if position > len {
+                return Err(PlaylistEditError::PositionOutOfRange { position, len });
+            }
+            let mut present: HashSet<MediaId> = if allow_duplicates {
+                HashSet::new()
+            } else {
+                entries.iter().map(|entry| entry.media_id.clone()).collect()
+            };
+            let inserted: Vec<PlaylistEntry> = media_ids
+                .into_iter()
+                .filter(|media_id| allow_duplicates || present.insert(media_id.clone()))
+                .map(|media_id| PlaylistEntry {
+                    item_id: new_item_id(),
+                    media_id,
+                })
+                .collect();
+            let changed = !inserted.is_empty();
+            entries.splice(position..position, inserted);
+            Ok(changed)
+        }
+        PlaylistEdit::Move { item_id, to } => {
+            let from = index_of(entries, item_id)?;
+            if to >= len {
+                return Err(PlaylistEditError::PositionOutOfRange { position: to, len });
+            }
+            let entry = entries.remove(from);
+            entries.insert(to, entry);
+            Ok(from != to)
+        }
+        PlaylistEdit::Remove { item_ids } => {
+            for &item_id in &item_ids {
+                index_of(entries, item_id)?;
+            }
+            entries.retain(|entry| !item_ids.contains(&entry.item_id));
+            Ok(entries.len() != len)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn playlist_edits_insert_move_and_remove_by_item_id() {
+        let mut next_id = 0_u128;
+        let mut new_item_id = move || {
+            next_id += 1;
+            Uuid::from_u128(next_id)
+        };
+        let mut entries = vec![];
+
+        let insert = |position, ids: &[&str], allow_duplicates| PlaylistEdit::Insert {
+            position,
+            media_ids: ids.iter().map(|id| MediaId::new(*id)).collect(),
+            allow_duplicates,
+        };
+        assert_eq!(
+            apply_playlist_edit(
+                &mut entries,
+                insert(None, &["a", "b", "a"], true),
+                &mut new_item_id
+            ),
+            Ok(true)
+        );
+        assert_eq!(media_ids(&entries), ["a", "b", "a"]);
+        assert_eq!(
+            apply_playlist_edit(
+                &mut entries,
+                insert(Some(1), &["c", "a", "c"], false),
+                &mut new_item_id
+            ),
+            Ok(true)
+        );
+        assert_eq!(media_ids(&entries), ["a", "c", "b", "a"]);
+        assert_eq!(
+            apply_playlist_edit(&mut entries, insert(None, &["b"], false), &mut new_item_id),
+            Ok(false)
+        );
+
+        // The second "a" is told apart from the first by its item id.
+        let second_a = entries[3].item_id;
+        assert_eq!(
+            apply_playlist_edit(
+                &mut entries,
+                PlaylistEdit::Move {
+                    item_id: second_a,
+                    to: 0
+                },
+                &mut new_item_id
+            ),
+            Ok(true)
+        );
+        assert_eq!(media_ids(&entries), ["a", "a", "c", "b"]);
+        assert_eq!(entries[0].item_id, second_a);
+
+        let (first, last) = (entries[1].item_id, entries[3].item_id);
+        assert_eq!(
+            apply_playlist_edit(
+                &mut entries,
+                PlaylistEdit::Remove {
+                    item_ids: vec![first, last]
+                },
+                &mut new_item_id
+            ),
+            Ok(true)
+        );
+        assert_eq!(media_ids(&entries), ["a", "c"]);
+    }
+
+    #[test]
+    fn playlist_edits_reject_unknown_items_and_positions_without_changes() {
+        let mut entries = vec![PlaylistEntry {
+            item_id: Uuid::from_u128(1),
+            media_id: MediaId::new("a"),
+        }];
+        let unknown = Uuid::from_u128(9);
+        for (edit, err) in [
+            (
+                PlaylistEdit::Insert {
+                    position: Some(2),
+                    media_ids: vec![MediaId::new("b")],
+                    allow_duplicates: true,
+                },
+                PlaylistEditError::PositionOutOfRange {
+                    position: 2,
+                    len: 1,
+                },
+            ),
+            (
+                PlaylistEdit::Move {
+                    item_id: Uuid::from_u128(1),
+                    to: 1,
+                },
+                PlaylistEditError::PositionOutOfRange {
+                    position: 1,
+                    len: 1,
+                },
+            ),
+            (
+                PlaylistEdit::Move {
+                    item_id: unknown,
+                    to: 0,
+                },
+                PlaylistEditError::UnknownItem(unknown),
+            ),
+            (
+                PlaylistEdit::Remove {
+                    item_ids: vec![Uuid::from_u128(1), unknown],
+                },
+                PlaylistEditError::UnknownItem(unknown),
+            ),
+        ] {
+            assert_eq!(
+                apply_playlist_edit(&mut entries, edit, Uuid::new_v4),
+                Err(err)
+            );
+        }
+        assert_eq!(media_ids(&entries), ["a"]);
+    }
+
+    fn media_ids(entries: &[PlaylistEntry]) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|entry| entry.media_id.0.as_str())
+            .collect()
+    }
+}
@@ -0,0 +1,629 @@
+use std::fmt::Write as _;
+
+use thiserror::Error;
+
+/// A playlist file format the server reads and writes. M3U and M3U8 share a
+/// layout; both are written as UTF-8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaylistFileFormat {
+    M3u,
+    M3u8,
+    Xspf,
+}
+
+impl PlaylistFileFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name
+            .trim()
+            .trim_start_matches('.')
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "m3u" => Some(Self::M3u),
+            "m3u8" => Some(Self::M3u8),
+            "xspf" => Some(Self::Xspf),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from a file's contents: XML is read as XSPF,
+    /// anything else as M3U8.
+    pub fn sniff(raw: &str) -> Self {
+        if raw
+            .trim_start_matches('\u{feff}')
+            .trim_start()
+            .starts_with('<')
+        {
+            Self::Xspf
+        } else {
+            Self::M3u8
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::M3u => "m3u",
+            Self::M3u8 => "m3u8",
+            Self::Xspf => "xspf",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::M3u | Self::M3u8 => "audio/x-mpegurl; charset=utf-8",
+            Self::Xspf => "application/xspf+xml; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlaylistFile {
+    pub title: String,
+    pub entries: Vec<PlaylistFileEntry>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlaylistFileEntry {
+    /// 1-based line the entry was read from: the location line of an M3U
+    /// entry, the `<track>` tag of an XSPF one. Ignored when writing.
+    pub line: usize,
+    /// A file path, absolute or relative to the playlist, or a URL.
+    pub location: String,
+    pub title: String,
+    pub artist: String,
+    pub duration_ms: Option<i32>,
+}
+
+/// What an entry's location points at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlaylistFileTarget {
+    None,
+    AbsolutePath(String),
+    /// A path relative to wherever the playlist file lived, with leading
+    /// `./` and `../` segments dropped.
+    RelativePath(String),
+    /// The video id of a YouTube or YouTube Music URL.
+    YouTube(String),
+    OtherUrl,
+}
+
+impl PlaylistFileEntry {
+    pub fn target(&self) -> PlaylistFileTarget {
+        let location = self.location.trim();
+        if location.is_empty() {
+            return PlaylistFileTarget::None;
+        }
+        let path = match url_scheme(location) {
+            Some("file") => file_uri_path(location),
+            Some(_) => {
+                return youtube_video_id(location)
+                    .map_or(PlaylistFileTarget::OtherUrl, PlaylistFileTarget::YouTube);
+            }
+            None => location.to_string(),
+        };
+        let path = path.replace('\\', "/");
+        if path.starts_with('/') || path.as_bytes().get(1) == Some(&b':') {
+            return PlaylistFileTarget::AbsolutePath(path);
+        }
+        let mut relative = path.as_str();
+        while let Some(rest) = relative
+            .strip_prefix("./")
+            .or_else(|| relative.strip_prefix("../"))
+        {
+            relative = rest;
+        }
+        PlaylistFileTarget::RelativePath(relative.to_string())
+    }
+
+    /// The entry's title and artist, falling back to an `Artist - Title` file
+    /// name when the playlist carried no tags.
+    pub fn tags(&self) -> (String, String) {
+        if !self.title.is_empty() {
+            return (self.title.clone(), self.artist.clone());
+        }
+        let name = match self.target() {
+            PlaylistFileTarget::AbsolutePath(path) | PlaylistFileTarget::RelativePath(path) => path,
+            _ => return (String::new(), String::new()),
+        };
+        let name = name.rsplit('/').next().unwrap_or_default();
+        let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+        let (artist, title) = split_artist_title(stem);
+        (title.to_string(), artist.to_string())
+    }
+}
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum PlaylistFileError {
+    #[error("not an XSPF playlist")]
+    NotXspf,
+    #[error("unclosed <track> at line {0}")]
+    UnclosedTrack(usize),
+}
+
+pub fn parse_playlist_file(
+    format: PlaylistFileFormat,
+    raw: &str,
+) -> Result<PlaylistFile, PlaylistFileError> {
+    let raw = raw.trim_start_matches('\u{feff}');
+    match format {
+        PlaylistFileFormat::M3u | PlaylistFileFormat::M3u8 => Ok(parse_m3u(raw)),
+        PlaylistFileFormat::Xspf => parse_xspf(raw),
+    }
+}
+
+pub fn write_playlist_file(format: PlaylistFileFormat, file: &PlaylistFile) -> String {
+    match format {
+        PlaylistFileFormat::M3u | PlaylistFileFormat::M3u8 => write_m3u(file),
+        PlaylistFileFormat::Xspf => write_xspf(file),
+    }
+}
+
+/// `path` relative to the directory `base`, both absolute and `/`-separated.
+pub fn relative_path(path: &str, base: &str) -> String {
+    let path_segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let base_segments: Vec<&str> = base.split('/').filter(|s| !s.is_empty()).collect();
+    // The file name itself is never shared with the base directory.
+    let shared = path_segments[..path_segments.len().saturating_sub(1)]
+        .iter()
+        .zip(&base_segments)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut segments = vec![".."; base_segments.len() - shared];
+    segments.extend(&path_segments[shared..]);
+    segments.join("/")
+}
+
+/// The deepest directory containing every path, or `/` when there are none.
+pub fn common_directory<'a>(paths: impl IntoIterator<Item = &'a str>) -> String {
+    let mut common: Option<Vec<&str>> = None;
+    for path in paths {
+        let mut dir: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        dir.pop();
+        common = Some(match common {
+            None => dir,
+            Some(common) => common
+                .into_iter()
+                .zip(dir)
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    format!("/{}", common.unwrap_or_default().join("/"))
+}
+
+fn parse_m3u(raw: &str) -> PlaylistFile {
+    let mut file = PlaylistFile::default();
+    let mut pending = PlaylistFileEntry::default();
+    for (index, line) in raw.lines().enumerate() {
+        let line = line.trim();
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            let (duration, label) = info.split_once(',').unwrap_or((info, ""));
+            // Extended players put attributes after the duration.
+            pending.duration_ms = duration
+                .split_whitespace()
+                .next()
+                .and_then(|seconds| seconds.parse::<f64>().ok())
+                .filter(|seconds| *seconds > 0.0)
+                .map(|seconds| (seconds * 1000.0).round() as i32);
+            let (artist, title) = split_artist_title(label.trim());
+            pending.artist = artist.to_string();
+            pending.title = title.to_string();
+        } else if let Some(artist) = line.strip_prefix("#EXTART:") {
+            pending.artist = artist.trim().to_string();
+        } else if let Some(title) = line.strip_prefix("#PLAYLIST:") {
+            file.title = title.trim().to_string();
+        } else if !line.is_empty() && !line.starts_with('#') {
+            pending.line = index + 1;
+            pending.location = line.to_string();
+            file.entries.push(std::mem::take(&mut pending));
+        }
+    }
+    file
+}
+
+fn parse_xspf(raw: &str) -> Result<PlaylistFile, PlaylistFileError> {
+    let (Some(_), Some(track_list)) = (find_tag(raw, "playlist"), find_tag(raw, "trackList"))
+    else {
+        return Err(PlaylistFileError::NotXspf);
+    };
+    let mut file = PlaylistFile {
+        title: element_text(&raw[..track_list], "title").unwrap_or_default(),
+        entries: vec![],
+    };
+    let mut from = track_list;
+    while let Some(offset) = find_tag(&raw[from..], "track") {
+        let start = from + offset;
+        let line = raw[..start].matches('\n').count() + 1;
+        let open_end = raw[start..]
+            .find('>')
+            .map(|end| start + end + 1)
+            .ok_or(PlaylistFileError::UnclosedTrack(line))?;
+        if raw[..open_end].ends_with("/>") {
+            from = open_end;
+            continue;
+        }
+        let end = raw[open_end..]
+            .find("</track>")
+            .map(|end| open_end + end)
+            .ok_or(PlaylistFileError::UnclosedTrack(line))?;
+        let track = &raw[open_end..end];
+        let location = element_text(track, "location").unwrap_or_default();
+        file.entries.push(PlaylistFileEntry {
+            line,
+            // Locations are URIs; plain ones are relative references that
+            // still need their escapes undone.
+            location: match url_scheme(&location) {
+                Some("file") => file_uri_path(&location),
+                Some(_) => location,
+                None => percent_decode(&location),
+            },
+            title: element_text(track, "title").unwrap_or_default(),
+            artist: element_text(track, "creator").unwrap_or_default(),
+            duration_ms: element_text(track, "duration")
+                .and_then(|duration| duration.parse::<i64>().ok())
+                .filter(|duration| *duration > 0)
+                .map(|duration| duration.min(i64::from(i32::MAX)) as i32),
+        });
+        from = end + "</track>".len();
+    }
+    Ok(file)
+}
+
+fn write_m3u(file: &PlaylistFile) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    if !file.title.is_empty() {
+        let _ = writeln!(out, "#PLAYLIST:{}", one_line(&file.title));
+    }
+    for entry in &file.entries {
+        let seconds = entry
+            .duration_ms
+            .map_or(-1, |ms| (i64::from(ms) + 500) / 1000);
+        let label = if entry.artist.is_empty() {
+            entry.title.clone()
+        } else {
+            format!("{} - {}", entry.artist, entry.title)
+        };
+        let _ = writeln!(out, "#EXTINF:{seconds},{}", one_line(&label));
+        let _ = writeln!(out, "{}", one_line(&entry.location));
+    }
+    out
+}
+
+fn write_xspf(file: &PlaylistFile) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+    );
+    if !file.title.is_empty() {
+        let _ = writeln!(out, "  <title>{}</title>", escape_xml(&file.title));
+    }
+    out.push_str("  <trackList>\n");
+    for entry in &file.entries {
+        out.push_str("    <track>\n");
+        let location = if url_scheme(&entry.location).is_some() {
+            entry.location.clone()
+        } else if entry.location.starts_with('/') {
+            format!("file://{}", percent_encode_path(&entry.location))
+        } else {
+            percent_encode_path(&entry.location)
+        };
+        for (name, value) in [
+            ("location", location.as_str()),
+            ("title", entry.title.as_str()),
+            ("creator", entry.artist.as_str()),
+        ] {
+            if !value.is_empty() {
+                let _ = writeln!(out, "      <{name}>{}</{name}>", escape_xml(value));
+            }
+        }
+        if let Some(duration_ms) = entry.duration_ms {
+            let _ = writeln!(out, "      <duration>{duration_ms}</duration>");
+        }
+        out.push_str("    </track>\n");
+    }
+    out.push_str("  </trackList>\n</playlist>\n");
+    out
+}
+
+fn split_artist_title(label: &str) -> (&str, &str) {
+    match label.split_once(" - ") {
+        Some((artist, title)) => (artist.trim(), title.trim()),
+        None => ("", label),
+    }
+}
+
+fn one_line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+/// The scheme of an absolute URL; Windows drive letters are not schemes.
+fn url_scheme(location: &str) -> Option<&str> {
+    let (scheme, _) = location.split_once(':')?;
+    (scheme.len() > 1
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')))
+    .then_some(scheme)
+}
+
+fn file_uri_path(uri: &str) -> String {
+    let path = uri["file:".len()..].trim_start_matches("//");
+    percent_decode(path.strip_prefix("localhost").unwrap_or(path))
+}
+
+fn youtube_video_id(url: &str) -> Option<String> {
+    let rest = url.split_once("://")?.1;
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = host
+        .trim_start_matches("www.")
+        .trim_start_matches("music.")
+        .trim_start_matches("m.");
+    let id = match host {
+        "youtu.be" => path.split(['?', '#']).next(),
+        "youtube.com" => path.split_once('?').and_then(|(_, query)| {
+            query
+                .split(['&', '#'])
+                .find_map(|pair| pair.strip_prefix("v="))
+        }),
+        _ => None,
+    }?;
+    (!id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+    .then(|| id.to_string())
+}
+
+fn find_tag(xml: &str, name: &str) -> Option<usize> {
+    let open = format!("<{name}");
+    let mut from = 0;
+    while let Some(offset) = xml[from..].find(&open) {
+        let at = from + offset;
+        match xml[at + open.len()..].chars().next() {
+            Some(c) if c == '>' || c == '/' || c.is_whitespace() => return Some(at),
+            _ => from = at + open.len(),
+        }
+    }
+    None
+}
+
+fn element_text(xml: &str, name: &str) -> Option<String> {
+    let start = find_tag(xml, name)?;
+    let open_end = start + xml[start..].find('>')? + 1;
+    if xml[..open_end].ends_with("/>") {
+        return Some(String::new());
+    }
+    let len = xml[open_end..].find(&format!("</{name}>"))?;
+    Some(unescape_xml(xml[open_end..open_end + len].trim()))
+}
+
+fn unescape_xml(text: &str) -> String {
+    if let Some(cdata) = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+    {
+        return cdata.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').and_then(|semi| {
+            let c = match &rest[1..semi] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                entity => entity
+                    .strip_prefix('#')
+                    .and_then(|code| match code.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => code.parse().ok(),
+                    })
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, semi))
+        });
+        match decoded {
+            Some((c, semi)) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn percent_encode_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'/' | b'-' | b'.' | b'_' | b'~') {
+            out.push(byte as char);
+        } else {
+            let _ = write!(out, "%{byte:02X}");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn playlist_files_parse_m3u_and_xspf_entries_with_tags_and_lines() {
+        let m3u = "\u{feff}#EXTM3U\n#PLAYLIST:Road trip\n\n\
+                   #EXTINF:215 tvg-id=\"x\",Daft Punk - One More Time\n\
+                   /music/Daft Punk/One More Time.flac\n\
+                   # a comment\n\
+                   ..\\Music\\Air - La femme d'argent.mp3\n\
+                   https://music.youtube.com/watch?v=abc-DEF_123&list=RD\n";
+        let file = parse_playlist_file(PlaylistFileFormat::M3u, m3u).unwrap();
+        assert_eq!(file.title, "Road trip");
+        assert_eq!(
+            file.entries[0],
+            PlaylistFileEntry {
+                line: 5,
+                location: "/music/Daft Punk/One More Time.flac".into(),
+                title: "One More Time".into(),
+                artist: "Daft Punk".into(),
+                duration_ms: Some(215_000),
+            }
+        );
+        assert_eq!(
+            file.entries[1].target(),
+            PlaylistFileTarget::RelativePath("Music/Air - La femme d'argent.mp3".into())
+        );
+        assert_eq!(
+            file.entries[1].tags(),
+            ("La femme d'argent".into(), "Air".into())
+        );
+        assert_eq!(file.entries[1].line, 7);
+        assert_eq!(
+            file.entries[2].target(),
+            PlaylistFileTarget::YouTube("abc-DEF_123".into())
+        );
+
+        let xspf = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <title>Rock &amp; Roll</title>
+  <trackList>
+    <track>
+      <location>file:///music/AC%2FDC%20Live/Thunder.mp3</location>
+      <title>Thunderstruck</title>
+      <creator>AC&#47;DC</creator>
+      <duration>292000</duration>
+    </track>
+    <track/>
+    <track><title><![CDATA[Song <2>]]></title></track>
+  </trackList>
+</playlist>"#;
+        let file = parse_playlist_file(PlaylistFileFormat::sniff(xspf), xspf).unwrap();
+        assert_eq!(file.title, "Rock & Roll");
+        assert_eq!(file.entries.len(), 2);
+        assert_eq!(file.entries[0].line, 5);
+        assert_eq!(file.entries[0].artist, "AC/DC");
+        assert_eq!(file.entries[0].duration_ms, Some(292_000));
+        assert_eq!(
+            file.entries[0].target(),
+            PlaylistFileTarget::AbsolutePath("/music/AC/DC Live/Thunder.mp3".into())
+        );
+        assert_eq!(file.entries[1].title, "Song <2>");
+        assert_eq!(file.entries[1].target(), PlaylistFileTarget::None);
+
+        assert_eq!(
+            parse_playlist_file(PlaylistFileFormat::Xspf, "<playlist><trackList><track>"),
+            Err(PlaylistFileError::UnclosedTrack(1))
+        );
+        assert_eq!(
+            parse_playlist_file(PlaylistFileFormat::Xspf, "<html></html>"),
+            Err(PlaylistFileError::NotXspf)
+        );
+    }
+
+    #[test]
+    fn playlist_files_round_trip_through_each_format() {
+        let file = PlaylistFile {
+            title: "Mix <1>".into(),
+            entries: vec![
+                PlaylistFileEntry {
+                    line: 0,
+                    location: "/music/Sigur Rós/Hoppípolla #1.flac".into(),
+                    title: "Hoppípolla".into(),
+                    artist: "Sigur Rós".into(),
+                    duration_ms: Some(268_000),
+                },
+                PlaylistFileEntry {
+                    line: 0,
+                    location: "../other/b.mp3".into(),
+                    title: "B".into(),
+                    artist: String::new(),
+                    duration_ms: None,
+                },
+                PlaylistFileEntry {
+                    line: 0,
+                    location: "https://music.youtube.com/watch?v=abc".into(),
+                    title: "C".into(),
+                    artist: "D".into(),
+                    duration_ms: Some(1_000),
+                },
+            ],
+        };
+        for format in [
+            PlaylistFileFormat::M3u,
+            PlaylistFileFormat::M3u8,
+            PlaylistFileFormat::Xspf,
+        ] {
+            let written = write_playlist_file(format, &file);
+            let mut parsed = parse_playlist_file(format, &written).unwrap();
+            for entry in &mut parsed.entries {
+                entry.line = 0;
+            }
+            assert_eq!(parsed, file, "{format:?}:\n{written}");
+        }
+        assert!(
+            write_playlist_file(PlaylistFileFormat::Xspf, &file)
+                .contains("file:///music/Sigur%20R%C3%B3s/Hopp%C3%ADpolla%20%231.flac")
+        );
+    }
+
+    #[test]
+    fn playlist_file_paths_are_made_relative_to_a_base_directory() {
+        let paths = ["/music/a/x.mp3", "/music/a/b/y.mp3", "/music/c/z.mp3"];
+        assert_eq!(common_directory(paths), "/music");
+        assert_eq!(common_directory(["/music/a/x.mp3"]), "/music/a");
+        assert_eq!(common_directory([]), "/");
+        assert_eq!(relative_path("/music/a/b/y.mp3", "/music"), "a/b/y.mp3");
+        assert_eq!(
+            relative_path("/music/c/z.mp3", "/music/a/b"),
+            "../../c/z.mp3"
+        );
+        assert_eq!(relative_path("/music/a/x.mp3", "/"), "music/a/x.mp3");
+    }
+}
@@ -1,15 +1,167 @@
-use crate::{MediaId, NextDecision, QueueItem, QueueSession, RecommendationSource, ResolvedStream};
+use crate::{
+    MediaId, NextDecision, QueueItem, QueueSession, RecommendationSource, RepeatMode,
+    ResolvedStream,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
 
 pub const DEFAULT_LOOKAHEAD_COUNT: usize = 8;
 pub const RADIO_MAX_PAGES: usize = 10;
 
+/// Bracketed title suffixes that mark another upload of the same recording,
+/// e.g. "(Official Video)" or "[Lyrics]".
+const TITLE_NOISE_WORDS: &[&str] = &[
+    "official",
+    "music",
+    "video",
+    "audio",
+    "lyric",
+    "lyrics",
+    "mv",
+    "visualizer",
+    "hd",
+    "4k",
+];
+
 #[derive(Debug, Error)]
 pub enum QueueError {
     #[error("position {position} is outside queue length {len}")]
     PositionOutOfRange { position: usize, len: usize },
 }
 
+/// One edit to a materialized queue, applied by [`apply_queue_edit`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueueEdit {
+    /// Inserts before `position`; `position == len` appends.
+    Insert {
+        position: usize,
+        items: Vec<QueueItem>,
+    },
+    Append {
+        items: Vec<QueueItem>,
+    },
+    /// Moves one item so that it ends up at index `to`.
+    Move {
+        from: usize,
+        to: usize,
+    },
+    Remove {
+        position: usize,
+    },
+    /// Drops everything after `position`, keeping `position` itself.
+    ClearAfter {
+        position: usize,
+    },
+}
+
+/// Applies `edit` to `items`, leaving them untouched when a position is out of
+/// range.
+pub fn apply_queue_edit(items: &mut Vec<QueueItem>, edit: QueueEdit) -> Result<(), QueueError> {
+    let len = items.len();
+    let check = |position: usize, len: usize| {
+        if position < len {
+            Ok(())
+        } else {
+            Err(QueueError::PositionOutOfRange { position, len })
+        }
+    };
+    match edit {
+        QueueEdit::Insert {
+            position,
+            items: inserted,
+        } => {
+            check(position, len + 1)?;
+            items.splice(position..position, inserted);
+        }
+        QueueEdit::Append { items: appended } => items.extend(appended),
+        QueueEdit::Move { from, to } => {
+            check(from, len)?;
+            check(to, len)?;
+            let item = items.remove(from);
+            items.insert(to, item);
+        }
+        QueueEdit::Remove { position } => {
+            check(position, len)?;
+            items.remove(position);
+        }
+        QueueEdit::ClearAfter { position } => {
+            check(position, len)?;
+            items.truncate(position + 1);
+        }
+    }
+    Ok(())
+}
+
+/// Applies `edit` to the items of `session`, keeping a shuffled play order in
+/// step: every item keeps its place in the order, inserted items play right
+/// after `current` (the item index playing now) and appended ones play last.
+pub fn apply_session_edit(
+    session: &mut QueueSession,
+    edit: QueueEdit,
+    current: usize,
+) -> Result<(), QueueError> {
+    let len = session.items.len();
+    let order = session_play_order(session);
+    let moved = edited_positions(&edit, len);
+    let (added, after_current) = match &edit {
+        QueueEdit::Insert { position, items } => (*position..position + items.len(), true),
+        QueueEdit::Append { items } => (len..len + items.len(), false),
+        _ => (0..0, false),
+    };
+    apply_queue_edit(&mut session.items, edit)?;
+    if session.shuffle_seed.is_none() {
+        return Ok(());
+    }
+
+    let mut order: Vec<usize> = order.iter().filter_map(|&index| moved[index]).collect();
+    let at = if after_current {
+        moved
+            .get(current)
+            .copied()
+            .flatten()
+            .and_then(|current| order.iter().position(|&index| index == current))
+            .map_or(0, |at| at + 1)
+    } else {
+        order.len()
+    };
+    order.splice(at..at, added);
+    session.shuffle_order = order;
+    Ok(())
+}
+
+/// Where each item of a queue of `len` ends up after `edit`, or `None` for
+/// one the edit drops. Positions are already checked against `len`.
+fn edited_positions(edit: &QueueEdit, len: usize) -> Vec<Option<usize>> {
+    (0..len)
+        .map(|index| match *edit {
+            QueueEdit::Insert {
+                position,
+                ref items,
+            } if index >= position => Some(index + items.len()),
+            QueueEdit::Insert { .. } | QueueEdit::Append { .. } => Some(index),
+            QueueEdit::Move { from, to } => Some(if index == from {
+                to
+            } else if from < index && index <= to {
+                index - 1
+            } else if to <= index && index < from {
+                index + 1
+            } else {
+                index
+            }),
+            QueueEdit::Remove { position } if index == position => None,
+            QueueEdit::Remove { position } if index > position => Some(index - 1),
+            QueueEdit::Remove { .. } => Some(index),
+            QueueEdit::ClearAfter { position } => (index <= position).then_some(index),
+        })
+        .collect()
+}
+
+/// Builds the `/next` window for the item at `position`, an index into
+/// `session.items`. The lookahead follows the session's play order: shuffled
+/// sessions walk [`session_play_order`], repeat-one plays the current item
+/// again and repeat-all wraps around to the start of the play order. Items
+/// already marked unavailable are left out of the lookahead.
 pub fn next_window(
     session: &QueueSession,
     position: usize,
@@ -17,27 +169,92 @@ pub fn next_window(
     lookahead_count: usize,
     recommender_source: RecommendationSource,
 ) -> Result<NextDecision, QueueError> {
-    if position >= session.items.len() {
-        return Err(QueueError::PositionOutOfRange {
-            position,
-            len: session.items.len(),
-        });
+    let len = session.items.len();
+    if position >= len {
+        return Err(QueueError::PositionOutOfRange { position, len });
     }
 
-    let end = (position + 1 + lookahead_count).min(session.items.len());
+    let order = session_play_order(session);
+    let at = order
+        .iter()
+        .position(|&index| index == position)
+        .unwrap_or(position);
+    let playable = |index: &usize| !session.items[*index].unavailable;
+    let (lookahead_positions, has_more): (Vec<usize>, bool) = match session.repeat {
+        RepeatMode::Off => {
+            let mut rest = order[at + 1..].iter().copied().filter(playable);
+            let lookahead = rest.by_ref().take(lookahead_count).collect();
+            (
+                lookahead,
+                rest.next().is_some() || session.continuation.is_some(),
+            )
+        }
+        RepeatMode::One => (vec![position; lookahead_count.min(1)], true),
+        RepeatMode::All => {
+            // One lap from the item after the current one, back round to it.
+            let lap: Vec<usize> = order
+                .iter()
+                .cycle()
+                .skip(at + 1)
+                .take(len)
+                .copied()
+                .filter(playable)
+                .collect();
+            (
+                lap.iter().cycle().take(lookahead_count).copied().collect(),
+                true,
+            )
+        }
+    };
     Ok(NextDecision {
         queue_id: session.id,
         position,
         current: Some(current),
-        lookahead: session.items[position + 1..end].to_vec(),
-        continuation: None,
+        lookahead: lookahead_positions
+            .iter()
+            .map(|&index| session.items[index].clone())
+            .collect(),
+        lookahead_positions,
+        continuation: session.continuation.clone(),
         automix: vec![],
-        has_more: end < session.items.len(),
+        has_more,
         queue_version: session.version,
+        shuffle: session.shuffle_seed.is_some(),
+        repeat: session.repeat,
         recommender_source,
     })
 }
 
+/// The order in which a queue of `len` items plays, as indexes into its
+/// items: the identity, or a permutation that is stable for a given seed and
+/// length.
+pub fn queue_play_order(len: usize, shuffle_seed: Option<u64>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..len).collect();
+    if let Some(seed) = shuffle_seed {
+        fisher_yates(&mut order, seed);
+    }
+    order
+}
+
+/// The order in which `session` plays, as indexes into its items. A shuffled
+/// session plays its stored order, with items it does not cover yet at the
+/// end; one saved before orders were stored falls back to its seed.
+pub fn session_play_order(session: &QueueSession) -> Vec<usize> {
+    let len = session.items.len();
+    if session.shuffle_seed.is_none() || session.shuffle_order.is_empty() {
+        return queue_play_order(len, session.shuffle_seed);
+    }
+    let mut seen = vec![false; len];
+    let mut order: Vec<usize> = session
+        .shuffle_order
+        .iter()
+        .copied()
+        .filter(|&index| index < len && !std::mem::replace(&mut seen[index], true))
+        .collect();
+    order.extend((0..len).filter(|&index| !seen[index]));
+    order
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct LikedSong {
     pub media_id: MediaId,
@@ -51,22 +268,32 @@ pub struct LikedSong {
 /// survive, the input slice is not mutated, and empty input returns an empty
 /// queue. The exact shuffled order is intentionally not a wire contract.
 pub fn build_automix(liked: &[LikedSong], seed: u64) -> Vec<QueueItem> {
-    let mut shuffled = liked.to_vec();
-    let mut rng = SplitMix64::new(seed);
-    for i in (1..shuffled.len()).rev() {
-        let j = rng.next_index(i + 1);
-        shuffled.swap(i, j);
-    }
-
-    shuffled
-        .into_iter()
+    let items = liked
+        .iter()
+        .cloned()
         .map(|song| QueueItem {
             media_id: song.media_id,
             title: song.title,
             artists: vec![],
             duration_ms: song.duration_ms,
+            unavailable: false,
         })
-        .collect()
+        .collect();
+    shuffle_queue_items(items, seed)
+}
+
+/// Fisher-Yates shuffle of a materialized queue, deterministic for a `seed`.
+pub fn shuffle_queue_items(mut items: Vec<QueueItem>, seed: u64) -> Vec<QueueItem> {
+    fisher_yates(&mut items, seed);
+    items
+}
+
+fn fisher_yates<T>(items: &mut [T], seed: u64) {
+    let mut rng = SplitMix64::new(seed);
+    for i in (1..items.len()).rev() {
+        let j = rng.next_index(i + 1);
+        items.swap(i, j);
+    }
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
@@ -75,10 +302,13 @@ pub struct RadioPage {
     pub continuation: Option<String>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct ExpandedRadio {
     pub items: Vec<QueueItem>,
     pub continuation: Option<String>,
+    /// What the page source reported about the items' content, for the hide
+    /// filters; items it marked neither explicit nor video are left out.
+    pub flags: HashMap<MediaId, ContentFlags>,
 }
 
 /// Expands already-normalized radio pages into a materialized queue.
@@ -94,7 +324,7 @@ where
 {
     let mut pages = pages.into_iter();
     let mut items = Vec::with_capacity(min_items);
-    let mut seen = std::collections::HashSet::new();
+    let mut seen = HashSet::new();
 
     let mut add = |items_out: &mut Vec<QueueItem>, page_items: Vec<QueueItem>| {
         let before = items_out.len();
@@ -110,7 +340,7 @@ where
     let Some(first) = pages.next() else {
         return ExpandedRadio {
             items,
-            continuation: None,
+            ..ExpandedRadio::default()
         };
     };
     let mut continuation = first.continuation;
@@ -132,9 +362,181 @@ where
     ExpandedRadio {
         items,
         continuation,
+        ..ExpandedRadio::default()
     }
 }
 
+/// The `artist_spacing` radio and shuffled seeds get unless the request sets
+/// one.
+pub const DEFAULT_ARTIST_SPACING: usize = 2;
+
+/// Post-processing applied by [`apply_queue_rules`] to a freshly seeded queue,
+/// and stored with it for the items appended later.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueueRules {
+    /// Minimum number of other items between two songs that share an artist;
+    /// `0` keeps the seeded order.
+    pub artist_spacing: usize,
+    /// Keeps only the first of several uploads of one recording.
+    pub dedupe: bool,
+    pub hide_explicit: bool,
+    pub hide_video: bool,
+}
+
+/// What the library or InnerTube knows about a song's content, for the hide
+/// filters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContentFlags {
+    pub explicit: bool,
+    pub video_only: bool,
+}
+
+impl ContentFlags {
+    /// Flags set by either source stay set.
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            explicit: self.explicit || other.explicit,
+            video_only: self.video_only || other.video_only,
+        }
+    }
+}
+
+/// Drops hidden items and, with `dedupe`, repeated recordings from `items`,
+/// then spreads out songs by the same artist.
+///
+/// Items missing from `flags` count as neither explicit nor video-only. Two
+/// items are the same recording when their normalized titles and primary
+/// artists match; the first one is kept, so a seed at the front stays there.
+/// Spacing is greedy: each slot takes the earliest remaining item whose
+/// artists are not among the previous `artist_spacing` items. When none fits it
+/// takes the one whose artist played longest ago, so a single-artist queue
+/// keeps its order.
+pub fn apply_queue_rules(
+    items: Vec<QueueItem>,
+    flags: &HashMap<MediaId, ContentFlags>,
+    rules: QueueRules,
+) -> Vec<QueueItem> {
+    apply_queue_rules_after(&[], items, flags, rules)
+}
+
+/// [`apply_queue_rules`] for `items` about to be appended to `queued`: they
+/// are deduped against the queued recordings too, and spaced from its last
+/// items.
+pub fn apply_queue_rules_after(
+    queued: &[QueueItem],
+    items: Vec<QueueItem>,
+    flags: &HashMap<MediaId, ContentFlags>,
+    rules: QueueRules,
+) -> Vec<QueueItem> {
+    let mut seen: HashSet<_> = if rules.dedupe {
+        queued.iter().map(recording_key).collect()
+    } else {
+        HashSet::new()
+    };
+    let mut remaining: Vec<QueueItem> = items
+        .into_iter()
+        .filter(|item| {
+            let flags = flags.get(&item.media_id).copied().unwrap_or_default();
+            let hidden =
+                (rules.hide_explicit && flags.explicit) || (rules.hide_video && flags.video_only);
+            !hidden
+        })
+        .filter(|item| !rules.dedupe || seen.insert(recording_key(item)))
+        .collect();
+    if rules.artist_spacing == 0 {
+        return remaining;
+    }
+    let tail = &queued[queued.len().saturating_sub(rules.artist_spacing)..];
+
+    let mut spaced: Vec<QueueItem> = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        // How far back the nearest item sharing an artist sits, capped at
+        // `artist_spacing` when there is none in range.
+        let gap = |item: &QueueItem| {
+            let artists: HashSet<String> = item
+                .artists
+                .iter()
+                .map(|artist| normalize_text(artist))
+                .collect();
+            tail.iter()
+                .chain(&spaced)
+                .rev()
+                .take(rules.artist_spacing)
+                .position(|previous| {
+                    previous
+                        .artists
+                        .iter()
+                        .any(|artist| artists.contains(&normalize_text(artist)))
+                })
+                .unwrap_or(rules.artist_spacing)
+        };
+        let mut next = 0;
+        let mut best_gap = gap(&remaining[0]);
+        for (index, item) in remaining.iter().enumerate().skip(1) {
+            if best_gap == rules.artist_spacing {
+                break;
+            }
+            let item_gap = gap(item);
+            if item_gap > best_gap {
+                next = index;
+                best_gap = item_gap;
+            }
+        }
+        spaced.push(remaining.remove(next));
+    }
+    spaced
+}
+
+fn recording_key(item: &QueueItem) -> (String, String) {
+    let title = strip_title_noise(&item.title);
+    let artist = item
+        .artists
+        .first()
+        .map(|artist| artist.strip_suffix(" - Topic").unwrap_or(artist))
+        .map(normalize_text)
+        .unwrap_or_default();
+    (normalize_text(&title), artist)
+}
+
+/// Removes bracketed groups made up of [`TITLE_NOISE_WORDS`], keeping ones
+/// such as "(Live)" or "[Remix]" that name a different recording.
+fn strip_title_noise(title: &str) -> String {
+    let mut out = String::with_capacity(title.len());
+    let mut rest = title;
+    while let Some(open) = rest.find(['(', '[']) {
+        let close = if rest[open..].starts_with('(') {
+            ')'
+        } else {
+            ']'
+        };
+        let Some(len) = rest[open..].find(close) else {
+            break;
+        };
+        let group = &rest[open..=open + len];
+        let noise = normalize_text(&group[1..group.len() - 1])
+            .split(' ')
+            .all(|word| TITLE_NOISE_WORDS.contains(&word));
+        out.push_str(&rest[..open]);
+        if !noise {
+            out.push_str(group);
+        }
+        rest = &rest[open + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Lowercases `text` and collapses everything but letters and digits into
+/// single spaces.
+fn normalize_text(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 #[derive(Clone, Debug)]
 struct SplitMix64 {
     state: u64,
@@ -182,8 +584,14 @@ mod tests {
                     title: format!("Track {i}"),
                     artists: vec![],
                     duration_ms: 1000,
+                    unavailable: false,
                 })
                 .collect(),
+            continuation: None,
+            shuffle_seed: None,
+            shuffle_order: vec![],
+            repeat: RepeatMode::Off,
+            rules: QueueRules::default(),
         };
         let current = ResolvedStream {
             media_id: MediaId::new("local:3"),
@@ -197,13 +605,213 @@ mod tests {
             metadata: json!({ "resolved_at": Utc::now() }),
         };
 
-        let decision = next_window(&session, 3, current, 4, RecommendationSource::Local).unwrap();
+        let decision =
+            next_window(&session, 3, current.clone(), 4, RecommendationSource::Local).unwrap();
 
         assert_eq!(decision.position, 3);
         assert_eq!(decision.lookahead.len(), 4);
         assert_eq!(decision.lookahead[0].media_id, MediaId::new("local:4"));
         assert!(decision.has_more);
         assert_eq!(decision.queue_version, 7);
+
+        let tail =
+            next_window(&session, 8, current.clone(), 4, RecommendationSource::Local).unwrap();
+        assert!(!tail.has_more);
+
+        let radio = QueueSession {
+            continuation: Some("radio-cursor".into()),
+            ..session
+        };
+        let tail = next_window(&radio, 8, current, 4, RecommendationSource::Local).unwrap();
+        assert!(tail.has_more);
+        assert_eq!(tail.continuation.as_deref(), Some("radio-cursor"));
+    }
+
+    #[test]
+    fn next_window_follows_shuffle_and_repeat_modes() {
+        let session = QueueSession {
+            id: Uuid::new_v4(),
+            seed_kind: "album".into(),
+            seed_id: "local:album".into(),
+            title: String::new(),
+            version: 1,
+            items: queue_items(&["a", "b", "c", "d", "e"]),
+            continuation: None,
+            shuffle_seed: Some(42),
+            shuffle_order: vec![],
+            repeat: RepeatMode::Off,
+            rules: QueueRules::default(),
+        };
+        let current = ResolvedStream {
+            media_id: MediaId::new("a"),
+            source: "local".into(),
+            stream_url: "file:///tmp/a.flac".into(),
+            stream_expires_at: None,
+            mime_type: None,
+            content_length: None,
+            loudness_db: None,
+            playback_tracking_url: None,
+            metadata: json!({}),
+        };
+        let window = |session: &QueueSession, position: usize, count: usize| {
+            next_window(
+                session,
+                position,
+                current.clone(),
+                count,
+                RecommendationSource::Local,
+            )
+            .unwrap()
+        };
+
+        let order = queue_play_order(5, Some(42));
+        assert_eq!(order, queue_play_order(5, Some(42)));
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, [0, 1, 2, 3, 4]);
+        assert_ne!(order, queue_play_order(5, None));
+
+        let shuffled = window(&session, order[1], 2);
+        assert_eq!(shuffled.lookahead_positions, order[2..4]);
+        assert_eq!(
+            shuffled.lookahead[0].media_id,
+            session.items[order[2]].media_id
+        );
+        assert!(shuffled.shuffle);
+        assert!(shuffled.has_more);
+        let last = window(&session, order[4], 2);
+        assert!(last.lookahead.is_empty());
+        assert!(!last.has_more);
+
+        let repeat_all = QueueSession {
+            repeat: RepeatMode::All,
+            ..session.clone()
+        };
+        let wrapped = window(&repeat_all, order[4], 7);
+        assert_eq!(
+            wrapped.lookahead_positions,
+            [order.clone(), order[..2].to_vec()].concat()
+        );
+        assert!(wrapped.has_more);
+        assert_eq!(wrapped.repeat, RepeatMode::All);
+
+        let repeat_one = QueueSession {
+            repeat: RepeatMode::One,
+            shuffle_seed: None,
+            shuffle_order: vec![],
+            ..session
+        };
+        let again = window(&repeat_one, 4, 3);
+        assert_eq!(again.lookahead_positions, [4]);
+        assert_eq!(media_ids(&again.lookahead), ["e"]);
+        assert!(again.has_more);
+        assert!(!again.shuffle);
+    }
+
+    #[test]
+    fn next_window_skips_items_marked_unavailable() {
+        let mut items = queue_items(&["a", "b", "c", "d"]);
+        items[1].unavailable = true;
+        items[3].unavailable = true;
+        let session = QueueSession {
+            id: Uuid::new_v4(),
+            seed_kind: "playlist".into(),
+            seed_id: "p".into(),
+            title: String::new(),
+            version: 1,
+            items,
+            continuation: None,
+            shuffle_seed: None,
+            shuffle_order: vec![],
+            repeat: RepeatMode::Off,
+            rules: QueueRules::default(),
+        };
+        let current = ResolvedStream {
+            media_id: MediaId::new("a"),
+            source: "local".into(),
+            stream_url: "file:///tmp/a.flac".into(),
+            stream_expires_at: None,
+            mime_type: None,
+            content_length: None,
+            loudness_db: None,
+            playback_tracking_url: None,
+            metadata: json!({}),
+        };
+
+        let window =
+            next_window(&session, 0, current.clone(), 1, RecommendationSource::Local).unwrap();
+        assert_eq!(window.lookahead_positions, [2]);
+        assert_eq!(media_ids(&window.lookahead), ["c"]);
+        assert!(!window.has_more);
+
+        let repeat_all = QueueSession {
+            repeat: RepeatMode::All,
+            ..session
+        };
+        let wrapped = next_window(&repeat_all, 2, current, 3, RecommendationSource::Local).unwrap();
+        assert_eq!(wrapped.lookahead_positions, [0, 2, 0]);
+    }
+
+    #[test]
+    fn edits_keep_the_shuffled_order_and_play_inserts_next() {
+        let mut session = QueueSession {
+            id: Uuid::new_v4(),
+            seed_kind: "album".into(),
+            seed_id: "local:album".into(),
+            title: String::new(),
+            version: 1,
+            items: queue_items(&["a", "b", "c", "d", "e"]),
+            continuation: None,
+            shuffle_seed: Some(42),
+            shuffle_order: vec![3, 0, 4, 1, 2],
+            repeat: RepeatMode::Off,
+            rules: QueueRules::default(),
+        };
+        let played = |session: &QueueSession| -> Vec<String> {
+            session_play_order(session)
+                .into_iter()
+                .map(|index| session.items[index].media_id.0.clone())
+                .collect()
+        };
+        assert_eq!(played(&session), ["d", "a", "e", "b", "c"]);
+
+        // "a" (index 0) is playing; the inserted item plays right after it.
+        apply_session_edit(
+            &mut session,
+            QueueEdit::Insert {
+                position: 2,
+                items: queue_items(&["x"]),
+            },
+            0,
+        )
+        .unwrap();
+        assert_eq!(media_ids(&session.items), ["a", "b", "x", "c", "d", "e"]);
+        assert_eq!(played(&session), ["d", "a", "x", "e", "b", "c"]);
+
+        apply_session_edit(
+            &mut session,
+            QueueEdit::Append {
+                items: queue_items(&["y"]),
+            },
+            0,
+        )
+        .unwrap();
+        apply_session_edit(&mut session, QueueEdit::Remove { position: 4 }, 0).unwrap();
+        apply_session_edit(&mut session, QueueEdit::Move { from: 0, to: 3 }, 3).unwrap();
+        assert_eq!(media_ids(&session.items), ["b", "x", "c", "a", "e", "y"]);
+        assert_eq!(played(&session), ["a", "x", "e", "b", "c", "y"]);
+
+        apply_session_edit(&mut session, QueueEdit::ClearAfter { position: 3 }, 3).unwrap();
+        assert_eq!(played(&session), ["a", "x", "b", "c"]);
+
+        let mut unshuffled = QueueSession {
+            shuffle_seed: None,
+            shuffle_order: vec![],
+            ..session
+        };
+        apply_session_edit(&mut unshuffled, QueueEdit::Remove { position: 0 }, 0).unwrap();
+        assert!(unshuffled.shuffle_order.is_empty());
+        assert_eq!(played(&unshuffled), ["x", "c", "a"]);
     }
 
     #[test]
@@ -305,6 +913,181 @@ mod tests {
         assert_eq!(expanded.items[0].media_id, MediaId::new("yt:a"));
     }
 
+    #[test]
+    fn queue_edits_insert_move_remove_and_clear() {
+        let mut items = queue_items(&["a", "b", "c"]);
+
+        apply_queue_edit(
+            &mut items,
+            QueueEdit::Insert {
+                position: 1,
+                items: queue_items(&["x"]),
+            },
+        )
+        .unwrap();
+        assert_eq!(media_ids(&items), ["a", "x", "b", "c"]);
+
+        apply_queue_edit(
+            &mut items,
+            QueueEdit::Append {
+                items: queue_items(&["y"]),
+            },
+        )
+        .unwrap();
+        apply_queue_edit(&mut items, QueueEdit::Move { from: 4, to: 0 }).unwrap();
+        assert_eq!(media_ids(&items), ["y", "a", "x", "b", "c"]);
+
+        apply_queue_edit(&mut items, QueueEdit::Move { from: 1, to: 3 }).unwrap();
+        assert_eq!(media_ids(&items), ["y", "x", "b", "a", "c"]);
+
+        apply_queue_edit(&mut items, QueueEdit::Remove { position: 2 }).unwrap();
+        apply_queue_edit(&mut items, QueueEdit::ClearAfter { position: 1 }).unwrap();
+        assert_eq!(media_ids(&items), ["y", "x"]);
+    }
+
+    #[test]
+    fn queue_edits_reject_out_of_range_positions() {
+        let mut items = queue_items(&["a", "b"]);
+
+        apply_queue_edit(
+            &mut items,
+            QueueEdit::Insert {
+                position: 2,
+                items: queue_items(&["tail"]),
+            },
+        )
+        .unwrap();
+        for edit in [
+            QueueEdit::Insert {
+                position: 4,
+                items: queue_items(&["x"]),
+            },
+            QueueEdit::Move { from: 3, to: 0 },
+            QueueEdit::Move { from: 0, to: 3 },
+            QueueEdit::Remove { position: 3 },
+            QueueEdit::ClearAfter { position: 3 },
+        ] {
+            assert!(apply_queue_edit(&mut items, edit).is_err());
+        }
+        assert_eq!(media_ids(&items), ["a", "b", "tail"]);
+    }
+
+    #[test]
+    fn queue_rules_dedupe_recordings_and_hide_flagged_items() {
+        let song = |id: &str, title: &str, artist: &str| QueueItem {
+            media_id: MediaId::new(id),
+            title: title.into(),
+            artists: vec![artist.into()],
+            duration_ms: 0,
+            unavailable: false,
+        };
+        let items = vec![
+            song("yt:seed", "Sunflower", "Post Malone"),
+            song("yt:mv", "Sunflower (Official Music Video)", "Post Malone"),
+            song("yt:topic", "SUNFLOWER [Lyrics]", "Post Malone - Topic"),
+            song("yt:live", "Sunflower (Live)", "Post Malone"),
+            song("yt:cover", "Sunflower", "Cover Band"),
+            song("yt:explicit", "Loud", "Other"),
+            song("yt:clip", "Clip", "Other"),
+        ];
+        let flags = HashMap::from([
+            (
+                MediaId::new("yt:explicit"),
+                ContentFlags {
+                    explicit: true,
+                    video_only: false,
+                },
+            ),
+            (
+                MediaId::new("yt:clip"),
+                ContentFlags {
+                    explicit: false,
+                    video_only: true,
+                },
+            ),
+        ]);
+        let rules = QueueRules {
+            dedupe: true,
+            ..QueueRules::default()
+        };
+
+        let kept = apply_queue_rules(items.clone(), &flags, rules);
+        assert_eq!(
+            media_ids(&kept),
+            ["yt:seed", "yt:live", "yt:cover", "yt:explicit", "yt:clip"]
+        );
+        let ordered = apply_queue_rules(items.clone(), &flags, QueueRules::default());
+        assert_eq!(ordered, items);
+
+        let hidden = apply_queue_rules(
+            items,
+            &flags,
+            QueueRules {
+                hide_explicit: true,
+                hide_video: true,
+                ..rules
+            },
+        );
+        assert_eq!(media_ids(&hidden), ["yt:seed", "yt:live", "yt:cover"]);
+    }
+
+    #[test]
+    fn queue_rules_space_out_artists_and_keep_single_artist_order() {
+        let by = |id: &str, artist: &str| QueueItem {
+            media_id: MediaId::new(id),
+            title: format!("Song {id}"),
+            artists: vec![artist.into()],
+            duration_ms: 0,
+            unavailable: false,
+        };
+        let items = vec![
+            by("a1", "A"),
+            by("a2", "A"),
+            by("a3", "A"),
+            by("b1", "B"),
+            by("c1", "c"),
+            by("c2", "C"),
+        ];
+
+        let rules = QueueRules {
+            artist_spacing: 2,
+            ..QueueRules::default()
+        };
+
+        let spaced = apply_queue_rules(items.clone(), &HashMap::new(), rules);
+        assert_eq!(media_ids(&spaced), ["a1", "b1", "c1", "a2", "c2", "a3"]);
+
+        let album: Vec<_> = (1..=4).map(|i| by(&format!("t{i}"), "A")).collect();
+        let kept = apply_queue_rules(album, &HashMap::new(), rules);
+        assert_eq!(media_ids(&kept), ["t1", "t2", "t3", "t4"]);
+
+        // Appended items keep their distance from the end of the queue.
+        let queued = vec![by("b1", "B"), by("a1", "A")];
+        let appended = apply_queue_rules_after(
+            &queued,
+            vec![by("a2", "A"), by("b2", "B"), by("c1", "C")],
+            &HashMap::new(),
+            rules,
+        );
+        assert_eq!(media_ids(&appended), ["c1", "b2", "a2"]);
+    }
+
+    fn queue_items(ids: &[&str]) -> Vec<QueueItem> {
+        ids.iter()
+            .map(|id| QueueItem {
+                media_id: MediaId::new(*id),
+                title: format!("Song {id}"),
+                artists: vec![],
+                duration_ms: 0,
+                unavailable: false,
+            })
+            .collect()
+    }
+
+    fn media_ids(items: &[QueueItem]) -> Vec<&str> {
+        items.iter().map(|item| item.media_id.0.as_str()).collect()
+    }
+
     fn radio_page(ids: &[&str], continuation: Option<&str>) -> RadioPage {
         RadioPage {
             related: ids
@@ -314,6 +1097,7 @@ mod tests {
                     title: format!("Song {id}"),
                     artists: vec![],
                     duration_ms: 0,
+                    unavailable: false,
                 })
                 .collect(),
             continuation: continuation.map(str::to_string),
@@ -1,6 +1,9 @@
 use std::collections::{HashMap, HashSet};
 
-use crate::{LocalStatsSnapshot, RecommendationCandidate, RecommendationSource, TrackStats};
+use crate::{
+    LocalStatsSnapshot, MediaId, QueueItem, RecommendationCandidate, RecommendationSource,
+    TrackStats,
+};
 
 pub const DEFAULT_RECOMMENDATION_LIMIT: usize = 20;
 
@@ -81,6 +84,65 @@ impl LocalRecommendationEngine {
             .collect()
     }
 
+    /// Ranks "add similar" suggestions for `current`. InnerTube's `related`
+    /// songs (best first) are pooled with the `local` candidates that share an
+    /// artist with `current`; anything already in `queued` is dropped and the
+    /// rest is ranked against `stats`.
+    pub fn automix(
+        &self,
+        current: &QueueItem,
+        related: &[QueueItem],
+        local: &[RecommendationCandidate],
+        stats: &LocalStatsSnapshot,
+        queued: &[QueueItem],
+        limit: usize,
+    ) -> Vec<QueueItem> {
+        let mut seen: HashSet<&MediaId> = queued.iter().map(|item| &item.media_id).collect();
+        seen.insert(&current.media_id);
+        let current_artists: HashSet<String> = current
+            .artists
+            .iter()
+            .map(|artist| artist.to_lowercase())
+            .collect();
+
+        let mut candidates = Vec::new();
+        for (index, item) in related.iter().enumerate() {
+            if !seen.insert(&item.media_id) {
+                continue;
+            }
+            candidates.push(RecommendationCandidate {
+                media_id: item.media_id.clone(),
+                title: item.title.clone(),
+                artists: item.artists.clone(),
+                album_id: None,
+                duration_ms: item.duration_ms,
+                source: RecommendationSource::Remote,
+                remote_score: 1.0 - index as f32 / related.len() as f32,
+                reason: None,
+            });
+        }
+        for candidate in local {
+            let shares_artist = candidate
+                .artists
+                .iter()
+                .any(|artist| current_artists.contains(&artist.to_lowercase()));
+            if shares_artist && seen.insert(&candidate.media_id) {
+                candidates.push(candidate.clone());
+            }
+        }
+
+        self.rank(&candidates, stats, limit)
+            .into_iter()
+            .map(|candidate| QueueItem {
+                media_id: candidate.media_id,
+                title: candidate.title,
+                artists: candidate.artists,
+                duration_ms: candidate.duration_ms,
+                unavailable: false,
+            })
+            .collect()
+    }
+
     fn score(&self, candidate: &RecommendationCandidate, stats: Option<&TrackStats>) -> f32 {
         let affinity = stats.map(track_affinity).unwrap_or(0.15);
         let availability = stats.map(availability_score).unwrap_or(0.0);
@@ -243,4 +305,59 @@ mod tests {
 
         assert_eq!(ranked[0].media_id, MediaId::new("local:next"));
     }
+
+    #[test]
+    fn automix_pools_related_and_same_artist_local_songs_outside_the_queue() {
+        let item = |id: &str, artist: &str| QueueItem {
+            media_id: MediaId::new(id),
+            title: id.into(),
+            artists: vec![artist.into()],
+            duration_ms: 0,
+            unavailable: false,
+        };
+        let local = |id: &str, artist: &str| RecommendationCandidate {
+            artists: vec![artist.into()],
+            source: RecommendationSource::Local,
+            ..candidate(id, 0.0)
+        };
+        let current = item("yt:now", "Band");
+        let queued = vec![current.clone(), item("yt:queued", "Band")];
+        let related = vec![
+            item("yt:queued", "Band"),
+            item("yt:first", "Other"),
+            item("yt:second", "Other"),
+        ];
+        let stats = LocalStatsSnapshot {
+            generated_at: Utc::now(),
+            tracks: vec![TrackStats {
+                media_id: MediaId::new("local:fav"),
+                play_count: 8,
+                completion_count: 8,
+                liked: true,
+                downloaded: true,
+                local_available: true,
+                ..TrackStats::default()
+            }],
+            recent_media_ids: vec![],
+            recent_artist_names: vec![],
+        };
+
+        let suggestions = LocalRecommendationEngine::default().automix(
+            &current,
+            &related,
+            &[
+                local("local:fav", "band"),
+                local("local:stranger", "Someone"),
+            ],
+            &stats,
+            &queued,
+            3,
+        );
+
+        let ids: Vec<_> = suggestions
+            .iter()
+            .map(|item| item.media_id.0.as_str())
+            .collect();
+        assert_eq!(ids, ["local:fav", "yt:first", "yt:second"]);
+    }
 }
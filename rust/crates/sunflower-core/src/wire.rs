@@ -10,7 +10,10 @@ use serde::{Deserialize, Serialize, Serializer, de::DeserializeOwned};
 use serde_json::Value;
 use uuid::Uuid;
 
-use crate::{NextDecision, QueueItem, QueueSession, ResolvedStream};
+use crate::{
+    DEFAULT_ARTIST_SPACING, Lyrics, LyricsLine, MediaId, NextDecision, PlaylistEdit,
+    PlaylistFileFormat, QueueEdit, QueueItem, QueueRules, QueueSession, RepeatMode, ResolvedStream,
+};
 
 fn default_on_null<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
@@ -314,6 +317,39 @@ pub struct AdminAuditResponse {
     pub events: Vec<AdminAuditEventResponse>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminInnerTubeDiagnosticsResponse {
+    pub endpoints: Vec<AdminParserDriftEndpointResponse>,
+    pub profiles: Vec<AdminInnerTubeProfileResponse>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminParserDriftEndpointResponse {
+    pub endpoint: String,
+    pub parsed_responses: u64,
+    pub parse_failures: u64,
+    pub unknown_renderers: Vec<AdminUnknownRendererResponse>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminUnknownRendererResponse {
+    pub renderer: String,
+    pub count: u64,
+    pub last_seen_at: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminInnerTubeProfileResponse {
+    pub profile: String,
+    pub served: u64,
+    pub failures: u64,
+    pub retries: u64,
+    pub consecutive_failures: u32,
+    pub circuit_open: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_served_at: Option<String>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct AdminNowPlayingResponse {
     pub now_playing: Vec<NowPlayingStateResponse>,
@@ -557,6 +593,25 @@ pub struct SongHashResponse {
     pub bytes: u64,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LyricsResponse {
+    pub media_id: String,
+    pub source: String,
+    pub synced: bool,
+    pub lines: Vec<LyricsLine>,
+}
+
+impl LyricsResponse {
+    pub fn from_lyrics(media_id: impl Into<String>, lyrics: &Lyrics) -> Self {
+        Self {
+            media_id: media_id.into(),
+            source: lyrics.source.clone(),
+            synced: lyrics.is_synced(),
+            lines: lyrics.lines.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RegisterDownloadRequest {
     #[serde(default, deserialize_with = "default_on_null")]
@@ -577,6 +632,29 @@ impl RegisterDownloadRequest {
     }
 }
 
+/// Asks the server to keep its own copy of a `yt:` track; answered with a
+/// `StartScanResponse` naming the download job.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartYouTubeDownloadRequest {
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub media_id: String,
+}
+
+impl StartYouTubeDownloadRequest {
+    pub fn parse_json(raw: &str) -> Result<Self, LegacyRequestError> {
+        let req: Self = decode_legacy_json(raw)?;
+        let video_id = req.media_id.strip_prefix("yt:").unwrap_or_default();
+        if video_id.is_empty()
+            || !video_id
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+        {
+            return Err(LegacyRequestError::InvalidRequest);
+        }
+        Ok(req)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DownloadListItemResponse {
     pub media_id: String,
@@ -777,6 +855,9 @@ pub struct PlaylistResponse {
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PlaylistItemResponse {
+    /// Names this entry among repeats of the same song.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub item_id: String,
     pub position: i32,
     pub media_id: String,
     pub title: String,
@@ -824,6 +905,123 @@ impl AddPlaylistItemRequest {
     }
 }
 
+/// Body of `POST /api/v1/playlists/{id}/edit`. `version`, when sent, is the
+/// playlist version the client last saw; the edit is rejected if the playlist
+/// has moved on.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistEditRequest {
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub version: Option<i64>,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub op: String,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub position: Option<usize>,
+    #[serde(default, deserialize_with = "vec_default_on_null")]
+    pub media_ids: Vec<String>,
+    /// Missing means duplicates are allowed, as with single adds.
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub allow_duplicates: Option<bool>,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub item_id: String,
+    #[serde(default, deserialize_with = "vec_default_on_null")]
+    pub item_ids: Vec<String>,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub to: Option<usize>,
+}
+
+impl PlaylistEditRequest {
+    /// Parses the body into the expected version, if any, and the edit to
+    /// apply.
+    pub fn parse_json(raw: &str) -> Result<(Option<i64>, PlaylistEdit), LegacyRequestError> {
+        let req: Self = decode_legacy_json(raw)?;
+        let version = req.version;
+        Ok((version, req.into_edit()?))
+    }
+
+    fn into_edit(self) -> Result<PlaylistEdit, LegacyRequestError> {
+        let item_id =
+            |raw: &str| Uuid::parse_str(raw).map_err(|_| LegacyRequestError::InvalidRequest);
+        match self.op.as_str() {
+            "insert" => {
+                if self.media_ids.is_empty() || self.media_ids.iter().any(String::is_empty) {
+                    return Err(LegacyRequestError::InvalidRequest);
+                }
+                Ok(PlaylistEdit::Insert {
+                    position: self.position,
+                    media_ids: self.media_ids.into_iter().map(MediaId::new).collect(),
+                    allow_duplicates: self.allow_duplicates.unwrap_or(true),
+                })
+            }
+            "move" => Ok(PlaylistEdit::Move {
+                item_id: item_id(&self.item_id)?,
+                to: self.to.ok_or(LegacyRequestError::InvalidRequest)?,
+            }),
+            "remove" if !self.item_ids.is_empty() => Ok(PlaylistEdit::Remove {
+                item_ids: self
+                    .item_ids
+                    .iter()
+                    .map(|raw| item_id(raw))
+                    .collect::<Result<_, _>>()?,
+            }),
+            _ => Err(LegacyRequestError::InvalidRequest),
+        }
+    }
+}
+
+/// Body of `POST /api/v1/playlists/import`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistImportRequest {
+    /// Empty means the title stored in the file, if any.
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub title: String,
+    /// `m3u`, `m3u8` or `xspf`; empty means guessed from `content`.
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub format: String,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub content: String,
+    /// Looks up entries no local song matched on YouTube.
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub youtube: bool,
+}
+
+impl PlaylistImportRequest {
+    pub fn parse_json(raw: &str) -> Result<Self, LegacyRequestError> {
+        let req: Self = decode_legacy_json(raw)?;
+        if req.content.trim().is_empty()
+            || (!req.format.is_empty() && PlaylistFileFormat::from_name(&req.format).is_none())
+        {
+            return Err(LegacyRequestError::InvalidRequest);
+        }
+        Ok(req)
+    }
+
+    pub fn file_format(&self) -> PlaylistFileFormat {
+        PlaylistFileFormat::from_name(&self.format)
+            .unwrap_or_else(|| PlaylistFileFormat::sniff(&self.content))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistImportResponse {
+    pub playlist: PlaylistResponse,
+    pub matched_by_path: usize,
+    pub matched_by_tags: usize,
+    pub matched_by_youtube: usize,
+    pub unmatched: Vec<PlaylistImportUnmatchedResponse>,
+}
+
+/// A file entry that matched no song, as it appeared in the file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistImportUnmatchedResponse {
+    pub line: usize,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub location: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub artist: String,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LegacyRequestError {
     InvalidRequest,
@@ -849,18 +1047,66 @@ pub struct StartQueueRequest {
     pub seed_id: String,
     #[serde(default, deserialize_with = "default_on_null")]
     pub title: String,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub shuffle: bool,
+    /// `off`, `one` or `all`; empty means `off`.
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub repeat: String,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub hide_explicit: bool,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub hide_video: bool,
+    /// Songs between two by the same artist; `0` keeps the seeded order.
+    /// Missing means [`DEFAULT_ARTIST_SPACING`] for radio and shuffled seeds,
+    /// and `0` for albums and playlists.
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub artist_spacing: Option<usize>,
+    /// Drops repeated uploads of one recording; missing means on, except for
+    /// seeds whose order is the point (albums and playlists).
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub dedupe: Option<bool>,
 }
 
 impl StartQueueRequest {
     pub fn parse_json(raw: &str) -> Result<Self, LegacyRequestError> {
         let req: Self = decode_legacy_json(raw)?;
         req.validate_seed_kind()?;
+        req.repeat_mode()?;
         Ok(req)
     }
 
+    pub fn repeat_mode(&self) -> Result<RepeatMode, LegacyRequestError> {
+        if self.repeat.is_empty() {
+            return Ok(RepeatMode::Off);
+        }
+        RepeatMode::parse(&self.repeat).ok_or(LegacyRequestError::InvalidRequest)
+    }
+
+    pub fn queue_rules(&self) -> QueueRules {
+        let ordered = matches!(
+            self.seed_kind.as_str(),
+            "album" | "playlist" | "yt_playlist"
+        );
+        QueueRules {
+            artist_spacing: self.artist_spacing.unwrap_or(if ordered {
+                0
+            } else {
+                DEFAULT_ARTIST_SPACING
+            }),
+            dedupe: self.dedupe.unwrap_or(!ordered),
+            hide_explicit: self.hide_explicit,
+            hide_video: self.hide_video,
+        }
+    }
+
     pub fn validate_seed_kind(&self) -> Result<(), LegacyRequestError> {
         match self.seed_kind.as_str() {
-            "song" | "shuffle_liked" => Ok(()),
+            "song" | "shuffle_liked" | "local_radio" => Ok(()),
+            // These name a collection, so there is nothing to play without one.
+            "album" | "artist" | "playlist" | "yt_playlist" if self.seed_id.is_empty() => {
+                Err(LegacyRequestError::InvalidRequest)
+            }
+            "album" | "artist" | "playlist" | "yt_playlist" => Ok(()),
             _ => Err(LegacyRequestError::InvalidSeedKind),
         }
     }
@@ -872,6 +1118,135 @@ impl StartQueueRequest {
         }
         Ok(video_id)
     }
+
+    /// The InnerTube browse id for a `yt_playlist` seed. Accepts a playlist
+    /// id (`PL…`, `OLAK5uy_…`), a `VL…` browse id or an album browse id
+    /// (`MPREb_…`), optionally prefixed with `yt:`.
+    pub fn yt_playlist_browse_id(&self) -> Result<String, LegacyRequestError> {
+        let id = self.seed_id.strip_prefix("yt:").unwrap_or(&self.seed_id);
+        if id.is_empty()
+            || !id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+        {
+            return Err(LegacyRequestError::InvalidRequest);
+        }
+        if id.starts_with("VL") || id.starts_with("MPREb_") {
+            Ok(id.to_string())
+        } else {
+            Ok(format!("VL{id}"))
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueEditItemRequest {
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub media_id: String,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub title: String,
+    #[serde(default, deserialize_with = "vec_default_on_null")]
+    pub artists: Vec<String>,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub duration_ms: i32,
+}
+
+/// Body of `POST /api/v1/queue/{id}/edit`. `version` is the queue version
+/// the client last saw; the edit is rejected if the queue has moved on.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueEditRequest {
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub version: Option<i64>,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub op: String,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub position: Option<usize>,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub from: Option<usize>,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub to: Option<usize>,
+    #[serde(default, deserialize_with = "vec_default_on_null")]
+    pub items: Vec<QueueEditItemRequest>,
+}
+
+impl QueueEditRequest {
+    /// Parses the body into the expected version and the edit to apply.
+    pub fn parse_json(raw: &str) -> Result<(i64, QueueEdit), LegacyRequestError> {
+        let req: Self = decode_legacy_json(raw)?;
+        let version = req.version.ok_or(LegacyRequestError::InvalidRequest)?;
+        let edit = req.into_edit()?;
+        Ok((version, edit))
+    }
+
+    fn into_edit(self) -> Result<QueueEdit, LegacyRequestError> {
+        let position = self.position.ok_or(LegacyRequestError::InvalidRequest);
+        match self.op.as_str() {
+            "insert" => Ok(QueueEdit::Insert {
+                position: position?,
+                items: queue_edit_items(self.items)?,
+            }),
+            "append" => Ok(QueueEdit::Append {
+                items: queue_edit_items(self.items)?,
+            }),
+            "move" => match (self.from, self.to) {
+                (Some(from), Some(to)) => Ok(QueueEdit::Move { from, to }),
+                _ => Err(LegacyRequestError::InvalidRequest),
+            },
+            "remove" => Ok(QueueEdit::Remove {
+                position: position?,
+            }),
+            "clear_after" => Ok(QueueEdit::ClearAfter {
+                position: position?,
+            }),
+            _ => Err(LegacyRequestError::InvalidRequest),
+        }
+    }
+}
+
+/// Body of `POST /api/v1/queue/{id}/mode`. Fields left out keep their
+/// current value.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueModeRequest {
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub shuffle: Option<bool>,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub repeat: Option<String>,
+}
+
+impl QueueModeRequest {
+    /// Parses the body into the requested shuffle flag and repeat mode, at
+    /// least one of which is set.
+    pub fn parse_json(raw: &str) -> Result<(Option<bool>, Option<RepeatMode>), LegacyRequestError> {
+        let req: Self = decode_legacy_json(raw)?;
+        let repeat = match req.repeat.as_deref() {
+            Some(repeat) => {
+                Some(RepeatMode::parse(repeat).ok_or(LegacyRequestError::InvalidRequest)?)
+            }
+            None => None,
+        };
+        if req.shuffle.is_none() && repeat.is_none() {
+            return Err(LegacyRequestError::InvalidRequest);
+        }
+        Ok((req.shuffle, repeat))
+    }
+}
+
+fn queue_edit_items(
+    items: Vec<QueueEditItemRequest>,
+) -> Result<Vec<QueueItem>, LegacyRequestError> {
+    if items.is_empty() || items.iter().any(|item| item.media_id.is_empty()) {
+        return Err(LegacyRequestError::InvalidRequest);
+    }
+    Ok(items
+        .into_iter()
+        .map(|item| QueueItem {
+            media_id: MediaId::new(item.media_id),
+            title: item.title,
+            artists: item.artists,
+            duration_ms: item.duration_ms,
+            unavailable: false,
+        })
+        .collect())
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -896,6 +1271,45 @@ impl ResolveStreamRequest {
     }
 }
 
+/// Upper bound on `media_ids` in one refresh call; roughly a full lookahead
+/// window plus the current item several times over.
+pub const MAX_REFRESH_STREAMS: usize = 50;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefreshStreamsRequest {
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub media_ids: Vec<String>,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub proxy: bool,
+    #[serde(default, deserialize_with = "default_on_null")]
+    pub audio_quality: String,
+}
+
+impl RefreshStreamsRequest {
+    pub fn parse_json(raw: &str) -> Result<Self, LegacyRequestError> {
+        let req: Self = decode_legacy_json(raw)?;
+        if req.media_ids.is_empty()
+            || req.media_ids.len() > MAX_REFRESH_STREAMS
+            || req.media_ids.iter().any(String::is_empty)
+        {
+            return Err(LegacyRequestError::InvalidRequest);
+        }
+        Ok(req)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefreshStreamFailure {
+    pub media_id: String,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RefreshStreamsResponse {
+    pub streams: Vec<ResolvedStreamResponse>,
+    pub failed: Vec<RefreshStreamFailure>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct NextQuery {
     pub queue_id: Uuid,
@@ -942,6 +1356,8 @@ pub struct QueueItemResponse {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub artists: Vec<String>,
     pub duration_ms: i32,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub unavailable: bool,
 }
 
 impl From<&QueueItem> for QueueItemResponse {
@@ -951,6 +1367,7 @@ impl From<&QueueItem> for QueueItemResponse {
             title: item.title.clone(),
             artists: item.artists.clone(),
             duration_ms: item.duration_ms,
+            unavailable: item.unavailable,
         }
     }
 }
@@ -963,6 +1380,10 @@ pub struct QueueResponse {
     pub title: String,
     pub version: i64,
     pub items: Vec<QueueItemResponse>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub shuffle: bool,
+    #[serde(default, skip_serializing_if = "is_repeat_off")]
+    pub repeat: RepeatMode,
 }
 
 impl From<&QueueSession> for QueueResponse {
@@ -973,10 +1394,39 @@ impl From<&QueueSession> for QueueResponse {
             title: session.title.clone(),
             version: session.version,
             items: session.items.iter().map(QueueItemResponse::from).collect(),
+            shuffle: session.shuffle_seed.is_some(),
+            repeat: session.repeat,
         }
     }
 }
 
+/// One entry of `GET /api/v1/queues`: a recent queue and where its user last
+/// was in it, on whichever device.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueSummaryResponse {
+    pub queue_id: String,
+    pub seed_kind: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub seed_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    pub version: i64,
+    pub item_count: i64,
+    pub position: i64,
+    pub position_ms: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_media_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_device_id: Option<String>,
+    pub created_at: String,
+    pub last_played_at: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueListResponse {
+    pub queues: Vec<QueueSummaryResponse>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ResolvedStreamResponse {
     pub media_id: String,
@@ -1055,6 +1505,9 @@ impl From<&ResolvedStream> for ResolvedStreamResponse {
 pub struct NextResponse {
     pub queue_id: String,
     pub position: usize,
+    /// Offset into the current item to continue from; set when resuming.
+    #[serde(default, skip_serializing_if = "is_zero_i32")]
+    pub position_ms: i32,
     pub current: Option<ResolvedStreamResponse>,
     pub lookahead: Vec<ResolvedStreamResponse>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -1063,6 +1516,14 @@ pub struct NextResponse {
     pub automix: Vec<QueueItemResponse>,
     pub queue_version: i64,
     pub has_more: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub shuffle: bool,
+    #[serde(default, skip_serializing_if = "is_repeat_off")]
+    pub repeat: RepeatMode,
+    /// Queue positions of the `lookahead` items, sent only when shuffle or
+    /// repeat makes them differ from `position + 1, position + 2, …`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lookahead_positions: Vec<usize>,
 }
 
 impl NextResponse {
@@ -1084,6 +1545,7 @@ impl NextResponse {
         Self {
             queue_id: decision.queue_id.to_string(),
             position: decision.position,
+            position_ms: 0,
             current,
             lookahead,
             continuation: decision.continuation.clone(),
@@ -1094,6 +1556,13 @@ impl NextResponse {
                 .collect(),
             queue_version: decision.queue_version,
             has_more: decision.has_more,
+            shuffle: decision.shuffle,
+            repeat: decision.repeat,
+            lookahead_positions: if decision.shuffle || decision.repeat != RepeatMode::Off {
+                decision.lookahead_positions.clone()
+            } else {
+                vec![]
+            },
         }
     }
 }
@@ -1110,6 +1579,10 @@ fn is_false(value: &bool) -> bool {
     !*value
 }
 
+fn is_repeat_off(value: &RepeatMode) -> bool {
+    *value == RepeatMode::Off
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1679,6 +2152,28 @@ mod tests {
         let liked = StartQueueRequest::parse_json(r#"{"seed_kind":"shuffle_liked"}"#).unwrap();
         assert_eq!(liked.seed_kind, "shuffle_liked");
         assert_eq!(liked.seed_id, "");
+
+        let radio = StartQueueRequest::parse_json(r#"{"seed_kind":"local_radio"}"#).unwrap();
+        assert_eq!(radio.seed_kind, "local_radio");
+    }
+
+    #[test]
+    fn start_queue_request_maps_youtube_playlist_seeds_to_browse_ids() {
+        let browse_id = |seed_id: &str| {
+            StartQueueRequest::parse_json(
+                &json!({"seed_kind": "yt_playlist", "seed_id": seed_id}).to_string(),
+            )
+            .unwrap()
+            .yt_playlist_browse_id()
+        };
+        assert_eq!(browse_id("PLabc-1_2").unwrap(), "VLPLabc-1_2");
+        assert_eq!(browse_id("yt:OLAK5uy_x").unwrap(), "VLOLAK5uy_x");
+        assert_eq!(browse_id("VLPLabc").unwrap(), "VLPLabc");
+        assert_eq!(browse_id("MPREb_album").unwrap(), "MPREb_album");
+        assert_eq!(
+            browse_id("PL/../x").unwrap_err().legacy_error_code(),
+            "invalid_request"
+        );
     }
 
     #[test]
@@ -1690,11 +2185,20 @@ mod tests {
             "invalid_request"
         );
         assert_eq!(
-            StartQueueRequest::parse_json(r#"{"seed_kind":"album"}"#)
+            StartQueueRequest::parse_json(r#"{"seed_kind":"mood"}"#)
                 .unwrap_err()
                 .legacy_error_code(),
             "invalid_seed_kind"
         );
+        for kind in ["album", "artist", "playlist", "yt_playlist"] {
+            assert_eq!(
+                StartQueueRequest::parse_json(&format!(r#"{{"seed_kind":"{kind}"}}"#))
+                    .unwrap_err()
+                    .legacy_error_code(),
+                "invalid_request",
+                "{kind}"
+            );
+        }
         let empty_song =
             StartQueueRequest::parse_json(r#"{"seed_kind":"song","seed_id":"yt:"}"#).unwrap();
         assert_eq!(
@@ -1732,6 +2236,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn refresh_streams_request_requires_bounded_media_ids() {
+        let req = RefreshStreamsRequest::parse_json(
+            r#"{"media_ids":["yt:abc","local:1"],"proxy":true,"audio_quality":"low"}"#,
+        )
+        .unwrap();
+        assert_eq!(req.media_ids, vec!["yt:abc", "local:1"]);
+        assert!(req.proxy);
+        assert_eq!(req.audio_quality, "low");
+
+        let too_many = json!({ "media_ids": vec!["yt:abc"; MAX_REFRESH_STREAMS + 1] }).to_string();
+        for raw in [
+            "{",
+            r#"{"media_ids":[]}"#,
+            r#"{"media_ids":null}"#,
+            r#"{"media_ids":[""]}"#,
+            too_many.as_str(),
+        ] {
+            assert_eq!(
+                RefreshStreamsRequest::parse_json(raw)
+                    .unwrap_err()
+                    .legacy_error_code(),
+                "invalid_request"
+            );
+        }
+    }
+
     #[test]
     fn next_query_defaults_position_to_zero_like_go() {
         let id = "018f3f27-0000-7000-8000-000000000001";
@@ -1783,7 +2314,13 @@ mod tests {
                 title: "One".into(),
                 artists: vec![],
                 duration_ms: 1234,
+                unavailable: false,
             }],
+            continuation: None,
+            shuffle_seed: None,
+            shuffle_order: vec![],
+            repeat: RepeatMode::Off,
+            rules: QueueRules::default(),
         };
 
         let value = serde_json::to_value(QueueResponse::from(&session)).unwrap();
@@ -1800,6 +2337,16 @@ mod tests {
                 }]
             })
         );
+
+        let modes = QueueSession {
+            shuffle_seed: Some(7),
+            shuffle_order: vec![],
+            repeat: RepeatMode::All,
+            ..session
+        };
+        let value = serde_json::to_value(QueueResponse::from(&modes)).unwrap();
+        assert_eq!(value["shuffle"], true);
+        assert_eq!(value["repeat"], "all");
     }
 
     #[test]
@@ -1936,6 +2483,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lyrics_response_omits_missing_timestamps() {
+        let lyrics = Lyrics {
+            source: "lrc".into(),
+            lines: vec![
+                LyricsLine {
+                    start_ms: Some(1_500),
+                    text: "Hello".into(),
+                },
+                LyricsLine {
+                    start_ms: None,
+                    text: "World".into(),
+                },
+            ],
+        };
+        assert_eq!(
+            serde_json::to_value(LyricsResponse::from_lyrics("local:one", &lyrics)).unwrap(),
+            json!({
+                "media_id": "local:one",
+                "source": "lrc",
+                "synced": false,
+                "lines": [
+                    {"start_ms": 1500, "text": "Hello"},
+                    {"text": "World"}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn youtube_download_request_requires_a_plain_video_id() {
+        let req =
+            StartYouTubeDownloadRequest::parse_json(r#"{"media_id":"yt:dQw4w9WgXcQ"}"#).unwrap();
+        assert_eq!(req.media_id, "yt:dQw4w9WgXcQ");
+        for raw in [
+            "{}",
+            r#"{"media_id":"yt:"}"#,
+            r#"{"media_id":"local:one"}"#,
+            r#"{"media_id":"yt:../etc"}"#,
+        ] {
+            assert_eq!(
+                StartYouTubeDownloadRequest::parse_json(raw)
+                    .unwrap_err()
+                    .legacy_error_code(),
+                "invalid_request"
+            );
+        }
+    }
+
     #[test]
     fn download_requests_and_responses_match_legacy_contract() {
         let req = RegisterDownloadRequest::parse_json(
@@ -2209,6 +2805,7 @@ mod tests {
             source_type: "local".into(),
             version: 2,
             items: vec![PlaylistItemResponse {
+                item_id: String::new(),
                 position: 0,
                 media_id: "local:one".into(),
                 title: "One".into(),
@@ -2248,11 +2845,234 @@ mod tests {
         );
     }
 
+    #[test]
+    fn playlist_edit_request_parses_each_operation() {
+        let item = "018f3f27-0000-7000-8000-000000000002";
+        assert_eq!(
+            PlaylistEditRequest::parse_json(
+                r#"{"version":3,"op":"insert","position":1,"media_ids":["yt:a","local:b"],"allow_duplicates":false}"#
+            )
+            .unwrap(),
+            (
+                Some(3),
+                PlaylistEdit::Insert {
+                    position: Some(1),
+                    media_ids: vec![MediaId::new("yt:a"), MediaId::new("local:b")],
+                    allow_duplicates: false,
+                }
+            )
+        );
+        assert_eq!(
+            PlaylistEditRequest::parse_json(r#"{"op":"insert","media_ids":["yt:a"]}"#).unwrap(),
+            (
+                None,
+                PlaylistEdit::Insert {
+                    position: None,
+                    media_ids: vec![MediaId::new("yt:a")],
+                    allow_duplicates: true,
+                }
+            )
+        );
+        assert_eq!(
+            PlaylistEditRequest::parse_json(&format!(
+                r#"{{"op":"move","item_id":"{item}","to":0}}"#
+            ))
+            .unwrap(),
+            (
+                None,
+                PlaylistEdit::Move {
+                    item_id: Uuid::parse_str(item).unwrap(),
+                    to: 0,
+                }
+            )
+        );
+        assert_eq!(
+            PlaylistEditRequest::parse_json(&format!(r#"{{"op":"remove","item_ids":["{item}"]}}"#))
+                .unwrap(),
+            (
+                None,
+                PlaylistEdit::Remove {
+                    item_ids: vec![Uuid::parse_str(item).unwrap()],
+                }
+            )
+        );
+        for raw in [
+            r#"{"op":"insert","media_ids":[]}"#,
+            r#"{"op":"insert","media_ids":[""]}"#,
+            r#"{"op":"move","item_id":"not-a-uuid","to":0}"#,
+            r#"{"op":"move","item_id":"018f3f27-0000-7000-8000-000000000002"}"#,
+            r#"{"op":"remove","item_ids":null}"#,
+            r#"{"op":"shuffle"}"#,
+        ] {
+            assert_eq!(
+                PlaylistEditRequest::parse_json(raw)
+                    .unwrap_err()
+                    .legacy_error_code(),
+                "invalid_request",
+                "{raw}"
+            );
+        }
+    }
+
+    #[test]
+    fn playlist_import_request_picks_or_guesses_the_file_format() {
+        let request =
+            PlaylistImportRequest::parse_json(r#"{"format":"M3U","content":"a.mp3"}"#).unwrap();
+        assert_eq!(request.file_format(), PlaylistFileFormat::M3u);
+        let request = PlaylistImportRequest::parse_json(
+            r#"{"title":"Mix","content":"<?xml version=\"1.0\"?><playlist/>","youtube":true}"#,
+        )
+        .unwrap();
+        assert_eq!(request.file_format(), PlaylistFileFormat::Xspf);
+        assert!(request.youtube);
+        for raw in [
+            r#"{"content":"  "}"#,
+            r#"{"format":"pls","content":"a.mp3"}"#,
+        ] {
+            assert_eq!(
+                PlaylistImportRequest::parse_json(raw)
+                    .unwrap_err()
+                    .legacy_error_code(),
+                "invalid_request",
+                "{raw}"
+            );
+        }
+    }
+
+    #[test]
+    fn queue_mode_requests_parse_shuffle_and_repeat() {
+        assert_eq!(
+            QueueModeRequest::parse_json(r#"{"shuffle":true}"#).unwrap(),
+            (Some(true), None)
+        );
+        assert_eq!(
+            QueueModeRequest::parse_json(r#"{"shuffle":false,"repeat":"one"}"#).unwrap(),
+            (Some(false), Some(RepeatMode::One))
+        );
+        for raw in ["{}", r#"{"shuffle":null}"#, r#"{"repeat":"forever"}"#] {
+            assert_eq!(
+                QueueModeRequest::parse_json(raw)
+                    .unwrap_err()
+                    .legacy_error_code(),
+                "invalid_request",
+                "{raw}"
+            );
+        }
+
+        let start = StartQueueRequest::parse_json(
+            r#"{"seed_kind":"shuffle_liked","shuffle":true,"repeat":"all"}"#,
+        )
+        .unwrap();
+        assert!(start.shuffle);
+        assert_eq!(start.repeat_mode().unwrap(), RepeatMode::All);
+        assert_eq!(
+            StartQueueRequest::parse_json(r#"{"seed_kind":"shuffle_liked","repeat":"twice"}"#)
+                .unwrap_err()
+                .legacy_error_code(),
+            "invalid_request"
+        );
+    }
+
+    #[test]
+    fn start_queue_request_builds_queue_rules_from_preferences() {
+        for seed_kind in ["song", "shuffle_liked", "local_radio"] {
+            let defaults =
+                StartQueueRequest::parse_json(&format!(r#"{{"seed_kind":"{seed_kind}"}}"#))
+                    .unwrap();
+            assert_eq!(
+                defaults.queue_rules(),
+                QueueRules {
+                    artist_spacing: DEFAULT_ARTIST_SPACING,
+                    dedupe: true,
+                    ..QueueRules::default()
+                },
+                "{seed_kind}"
+            );
+        }
+        let unspaced =
+            StartQueueRequest::parse_json(r#"{"seed_kind":"song","artist_spacing":0}"#).unwrap();
+        assert_eq!(unspaced.queue_rules().artist_spacing, 0);
+        let album =
+            StartQueueRequest::parse_json(r#"{"seed_kind":"album","seed_id":"local:album"}"#)
+                .unwrap();
+        assert_eq!(album.queue_rules(), QueueRules::default());
+
+        let strict = StartQueueRequest::parse_json(
+            r#"{"seed_kind":"playlist","seed_id":"p","hide_explicit":true,"hide_video":true,"artist_spacing":2,"dedupe":true}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            strict.queue_rules(),
+            QueueRules {
+                artist_spacing: 2,
+                dedupe: true,
+                hide_explicit: true,
+                hide_video: true,
+            }
+        );
+        assert_eq!(
+            StartQueueRequest::parse_json(r#"{"seed_kind":"local_radio","artist_spacing":-1}"#)
+                .unwrap_err()
+                .legacy_error_code(),
+            "invalid_request"
+        );
+    }
+
+    #[test]
+    fn queue_edit_request_parses_each_operation() {
+        assert_eq!(
+            QueueEditRequest::parse_json(
+                r#"{"version":3,"op":"insert","position":1,"items":[{"media_id":"yt:a","title":"A","artists":null}]}"#
+            )
+            .unwrap(),
+            (
+                3,
+                QueueEdit::Insert {
+                    position: 1,
+                    items: vec![QueueItem {
+                        media_id: MediaId::new("yt:a"),
+                        title: "A".into(),
+                        artists: vec![],
+                        duration_ms: 0,
+                        unavailable: false,
+                    }],
+                }
+            )
+        );
+        assert_eq!(
+            QueueEditRequest::parse_json(r#"{"version":1,"op":"move","from":2,"to":0}"#).unwrap(),
+            (1, QueueEdit::Move { from: 2, to: 0 })
+        );
+        assert_eq!(
+            QueueEditRequest::parse_json(r#"{"version":1,"op":"clear_after","position":4}"#)
+                .unwrap(),
+            (1, QueueEdit::ClearAfter { position: 4 })
+        );
+        for raw in [
+            r#"{"op":"remove","position":0}"#,
+            r#"{"version":1,"op":"remove"}"#,
+            r#"{"version":1,"op":"remove","position":-1}"#,
+            r#"{"version":1,"op":"append","items":[]}"#,
+            r#"{"version":1,"op":"append","items":[{"media_id":""}]}"#,
+            r#"{"version":1,"op":"move","from":1}"#,
+            r#"{"version":1,"op":"shuffle"}"#,
+        ] {
+            assert_eq!(
+                QueueEditRequest::parse_json(raw)
+                    .unwrap_err()
+                    .legacy_error_code(),
+                "invalid_request",
+                "{raw}"
+            );
+        }
+    }
+
     #[test]
     fn next_response_preserves_legacy_fields_and_adds_documented_stream_lookahead() {
         let value = serde_json::to_value(NextResponse {
             queue_id: "018f3f27-0000-7000-8000-000000000001".into(),
             position: 0,
+            position_ms: 0,
             current: Some(ResolvedStreamResponse {
                 media_id: "yt:abc".into(),
                 title: "Song".into(),
@@ -2287,6 +3107,9 @@ mod tests {
             automix: vec![],
             queue_version: 7,
             has_more: true,
+            shuffle: false,
+            repeat: RepeatMode::Off,
+            lookahead_positions: vec![],
         })
         .unwrap();
 
@@ -2339,12 +3162,16 @@ mod tests {
         let value = serde_json::to_value(NextResponse {
             queue_id: "018f3f27-0000-7000-8000-000000000001".into(),
             position: 9,
+            position_ms: 0,
             current: None,
             lookahead: vec![],
             continuation: None,
             automix: vec![],
             queue_version: 3,
             has_more: false,
+            shuffle: false,
+            repeat: RepeatMode::Off,
+            lookahead_positions: vec![],
         })
         .unwrap();
 
@@ -1,14 +1,15 @@
 use crate::*;
 
-pub(crate) fn default_innertube_backend(
+/// Builds the InnerTube client from the environment. Returns `None` when
+/// InnerTube is disabled.
+pub(crate) fn default_innertube_client(
     store: Option<PostgresStore>,
     cookie_key: Option<[u8; 32]>,
     cookie_file: Option<String>,
-) -> Option<Arc<dyn innertube::InnerTubeBackend>> {
-    if matches!(
-        env::var("SUNFLOWER_INNERTUBE_DISABLED").ok().as_deref(),
-        Some("1" | "true" | "TRUE" | "True")
-    ) {
+    parser_drift: Arc<ParserDriftRegistry>,
+    innertube_profiles: Arc<InnerTubeProfileHealth>,
+) -> Option<innertube::HttpInnerTubeClient> {
+    if env_flag("SUNFLOWER_INNERTUBE_DISABLED") {
         return None;
     }
     let locale = innertube::Locale {
@@ -21,7 +22,18 @@ pub(crate) fn default_innertube_backend(
         }
         _ => innertube::HttpInnerTubeClient::production(locale),
     }
-    .ok()?;
+    .ok()?
+    .with_parser_drift(parser_drift)
+    .with_profile_health(innertube_profiles)
+    .with_retry_policy(configured_innertube_retry_policy(
+        env::var("SUNFLOWER_INNERTUBE_MAX_ATTEMPTS").ok(),
+    ));
+    if let Some(url) = env::var("SUNFLOWER_YT_PLAYER_JS_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+    {
+        client = client.with_player_js_url(url);
+    }
     if cookie_key.is_some() || cookie_file.is_some() {
         client = client.with_cookie_provider(Arc::new(YoutubeCookieProvider::new(
             store.clone(),
@@ -34,29 +46,111 @@ pub(crate) fn default_innertube_backend(
             YoutubeInnerTubeTokenProvider::new(store, key),
         ));
     }
-    Some(Arc::new(client))
+    Some(client)
+}
+
+/// The fixture directory to serve InnerTube from instead of the network,
+/// when `SUNFLOWER_INNERTUBE_REPLAY_DIR` is set and InnerTube is enabled.
+pub(crate) fn configured_innertube_replay_dir() -> Option<String> {
+    if env_flag("SUNFLOWER_INNERTUBE_DISABLED") {
+        return None;
+    }
+    env::var("SUNFLOWER_INNERTUBE_REPLAY_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+}
+
+/// Replayed fixtures are served as recorded, without the response cache.
+pub(crate) fn replay_innertube_backend(dir: &str) -> Option<Arc<dyn innertube::InnerTubeBackend>> {
+    match ReplayInnerTube::from_dir(dir) {
+        Ok(replay) => Some(Arc::new(replay)),
+        Err(err) => {
+            eprintln!("innertube replay fixtures: {err}");
+            None
+        }
+    }
+}
+
+/// Wraps `client` in fixture recording when `SUNFLOWER_INNERTUBE_RECORD_DIR`
+/// is set, then in the response cache unless caching is disabled. Returns
+/// `None` when the fixture directory can't be used.
+pub(crate) fn default_innertube_backend(
+    client: innertube::HttpInnerTubeClient,
+) -> Option<Arc<dyn innertube::InnerTubeBackend>> {
+    let locale = client.locale().clone();
+    let backend: Arc<dyn innertube::InnerTubeBackend> =
+        match env::var("SUNFLOWER_INNERTUBE_RECORD_DIR")
+            .ok()
+            .filter(|dir| !dir.is_empty())
+        {
+            Some(dir) => match RecordingInnerTube::new(client, dir) {
+                Ok(recorder) => Arc::new(recorder),
+                Err(err) => {
+                    eprintln!("innertube record fixtures: {err}");
+                    return None;
+                }
+            },
+            None => Arc::new(client),
+        };
+    if env_flag("SUNFLOWER_INNERTUBE_CACHE_DISABLED") {
+        return Some(backend);
+    }
+    Some(Arc::new(CachedInnerTube::new(
+        backend,
+        locale,
+        InnerTubeCacheConfig::default(),
+    )))
 }
 
+fn env_flag(name: &str) -> bool {
+    matches!(
+        env::var(name).ok().as_deref(),
+        Some("1" | "true" | "TRUE" | "True")
+    )
+}
+
+pub(crate) fn configured_innertube_retry_policy(max_attempts: Option<String>) -> RetryPolicy {
+    let mut policy = RetryPolicy::default();
+    if let Some(max_attempts) = max_attempts
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .filter(|value| *value > 0)
+    {
+        policy.max_attempts = max_attempts;
+    }
+    policy
+}
+
+/// Lookups keyed by user, each remembered for a minute.
+type PerUserCache<T> = Mutex<HashMap<Option<Uuid>, (SystemTime, Option<T>)>>;
+
 pub(crate) struct YoutubeCookieProvider {
     store: Option<PostgresStore>,
     key: Option<[u8; 32]>,
     file: Option<String>,
-    cache: Mutex<Option<(SystemTime, Option<String>)>>,
+    cache: PerUserCache<String>,
+    degraded_reported_at: Mutex<HashMap<Option<Uuid>, SystemTime>>,
 }
 
 impl YoutubeCookieProvider {
-    fn new(store: Option<PostgresStore>, key: Option<[u8; 32]>, file: Option<String>) -> Self {
+    pub(crate) fn new(
+        store: Option<PostgresStore>,
+        key: Option<[u8; 32]>,
+        file: Option<String>,
+    ) -> Self {
         Self {
             store,
             key,
             file,
-            cache: Mutex::new(None),
+            cache: Mutex::default(),
+            degraded_reported_at: Mutex::default(),
         }
     }
 
-    async fn load_cookie_header(&self) -> Option<String> {
+    /// The user's cookies, then the owner's, then the cookie file; `None`
+    /// means guest mode.
+    async fn load_cookie_header(&self, user_id: Option<Uuid>) -> Option<String> {
         if let (Some(store), Some(key)) = (&self.store, self.key)
-            && let Ok(Some(raw)) = store.load_first_youtube_cookies(key).await
+            && let Ok(Some(raw)) = store.load_youtube_cookies_for_user(user_id, key).await
             && let Some(header) = parse_youtube_cookie_header(&raw)
         {
             return Some(header);
@@ -71,27 +165,79 @@ impl YoutubeCookieProvider {
 }
 
 impl innertube::CookieProvider for YoutubeCookieProvider {
-    fn cookie_header<'a>(&'a self) -> BoxFuture<'a, Option<String>> {
+    fn cookie_header<'a>(&'a self, user_id: Option<Uuid>) -> BoxFuture<'a, Option<String>> {
         Box::pin(async move {
-            if let Some((fetched_at, cached)) =
-                self.cache.lock().ok().and_then(|cache| cache.clone())
+            if let Some((fetched_at, cached)) = self
+                .cache
+                .lock()
+                .ok()
+                .and_then(|cache| cache.get(&user_id).cloned())
                 && fetched_at.elapsed().unwrap_or_default() < Duration::from_secs(60)
             {
                 return cached;
             }
-            let loaded = self.load_cookie_header().await;
+            let loaded = self.load_cookie_header(user_id).await;
             if let Ok(mut cache) = self.cache.lock() {
-                *cache = Some((SystemTime::now(), loaded.clone()));
+                cache.insert(user_id, (SystemTime::now(), loaded.clone()));
             }
             loaded
         })
     }
+
+    fn own_cookie_header<'a>(&'a self, user_id: Uuid) -> BoxFuture<'a, Option<String>> {
+        Box::pin(async move {
+            let (Some(store), Some(key)) = (&self.store, self.key) else {
+                return None;
+            };
+            store
+                .load_own_youtube_cookies(user_id, key)
+                .await
+                .ok()
+                .flatten()
+                .and_then(|raw| parse_youtube_cookie_header(&raw))
+        })
+    }
+
+    fn report_guest_fallback<'a>(
+        &'a self,
+        user_id: Option<Uuid>,
+        detail: String,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            // Every call falls back while cookies stay bad; one health write
+            // per user every few minutes is enough.
+            let should_report = match self.degraded_reported_at.lock() {
+                Ok(mut reported_at) => {
+                    let recent = reported_at
+                        .get(&user_id)
+                        .and_then(|at| at.elapsed().ok())
+                        .is_some_and(|elapsed| elapsed < Duration::from_secs(5 * 60));
+                    if !recent {
+                        reported_at.insert(user_id, SystemTime::now());
+                    }
+                    !recent
+                }
+                Err(_) => false,
+            };
+            if !should_report {
+                return;
+            }
+            eprintln!("youtube cookies: falling back to guest mode: {detail}");
+            // Health belongs to the account whose stored cookies were sent;
+            // cookies from the file have no row to mark.
+            if let Some(store) = &self.store
+                && let Ok(Some(owner)) = store.youtube_cookies_owner(user_id).await
+            {
+                let _ = store.mark_youtube_cookies_degraded(owner, &detail).await;
+            }
+        })
+    }
 }
 
 pub(crate) struct YoutubeInnerTubeTokenProvider {
     store: PostgresStore,
     key: [u8; 32],
-    cache: Mutex<Option<(SystemTime, Option<innertube::InnerTubeToken>)>>,
+    cache: PerUserCache<innertube::InnerTubeToken>,
 }
 
 impl YoutubeInnerTubeTokenProvider {
@@ -99,13 +245,16 @@ impl YoutubeInnerTubeTokenProvider {
         Self {
             store,
             key,
-            cache: Mutex::new(None),
+            cache: Mutex::default(),
         }
     }
 
-    async fn load_innertube_token(&self) -> Option<innertube::InnerTubeToken> {
+    async fn load_innertube_token(
+        &self,
+        user_id: Option<Uuid>,
+    ) -> Option<innertube::InnerTubeToken> {
         self.store
-            .load_first_youtube_innertube_token(self.key)
+            .load_youtube_innertube_token_for_user(user_id, self.key)
             .await
             .ok()
             .flatten()
@@ -114,17 +263,23 @@ impl YoutubeInnerTubeTokenProvider {
 }
 
 impl innertube::InnerTubeTokenProvider for YoutubeInnerTubeTokenProvider {
-    fn innertube_token<'a>(&'a self) -> BoxFuture<'a, Option<innertube::InnerTubeToken>> {
+    fn innertube_token<'a>(
+        &'a self,
+        user_id: Option<Uuid>,
+    ) -> BoxFuture<'a, Option<innertube::InnerTubeToken>> {
         Box::pin(async move {
-            if let Some((fetched_at, cached)) =
-                self.cache.lock().ok().and_then(|cache| cache.clone())
+            if let Some((fetched_at, cached)) = self
+                .cache
+                .lock()
+                .ok()
+                .and_then(|cache| cache.get(&user_id).cloned())
                 && fetched_at.elapsed().unwrap_or_default() < Duration::from_secs(60)
             {
                 return cached;
             }
-            let loaded = self.load_innertube_token().await;
+            let loaded = self.load_innertube_token(user_id).await;
             if let Ok(mut cache) = self.cache.lock() {
-                *cache = Some((SystemTime::now(), loaded.clone()));
+                cache.insert(user_id, (SystemTime::now(), loaded.clone()));
             }
             loaded
         })
@@ -62,6 +62,7 @@ pub(crate) fn admin_html_page(
 <a href="/admin/cookies/youtube">Cookies</a>
 <a href="/admin/now-playing">Now Playing</a>
 <a href="/admin/audit">Audit</a>
+<a href="/admin/diagnostics">Diagnostics</a>
 </nav>
 <h1>{title}</h1>
 {flash_html}
@@ -1,12 +1,32 @@
-use std::{collections::HashSet, fmt, sync::Arc};
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use chrono::{DateTime, Utc};
 use futures_util::future::BoxFuture;
+use rand::Rng;
 use reqwest::StatusCode;
 use serde_json::{Value, json};
-use sunflower_core::{MediaId, QueueItem};
+use sunflower_core::{
+    ContentFlags, ExpandedRadio, LYRICS_SOURCE_YOUTUBE, Lyrics, LyricsLine, MediaId, QueueItem,
+    RADIO_MAX_PAGES,
+};
+use uuid::Uuid;
+
+use crate::{
+    innertube_policy::{InnerTubeProfileHealth, RetryPolicy},
+    innertube_sig::{
+        PlayerScript, PlayerScriptCache, player_hash, player_hash_from_iframe_api, player_js_url,
+    },
+    parser_drift::{ParserDrift, ParserDriftRegistry},
+};
 
 const DEFAULT_BASE_URL: &str = "https://music.youtube.com";
+/// `iframe_api` and `base.js` are served from the main site.
+const DEFAULT_PLAYER_BASE_URL: &str = "https://www.youtube.com";
 const ANDROID_MUSIC_CLIENT_NAME: &str = "ANDROID_MUSIC";
 const ANDROID_MUSIC_CLIENT_VERSION: &str = "7.27.52";
 const ANDROID_MUSIC_CLIENT_ID: &str = "21";
@@ -50,6 +70,9 @@ pub struct SongItem {
     /// Set when the item carries an `MUSIC_EXPLICIT_BADGE` inline badge.
     /// Defaults to `false` when the badge array is absent (optional-field tolerant).
     pub is_explicit: bool,
+    /// Set when the watch endpoint marks the item as a music video
+    /// (`MUSIC_VIDEO_TYPE_OMV`/`_UGC`) rather than a song (`_ATV`).
+    pub is_video: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -107,6 +130,26 @@ pub struct PlayerResponse {
     pub video_id: String,
     pub stream: StreamUrl,
     pub all_streams: Vec<StreamUrl>,
+    pub tracking: PlaybackTracking,
+    pub details: VideoDetails,
+}
+
+/// What `videoDetails` says about the track itself.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VideoDetails {
+    pub title: String,
+    pub author: String,
+    pub channel_id: String,
+    pub duration_ms: i32,
+    pub thumbnail_url: String,
+}
+
+/// `playbackTracking` base URLs; pinging them records the play in the
+/// signed-in account's history.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlaybackTracking {
+    pub playback_url: String,
+    pub watchtime_url: String,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -115,7 +158,7 @@ pub struct InnerTubeError {
 }
 
 impl InnerTubeError {
-    fn new(detail: impl Into<String>) -> Self {
+    pub(crate) fn new(detail: impl Into<String>) -> Self {
         Self {
             detail: detail.into(),
         }
@@ -149,6 +192,29 @@ pub trait InnerTubeBackend: Send + Sync {
         &'a self,
         video_id: &'a str,
     ) -> BoxFuture<'a, Result<PlayerResponse, InnerTubeError>>;
+
+    fn lyrics<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Lyrics>, InnerTubeError>>;
+
+    /// A backend whose calls use `user_id`'s YouTube credentials, or `None`
+    /// when this backend has no per-user credentials.
+    fn for_user(&self, _user_id: Uuid) -> Option<Arc<dyn InnerTubeBackend>> {
+        None
+    }
+
+    /// Reports `played_ms` of `video_id` to the signed-in account's history,
+    /// pinging `tracking` when an earlier player response left one.
+    /// `Ok(false)` means there is no account to report to.
+    fn report_playback<'a>(
+        &'a self,
+        _video_id: &'a str,
+        _played_ms: i64,
+        _tracking: Option<&'a PlaybackTracking>,
+    ) -> BoxFuture<'a, Result<bool, InnerTubeError>> {
+        Box::pin(async { Ok(false) })
+    }
 }
 
 #[derive(Clone)]
@@ -158,10 +224,36 @@ pub struct HttpInnerTubeClient {
     locale: Locale,
     cookie_provider: Option<Arc<dyn CookieProvider>>,
     token_provider: Option<Arc<dyn InnerTubeTokenProvider>>,
+    parser_drift: Option<Arc<ParserDriftRegistry>>,
+    retry_policy: RetryPolicy,
+    profile_health: Arc<InnerTubeProfileHealth>,
+    user_id: Option<Uuid>,
+    player_base_url: String,
+    /// Pinned `base.js` URL; skips the `iframe_api` lookup.
+    player_js_url: Option<String>,
+    player_scripts: Arc<PlayerScriptCache>,
 }
 
+/// Credentials are looked up for the user a call is made for; `None` means no
+/// user, e.g. background probes.
 pub trait CookieProvider: Send + Sync {
-    fn cookie_header<'a>(&'a self) -> BoxFuture<'a, Option<String>>;
+    fn cookie_header<'a>(&'a self, user_id: Option<Uuid>) -> BoxFuture<'a, Option<String>>;
+
+    /// Cookies `user_id` stored themselves, with no fallback to another
+    /// account's or the cookie file, for calls that act as that account.
+    fn own_cookie_header<'a>(&'a self, _user_id: Uuid) -> BoxFuture<'a, Option<String>> {
+        Box::pin(async { None })
+    }
+
+    /// Called when a signed-in request made for `user_id` was rejected or
+    /// answered as logged out and the client fell back to guest mode.
+    fn report_guest_fallback<'a>(
+        &'a self,
+        _user_id: Option<Uuid>,
+        _detail: String,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(async {})
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -171,26 +263,39 @@ pub struct InnerTubeToken {
 }
 
 pub trait InnerTubeTokenProvider: Send + Sync {
-    fn innertube_token<'a>(&'a self) -> BoxFuture<'a, Option<InnerTubeToken>>;
+    fn innertube_token<'a>(
+        &'a self,
+        user_id: Option<Uuid>,
+    ) -> BoxFuture<'a, Option<InnerTubeToken>>;
 }
 
 impl HttpInnerTubeClient {
     pub fn new(base_url: impl Into<String>, locale: Locale) -> Result<Self, InnerTubeError> {
         let http = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(12))
+            .timeout(Duration::from_secs(12))
             .build()
             .map_err(|err| InnerTubeError::new(format!("innertube client: {err}")))?;
+        let base_url = base_url.into();
         Ok(Self {
             http,
-            base_url: base_url.into(),
+            player_base_url: base_url.clone(),
+            player_js_url: None,
+            player_scripts: Arc::default(),
+            base_url,
             locale,
             cookie_provider: None,
             token_provider: None,
+            parser_drift: None,
+            retry_policy: RetryPolicy::default(),
+            profile_health: Arc::default(),
+            user_id: None,
         })
     }
 
     pub fn production(locale: Locale) -> Result<Self, InnerTubeError> {
-        Self::new(DEFAULT_BASE_URL, locale)
+        let mut client = Self::new(DEFAULT_BASE_URL, locale)?;
+        client.player_base_url = DEFAULT_PLAYER_BASE_URL.into();
+        Ok(client)
     }
 
     pub fn with_cookie_provider(mut self, provider: Arc<dyn CookieProvider>) -> Self {
@@ -206,43 +311,391 @@ impl HttpInnerTubeClient {
         self
     }
 
+    pub fn with_parser_drift(mut self, registry: Arc<ParserDriftRegistry>) -> Self {
+        self.parser_drift = Some(registry);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    pub fn with_profile_health(mut self, health: Arc<InnerTubeProfileHealth>) -> Self {
+        self.profile_health = health;
+        self
+    }
+
+    /// Uses this `base.js` instead of the one `iframe_api` points at, for
+    /// when the current player breaks the parser.
+    pub fn with_player_js_url(mut self, url: impl Into<String>) -> Self {
+        self.player_js_url = Some(url.into());
+        self
+    }
+
+    pub fn locale(&self) -> &Locale {
+        &self.locale
+    }
+
+    /// A copy of this client that calls on behalf of `user_id`; `None` when
+    /// there are no per-user credentials to look up.
+    pub(crate) fn for_user_client(&self, user_id: Uuid) -> Option<Self> {
+        if self.cookie_provider.is_none() && self.token_provider.is_none() {
+            return None;
+        }
+        Some(Self {
+            user_id: Some(user_id),
+            ..self.clone()
+        })
+    }
+
+    /// `InnerTubeCall::player` with the signature timestamp of the current
+    /// `base.js`, once one has been fetched.
+    pub(crate) fn player_call(&self, video_id: &str) -> InnerTubeCall {
+        let mut call = InnerTubeCall::player(video_id);
+        if let Some(timestamp) = self
+            .player_scripts
+            .current()
+            .and_then(|script| script.signature_timestamp)
+        {
+            call.fields["playbackContext"] =
+                json!({ "contentPlaybackContext": { "signatureTimestamp": timestamp } });
+        }
+        call
+    }
+
+    pub(crate) async fn send(&self, call: &InnerTubeCall) -> Result<Value, InnerTubeError> {
+        self.post(call.path, call.profiles, call.fields.clone())
+            .await
+    }
+
+    pub(crate) fn parse_browse(&self, raw: &Value) -> HomePage {
+        self.parse_with_drift("browse", raw, parse_home_page)
+    }
+
+    pub(crate) fn parse_search(&self, raw: &Value) -> SearchPage {
+        self.parse_with_drift("search", raw, parse_search_page)
+    }
+
+    pub(crate) fn parse_next(&self, raw: &Value) -> NextPage {
+        self.parse_with_drift("next", raw, parse_next_page)
+    }
+
+    /// Fetches `base.js` first when the formats in `raw` are ciphered.
+    pub(crate) async fn parse_player(&self, video_id: &str, raw: &Value) -> PlayerResponse {
+        let script = if player_formats_need_script(raw) {
+            match self.player_script().await {
+                Ok(script) => Some(script),
+                Err(err) => {
+                    eprintln!("innertube player {video_id}: {err}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        self.parse_with_drift("player", raw, |raw, drift| {
+            parse_player_response(raw, drift, script.as_deref())
+        })
+    }
+
+    fn parse_with_drift<T>(
+        &self,
+        endpoint: &str,
+        raw: &Value,
+        parse: impl FnOnce(&Value, &mut ParserDrift) -> T,
+    ) -> T {
+        let mut drift = ParserDrift::default();
+        let parsed = parse(raw, &mut drift);
+        if let Some(registry) = &self.parser_drift {
+            registry.record(endpoint, drift);
+        }
+        parsed
+    }
+
+    /// Posts `fields` to `path` with the first healthy profile in `profiles`,
+    /// retrying 429/5xx/transport errors with backoff and failing over to the
+    /// next profile once retries are spent. A 401/403 on a signed-in request is
+    /// retried once as guest, without cookies or token.
     async fn post(
         &self,
         path: &str,
-        profile: ClientProfile,
-        mut payload: Value,
+        profiles: &[ClientProfile],
+        fields: Value,
     ) -> Result<Value, InnerTubeError> {
+        let deadline = Instant::now() + self.retry_policy.deadline;
+        let (token, cookie_header) = self.credentials().await;
+        let signed_in = token.is_some() || cookie_header.is_some();
+
+        let mut last_err =
+            InnerTubeError::new(format!("innertube post {path}: every profile is paused"));
+        for &profile in profiles {
+            if Instant::now() >= deadline {
+                break;
+            }
+            // Asked per profile, so a half-open probe is only taken when the
+            // profile is actually tried.
+            if !self.profile_health.allows(profile.name, &self.retry_policy) {
+                continue;
+            }
+            let mut result = self
+                .post_profile(
+                    path,
+                    profile,
+                    &fields,
+                    token.as_ref(),
+                    cookie_header.as_deref(),
+                    deadline,
+                )
+                .await;
+            if signed_in && let Err(PostError::Unauthorized(err)) = &result {
+                self.report_guest_fallback(format!("{err}; retried as guest"))
+                    .await;
+                result = self
+                    .post_profile(path, profile, &fields, None, None, deadline)
+                    .await;
+            }
+            match result {
+                Ok(raw) => {
+                    self.profile_health.record_success(profile.name);
+                    if signed_in && response_logged_out(&raw) {
+                        // The response is already a guest response; no need
+                        // to ask again without credentials.
+                        self.report_guest_fallback(format!(
+                            "innertube post {path} ({}): signed-in request answered as logged out",
+                            profile.name
+                        ))
+                        .await;
+                    }
+                    return Ok(raw);
+                }
+                Err(PostError::Fatal(err) | PostError::Unauthorized(err)) => return Err(err),
+                Err(PostError::Retryable(err)) => {
+                    self.profile_health
+                        .record_failure(profile.name, &self.retry_policy);
+                    last_err = err;
+                }
+            }
+        }
+        Err(last_err)
+    }
+
+    async fn credentials(&self) -> (Option<InnerTubeToken>, Option<String>) {
         let token = match &self.token_provider {
-            Some(provider) => provider.innertube_token().await,
+            Some(provider) => provider.innertube_token(self.user_id).await,
+            None => None,
+        };
+        let cookie_header = match &self.cookie_provider {
+            Some(provider) => provider
+                .cookie_header(self.user_id)
+                .await
+                .filter(|value| !value.is_empty()),
             None => None,
         };
+        (token, cookie_header)
+    }
+
+    /// Makes one signed-in `next` call for `video_id` to check whether the
+    /// stored cookies still work. Unlike regular calls it never retries,
+    /// fails over, or falls back to guest mode, and it skips the breakers.
+    pub async fn probe_cookies(&self, video_id: &str) -> CookieProbe {
+        let (token, cookie_header) = self.credentials().await;
+        if token.is_none() && cookie_header.is_none() {
+            return CookieProbe::NoCredentials;
+        }
+        let profile = ANDROID_MUSIC_PROFILE;
+        let url = format!("{}/youtubei/v1/next?key={}", self.base_url, profile.api_key);
+        let mut payload = profile_payload(profile, &self.locale, &json!({ "videoId": video_id }));
         if let Some(token) = &token {
             apply_innertube_token(&mut payload, token);
         }
-        let url = format!("{}{}?key={}", self.base_url, path, profile.api_key);
-        let body = payload.to_string();
         let visitor_data = token
             .as_ref()
             .map(|token| token.visitor_data.as_str())
             .filter(|value| !value.is_empty());
-        let mut response = self
-            .post_once(&url, profile, body.clone(), visitor_data)
-            .await?;
-        if response.status().as_u16() >= 500 {
-            response = self.post_once(&url, profile, body, visitor_data).await?;
+        let response = match self
+            .post_once(
+                &url,
+                profile,
+                payload.to_string(),
+                visitor_data,
+                cookie_header.as_deref(),
+                self.retry_policy.attempt_timeout,
+            )
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => return CookieProbe::Failed(err.to_string()),
+        };
+        let status = response.status();
+        if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+            return CookieProbe::Rejected(format!("next: status {status}"));
+        }
+        if status != StatusCode::OK {
+            return CookieProbe::Failed(format!("next: status {status}"));
         }
+        let raw = match response
+            .bytes()
+            .await
+            .map_err(|err| err.to_string())
+            .and_then(|body| serde_json::from_slice::<Value>(&body).map_err(|err| err.to_string()))
+        {
+            Ok(raw) => raw,
+            Err(err) => return CookieProbe::Failed(format!("next: {err}")),
+        };
+        if response_logged_out(&raw) {
+            return CookieProbe::Rejected("next: signed-in request answered as logged out".into());
+        }
+        CookieProbe::SignedIn
+    }
+
+    /// The parsed `base.js` for the current player, fetched and parsed once
+    /// per player hash.
+    async fn player_script(&self) -> Result<Arc<PlayerScript>, InnerTubeError> {
+        let url = match &self.player_js_url {
+            Some(url) => url.clone(),
+            None => {
+                let hash = match self.player_scripts.current_hash() {
+                    Some(hash) => hash,
+                    None => {
+                        let body = self
+                            .get_text(&format!("{}/iframe_api", self.player_base_url))
+                            .await?;
+                        let hash = player_hash_from_iframe_api(&body).ok_or_else(|| {
+                            InnerTubeError::new("innertube iframe_api: player hash not found")
+                        })?;
+                        self.player_scripts.set_current(&hash);
+                        hash
+                    }
+                };
+                player_js_url(&self.player_base_url, &hash)
+            }
+        };
+        let hash = player_hash(&url).unwrap_or(&url).to_string();
+        if let Some(script) = self.player_scripts.get(&hash) {
+            return Ok(script);
+        }
+        let script = PlayerScript::parse(&self.get_text(&url).await?)?;
+        Ok(self.player_scripts.insert(&hash, script))
+    }
+
+    async fn get_text(&self, url: &str) -> Result<String, InnerTubeError> {
+        let response = self
+            .http
+            .get(url)
+            .timeout(self.retry_policy.attempt_timeout)
+            .header(reqwest::header::USER_AGENT, WEB_REMIX_USER_AGENT)
+            .send()
+            .await
+            .map_err(|err| InnerTubeError::new(format!("innertube get {url}: {err}")))?;
         if response.status() != StatusCode::OK {
             return Err(InnerTubeError::new(format!(
-                "innertube post {path}: status {}",
+                "innertube get {url}: status {}",
                 response.status()
             )));
         }
-        let body = response
-            .bytes()
+        response
+            .text()
+            .await
+            .map_err(|err| InnerTubeError::new(format!("innertube get {url}: {err}")))
+    }
+
+    async fn ping(&self, url: &str, cookie_header: &str) -> Result<(), InnerTubeError> {
+        let response = self
+            .http
+            .get(url)
+            .timeout(self.retry_policy.attempt_timeout)
+            .header(reqwest::header::USER_AGENT, WEB_REMIX_USER_AGENT)
+            .header(reqwest::header::COOKIE, cookie_header)
+            .send()
             .await
-            .map_err(|err| InnerTubeError::new(format!("innertube post {path}: {err}")))?;
-        serde_json::from_slice(&body)
-            .map_err(|err| InnerTubeError::new(format!("innertube post {path}: {err}")))
+            .map_err(|err| InnerTubeError::new(format!("innertube tracking: {err}")))?;
+        if !response.status().is_success() {
+            return Err(InnerTubeError::new(format!(
+                "innertube tracking: status {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+
+    async fn report_guest_fallback(&self, detail: String) {
+        if let Some(provider) = &self.cookie_provider {
+            provider.report_guest_fallback(self.user_id, detail).await;
+        }
+    }
+
+    async fn post_profile(
+        &self,
+        path: &str,
+        profile: ClientProfile,
+        fields: &Value,
+        token: Option<&InnerTubeToken>,
+        cookie_header: Option<&str>,
+        deadline: Instant,
+    ) -> Result<Value, PostError> {
+        let url = format!("{}{}?key={}", self.base_url, path, profile.api_key);
+        let mut payload = profile_payload(profile, &self.locale, fields);
+        if let Some(token) = token {
+            apply_innertube_token(&mut payload, token);
+        }
+        let body = payload.to_string();
+        let visitor_data = token
+            .map(|token| token.visitor_data.as_str())
+            .filter(|value| !value.is_empty());
+        let mut attempt = 1;
+        loop {
+            let timeout = self
+                .retry_policy
+                .attempt_timeout
+                .min(deadline.saturating_duration_since(Instant::now()));
+            let err = match self
+                .post_once(
+                    &url,
+                    profile,
+                    body.clone(),
+                    visitor_data,
+                    cookie_header,
+                    timeout,
+                )
+                .await
+            {
+                Ok(response) if response.status() == StatusCode::OK => {
+                    let body = response.bytes().await.map_err(|err| {
+                        PostError::Retryable(InnerTubeError::new(format!(
+                            "innertube post {path}: {err}"
+                        )))
+                    })?;
+                    return serde_json::from_slice(&body).map_err(|err| {
+                        PostError::Fatal(InnerTubeError::new(format!(
+                            "innertube post {path}: {err}"
+                        )))
+                    });
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let err = InnerTubeError::new(format!(
+                        "innertube post {path} ({}): status {status}",
+                        profile.name
+                    ));
+                    if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+                        return Err(PostError::Unauthorized(err));
+                    }
+                    if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+                        return Err(PostError::Fatal(err));
+                    }
+                    err
+                }
+                Err(err) => err,
+            };
+            let backoff = self.retry_policy.backoff(attempt);
+            if attempt >= self.retry_policy.max_attempts || Instant::now() + backoff >= deadline {
+                return Err(PostError::Retryable(err));
+            }
+            self.profile_health.record_retry(profile.name);
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
     }
 
     async fn post_once(
@@ -251,10 +704,13 @@ impl HttpInnerTubeClient {
         profile: ClientProfile,
         body: String,
         visitor_data: Option<&str>,
+        cookie_header: Option<&str>,
+        timeout: Duration,
     ) -> Result<reqwest::Response, InnerTubeError> {
         let mut request = self
             .http
             .post(url)
+            .timeout(timeout)
             .header(reqwest::header::CONTENT_TYPE, "application/json")
             .header(reqwest::header::USER_AGENT, profile.user_agent)
             .header("X-YouTube-Client-Name", profile.client_name_id)
@@ -263,19 +719,49 @@ impl HttpInnerTubeClient {
         if let Some(visitor_data) = visitor_data {
             request = request.header("X-Goog-Visitor-Id", visitor_data);
         }
-        if let Some(provider) = &self.cookie_provider
-            && let Some(cookie_header) = provider
-                .cookie_header()
-                .await
-                .filter(|value| !value.is_empty())
-        {
+        if let Some(cookie_header) = cookie_header {
             request = request.header(reqwest::header::COOKIE, cookie_header);
         }
         request
             .send()
             .await
-            .map_err(|err| InnerTubeError::new(format!("innertube post: {err}")))
+            .map_err(|err| InnerTubeError::new(format!("innertube post ({}): {err}", profile.name)))
+    }
+}
+
+/// Outcome of `HttpInnerTubeClient::probe_cookies`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CookieProbe {
+    /// Neither cookies nor a token are configured; there is nothing to probe.
+    NoCredentials,
+    SignedIn,
+    /// 401/403, or the response came back logged out.
+    Rejected(String),
+    /// Transport error or unexpected status; says nothing about the cookies.
+    Failed(String),
+}
+
+enum PostError {
+    /// Not worth retrying or failing over, e.g. a 400 for a bad continuation.
+    Fatal(InnerTubeError),
+    Retryable(InnerTubeError),
+    /// 401/403: the credentials were rejected.
+    Unauthorized(InnerTubeError),
+}
+
+fn response_logged_out(raw: &Value) -> bool {
+    get_map(raw, &["responseContext", "mainAppWebResponseContext"])
+        .and_then(|context| context.get("loggedOut"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+fn profile_payload(profile: ClientProfile, locale: &Locale, fields: &Value) -> Value {
+    let mut payload = (profile.context)(locale);
+    for (key, value) in fields.as_object().into_iter().flatten() {
+        set_field(&mut payload, key, value.clone());
     }
+    payload
 }
 
 impl InnerTubeBackend for HttpInnerTubeClient {
@@ -285,24 +771,17 @@ impl InnerTubeBackend for HttpInnerTubeClient {
         continuation: Option<&'a str>,
     ) -> BoxFuture<'a, Result<HomePage, InnerTubeError>> {
         Box::pin(async move {
-            let mut body = build_web_remix_context(&self.locale);
-            set_field(&mut body, "browseId", json!(browse_id));
-            if let Some(continuation) = continuation.filter(|value| !value.is_empty()) {
-                set_field(&mut body, "continuation", json!(continuation));
-            }
-            self.post("/youtubei/v1/browse", WEB_REMIX_PROFILE, body)
-                .await
-                .map(|raw| parse_home_page(&raw))
+            let raw = self
+                .send(&InnerTubeCall::browse(browse_id, continuation))
+                .await?;
+            Ok(self.parse_browse(&raw))
         })
     }
 
     fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<SearchPage, InnerTubeError>> {
         Box::pin(async move {
-            let mut body = build_web_remix_context(&self.locale);
-            set_field(&mut body, "query", json!(query));
-            self.post("/youtubei/v1/search", WEB_REMIX_PROFILE, body)
-                .await
-                .map(|raw| parse_search_page(&raw))
+            let raw = self.send(&InnerTubeCall::search(query)).await?;
+            Ok(self.parse_search(&raw))
         })
     }
 
@@ -312,14 +791,10 @@ impl InnerTubeBackend for HttpInnerTubeClient {
         continuation: Option<&'a str>,
     ) -> BoxFuture<'a, Result<NextPage, InnerTubeError>> {
         Box::pin(async move {
-            let mut body = build_android_music_context(&self.locale);
-            set_field(&mut body, "videoId", json!(video_id));
-            if let Some(continuation) = continuation.filter(|value| !value.is_empty()) {
-                set_field(&mut body, "continuation", json!(continuation));
-            }
-            self.post("/youtubei/v1/next", ANDROID_MUSIC_PROFILE, body)
-                .await
-                .map(|raw| parse_next_page(&raw))
+            let raw = self
+                .send(&InnerTubeCall::next(video_id, continuation))
+                .await?;
+            Ok(self.parse_next(&raw))
         })
     }
 
@@ -328,18 +803,99 @@ impl InnerTubeBackend for HttpInnerTubeClient {
         video_id: &'a str,
     ) -> BoxFuture<'a, Result<PlayerResponse, InnerTubeError>> {
         Box::pin(async move {
-            let mut body = build_android_vr_context(&self.locale);
-            set_field(&mut body, "videoId", json!(video_id));
-            set_field(&mut body, "params", json!("CgIQBg=="));
-            self.post("/youtubei/v1/player", ANDROID_VR_PROFILE, body)
+            let raw = self.send(&self.player_call(video_id)).await?;
+            Ok(self.parse_player(video_id, &raw).await)
+        })
+    }
+
+    fn lyrics<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Lyrics>, InnerTubeError>> {
+        Box::pin(async move {
+            let next = self.send(&InnerTubeCall::next(video_id, None)).await?;
+            let Some(browse_id) = extract_lyrics_browse_id(&next) else {
+                return Ok(None);
+            };
+            let raw = self.send(&InnerTubeCall::lyrics(&browse_id)).await?;
+            Ok(parse_lyrics_page(&raw))
+        })
+    }
+
+    fn for_user(&self, user_id: Uuid) -> Option<Arc<dyn InnerTubeBackend>> {
+        self.for_user_client(user_id)
+            .map(|client| Arc::new(client) as Arc<dyn InnerTubeBackend>)
+    }
+
+    fn report_playback<'a>(
+        &'a self,
+        video_id: &'a str,
+        played_ms: i64,
+        tracking: Option<&'a PlaybackTracking>,
+    ) -> BoxFuture<'a, Result<bool, InnerTubeError>> {
+        Box::pin(async move {
+            // Reports land in the history of the account whose cookies they
+            // carry, so a user without their own cookies is not reported.
+            let (Some(user_id), Some(provider)) = (self.user_id, &self.cookie_provider) else {
+                return Ok(false);
+            };
+            let Some(cookie_header) = provider
+                .own_cookie_header(user_id)
                 .await
-                .map(|raw| parse_player_response(&raw))
+                .filter(|value| !value.is_empty())
+            else {
+                return Ok(false);
+            };
+            let tracking = match tracking.filter(|tracking| !tracking.playback_url.is_empty()) {
+                Some(tracking) => tracking.clone(),
+                None => {
+                    let raw = self
+                        .post(
+                            "/youtubei/v1/player",
+                            &[WEB_REMIX_PROFILE],
+                            json!({ "videoId": video_id }),
+                        )
+                        .await?;
+                    parse_playback_tracking(&raw)
+                }
+            };
+            if tracking.playback_url.is_empty() {
+                return Err(InnerTubeError::new(format!(
+                    "innertube tracking {video_id}: no playback url"
+                )));
+            }
+            let cpn = client_playback_nonce();
+            let played = format!("{:.3}", played_ms.max(0) as f64 / 1000.0);
+            self.ping(
+                &tracking_url(&tracking.playback_url, &[("ver", "2"), ("cpn", &cpn)])?,
+                &cookie_header,
+            )
+            .await?;
+            if !tracking.watchtime_url.is_empty() {
+                self.ping(
+                    &tracking_url(
+                        &tracking.watchtime_url,
+                        &[
+                            ("ver", "2"),
+                            ("cpn", &cpn),
+                            ("st", "0"),
+                            ("et", &played),
+                            ("cmt", &played),
+                        ],
+                    )?,
+                    &cookie_header,
+                )
+                .await?;
+            }
+            Ok(true)
         })
     }
 }
 
 #[derive(Clone, Copy)]
 struct ClientProfile {
+    name: &'static str,
+    context: fn(&Locale) -> Value,
     api_key: &'static str,
     user_agent: &'static str,
     client_name_id: &'static str,
@@ -347,6 +903,8 @@ struct ClientProfile {
 }
 
 const ANDROID_MUSIC_PROFILE: ClientProfile = ClientProfile {
+    name: ANDROID_MUSIC_CLIENT_NAME,
+    context: build_android_music_context,
     api_key: ANDROID_MUSIC_API_KEY,
     user_agent: ANDROID_MUSIC_USER_AGENT,
     client_name_id: ANDROID_MUSIC_CLIENT_ID,
@@ -354,6 +912,8 @@ const ANDROID_MUSIC_PROFILE: ClientProfile = ClientProfile {
 };
 
 const ANDROID_VR_PROFILE: ClientProfile = ClientProfile {
+    name: ANDROID_VR_CLIENT_NAME,
+    context: build_android_vr_context,
     api_key: ANDROID_MUSIC_API_KEY,
     user_agent: ANDROID_VR_USER_AGENT,
     client_name_id: ANDROID_VR_CLIENT_ID,
@@ -361,61 +921,250 @@ const ANDROID_VR_PROFILE: ClientProfile = ClientProfile {
 };
 
 const WEB_REMIX_PROFILE: ClientProfile = ClientProfile {
+    name: WEB_REMIX_CLIENT_NAME,
+    context: build_web_remix_context,
     api_key: WEB_REMIX_API_KEY,
     user_agent: WEB_REMIX_USER_AGENT,
     client_name_id: WEB_REMIX_CLIENT_ID,
     client_version: WEB_REMIX_CLIENT_VERSION,
 };
 
+// Failover chains only pair profiles whose responses share a layout the
+// parsers understand. Browse and search stay on WEB_REMIX: the mobile clients
+// return different home/search renderers.
+const NEXT_PROFILES: &[ClientProfile] = &[ANDROID_MUSIC_PROFILE, WEB_REMIX_PROFILE];
+// WEB_REMIX comes last for `player`: its formats are ciphered and need
+// `base.js`.
+const PLAYER_PROFILES: &[ClientProfile] =
+    &[ANDROID_VR_PROFILE, ANDROID_MUSIC_PROFILE, WEB_REMIX_PROFILE];
+
+/// One InnerTube request before the client context is added: the endpoint,
+/// the profiles to try it with, and the request fields.
+#[derive(Clone)]
+pub(crate) struct InnerTubeCall {
+    pub(crate) path: &'static str,
+    profiles: &'static [ClientProfile],
+    pub(crate) fields: Value,
+}
+
+impl InnerTubeCall {
+    pub(crate) fn browse(browse_id: &str, continuation: Option<&str>) -> Self {
+        let mut fields = json!({ "browseId": browse_id });
+        if let Some(continuation) = continuation.filter(|value| !value.is_empty()) {
+            set_field(&mut fields, "continuation", json!(continuation));
+        }
+        Self {
+            path: "/youtubei/v1/browse",
+            profiles: &[WEB_REMIX_PROFILE],
+            fields,
+        }
+    }
+
+    pub(crate) fn search(query: &str) -> Self {
+        Self {
+            path: "/youtubei/v1/search",
+            profiles: &[WEB_REMIX_PROFILE],
+            fields: json!({ "query": query }),
+        }
+    }
+
+    pub(crate) fn next(video_id: &str, continuation: Option<&str>) -> Self {
+        let mut fields = json!({ "videoId": video_id });
+        if let Some(continuation) = continuation.filter(|value| !value.is_empty()) {
+            set_field(&mut fields, "continuation", json!(continuation));
+        }
+        Self {
+            path: "/youtubei/v1/next",
+            profiles: NEXT_PROFILES,
+            fields,
+        }
+    }
+
+    pub(crate) fn player(video_id: &str) -> Self {
+        Self {
+            path: "/youtubei/v1/player",
+            profiles: PLAYER_PROFILES,
+            fields: json!({ "videoId": video_id, "params": "CgIQBg==" }),
+        }
+    }
+
+    /// The mobile client is the one that returns timed lyrics.
+    pub(crate) fn lyrics(browse_id: &str) -> Self {
+        Self {
+            path: "/youtubei/v1/browse",
+            profiles: &[ANDROID_MUSIC_PROFILE],
+            fields: json!({ "browseId": browse_id }),
+        }
+    }
+}
+
 pub async fn expand_radio(
     backend: &dyn InnerTubeBackend,
     seed_video_id: &str,
     min_items: usize,
-) -> Result<Vec<QueueItem>, InnerTubeError> {
+) -> Result<ExpandedRadio, InnerTubeError> {
     let page = backend.next(seed_video_id, None).await?;
-    let mut items = Vec::with_capacity(min_items);
+    let mut radio = ExpandedRadio {
+        items: Vec::with_capacity(min_items),
+        ..ExpandedRadio::default()
+    };
+    let mut seen = HashSet::new();
+    add_songs(&mut radio, &mut seen, page.related);
+    radio.continuation = follow_radio(
+        backend,
+        seed_video_id,
+        page.continuation,
+        &mut radio,
+        &mut seen,
+        min_items,
+    )
+    .await;
+    Ok(radio)
+}
+
+/// The songs InnerTube relates to `video_id`: the first page of its radio,
+/// without following continuations.
+pub async fn related_songs(
+    backend: &dyn InnerTubeBackend,
+    video_id: &str,
+) -> Result<Vec<QueueItem>, InnerTubeError> {
+    let page = backend.next(video_id, None).await?;
+    let mut related = ExpandedRadio::default();
+    add_songs(&mut related, &mut HashSet::new(), page.related);
+    Ok(related.items)
+}
+
+/// The tracks of a YouTube Music playlist or album page, in page order. The
+/// result never has a continuation.
+pub async fn playlist_items(
+    backend: &dyn InnerTubeBackend,
+    browse_id: &str,
+) -> Result<ExpandedRadio, InnerTubeError> {
+    let page = backend.browse(browse_id, None).await?;
+    let mut playlist = ExpandedRadio::default();
     let mut seen = HashSet::new();
-    add_songs(&mut items, &mut seen, page.related);
+    for section in page.sections {
+        add_songs(&mut playlist, &mut seen, section.songs);
+    }
+    Ok(playlist)
+}
+
+/// Fetches further radio pages from a stored `continuation`, skipping songs
+/// already in `existing`. A `None` continuation on the result means the radio
+/// has run dry.
+pub async fn continue_radio(
+    backend: &dyn InnerTubeBackend,
+    seed_video_id: &str,
+    continuation: &str,
+    existing: &[QueueItem],
+    min_items: usize,
+) -> Result<ExpandedRadio, InnerTubeError> {
+    let page = backend.next(seed_video_id, Some(continuation)).await?;
+    let mut radio = ExpandedRadio {
+        items: Vec::with_capacity(min_items),
+        ..ExpandedRadio::default()
+    };
+    let mut seen = existing
+        .iter()
+        .filter_map(|item| item.media_id.0.strip_prefix("yt:"))
+        .map(str::to_string)
+        .collect();
+    add_songs(&mut radio, &mut seen, page.related);
+    // A cursor that yields nothing new would be refetched forever.
+    if !radio.items.is_empty() {
+        radio.continuation = follow_radio(
+            backend,
+            seed_video_id,
+            page.continuation,
+            &mut radio,
+            &mut seen,
+            min_items,
+        )
+        .await;
+    }
+    Ok(radio)
+}
 
-    let mut continuation = page.continuation;
-    for _ in 0..10 {
-        if items.len() >= min_items {
+async fn follow_radio(
+    backend: &dyn InnerTubeBackend,
+    seed_video_id: &str,
+    mut continuation: Option<String>,
+    radio: &mut ExpandedRadio,
+    seen: &mut HashSet<String>,
+    min_items: usize,
+) -> Option<String> {
+    for _ in 0..RADIO_MAX_PAGES {
+        if radio.items.len() >= min_items {
             break;
         }
-        let Some(cursor) = continuation.as_deref().filter(|cursor| !cursor.is_empty()) else {
-            break;
-        };
+        let cursor = continuation
+            .as_deref()
+            .filter(|cursor| !cursor.is_empty())?;
         let Ok(next_page) = backend.next(seed_video_id, Some(cursor)).await else {
             break;
         };
-        let before = items.len();
-        add_songs(&mut items, &mut seen, next_page.related);
+        let before = radio.items.len();
+        add_songs(radio, seen, next_page.related);
         continuation = next_page.continuation;
-        if items.len() == before {
+        if radio.items.len() == before {
             break;
         }
     }
-
-    Ok(items)
+    continuation.filter(|cursor| !cursor.is_empty())
 }
 
-fn add_songs(items: &mut Vec<QueueItem>, seen: &mut HashSet<String>, songs: Vec<SongItem>) {
+fn add_songs(radio: &mut ExpandedRadio, seen: &mut HashSet<String>, songs: Vec<SongItem>) {
     for song in songs {
         if song.video_id.is_empty() || !seen.insert(song.video_id.clone()) {
             continue;
         }
-        items.push(QueueItem {
-            media_id: MediaId::new(format!("yt:{}", song.video_id)),
+        let media_id = MediaId::new(format!("yt:{}", song.video_id));
+        if song.is_explicit || song.is_video {
+            radio.flags.insert(
+                media_id.clone(),
+                ContentFlags {
+                    explicit: song.is_explicit,
+                    video_only: song.is_video,
+                },
+            );
+        }
+        radio.items.push(QueueItem {
+            media_id,
             title: song.title,
             artists: song.artists,
             duration_ms: song.duration_ms,
+            unavailable: false,
         });
     }
 }
 
-fn parse_player_response(raw: &Value) -> PlayerResponse {
+/// WEB clients return `signatureCipher` instead of `url` for most formats.
+fn player_formats_need_script(raw: &Value) -> bool {
+    get_array(raw, &["streamingData", "adaptiveFormats"])
+        .into_iter()
+        .flatten()
+        .any(|format| get_string(format, &["url"]).is_empty() && !format_cipher(format).is_empty())
+}
+
+fn format_cipher(format: &Value) -> String {
+    let cipher = get_string(format, &["signatureCipher"]);
+    if cipher.is_empty() {
+        get_string(format, &["cipher"])
+    } else {
+        cipher
+    }
+}
+
+/// Ciphered formats are resolved with `script`; without one they are
+/// skipped.
+pub(crate) fn parse_player_response(
+    raw: &Value,
+    drift: &mut ParserDrift,
+    script: Option<&PlayerScript>,
+) -> PlayerResponse {
     let video_id = get_string(raw, &["videoDetails", "videoId"]);
     let mut streams = Vec::new();
+    let mut unresolved_ciphers = false;
     for format in get_array(raw, &["streamingData", "adaptiveFormats"])
         .cloned()
         .unwrap_or_default()
@@ -424,7 +1173,14 @@ fn parse_player_response(raw: &Value) -> PlayerResponse {
         if !mime_type.starts_with("audio/") {
             continue;
         }
-        let url = get_string(&format, &["url"]);
+        let mut url = get_string(&format, &["url"]);
+        if url.is_empty() {
+            let cipher = format_cipher(&format);
+            match script.and_then(|script| script.resolve_cipher(&cipher)) {
+                Some(resolved) => url = resolved,
+                None => unresolved_ciphers |= !cipher.is_empty(),
+            }
+        }
         if url.is_empty() {
             continue;
         }
@@ -437,6 +1193,14 @@ fn parse_player_response(raw: &Value) -> PlayerResponse {
         });
     }
 
+    // Unplayable videos legitimately carry no formats; a playable one without
+    // any audio URL means the format layout moved.
+    let playability = get_string(raw, &["playabilityStatus", "status"]);
+    if streams.is_empty() && !unresolved_ciphers && (playability.is_empty() || playability == "OK")
+    {
+        drift.fail();
+    }
+
     let stream = streams
         .iter()
         .cloned()
@@ -446,10 +1210,68 @@ fn parse_player_response(raw: &Value) -> PlayerResponse {
         video_id,
         stream,
         all_streams: streams,
+        tracking: parse_playback_tracking(raw),
+        details: parse_video_details(raw),
+    }
+}
+
+fn parse_video_details(raw: &Value) -> VideoDetails {
+    // lengthSeconds is a decimal string in player responses.
+    let seconds = get_string(raw, &["videoDetails", "lengthSeconds"])
+        .parse::<i32>()
+        .unwrap_or_default();
+    VideoDetails {
+        title: get_string(raw, &["videoDetails", "title"]),
+        author: get_string(raw, &["videoDetails", "author"]),
+        channel_id: get_string(raw, &["videoDetails", "channelId"]),
+        duration_ms: seconds.saturating_mul(1000),
+        thumbnail_url: get_array(raw, &["videoDetails", "thumbnail", "thumbnails"])
+            .and_then(|thumbnails| thumbnails.last())
+            .map(|thumbnail| get_string(thumbnail, &["url"]))
+            .unwrap_or_default(),
+    }
+}
+
+fn parse_playback_tracking(raw: &Value) -> PlaybackTracking {
+    PlaybackTracking {
+        playback_url: get_string(
+            raw,
+            &["playbackTracking", "videostatsPlaybackUrl", "baseUrl"],
+        ),
+        watchtime_url: get_string(
+            raw,
+            &["playbackTracking", "videostatsWatchtimeUrl", "baseUrl"],
+        ),
     }
 }
 
-pub fn parse_search_page(raw: &Value) -> SearchPage {
+/// `base` with `params` set, replacing any values it already carries.
+fn tracking_url(base: &str, params: &[(&str, &str)]) -> Result<String, InnerTubeError> {
+    let mut url = reqwest::Url::parse(base)
+        .map_err(|err| InnerTubeError::new(format!("innertube tracking url: {err}")))?;
+    let kept = url
+        .query_pairs()
+        .filter(|(key, _)| !params.iter().any(|(name, _)| name == key))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(kept)
+        .extend_pairs(params);
+    Ok(url.into())
+}
+
+/// A random 16-character client playback nonce tying the tracking pings of
+/// one play together.
+fn client_playback_nonce() -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut rng = rand::thread_rng();
+    (0..16)
+        .map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char)
+        .collect()
+}
+
+pub fn parse_search_page(raw: &Value, drift: &mut ParserDrift) -> SearchPage {
     let mut page = SearchPage::default();
     let tab = get_array(raw, &["contents", "tabbedSearchResultsRenderer", "tabs"])
         .and_then(|tabs| tabs.first());
@@ -468,13 +1290,18 @@ pub fn parse_search_page(raw: &Value) -> SearchPage {
         )
         .cloned();
     }
+    if contents.is_none() {
+        drift.fail();
+    }
 
     for section in contents.unwrap_or_default() {
         let Some(shelf) = get_map(&section, &["musicShelfRenderer"]) else {
+            drift.unknown(&section);
             continue;
         };
         for item in get_array(shelf, &["contents"]).cloned().unwrap_or_default() {
             let Some(renderer) = get_map(&item, &["musicResponsiveListItemRenderer"]) else {
+                drift.unknown(&item);
                 continue;
             };
 
@@ -543,7 +1370,7 @@ pub fn parse_search_page(raw: &Value) -> SearchPage {
     page
 }
 
-pub fn parse_home_page(raw: &Value) -> HomePage {
+pub fn parse_home_page(raw: &Value, drift: &mut ParserDrift) -> HomePage {
     let mut page = HomePage::default();
 
     for chip in get_array(
@@ -560,6 +1387,7 @@ pub fn parse_home_page(raw: &Value) -> HomePage {
     .unwrap_or_default()
     {
         let Some(renderer) = get_map(&chip, &["chipCloudChipRenderer"]) else {
+            drift.unknown(&chip);
             continue;
         };
         let text = first_run_text(renderer, "text");
@@ -595,9 +1423,27 @@ pub fn parse_home_page(raw: &Value) -> HomePage {
         })
         .cloned();
     }
+    if sections.is_none() {
+        // Album and playlist pages put the track list beside the header.
+        sections = get_array(
+            raw,
+            &[
+                "contents",
+                "twoColumnBrowseResultsRenderer",
+                "secondaryContents",
+                "sectionListRenderer",
+                "contents",
+            ],
+        )
+        .cloned();
+    }
+
+    if sections.is_none() {
+        drift.fail();
+    }
 
     for section in sections.unwrap_or_default() {
-        let parsed = parse_home_section(&section);
+        let parsed = parse_home_section(&section, drift);
         if !parsed.songs.is_empty() || !parsed.title.is_empty() {
             page.sections.push(parsed);
         }
@@ -606,23 +1452,31 @@ pub fn parse_home_page(raw: &Value) -> HomePage {
     page
 }
 
-fn parse_home_section(raw: &Value) -> HomeSection {
-    if let Some(renderer) = get_map(raw, &["musicShelfRenderer"]) {
+fn parse_home_section(raw: &Value, drift: &mut ParserDrift) -> HomeSection {
+    // Playlist pages list their tracks in the same row layout as search shelves.
+    if let Some(renderer) = get_map(raw, &["musicShelfRenderer"])
+        .or_else(|| get_map(raw, &["musicPlaylistShelfRenderer"]))
+    {
         let title = first_run_text(renderer, "title");
-        let songs = get_array(renderer, &["contents"])
+        let mut songs = Vec::new();
+        for item in get_array(renderer, &["contents"])
             .cloned()
             .unwrap_or_default()
-            .into_iter()
-            .filter_map(|item| {
-                get_map(&item, &["musicResponsiveListItemRenderer"])
-                    .map(parse_responsive_list_song)
-                    .filter(|song| !song.video_id.is_empty())
-            })
-            .collect();
+        {
+            let Some(item_renderer) = get_map(&item, &["musicResponsiveListItemRenderer"]) else {
+                drift.unknown(&item);
+                continue;
+            };
+            let song = parse_responsive_list_song(item_renderer);
+            if !song.video_id.is_empty() {
+                songs.push(song);
+            }
+        }
         return HomeSection { title, songs };
     }
 
     let Some(renderer) = get_map(raw, &["musicCarouselShelfRenderer"]) else {
+        drift.unknown(raw);
         return HomeSection::default();
     };
     let title = get_map(
@@ -637,6 +1491,7 @@ fn parse_home_section(raw: &Value) -> HomeSection {
         .unwrap_or_default()
     {
         let Some(item_renderer) = get_map(&item, &["musicTwoRowItemRenderer"]) else {
+            drift.unknown(&item);
             continue;
         };
         let page_type = get_string(
@@ -668,14 +1523,23 @@ fn parse_home_section(raw: &Value) -> HomeSection {
     HomeSection { title, songs }
 }
 
-pub fn parse_next_page(raw: &Value) -> NextPage {
+pub fn parse_next_page(raw: &Value, drift: &mut ParserDrift) -> NextPage {
+    if get_map(
+        raw,
+        &["contents", "singleColumnMusicWatchNextResultsRenderer"],
+    )
+    .is_none()
+        && get_map(raw, &["continuationContents"]).is_none()
+    {
+        drift.fail();
+    }
     NextPage {
-        related: extract_related_items(raw),
+        related: extract_related_items(raw, drift),
         continuation: extract_continuation(raw),
     }
 }
 
-fn extract_related_items(raw: &Value) -> Vec<SongItem> {
+fn extract_related_items(raw: &Value, drift: &mut ParserDrift) -> Vec<SongItem> {
     let tabs = get_array(
         raw,
         &[
@@ -698,8 +1562,9 @@ fn extract_related_items(raw: &Value) -> Vec<SongItem> {
             .cloned()
             .unwrap_or_default()
         {
-            if let Some(renderer) = get_map(&item, &["playlistPanelVideoRenderer"]) {
-                items.push(parse_song_item(renderer));
+            match get_map(&item, &["playlistPanelVideoRenderer"]) {
+                Some(renderer) => items.push(parse_song_item(renderer)),
+                None => drift.unknown(&item),
             }
         }
     }
@@ -751,6 +1616,76 @@ fn extract_continuation(raw: &Value) -> Option<String> {
     .filter(|token| !token.is_empty())
 }
 
+pub(crate) fn extract_lyrics_browse_id(raw: &Value) -> Option<String> {
+    get_array(
+        raw,
+        &[
+            "contents",
+            "singleColumnMusicWatchNextResultsRenderer",
+            "tabbedRenderer",
+            "watchNextTabbedResultsRenderer",
+            "tabs",
+        ],
+    )?
+    .iter()
+    .filter_map(|tab| get_map(tab, &["tabRenderer", "endpoint", "browseEndpoint"]))
+    .find(|endpoint| {
+        get_string(
+            endpoint,
+            &[
+                "browseEndpointContextSupportedConfigs",
+                "browseEndpointContextMusicConfig",
+                "pageType",
+            ],
+        ) == "MUSIC_PAGE_TYPE_TRACK_LYRICS"
+            || get_string(endpoint, &["browseId"]).starts_with("MPLYt")
+    })
+    .map(|endpoint| get_string(endpoint, &["browseId"]))
+    .filter(|browse_id| !browse_id.is_empty())
+}
+
+/// Parses a lyrics browse response, preferring the timed lyrics model and
+/// falling back to the plain description shelf.
+pub fn parse_lyrics_page(raw: &Value) -> Option<Lyrics> {
+    let timed = get_array(
+        raw,
+        &[
+            "contents",
+            "elementRenderer",
+            "newElement",
+            "type",
+            "componentType",
+            "model",
+            "timedLyricsModel",
+            "lyricsData",
+            "timedLyricsData",
+        ],
+    );
+    let mut lines = Vec::new();
+    for entry in timed.cloned().unwrap_or_default() {
+        let start = get_string(&entry, &["cueRange", "startTimeMilliseconds"]);
+        let Ok(start_ms) = start.parse::<i64>() else {
+            continue;
+        };
+        lines.push(LyricsLine {
+            start_ms: Some(start_ms),
+            text: get_string(&entry, &["lyricLine"]).trim().to_string(),
+        });
+    }
+    if lines.is_empty() {
+        let text = get_array(raw, &["contents", "sectionListRenderer", "contents"])
+            .and_then(|contents| contents.first())
+            .and_then(|shelf| get_map(shelf, &["musicDescriptionShelfRenderer"]))
+            .map(|shelf| first_run_text(shelf, "description"))
+            .unwrap_or_default();
+        lines = sunflower_core::parse_lrc(&text);
+    }
+    (!lines.is_empty()).then(|| Lyrics {
+        source: LYRICS_SOURCE_YOUTUBE.to_string(),
+        lines,
+    })
+}
+
 /// Returns `true` when the renderer carries an `MUSIC_EXPLICIT_BADGE` inline badge.
 /// Follows the AGENTS.md rule: optional-field tolerant, returns `false` on any
 /// missing key rather than erroring.
@@ -764,6 +1699,39 @@ fn parse_explicit_badge(renderer: &Value) -> bool {
     })
 }
 
+/// Returns `true` when the renderer's watch endpoint names a music video type.
+/// Like [`parse_explicit_badge`], a missing type reads as a song.
+fn parse_music_video(renderer: &Value) -> bool {
+    let endpoints = [
+        get_map(renderer, &["navigationEndpoint", "watchEndpoint"]),
+        get_map(
+            renderer,
+            &[
+                "overlay",
+                "musicItemThumbnailOverlayRenderer",
+                "content",
+                "musicPlayButtonRenderer",
+                "playNavigationEndpoint",
+                "watchEndpoint",
+            ],
+        ),
+    ];
+    endpoints.into_iter().flatten().any(|endpoint| {
+        matches!(
+            get_string(
+                endpoint,
+                &[
+                    "watchEndpointMusicSupportedConfigs",
+                    "watchEndpointMusicConfig",
+                    "musicVideoType",
+                ],
+            )
+            .as_str(),
+            "MUSIC_VIDEO_TYPE_OMV" | "MUSIC_VIDEO_TYPE_UGC"
+        )
+    })
+}
+
 fn parse_song_item(renderer: &Value) -> SongItem {
     let thumbnail_url = get_array(renderer, &["thumbnail", "thumbnails"])
         .and_then(|thumbnails| thumbnails.last())
@@ -776,6 +1744,7 @@ fn parse_song_item(renderer: &Value) -> SongItem {
         duration_ms: 0,
         thumbnail_url,
         is_explicit: parse_explicit_badge(renderer),
+        is_video: parse_music_video(renderer),
     }
 }
 
@@ -802,6 +1771,7 @@ fn parse_responsive_list_song(renderer: &Value) -> SongItem {
         duration_ms: 0,
         thumbnail_url: responsive_thumbnail(renderer),
         is_explicit: parse_explicit_badge(renderer),
+        is_video: parse_music_video(renderer),
     }
 }
 
@@ -1153,7 +2123,12 @@ pub fn expiry_from_url(stream_url: &str) -> Option<DateTime<Utc>> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use axum::{Json, Router, body::Bytes, http::HeaderMap, routing::post};
+    use axum::{
+        Json, Router,
+        body::Bytes,
+        http::HeaderMap,
+        routing::{get, post},
+    };
     use futures_util::future::BoxFuture;
     use serde_json::Value;
     use std::sync::{Arc, Mutex};
@@ -1193,26 +2168,360 @@ mod tests {
                                         }
                                     }
                                 }
-                            }]
-                        }
+                            }]
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    struct StaticCookieProvider;
+
+    impl CookieProvider for StaticCookieProvider {
+        fn cookie_header<'a>(&'a self, _user_id: Option<Uuid>) -> BoxFuture<'a, Option<String>> {
+            Box::pin(async { Some("SID=abc; __Secure-3PSID=xyz".into()) })
+        }
+    }
+
+    /// Cookies for users named in `accounts`; everyone else gets the owner's.
+    struct PerUserCookieProvider {
+        accounts: Vec<(Uuid, &'static str)>,
+    }
+
+    impl CookieProvider for PerUserCookieProvider {
+        fn cookie_header<'a>(&'a self, user_id: Option<Uuid>) -> BoxFuture<'a, Option<String>> {
+            let cookie = self
+                .accounts
+                .iter()
+                .find(|(id, _)| Some(*id) == user_id)
+                .map_or("SID=owner", |(_, cookie)| cookie);
+            Box::pin(async move { Some(cookie.to_string()) })
+        }
+
+        fn own_cookie_header<'a>(&'a self, user_id: Uuid) -> BoxFuture<'a, Option<String>> {
+            let cookie = self
+                .accounts
+                .iter()
+                .find(|(id, _)| *id == user_id)
+                .map(|(_, cookie)| cookie.to_string());
+            Box::pin(async move { cookie })
+        }
+    }
+
+    #[derive(Default)]
+    struct GuestFallbackCookieProvider {
+        reports: Mutex<Vec<String>>,
+    }
+
+    impl CookieProvider for GuestFallbackCookieProvider {
+        fn cookie_header<'a>(&'a self, _user_id: Option<Uuid>) -> BoxFuture<'a, Option<String>> {
+            Box::pin(async { Some("SID=expired".into()) })
+        }
+
+        fn report_guest_fallback<'a>(
+            &'a self,
+            _user_id: Option<Uuid>,
+            detail: String,
+        ) -> BoxFuture<'a, ()> {
+            self.reports.lock().unwrap().push(detail);
+            Box::pin(async {})
+        }
+    }
+
+    /// Serves `path` with `respond(cookie header)` and records the cookie
+    /// header of every request.
+    async fn cookie_aware_server(
+        path: &str,
+        respond: fn(Option<&str>) -> (axum::http::StatusCode, Value),
+    ) -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_for_route = seen.clone();
+        let app = Router::new().route(
+            path,
+            post(move |headers: HeaderMap| {
+                let seen = seen_for_route.clone();
+                async move {
+                    let cookie = headers
+                        .get(axum::http::header::COOKIE)
+                        .and_then(|value| value.to_str().ok())
+                        .map(ToOwned::to_owned);
+                    let (status, body) = respond(cookie.as_deref());
+                    seen.lock().unwrap().push(cookie);
+                    (status, Json(body))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (base_url, seen)
+    }
+
+    #[tokio::test]
+    async fn http_client_retries_rejected_cookies_as_guest() {
+        let (base_url, seen) = cookie_aware_server("/youtubei/v1/search", |cookie| match cookie {
+            Some(_) => (axum::http::StatusCode::FORBIDDEN, json!({})),
+            None => (
+                axum::http::StatusCode::OK,
+                serde_json::from_str(include_str!("../testdata/innertube/search_response.json"))
+                    .unwrap(),
+            ),
+        })
+        .await;
+        let provider = Arc::new(GuestFallbackCookieProvider::default());
+        let client = HttpInnerTubeClient::new(base_url, Locale::default())
+            .unwrap()
+            .with_cookie_provider(provider.clone());
+
+        let page = client.search("guest").await.unwrap();
+        assert!(!page.songs.is_empty());
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![Some("SID=expired".into()), None]
+        );
+        let reports = provider.reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].contains("403"));
+    }
+
+    #[tokio::test]
+    async fn http_client_reports_logged_out_responses_without_retrying() {
+        let (base_url, seen) = cookie_aware_server("/youtubei/v1/search", |_| {
+            (
+                axum::http::StatusCode::OK,
+                json!({
+                    "responseContext": {
+                        "mainAppWebResponseContext": { "loggedOut": true }
+                    }
+                }),
+            )
+        })
+        .await;
+        let provider = Arc::new(GuestFallbackCookieProvider::default());
+        let client = HttpInnerTubeClient::new(base_url, Locale::default())
+            .unwrap()
+            .with_cookie_provider(provider.clone());
+
+        client.search("guest").await.unwrap();
+        assert_eq!(seen.lock().unwrap().len(), 1);
+        assert_eq!(provider.reports.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn report_playback_pings_tracking_urls_with_the_users_own_cookies() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let pings = Arc::new(Mutex::new(Vec::new()));
+        let pings_for_route = pings.clone();
+        let tracking_base = base_url.clone();
+        let app = Router::new()
+            .route(
+                "/youtubei/v1/player",
+                post(move |body: Bytes| {
+                    let tracking_base = tracking_base.clone();
+                    async move {
+                        let body: Value = serde_json::from_slice(&body).unwrap();
+                        assert_eq!(body["context"]["client"]["clientName"], "WEB_REMIX");
+                        Json(json!({
+                            "videoDetails": { "videoId": body["videoId"] },
+                            "playbackTracking": {
+                                "videostatsPlaybackUrl": {
+                                    "baseUrl": format!("{tracking_base}/api/stats/playback?docid=abc&ver=1")
+                                },
+                                "videostatsWatchtimeUrl": {
+                                    "baseUrl": format!("{tracking_base}/api/stats/watchtime?docid=abc")
+                                }
+                            }
+                        }))
                     }
-                }
-            }
+                }),
+            )
+            .route(
+                "/api/stats/:kind",
+                get(
+                    move |axum::extract::Path(kind): axum::extract::Path<String>,
+                          axum::extract::RawQuery(query): axum::extract::RawQuery,
+                          headers: HeaderMap| {
+                        let pings = pings_for_route.clone();
+                        async move {
+                            let cookie = headers
+                                .get(axum::http::header::COOKIE)
+                                .and_then(|value| value.to_str().ok())
+                                .map(ToOwned::to_owned);
+                            pings
+                                .lock()
+                                .unwrap()
+                                .push((kind, query.unwrap_or_default(), cookie));
+                            axum::http::StatusCode::NO_CONTENT
+                        }
+                    },
+                ),
+            );
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let guest = HttpInnerTubeClient::new(base_url.clone(), Locale::default()).unwrap();
+        assert!(!guest.report_playback("abc", 61_500, None).await.unwrap());
+        assert!(pings.lock().unwrap().is_empty());
+
+        let member = Uuid::new_v4();
+        let client = HttpInnerTubeClient::new(base_url, Locale::default())
+            .unwrap()
+            .with_cookie_provider(Arc::new(PerUserCookieProvider {
+                accounts: vec![(member, "SID=abc; __Secure-3PSID=xyz")],
+            }));
+        // Neither a call with no user nor a user who only has the owner's
+        // cookies to fall back on reports anything.
+        assert!(!client.report_playback("abc", 61_500, None).await.unwrap());
+        let without_cookies = client.for_user_client(Uuid::new_v4()).unwrap();
+        assert!(
+            !without_cookies
+                .report_playback("abc", 61_500, None)
+                .await
+                .unwrap()
+        );
+        assert!(pings.lock().unwrap().is_empty());
+
+        let client = client.for_user_client(member).unwrap();
+        assert!(client.report_playback("abc", 61_500, None).await.unwrap());
+
+        let pings = pings.lock().unwrap();
+        assert_eq!(pings.len(), 2);
+        let query = |index: usize| url_params(&format!("http://localhost/?{}", pings[index].1));
+        let (playback, watchtime) = (query(0), query(1));
+        assert_eq!(pings[0].0, "playback");
+        assert_eq!(pings[1].0, "watchtime");
+        for (_, _, cookie) in pings.iter() {
+            assert_eq!(cookie.as_deref(), Some("SID=abc; __Secure-3PSID=xyz"));
+        }
+        assert_eq!(playback["docid"], "abc");
+        assert_eq!(playback["ver"], "2");
+        assert_eq!(playback["cpn"].len(), 16);
+        assert_eq!(watchtime["cpn"], playback["cpn"]);
+        assert_eq!(watchtime["st"], "0");
+        assert_eq!(watchtime["et"], "61.500");
+        assert_eq!(watchtime["cmt"], "61.500");
+    }
+
+    fn url_params(url: &str) -> std::collections::HashMap<String, String> {
+        reqwest::Url::parse(url)
+            .unwrap()
+            .query_pairs()
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn http_client_for_user_uses_that_users_cookies() {
+        let (base_url, seen) = cookie_aware_server("/youtubei/v1/search", |_| {
+            (
+                axum::http::StatusCode::OK,
+                serde_json::from_str(include_str!("../testdata/innertube/search_response.json"))
+                    .unwrap(),
+            )
         })
+        .await;
+        let member = Uuid::new_v4();
+        let client = HttpInnerTubeClient::new(base_url, Locale::default())
+            .unwrap()
+            .with_cookie_provider(Arc::new(PerUserCookieProvider {
+                accounts: vec![(member, "SID=member")],
+            }));
+
+        client.search("shared").await.unwrap();
+        client
+            .for_user(member)
+            .unwrap()
+            .search("mine")
+            .await
+            .unwrap();
+        client
+            .for_user(Uuid::new_v4())
+            .unwrap()
+            .search("fallback")
+            .await
+            .unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                Some("SID=owner".into()),
+                Some("SID=member".into()),
+                Some("SID=owner".into())
+            ]
+        );
+        assert!(
+            HttpInnerTubeClient::new("http://127.0.0.1:9", Locale::default())
+                .unwrap()
+                .for_user(member)
+                .is_none()
+        );
     }
 
-    struct StaticCookieProvider;
+    #[tokio::test]
+    async fn http_client_without_credentials_does_not_retry_forbidden() {
+        let (base_url, seen) = cookie_aware_server("/youtubei/v1/search", |_| {
+            (axum::http::StatusCode::FORBIDDEN, json!({}))
+        })
+        .await;
+        let client = HttpInnerTubeClient::new(base_url, Locale::default()).unwrap();
 
-    impl CookieProvider for StaticCookieProvider {
-        fn cookie_header<'a>(&'a self) -> BoxFuture<'a, Option<String>> {
-            Box::pin(async { Some("SID=abc; __Secure-3PSID=xyz".into()) })
+        assert!(client.search("guest").await.is_err());
+        assert_eq!(*seen.lock().unwrap(), vec![None]);
+    }
+
+    #[tokio::test]
+    async fn cookie_probe_reports_signed_in_rejected_and_failed() {
+        async fn probe(
+            respond: fn(Option<&str>) -> (axum::http::StatusCode, Value),
+        ) -> CookieProbe {
+            let (base_url, seen) = cookie_aware_server("/youtubei/v1/next", respond).await;
+            let client = HttpInnerTubeClient::new(base_url, Locale::default())
+                .unwrap()
+                .with_cookie_provider(Arc::new(GuestFallbackCookieProvider::default()));
+            let outcome = client.probe_cookies("dQw4w9WgXcQ").await;
+            assert_eq!(*seen.lock().unwrap(), vec![Some("SID=expired".into())]);
+            outcome
         }
+
+        assert_eq!(
+            probe(|_| (axum::http::StatusCode::OK, json!({}))).await,
+            CookieProbe::SignedIn
+        );
+        assert!(matches!(
+            probe(|_| (axum::http::StatusCode::FORBIDDEN, json!({}))).await,
+            CookieProbe::Rejected(detail) if detail.contains("403")
+        ));
+        assert!(matches!(
+            probe(|_| (
+                axum::http::StatusCode::OK,
+                json!({ "responseContext": { "mainAppWebResponseContext": { "loggedOut": true } } }),
+            ))
+            .await,
+            CookieProbe::Rejected(_)
+        ));
+        assert!(matches!(
+            probe(|_| (axum::http::StatusCode::SERVICE_UNAVAILABLE, json!({}))).await,
+            CookieProbe::Failed(detail) if detail.contains("503")
+        ));
+
+        let client = HttpInnerTubeClient::new("http://127.0.0.1:9", Locale::default()).unwrap();
+        assert_eq!(
+            client.probe_cookies("dQw4w9WgXcQ").await,
+            CookieProbe::NoCredentials
+        );
     }
 
     struct StaticTokenProvider;
 
     impl InnerTubeTokenProvider for StaticTokenProvider {
-        fn innertube_token<'a>(&'a self) -> BoxFuture<'a, Option<InnerTubeToken>> {
+        fn innertube_token<'a>(
+            &'a self,
+            _user_id: Option<Uuid>,
+        ) -> BoxFuture<'a, Option<InnerTubeToken>> {
             Box::pin(async {
                 Some(InnerTubeToken {
                     po_token: "po-test".into(),
@@ -1249,7 +2558,7 @@ mod tests {
         ) -> BoxFuture<'a, Result<NextPage, InnerTubeError>> {
             Box::pin(async move {
                 let raw = self.pages.lock().unwrap().remove(0);
-                Ok(parse_next_page(&raw))
+                Ok(parse_next_page(&raw, &mut ParserDrift::default()))
             })
         }
 
@@ -1259,6 +2568,13 @@ mod tests {
         ) -> BoxFuture<'a, Result<PlayerResponse, InnerTubeError>> {
             Box::pin(async { Ok(PlayerResponse::default()) })
         }
+
+        fn lyrics<'a>(
+            &'a self,
+            _video_id: &'a str,
+        ) -> BoxFuture<'a, Result<Option<Lyrics>, InnerTubeError>> {
+            Box::pin(async { Ok(None) })
+        }
     }
 
     #[test]
@@ -1266,12 +2582,102 @@ mod tests {
         let raw: Value =
             serde_json::from_str(include_str!("../testdata/innertube/search_response.json"))
                 .unwrap();
-        let page = parse_search_page(&raw);
+        let page = parse_search_page(&raw, &mut ParserDrift::default());
         assert!(!page.songs.is_empty());
         assert!(!page.songs[0].video_id.is_empty());
         assert!(!page.songs[0].title.is_empty());
     }
 
+    #[test]
+    fn legacy_fixtures_parse_without_renderer_drift() {
+        let fixture = |raw: &str| serde_json::from_str::<Value>(raw).unwrap();
+        let mut drift = ParserDrift::default();
+        parse_home_page(
+            &fixture(include_str!("../testdata/innertube/home_response.json")),
+            &mut drift,
+        );
+        parse_search_page(
+            &fixture(include_str!("../testdata/innertube/search_response.json")),
+            &mut drift,
+        );
+        parse_next_page(
+            &fixture(include_str!("../testdata/innertube/next_response.json")),
+            &mut drift,
+        );
+        assert_eq!(drift, ParserDrift::default());
+    }
+
+    #[test]
+    fn parsers_report_unknown_renderers_and_missing_layouts() {
+        let raw = json!({
+            "contents": { "tabbedSearchResultsRenderer": { "tabs": [{ "tabRenderer": {
+                "content": { "sectionListRenderer": { "contents": [
+                    { "musicCardShelfRenderer": {} },
+                    { "musicShelfRenderer": { "contents": [
+                        { "musicMultiRowListItemRenderer": {} }
+                    ] } }
+                ] } }
+            } }] } }
+        });
+        let mut drift = ParserDrift::default();
+        parse_search_page(&raw, &mut drift);
+        assert_eq!(
+            drift.unknown_renderers,
+            vec!["musicCardShelfRenderer", "musicMultiRowListItemRenderer"]
+        );
+        assert!(!drift.failed);
+
+        for parse in [
+            |raw: &Value, drift: &mut ParserDrift| {
+                parse_home_page(raw, drift);
+            },
+            |raw: &Value, drift: &mut ParserDrift| {
+                parse_next_page(raw, drift);
+            },
+            |raw: &Value, drift: &mut ParserDrift| {
+                parse_player_response(raw, drift, None);
+            },
+        ] {
+            let mut drift = ParserDrift::default();
+            parse(&json!({ "responseContext": {} }), &mut drift);
+            assert!(drift.failed);
+        }
+
+        let mut drift = ParserDrift::default();
+        parse_player_response(
+            &json!({ "playabilityStatus": { "status": "UNPLAYABLE" } }),
+            &mut drift,
+            None,
+        );
+        assert!(!drift.failed);
+    }
+
+    #[tokio::test]
+    async fn http_client_records_parser_drift_per_endpoint() {
+        let app = Router::new().route(
+            "/youtubei/v1/search",
+            post(|| async { Json(json!({ "responseContext": {} })) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let registry = Arc::new(ParserDriftRegistry::default());
+        let client = HttpInnerTubeClient::new(base_url, Locale::default())
+            .unwrap()
+            .with_parser_drift(registry.clone());
+        client.search("drift").await.unwrap();
+        client.search("drift").await.unwrap();
+
+        let snapshot = registry.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].endpoint, "search");
+        assert_eq!(snapshot[0].parsed_responses, 2);
+        assert_eq!(snapshot[0].parse_failures, 2);
+    }
+
     #[tokio::test]
     async fn http_client_attaches_cookie_provider_header_like_go_client() {
         let captured = Arc::new(Mutex::new(None::<String>));
@@ -1354,6 +2760,208 @@ mod tests {
         );
     }
 
+    /// Serves `/youtubei/v1/next`, answering each client profile with the
+    /// status its closure returns for that profile's n-th request.
+    async fn profile_status_server(
+        status_for: fn(&str, usize) -> axum::http::StatusCode,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_for_route = seen.clone();
+        let app = Router::new().route(
+            "/youtubei/v1/next",
+            post(move |headers: HeaderMap| {
+                let seen = seen_for_route.clone();
+                async move {
+                    let client = headers
+                        .get("X-YouTube-Client-Name")
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string();
+                    let mut seen = seen.lock().unwrap();
+                    let count = seen.iter().filter(|seen| **seen == client).count();
+                    seen.push(client.clone());
+                    let status = status_for(&client, count);
+                    (status, Json(next_page_json(&["served"], "")))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (base_url, seen)
+    }
+
+    fn fast_retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::ZERO,
+            breaker_threshold: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn http_client_retries_server_errors_on_the_same_profile() {
+        let (base_url, seen) = profile_status_server(|_, count| {
+            if count == 0 {
+                axum::http::StatusCode::TOO_MANY_REQUESTS
+            } else {
+                axum::http::StatusCode::OK
+            }
+        })
+        .await;
+        let health = Arc::new(InnerTubeProfileHealth::default());
+        let client = HttpInnerTubeClient::new(base_url, Locale::default())
+            .unwrap()
+            .with_retry_policy(fast_retry_policy())
+            .with_profile_health(health.clone());
+
+        let page = client.next("seed", None).await.unwrap();
+        assert_eq!(page.related[0].video_id, "served");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![ANDROID_MUSIC_CLIENT_ID, ANDROID_MUSIC_CLIENT_ID]
+        );
+        let snapshot = health.snapshot();
+        assert_eq!(snapshot[0].profile, ANDROID_MUSIC_CLIENT_NAME);
+        assert_eq!((snapshot[0].served, snapshot[0].retries), (1, 1));
+        assert!(!snapshot[0].circuit_open);
+    }
+
+    #[tokio::test]
+    async fn http_client_fails_over_and_skips_profiles_with_open_breakers() {
+        let (base_url, seen) = profile_status_server(|client, _| {
+            if client == ANDROID_MUSIC_CLIENT_ID {
+                axum::http::StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                axum::http::StatusCode::OK
+            }
+        })
+        .await;
+        let health = Arc::new(InnerTubeProfileHealth::default());
+        let client = HttpInnerTubeClient::new(base_url, Locale::default())
+            .unwrap()
+            .with_retry_policy(fast_retry_policy())
+            .with_profile_health(health.clone());
+
+        client.next("seed", None).await.unwrap();
+        client.next("seed", None).await.unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ANDROID_MUSIC_CLIENT_ID,
+                ANDROID_MUSIC_CLIENT_ID,
+                WEB_REMIX_CLIENT_ID,
+                WEB_REMIX_CLIENT_ID,
+            ]
+        );
+        let snapshot = health.snapshot();
+        assert_eq!(snapshot[0].profile, ANDROID_MUSIC_CLIENT_NAME);
+        assert!(snapshot[0].circuit_open);
+        assert_eq!(snapshot[0].failures, 1);
+        assert_eq!(snapshot[1].profile, WEB_REMIX_CLIENT_NAME);
+        assert_eq!(snapshot[1].served, 2);
+    }
+
+    #[tokio::test]
+    async fn http_client_deciphers_web_formats_with_cached_player_script() {
+        let player_bodies = Arc::new(Mutex::new(Vec::<Value>::new()));
+        let script_fetches = Arc::new(Mutex::new(0));
+        let bodies_for_route = player_bodies.clone();
+        let fetches_for_route = script_fetches.clone();
+        let app = Router::new()
+            .route(
+                "/youtubei/v1/player",
+                post(move |Json(body): Json<Value>| {
+                    let bodies = bodies_for_route.clone();
+                    async move {
+                        bodies.lock().unwrap().push(body);
+                        let cipher = format!(
+                            "s=AOq0QJ8wRQIgF4yk0XGb2lHhP3fQOK8qvV0XYcbXx9T7vDcAE2Kf0pUCIQDz&sp=sig&url={}",
+                            crate::path_segment(
+                                "https://rr1.googlevideo.com/videoplayback?itag=251&n=kP1xQdY3bGhL9w"
+                            )
+                        );
+                        Json(json!({
+                            "videoDetails": { "videoId": "ciphered" },
+                            "streamingData": { "adaptiveFormats": [{
+                                "itag": 251,
+                                "mimeType": "audio/webm; codecs=\"opus\"",
+                                "bitrate": 160000,
+                                "signatureCipher": cipher,
+                            }] },
+                        }))
+                    }
+                }),
+            )
+            .route(
+                "/iframe_api",
+                get(|| async { r"var scriptUrl = 'https:\/\/www.youtube.com\/s\/player\/3f2a1b7c\/www-widgetapi.vflset\/www-widgetapi.js';" }),
+            )
+            .route(
+                "/s/player/3f2a1b7c/player_ias.vflset/en_US/base.js",
+                get(move || {
+                    let fetches = fetches_for_route.clone();
+                    async move {
+                        *fetches.lock().unwrap() += 1;
+                        include_str!("../testdata/innertube/player/base-3f2a1b7c.js")
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        let client = HttpInnerTubeClient::new(base_url, Locale::default()).unwrap();
+
+        let player = client.player("ciphered").await.unwrap();
+        assert_eq!(
+            player.stream.url,
+            "https://rr1.googlevideo.com/videoplayback?itag=251&n=LwGb3YdQx1Pk&sig=Oq0QJ8wRQIgF4yk0XGb2lHhP3fQOK8qvVQXYcbXx9T7vDcAE2Kf0pUCI0"
+        );
+        client.player("ciphered").await.unwrap();
+        assert_eq!(*script_fetches.lock().unwrap(), 1);
+        let bodies = player_bodies.lock().unwrap();
+        assert!(bodies[0].get("playbackContext").is_none());
+        assert_eq!(
+            bodies[1]["playbackContext"]["contentPlaybackContext"]["signatureTimestamp"],
+            20158
+        );
+    }
+
+    #[test]
+    fn ciphered_formats_without_a_script_are_not_parser_drift() {
+        let raw = json!({
+            "playabilityStatus": { "status": "OK" },
+            "streamingData": { "adaptiveFormats": [{
+                "mimeType": "audio/webm",
+                "signatureCipher": "s=abc&sp=sig&url=https%3A%2F%2Fexample",
+            }] },
+        });
+        assert!(player_formats_need_script(&raw));
+        let mut drift = ParserDrift::default();
+        let player = parse_player_response(&raw, &mut drift, None);
+        assert!(player.all_streams.is_empty());
+        assert!(!drift.failed);
+    }
+
+    #[tokio::test]
+    async fn http_client_does_not_retry_or_fail_over_client_errors() {
+        let (base_url, seen) =
+            profile_status_server(|_, _| axum::http::StatusCode::BAD_REQUEST).await;
+        let health = Arc::new(InnerTubeProfileHealth::default());
+        let client = HttpInnerTubeClient::new(base_url, Locale::default())
+            .unwrap()
+            .with_retry_policy(fast_retry_policy())
+            .with_profile_health(health.clone());
+
+        assert!(client.next("seed", Some("stale")).await.is_err());
+        assert_eq!(*seen.lock().unwrap(), vec![ANDROID_MUSIC_CLIENT_ID]);
+        assert!(health.allows(ANDROID_MUSIC_CLIENT_NAME, &fast_retry_policy()));
+    }
+
     #[test]
     fn parse_innertube_token_accepts_plain_key_value_and_json() {
         assert_eq!(
@@ -1400,7 +3008,7 @@ mod tests {
     fn parse_home_page_from_legacy_fixture() {
         let raw: Value =
             serde_json::from_str(include_str!("../testdata/innertube/home_response.json")).unwrap();
-        let page = parse_home_page(&raw);
+        let page = parse_home_page(&raw, &mut ParserDrift::default());
         assert!(!page.sections.is_empty());
     }
 
@@ -1451,13 +3059,53 @@ mod tests {
                 }
             }
         });
-        let page = parse_home_page(&raw);
+        let page = parse_home_page(&raw, &mut ParserDrift::default());
         assert_eq!(page.chips, vec!["Relax"]);
         assert_eq!(page.sections[0].title, "For you");
         assert_eq!(page.sections[0].songs[0].video_id, "abc");
         assert_eq!(page.sections[0].songs[0].title, "Song abc");
     }
 
+    #[test]
+    fn parse_home_page_reads_two_column_playlist_shelves() {
+        let track = |video_id: &str, title: &str| {
+            json!({
+                "musicResponsiveListItemRenderer": {
+                    "playlistItemData": { "videoId": video_id },
+                    "flexColumns": [{
+                        "musicResponsiveListItemFlexColumnRenderer": {
+                            "text": { "runs": [{ "text": title }] }
+                        }
+                    }]
+                }
+            })
+        };
+        let raw = json!({
+            "contents": {
+                "twoColumnBrowseResultsRenderer": {
+                    "secondaryContents": {
+                        "sectionListRenderer": {
+                            "contents": [{
+                                "musicPlaylistShelfRenderer": {
+                                    "contents": [track("pl-a", "First"), track("pl-b", "Second")]
+                                }
+                            }]
+                        }
+                    }
+                }
+            }
+        });
+        let page = parse_home_page(&raw, &mut ParserDrift::default());
+        assert_eq!(
+            page.sections[0]
+                .songs
+                .iter()
+                .map(|song| (song.video_id.as_str(), song.title.as_str()))
+                .collect::<Vec<_>>(),
+            [("pl-a", "First"), ("pl-b", "Second")]
+        );
+    }
+
     #[test]
     fn parse_home_page_extracts_related_music_shelf_songs() {
         let raw = json!({
@@ -1517,7 +3165,7 @@ mod tests {
                 }
             }
         });
-        let page = parse_home_page(&raw);
+        let page = parse_home_page(&raw, &mut ParserDrift::default());
         assert_eq!(page.sections[0].title, "Related");
         assert_eq!(page.sections[0].songs[0].video_id, "related-a");
         assert_eq!(page.sections[0].songs[0].title, "Related A");
@@ -1532,7 +3180,7 @@ mod tests {
     fn parse_next_page_from_legacy_fixture() {
         let raw: Value =
             serde_json::from_str(include_str!("../testdata/innertube/next_response.json")).unwrap();
-        let page = parse_next_page(&raw);
+        let page = parse_next_page(&raw, &mut ParserDrift::default());
         assert!(!page.related.is_empty());
         assert!(!page.related[0].video_id.is_empty());
         assert!(page.continuation.is_some());
@@ -1547,15 +3195,155 @@ mod tests {
                 next_page_json(&["g", "h", "i", "j", "k"], ""),
             ]),
         };
-        let items = expand_radio(&backend, "a", 10).await.unwrap();
-        assert!(items.len() >= 10);
-        assert_eq!(items[0].media_id.0, "yt:a");
+        let radio = expand_radio(&backend, "a", 10).await.unwrap();
+        assert!(radio.items.len() >= 10);
+        assert_eq!(radio.items[0].media_id.0, "yt:a");
+        assert_eq!(radio.continuation, None);
+    }
+
+    #[tokio::test]
+    async fn continue_radio_skips_queued_songs_and_drops_dry_cursors() {
+        let backend = FakeBackend {
+            pages: std::sync::Mutex::new(vec![
+                next_page_json(&["a", "b", "c"], "cont2"),
+                next_page_json(&["d", "e"], "cont3"),
+                next_page_json(&["d", "e"], "cont4"),
+            ]),
+        };
+        let existing = expand_radio(&backend, "a", 2).await.unwrap();
+        assert_eq!(existing.continuation.as_deref(), Some("cont2"));
+
+        let more = continue_radio(&backend, "a", "cont2", &existing.items, 2)
+            .await
+            .unwrap();
+        assert_eq!(
+            more.items
+                .iter()
+                .map(|item| item.media_id.0.as_str())
+                .collect::<Vec<_>>(),
+            ["yt:d", "yt:e"]
+        );
+        assert_eq!(more.continuation.as_deref(), Some("cont3"));
+
+        let mut queued = existing.items;
+        queued.extend(more.items);
+        let dry = continue_radio(&backend, "a", "cont3", &queued, 2)
+            .await
+            .unwrap();
+        assert!(dry.items.is_empty());
+        assert_eq!(dry.continuation, None);
+    }
+
+    #[tokio::test]
+    async fn http_client_fetches_timed_lyrics_through_lyrics_tab() {
+        let browsed = Arc::new(Mutex::new(None::<Value>));
+        let browsed_for_route = browsed.clone();
+        let app = Router::new()
+            .route(
+                "/youtubei/v1/next",
+                post(|| async {
+                    Json(json!({
+                        "contents": {
+                            "singleColumnMusicWatchNextResultsRenderer": {
+                                "tabbedRenderer": {
+                                    "watchNextTabbedResultsRenderer": {
+                                        "tabs": [
+                                            { "tabRenderer": { "content": {} } },
+                                            { "tabRenderer": { "endpoint": { "browseEndpoint": {
+                                                "browseId": "MPLYt_lyrics",
+                                                "browseEndpointContextSupportedConfigs": {
+                                                    "browseEndpointContextMusicConfig": {
+                                                        "pageType": "MUSIC_PAGE_TYPE_TRACK_LYRICS"
+                                                    }
+                                                }
+                                            } } } }
+                                        ]
+                                    }
+                                }
+                            }
+                        }
+                    }))
+                }),
+            )
+            .route(
+                "/youtubei/v1/browse",
+                post(move |body: Bytes| {
+                    let browsed = browsed_for_route.clone();
+                    async move {
+                        *browsed.lock().unwrap() = serde_json::from_slice(&body).ok();
+                        Json(json!({
+                            "contents": { "elementRenderer": { "newElement": { "type": {
+                                "componentType": { "model": { "timedLyricsModel": {
+                                    "lyricsData": { "timedLyricsData": [
+                                        { "lyricLine": "Hello", "cueRange": { "startTimeMilliseconds": "1200" } },
+                                        { "lyricLine": "World", "cueRange": { "startTimeMilliseconds": "3400" } }
+                                    ] }
+                                } } }
+                            } } } }
+                        }))
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let client = HttpInnerTubeClient::new(base_url, Locale::default()).unwrap();
+        let lyrics = client.lyrics("abc").await.unwrap().expect("lyrics");
+
+        assert_eq!(lyrics.source, LYRICS_SOURCE_YOUTUBE);
+        assert!(lyrics.is_synced());
+        assert_eq!(
+            lyrics
+                .lines
+                .iter()
+                .map(|line| (line.start_ms, line.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(Some(1_200), "Hello"), (Some(3_400), "World")]
+        );
+        let browsed = browsed.lock().unwrap().clone().unwrap();
+        assert_eq!(browsed["browseId"], "MPLYt_lyrics");
+        assert_eq!(browsed["context"]["client"]["clientName"], "ANDROID_MUSIC");
+    }
+
+    #[test]
+    fn parse_lyrics_page_falls_back_to_description_shelf() {
+        let raw = json!({
+            "contents": { "sectionListRenderer": { "contents": [{
+                "musicDescriptionShelfRenderer": {
+                    "description": { "runs": [{ "text": "Line one\nLine two" }] }
+                }
+            }] } }
+        });
+        let lyrics = parse_lyrics_page(&raw).expect("lyrics");
+        assert!(!lyrics.is_synced());
+        assert_eq!(
+            lyrics
+                .lines
+                .iter()
+                .map(|line| line.text.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Line one", "Line two"]
+        );
+        assert_eq!(parse_lyrics_page(&json!({})), None);
     }
 
     #[test]
     fn parse_player_response_picks_highest_bitrate_audio() {
         let raw = json!({
-            "videoDetails": { "videoId": "abc" },
+            "videoDetails": {
+                "videoId": "abc",
+                "title": "Song",
+                "author": "Artist",
+                "channelId": "UC123",
+                "lengthSeconds": "215",
+                "thumbnail": { "thumbnails": [
+                    { "url": "https://i.ytimg.com/vi/abc/default.jpg" },
+                    { "url": "https://i.ytimg.com/vi/abc/maxresdefault.jpg" }
+                ] }
+            },
             "streamingData": {
                 "adaptiveFormats": [
                     { "itag": 18, "mimeType": "video/mp4", "bitrate": 1000, "url": "https://video.example" },
@@ -1564,11 +3352,21 @@ mod tests {
                 ]
             }
         });
-        let player = parse_player_response(&raw);
+        let player = parse_player_response(&raw, &mut ParserDrift::default(), None);
         assert_eq!(player.video_id, "abc");
         assert_eq!(player.stream.itag, 251);
         assert_eq!(player.all_streams.len(), 2);
         assert!(expiry_from_url(&player.stream.url).is_some());
+        assert_eq!(
+            player.details,
+            VideoDetails {
+                title: "Song".into(),
+                author: "Artist".into(),
+                channel_id: "UC123".into(),
+                duration_ms: 215_000,
+                thumbnail_url: "https://i.ytimg.com/vi/abc/maxresdefault.jpg".into(),
+            }
+        );
     }
 
     #[test]
@@ -1615,4 +3413,35 @@ mod tests {
             "expected is_explicit=false for unrelated badge"
         );
     }
+
+    #[test]
+    fn music_video_type_marks_videos_but_not_songs() {
+        let watch = |video_type: &str| {
+            json!({
+                "watchEndpoint": {
+                    "watchEndpointMusicSupportedConfigs": {
+                        "watchEndpointMusicConfig": { "musicVideoType": video_type }
+                    }
+                }
+            })
+        };
+        let panel = |video_type: &str| json!({ "navigationEndpoint": watch(video_type) });
+        assert!(parse_song_item(&panel("MUSIC_VIDEO_TYPE_OMV")).is_video);
+        assert!(parse_song_item(&panel("MUSIC_VIDEO_TYPE_UGC")).is_video);
+        assert!(!parse_song_item(&panel("MUSIC_VIDEO_TYPE_ATV")).is_video);
+        assert!(!parse_song_item(&json!({ "videoId": "untyped" })).is_video);
+
+        let list_item = json!({
+            "overlay": {
+                "musicItemThumbnailOverlayRenderer": {
+                    "content": {
+                        "musicPlayButtonRenderer": {
+                            "playNavigationEndpoint": watch("MUSIC_VIDEO_TYPE_OMV")
+                        }
+                    }
+                }
+            }
+        });
+        assert!(parse_responsive_list_song(&list_item).is_video);
+    }
 }
@@ -0,0 +1,590 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use futures_util::future::BoxFuture;
+use sunflower_core::Lyrics;
+use uuid::Uuid;
+
+use crate::innertube::{
+    HomePage, InnerTubeBackend, InnerTubeError, Locale, NextPage, PlaybackTracking, PlayerResponse,
+    SearchPage,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InnerTubeCacheConfig {
+    pub browse_ttl: Duration,
+    pub search_ttl: Duration,
+    pub next_ttl: Duration,
+    pub lyrics_ttl: Duration,
+    /// How long the tracking URLs of a player response are reused for
+    /// playback reports; they live about as long as its stream URLs.
+    pub tracking_ttl: Duration,
+    /// How long past its TTL an entry is still served while a background
+    /// refresh runs.
+    pub stale_for: Duration,
+    pub max_entries: usize,
+}
+
+impl Default for InnerTubeCacheConfig {
+    fn default() -> Self {
+        Self {
+            browse_ttl: Duration::from_secs(30 * 60),
+            search_ttl: Duration::from_secs(10 * 60),
+            next_ttl: Duration::from_secs(5 * 60),
+            lyrics_ttl: Duration::from_secs(24 * 60 * 60),
+            tracking_ttl: Duration::from_secs(6 * 60 * 60),
+            stale_for: Duration::from_secs(60 * 60),
+            max_entries: 1024,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum CachedValue {
+    Browse(HomePage),
+    Search(SearchPage),
+    Next(NextPage),
+    Lyrics(Option<Lyrics>),
+    Tracking(PlaybackTracking),
+}
+
+struct CacheEntry {
+    value: CachedValue,
+    fetched_at: Instant,
+    last_used: Instant,
+    refreshing: bool,
+}
+
+type Entries = Arc<Mutex<HashMap<String, CacheEntry>>>;
+
+/// TTL cache in front of another InnerTube backend.
+///
+/// `player` is never cached: stream URLs are short-lived and resolved per
+/// request. Only its tracking URLs are kept, so playback reports need no
+/// second player call. Errors are not cached; a failed background refresh
+/// leaves the stale entry in place until its stale window ends. Per-user views
+/// share one entry map but key entries by user, since signed-in responses are
+/// personalized.
+pub struct CachedInnerTube {
+    inner: Arc<dyn InnerTubeBackend>,
+    locale: Locale,
+    config: InnerTubeCacheConfig,
+    entries: Entries,
+    user_id: Option<Uuid>,
+}
+
+impl CachedInnerTube {
+    pub fn new(
+        inner: Arc<dyn InnerTubeBackend>,
+        locale: Locale,
+        config: InnerTubeCacheConfig,
+    ) -> Self {
+        Self {
+            inner,
+            locale,
+            config,
+            entries: Arc::default(),
+            user_id: None,
+        }
+    }
+
+    fn key(&self, endpoint: &str, parts: &[&str]) -> String {
+        format!(
+            "{endpoint}\n{}\n{}\n{}\n{}",
+            self.locale.hl,
+            self.locale.gl,
+            self.user_id.map(|id| id.to_string()).unwrap_or_default(),
+            parts.join("\n")
+        )
+    }
+
+    fn cached_tracking(&self, video_id: &str) -> Option<PlaybackTracking> {
+        let entries = lock_entries(&self.entries);
+        let entry = entries.get(&self.key("tracking", &[video_id]))?;
+        if entry.fetched_at.elapsed() >= self.config.tracking_ttl {
+            return None;
+        }
+        match &entry.value {
+            CachedValue::Tracking(tracking) => Some(tracking.clone()),
+            _ => None,
+        }
+    }
+
+    async fn cached<T>(
+        &self,
+        key: String,
+        ttl: Duration,
+        unwrap: fn(CachedValue) -> Option<T>,
+        wrap: fn(T) -> CachedValue,
+        fetch: impl FnOnce(Arc<dyn InnerTubeBackend>) -> BoxFuture<'static, Result<T, InnerTubeError>>
+        + Send
+        + 'static,
+    ) -> Result<T, InnerTubeError>
+    where
+        T: Clone + Send + 'static,
+    {
+        let now = Instant::now();
+        let mut refresh = false;
+        let hit = {
+            let mut entries = lock_entries(&self.entries);
+            match entries.get_mut(&key) {
+                Some(entry)
+                    if now.duration_since(entry.fetched_at) < ttl + self.config.stale_for =>
+                {
+                    entry.last_used = now;
+                    if now.duration_since(entry.fetched_at) >= ttl && !entry.refreshing {
+                        entry.refreshing = true;
+                        refresh = true;
+                    }
+                    unwrap(entry.value.clone())
+                }
+                _ => None,
+            }
+        };
+        if let Some(value) = hit {
+            if refresh {
+                let entries = self.entries.clone();
+                let max_entries = self.config.max_entries;
+                let future = fetch(self.inner.clone());
+                tokio::spawn(async move {
+                    let result = future.await;
+                    let mut entries = lock_entries(&entries);
+                    match result {
+                        Ok(value) => insert(&mut entries, key, wrap(value), max_entries),
+                        Err(err) => {
+                            eprintln!("innertube cache: background refresh failed: {err}");
+                            if let Some(entry) = entries.get_mut(&key) {
+                                entry.refreshing = false;
+                            }
+                        }
+                    }
+                });
+            }
+            return Ok(value);
+        }
+
+        let value = fetch(self.inner.clone()).await?;
+        insert(
+            &mut lock_entries(&self.entries),
+            key,
+            wrap(value.clone()),
+            self.config.max_entries,
+        );
+        Ok(value)
+    }
+}
+
+fn lock_entries(entries: &Entries) -> MutexGuard<'_, HashMap<String, CacheEntry>> {
+    entries
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn insert(
+    entries: &mut HashMap<String, CacheEntry>,
+    key: String,
+    value: CachedValue,
+    max_entries: usize,
+) {
+    let now = Instant::now();
+    entries.insert(
+        key,
+        CacheEntry {
+            value,
+            fetched_at: now,
+            last_used: now,
+            refreshing: false,
+        },
+    );
+    while entries.len() > max_entries {
+        let Some(oldest) = entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone())
+        else {
+            break;
+        };
+        entries.remove(&oldest);
+    }
+}
+
+impl InnerTubeBackend for CachedInnerTube {
+    fn browse<'a>(
+        &'a self,
+        browse_id: &'a str,
+        continuation: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<HomePage, InnerTubeError>> {
+        let key = self.key("browse", &[browse_id, continuation.unwrap_or_default()]);
+        let browse_id = browse_id.to_string();
+        let continuation = continuation.map(str::to_string);
+        Box::pin(self.cached(
+            key,
+            self.config.browse_ttl,
+            |value| match value {
+                CachedValue::Browse(page) => Some(page),
+                _ => None,
+            },
+            CachedValue::Browse,
+            move |inner| {
+                Box::pin(async move { inner.browse(&browse_id, continuation.as_deref()).await })
+            },
+        ))
+    }
+
+    fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<SearchPage, InnerTubeError>> {
+        let key = self.key("search", &[query]);
+        let query = query.to_string();
+        Box::pin(self.cached(
+            key,
+            self.config.search_ttl,
+            |value| match value {
+                CachedValue::Search(page) => Some(page),
+                _ => None,
+            },
+            CachedValue::Search,
+            move |inner| Box::pin(async move { inner.search(&query).await }),
+        ))
+    }
+
+    fn next<'a>(
+        &'a self,
+        video_id: &'a str,
+        continuation: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<NextPage, InnerTubeError>> {
+        let key = self.key("next", &[video_id, continuation.unwrap_or_default()]);
+        let video_id = video_id.to_string();
+        let continuation = continuation.map(str::to_string);
+        Box::pin(self.cached(
+            key,
+            self.config.next_ttl,
+            |value| match value {
+                CachedValue::Next(page) => Some(page),
+                _ => None,
+            },
+            CachedValue::Next,
+            move |inner| {
+                Box::pin(async move { inner.next(&video_id, continuation.as_deref()).await })
+            },
+        ))
+    }
+
+    fn player<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> BoxFuture<'a, Result<PlayerResponse, InnerTubeError>> {
+        Box::pin(async move {
+            let player = self.inner.player(video_id).await?;
+            if !player.tracking.playback_url.is_empty() {
+                insert(
+                    &mut lock_entries(&self.entries),
+                    self.key("tracking", &[video_id]),
+                    CachedValue::Tracking(player.tracking.clone()),
+                    self.config.max_entries,
+                );
+            }
+            Ok(player)
+        })
+    }
+
+    fn lyrics<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Lyrics>, InnerTubeError>> {
+        let key = self.key("lyrics", &[video_id]);
+        let video_id = video_id.to_string();
+        Box::pin(self.cached(
+            key,
+            self.config.lyrics_ttl,
+            |value| match value {
+                CachedValue::Lyrics(lyrics) => Some(lyrics),
+                _ => None,
+            },
+            CachedValue::Lyrics,
+            move |inner| Box::pin(async move { inner.lyrics(&video_id).await }),
+        ))
+    }
+
+    fn for_user(&self, user_id: Uuid) -> Option<Arc<dyn InnerTubeBackend>> {
+        Some(Arc::new(Self {
+            inner: self.inner.for_user(user_id)?,
+            locale: self.locale.clone(),
+            config: self.config.clone(),
+            entries: self.entries.clone(),
+            user_id: Some(user_id),
+        }))
+    }
+
+    fn report_playback<'a>(
+        &'a self,
+        video_id: &'a str,
+        played_ms: i64,
+        tracking: Option<&'a PlaybackTracking>,
+    ) -> BoxFuture<'a, Result<bool, InnerTubeError>> {
+        Box::pin(async move {
+            let cached = match tracking {
+                Some(_) => None,
+                None => self.cached_tracking(video_id),
+            };
+            self.inner
+                .report_playback(video_id, played_ms, tracking.or(cached.as_ref()))
+                .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::innertube::SongItem;
+
+    #[derive(Default)]
+    struct CountingBackend {
+        searches: AtomicUsize,
+        players: AtomicUsize,
+        fail: std::sync::atomic::AtomicBool,
+        reported: std::sync::Mutex<Vec<Option<String>>>,
+    }
+
+    impl InnerTubeBackend for CountingBackend {
+        fn browse<'a>(
+            &'a self,
+            _browse_id: &'a str,
+            _continuation: Option<&'a str>,
+        ) -> BoxFuture<'a, Result<HomePage, InnerTubeError>> {
+            Box::pin(async { Ok(HomePage::default()) })
+        }
+
+        fn search<'a>(
+            &'a self,
+            query: &'a str,
+        ) -> BoxFuture<'a, Result<SearchPage, InnerTubeError>> {
+            Box::pin(async move {
+                let call = self.searches.fetch_add(1, Ordering::SeqCst) + 1;
+                if self.fail.load(Ordering::SeqCst) {
+                    return Err(InnerTubeError::new("offline"));
+                }
+                Ok(SearchPage {
+                    songs: vec![SongItem {
+                        video_id: format!("{query}-{call}"),
+                        title: query.to_string(),
+                        artists: vec![],
+                        duration_ms: 0,
+                        thumbnail_url: String::new(),
+                        is_explicit: false,
+                        is_video: false,
+                    }],
+                    ..SearchPage::default()
+                })
+            })
+        }
+
+        fn next<'a>(
+            &'a self,
+            _video_id: &'a str,
+            _continuation: Option<&'a str>,
+        ) -> BoxFuture<'a, Result<NextPage, InnerTubeError>> {
+            Box::pin(async { Ok(NextPage::default()) })
+        }
+
+        fn player<'a>(
+            &'a self,
+            video_id: &'a str,
+        ) -> BoxFuture<'a, Result<PlayerResponse, InnerTubeError>> {
+            self.players.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                Ok(PlayerResponse {
+                    tracking: PlaybackTracking {
+                        playback_url: format!("https://s.youtube.com/playback?docid={video_id}"),
+                        watchtime_url: String::new(),
+                    },
+                    ..PlayerResponse::default()
+                })
+            })
+        }
+
+        fn lyrics<'a>(
+            &'a self,
+            _video_id: &'a str,
+        ) -> BoxFuture<'a, Result<Option<Lyrics>, InnerTubeError>> {
+            Box::pin(async { Ok(None) })
+        }
+
+        fn for_user(&self, _user_id: Uuid) -> Option<Arc<dyn InnerTubeBackend>> {
+            Some(Arc::new(CountingBackend::default()))
+        }
+
+        fn report_playback<'a>(
+            &'a self,
+            _video_id: &'a str,
+            _played_ms: i64,
+            tracking: Option<&'a PlaybackTracking>,
+        ) -> BoxFuture<'a, Result<bool, InnerTubeError>> {
+            self.reported
+                .lock()
+                .unwrap()
+                .push(tracking.map(|tracking| tracking.playback_url.clone()));
+            Box::pin(async { Ok(true) })
+        }
+    }
+
+    fn first_video_id(page: &SearchPage) -> &str {
+        &page.songs[0].video_id
+    }
+
+    #[tokio::test]
+    async fn fresh_entries_are_served_from_cache_and_player_is_not_cached() {
+        let backend = Arc::new(CountingBackend::default());
+        let cache = CachedInnerTube::new(
+            backend.clone(),
+            Locale::default(),
+            InnerTubeCacheConfig::default(),
+        );
+        let first = cache.search("song").await.unwrap();
+        let second = cache.search("song").await.unwrap();
+        assert_eq!(first_video_id(&first), "song-1");
+        assert_eq!(first, second);
+        cache.search("other").await.unwrap();
+        assert_eq!(backend.searches.load(Ordering::SeqCst), 2);
+
+        cache.player("abc").await.unwrap();
+        cache.player("abc").await.unwrap();
+        assert_eq!(backend.players.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn cache_keys_include_locale_and_evict_least_recently_used() {
+        let backend = Arc::new(CountingBackend::default());
+        let config = InnerTubeCacheConfig {
+            max_entries: 2,
+            ..InnerTubeCacheConfig::default()
+        };
+        let cache = CachedInnerTube::new(backend.clone(), Locale::default(), config.clone());
+        cache.search("a").await.unwrap();
+        cache.search("b").await.unwrap();
+        cache.search("a").await.unwrap();
+        cache.search("c").await.unwrap();
+        assert_eq!(backend.searches.load(Ordering::SeqCst), 3);
+        // "b" was least recently used when "c" arrived.
+        cache.search("a").await.unwrap();
+        assert_eq!(backend.searches.load(Ordering::SeqCst), 3);
+        cache.search("b").await.unwrap();
+        assert_eq!(backend.searches.load(Ordering::SeqCst), 4);
+
+        let german = CachedInnerTube::new(
+            backend.clone(),
+            Locale {
+                hl: "de".into(),
+                gl: "DE".into(),
+            },
+            config,
+        );
+        assert_ne!(german.key("search", &["a"]), cache.key("search", &["a"]));
+    }
+
+    #[tokio::test]
+    async fn per_user_views_share_storage_but_not_entries() {
+        let backend = Arc::new(CountingBackend::default());
+        let cache = CachedInnerTube::new(
+            backend.clone(),
+            Locale::default(),
+            InnerTubeCacheConfig::default(),
+        );
+        cache.search("a").await.unwrap();
+        let member = cache.for_user(Uuid::new_v4()).unwrap();
+        member.search("a").await.unwrap();
+        member.search("a").await.unwrap();
+        assert_eq!(backend.searches.load(Ordering::SeqCst), 1);
+        assert_eq!(lock_entries(&cache.entries).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn playback_reports_reuse_tracking_urls_from_player_responses() {
+        let backend = Arc::new(CountingBackend::default());
+        let cache = CachedInnerTube::new(
+            backend.clone(),
+            Locale::default(),
+            InnerTubeCacheConfig::default(),
+        );
+        // Another user's view over the same backend and entries.
+        let member = CachedInnerTube {
+            inner: backend.clone(),
+            locale: Locale::default(),
+            config: InnerTubeCacheConfig::default(),
+            entries: cache.entries.clone(),
+            user_id: Some(Uuid::new_v4()),
+        };
+        cache.report_playback("abc", 30_000, None).await.unwrap();
+        cache.player("abc").await.unwrap();
+        cache.report_playback("abc", 30_000, None).await.unwrap();
+        member.report_playback("abc", 30_000, None).await.unwrap();
+        assert_eq!(
+            *backend.reported.lock().unwrap(),
+            vec![
+                None,
+                Some("https://s.youtube.com/playback?docid=abc".into()),
+                None,
+            ]
+        );
+        assert_eq!(backend.players.load(Ordering::SeqCst), 1);
+    }
+
+    async fn settle(cache: &CachedInnerTube) {
+        for _ in 0..200 {
+            if !lock_entries(&cache.entries)
+                .values()
+                .any(|entry| entry.refreshing)
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("background refresh did not finish");
+    }
+
+    #[tokio::test]
+    async fn stale_entries_are_served_while_refreshing_and_survive_outages() {
+        let backend = Arc::new(CountingBackend::default());
+        let cache = CachedInnerTube::new(
+            backend.clone(),
+            Locale::default(),
+            InnerTubeCacheConfig {
+                search_ttl: Duration::ZERO,
+                ..InnerTubeCacheConfig::default()
+            },
+        );
+        assert_eq!(
+            first_video_id(&cache.search("song").await.unwrap()),
+            "song-1"
+        );
+        assert_eq!(
+            first_video_id(&cache.search("song").await.unwrap()),
+            "song-1"
+        );
+        settle(&cache).await;
+        assert_eq!(backend.searches.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            first_video_id(&cache.search("song").await.unwrap()),
+            "song-2"
+        );
+        settle(&cache).await;
+
+        backend.fail.store(true, Ordering::SeqCst);
+        assert_eq!(
+            first_video_id(&cache.search("song").await.unwrap()),
+            "song-3"
+        );
+        settle(&cache).await;
+        assert_eq!(backend.searches.load(Ordering::SeqCst), 4);
+        assert_eq!(
+            first_video_id(&cache.search("song").await.unwrap()),
+            "song-3"
+        );
+        settle(&cache).await;
+        assert!(cache.search("uncached").await.is_err());
+    }
+}
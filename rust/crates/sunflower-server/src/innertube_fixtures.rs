@@ -0,0 +1,317 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use futures_util::future::BoxFuture;
+use serde_json::{Value, json};
+use sha1::{Digest, Sha1};
+use sunflower_core::Lyrics;
+use uuid::Uuid;
+
+use crate::{
+    innertube::{
+        HomePage, HttpInnerTubeClient, InnerTubeBackend, InnerTubeCall, InnerTubeError, NextPage,
+        PlaybackTracking, PlayerResponse, SearchPage, extract_lyrics_browse_id, parse_home_page,
+        parse_lyrics_page, parse_next_page, parse_player_response, parse_search_page,
+    },
+    parser_drift::ParserDrift,
+};
+
+/// Wraps the HTTP client and writes every raw request/response pair it makes
+/// to a fixture directory.
+///
+/// Each fixture file is `{"endpoint", "request", "response"}` where `request`
+/// is the POST body without its `context` object: the context only carries
+/// client versions, locale and per-user tokens, none of which change what the
+/// parsers see, and leaving it out keeps tokens out of committed fixtures.
+#[derive(Clone)]
+pub struct RecordingInnerTube {
+    client: HttpInnerTubeClient,
+    dir: Arc<PathBuf>,
+}
+
+impl RecordingInnerTube {
+    pub fn new(client: HttpInnerTubeClient, dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            client,
+            dir: Arc::new(dir),
+        })
+    }
+
+    async fn send(&self, call: &InnerTubeCall) -> Result<Value, InnerTubeError> {
+        let raw = self.client.send(call).await?;
+        self.record(call, &raw);
+        Ok(raw)
+    }
+
+    fn record(&self, call: &InnerTubeCall, response: &Value) {
+        let request = fixture_request(&call.fields);
+        let key = fixture_key(call.path, &request);
+        let name = call.path.rsplit('/').next().unwrap_or("innertube");
+        let digest = Sha1::digest(key.as_bytes());
+        let hash: String = digest[..6]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        let path = self.dir.join(format!("{name}-{hash}.json"));
+        let fixture = json!({
+            "endpoint": call.path,
+            "request": request,
+            "response": response,
+        });
+        let written = serde_json::to_vec_pretty(&fixture)
+            .map_err(io::Error::other)
+            .and_then(|body| fs::write(&path, body));
+        if let Err(err) = written {
+            eprintln!("innertube: record fixture {}: {err}", path.display());
+        }
+    }
+}
+
+impl InnerTubeBackend for RecordingInnerTube {
+    fn browse<'a>(
+        &'a self,
+        browse_id: &'a str,
+        continuation: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<HomePage, InnerTubeError>> {
+        Box::pin(async move {
+            let raw = self
+                .send(&InnerTubeCall::browse(browse_id, continuation))
+                .await?;
+            Ok(self.client.parse_browse(&raw))
+        })
+    }
+
+    fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<SearchPage, InnerTubeError>> {
+        Box::pin(async move {
+            let raw = self.send(&InnerTubeCall::search(query)).await?;
+            Ok(self.client.parse_search(&raw))
+        })
+    }
+
+    fn next<'a>(
+        &'a self,
+        video_id: &'a str,
+        continuation: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<NextPage, InnerTubeError>> {
+        Box::pin(async move {
+            let raw = self
+                .send(&InnerTubeCall::next(video_id, continuation))
+                .await?;
+            Ok(self.client.parse_next(&raw))
+        })
+    }
+
+    fn player<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> BoxFuture<'a, Result<PlayerResponse, InnerTubeError>> {
+        Box::pin(async move {
+            let raw = self.send(&self.client.player_call(video_id)).await?;
+            Ok(self.client.parse_player(video_id, &raw).await)
+        })
+    }
+
+    fn lyrics<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Lyrics>, InnerTubeError>> {
+        Box::pin(async move {
+            let next = self.send(&InnerTubeCall::next(video_id, None)).await?;
+            let Some(browse_id) = extract_lyrics_browse_id(&next) else {
+                return Ok(None);
+            };
+            let raw = self.send(&InnerTubeCall::lyrics(&browse_id)).await?;
+            Ok(parse_lyrics_page(&raw))
+        })
+    }
+
+    fn for_user(&self, user_id: Uuid) -> Option<Arc<dyn InnerTubeBackend>> {
+        let client = self.client.for_user_client(user_id)?;
+        Some(Arc::new(Self {
+            client,
+            dir: self.dir.clone(),
+        }))
+    }
+
+    fn report_playback<'a>(
+        &'a self,
+        video_id: &'a str,
+        played_ms: i64,
+        tracking: Option<&'a PlaybackTracking>,
+    ) -> BoxFuture<'a, Result<bool, InnerTubeError>> {
+        self.client.report_playback(video_id, played_ms, tracking)
+    }
+}
+
+/// Serves fixtures recorded by `RecordingInnerTube` without a network;
+/// requests without a fixture fail. Ciphered formats are skipped, as there
+/// is no `base.js` to decipher them with.
+#[derive(Clone)]
+pub struct ReplayInnerTube {
+    fixtures: Arc<BTreeMap<String, Value>>,
+}
+
+impl ReplayInnerTube {
+    pub fn from_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let mut fixtures = BTreeMap::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let raw: Value = serde_json::from_slice(&fs::read(&path)?).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {err}", path.display()),
+                )
+            })?;
+            let (Some(endpoint), Some(request), Some(response)) = (
+                raw.get("endpoint").and_then(Value::as_str),
+                raw.get("request"),
+                raw.get("response"),
+            ) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: missing endpoint, request or response", path.display()),
+                ));
+            };
+            fixtures.insert(
+                fixture_key(endpoint, &fixture_request(request)),
+                response.clone(),
+            );
+        }
+        Ok(Self {
+            fixtures: Arc::new(fixtures),
+        })
+    }
+
+    fn replay(&self, call: &InnerTubeCall) -> Result<Value, InnerTubeError> {
+        let request = fixture_request(&call.fields);
+        self.fixtures
+            .get(&fixture_key(call.path, &request))
+            .cloned()
+            .ok_or_else(|| {
+                InnerTubeError::new(format!(
+                    "innertube replay: no fixture for {} {request}",
+                    call.path
+                ))
+            })
+    }
+}
+
+impl InnerTubeBackend for ReplayInnerTube {
+    fn browse<'a>(
+        &'a self,
+        browse_id: &'a str,
+        continuation: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<HomePage, InnerTubeError>> {
+        let page = self
+            .replay(&InnerTubeCall::browse(browse_id, continuation))
+            .map(|raw| parse_home_page(&raw, &mut ParserDrift::default()));
+        Box::pin(async move { page })
+    }
+
+    fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<SearchPage, InnerTubeError>> {
+        let page = self
+            .replay(&InnerTubeCall::search(query))
+            .map(|raw| parse_search_page(&raw, &mut ParserDrift::default()));
+        Box::pin(async move { page })
+    }
+
+    fn next<'a>(
+        &'a self,
+        video_id: &'a str,
+        continuation: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<NextPage, InnerTubeError>> {
+        let page = self
+            .replay(&InnerTubeCall::next(video_id, continuation))
+            .map(|raw| parse_next_page(&raw, &mut ParserDrift::default()));
+        Box::pin(async move { page })
+    }
+
+    fn player<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> BoxFuture<'a, Result<PlayerResponse, InnerTubeError>> {
+        let player = self
+            .replay(&InnerTubeCall::player(video_id))
+            .map(|raw| parse_player_response(&raw, &mut ParserDrift::default(), None));
+        Box::pin(async move { player })
+    }
+
+    fn lyrics<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Lyrics>, InnerTubeError>> {
+        let lyrics = self
+            .replay(&InnerTubeCall::next(video_id, None))
+            .and_then(|next| match extract_lyrics_browse_id(&next) {
+                Some(browse_id) => self
+                    .replay(&InnerTubeCall::lyrics(&browse_id))
+                    .map(|raw| parse_lyrics_page(&raw)),
+                None => Ok(None),
+            });
+        Box::pin(async move { lyrics })
+    }
+}
+
+/// The request minus `context`, and minus the `base.js` signature timestamp
+/// in `playbackContext`, which changes with every player release.
+fn fixture_request(fields: &Value) -> Value {
+    let mut request = fields.clone();
+    if let Some(object) = request.as_object_mut() {
+        object.remove("context");
+        object.remove("playbackContext");
+    }
+    request
+}
+
+/// serde_json objects serialize with sorted keys, so the rendered request is a
+/// stable lookup key.
+fn fixture_key(endpoint: &str, request: &Value) -> String {
+    format!("{endpoint} {request}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::innertube::Locale;
+
+    #[test]
+    fn recorded_fixtures_replay_by_request_fields() {
+        let dir = std::env::temp_dir().join(format!("sunflower-fixtures-{}", Uuid::new_v4()));
+        let client = HttpInnerTubeClient::new("http://127.0.0.1:9", Locale::default()).unwrap();
+        let recorder = RecordingInnerTube::new(client.clone(), &dir).unwrap();
+        recorder.record(
+            &InnerTubeCall::search("rick astley"),
+            &json!({"contents": {}}),
+        );
+        let mut player = InnerTubeCall::player("abc");
+        player.fields["playbackContext"] =
+            json!({ "contentPlaybackContext": { "signatureTimestamp": 20000 } });
+        recorder.record(&player, &json!({"videoDetails": {"videoId": "abc"}}));
+        let files = fs::read_dir(&dir).unwrap().count();
+        assert_eq!(files, 2);
+
+        let replay = ReplayInnerTube::from_dir(&dir).unwrap();
+        assert_eq!(
+            replay
+                .replay(&InnerTubeCall::search("rick astley"))
+                .unwrap(),
+            json!({"contents": {}})
+        );
+        assert_eq!(
+            replay.replay(&client.player_call("abc")).unwrap()["videoDetails"]["videoId"],
+            "abc"
+        );
+        let missing = replay.replay(&InnerTubeCall::search("other")).unwrap_err();
+        assert!(missing.to_string().contains("no fixture"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rand::Rng;
+use sunflower_core::AdminInnerTubeProfileResponse;
+
+/// How one InnerTube call is retried and when a client profile is taken out
+/// of rotation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per client profile, including the first.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub attempt_timeout: Duration,
+    /// Budget for the whole call across retries and profile failover. Kept
+    /// under the 8s handler timeouts so a fallback answer still arrives.
+    pub deadline: Duration,
+    /// Consecutive failed calls before a profile's breaker opens.
+    pub breaker_threshold: u32,
+    pub breaker_cooldown: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 2,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(2),
+            attempt_timeout: Duration::from_secs(4),
+            deadline: Duration::from_secs(7),
+            breaker_threshold: 5,
+            breaker_cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Full-jitter exponential backoff before retry number `retry` (1-based).
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let cap = self
+            .base_delay
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+            .min(self.max_delay);
+        if cap.is_zero() {
+            return cap;
+        }
+        rand::thread_rng().gen_range(Duration::ZERO..=cap)
+    }
+}
+
+#[derive(Default)]
+struct ProfileState {
+    served: u64,
+    failures: u64,
+    retries: u64,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    last_served_at: Option<DateTime<Utc>>,
+}
+
+/// Circuit breakers and served/failed counters per InnerTube client profile.
+#[derive(Default)]
+pub struct InnerTubeProfileHealth {
+    profiles: Mutex<BTreeMap<&'static str, ProfileState>>,
+}
+
+impl InnerTubeProfileHealth {
+    fn lock_profiles(&self) -> MutexGuard<'_, BTreeMap<&'static str, ProfileState>> {
+        self.profiles
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Closed breakers allow calls. Once an open breaker's cooldown has
+    /// passed it lets one probe through and keeps failing the rest fast until
+    /// the probe's result closes or reopens it; a probe that never reports
+    /// back is replaced after another cooldown.
+    pub fn allows(&self, profile: &'static str, policy: &RetryPolicy) -> bool {
+        let mut profiles = self.lock_profiles();
+        let Some(open_until) = profiles
+            .get_mut(profile)
+            .and_then(|state| state.open_until.as_mut())
+        else {
+            return true;
+        };
+        let now = Instant::now();
+        if now < *open_until {
+            return false;
+        }
+        *open_until = now + policy.breaker_cooldown;
+        true
+    }
+
+    pub fn record_success(&self, profile: &'static str) {
+        let mut profiles = self.lock_profiles();
+        let state = profiles.entry(profile).or_default();
+        state.served += 1;
+        state.consecutive_failures = 0;
+        state.open_until = None;
+        state.last_served_at = Some(Utc::now());
+    }
+
+    pub fn record_retry(&self, profile: &'static str) {
+        self.lock_profiles().entry(profile).or_default().retries += 1;
+    }
+
+    pub fn record_failure(&self, profile: &'static str, policy: &RetryPolicy) {
+        let mut profiles = self.lock_profiles();
+        let state = profiles.entry(profile).or_default();
+        state.failures += 1;
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= policy.breaker_threshold {
+            if state
+                .open_until
+                .is_none_or(|open_until| Instant::now() >= open_until)
+            {
+                eprintln!(
+                    "innertube: {profile} failed {} calls in a row, pausing it for {}s",
+                    state.consecutive_failures,
+                    policy.breaker_cooldown.as_secs()
+                );
+            }
+            state.open_until = Some(Instant::now() + policy.breaker_cooldown);
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<AdminInnerTubeProfileResponse> {
+        let now = Instant::now();
+        self.lock_profiles()
+            .iter()
+            .map(|(profile, state)| AdminInnerTubeProfileResponse {
+                profile: profile.to_string(),
+                served: state.served,
+                failures: state.failures,
+                retries: state.retries,
+                consecutive_failures: state.consecutive_failures,
+                circuit_open: state.open_until.is_some_and(|open_until| now < open_until),
+                last_served_at: state
+                    .last_served_at
+                    .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_is_jittered_within_exponential_cap() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(350),
+            ..RetryPolicy::default()
+        };
+        for _ in 0..50 {
+            assert!(policy.backoff(1) <= Duration::from_millis(100));
+            assert!(policy.backoff(2) <= Duration::from_millis(200));
+            assert!(policy.backoff(6) <= Duration::from_millis(350));
+        }
+        let zero = RetryPolicy {
+            base_delay: Duration::ZERO,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(zero.backoff(3), Duration::ZERO);
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_and_closes_on_success() {
+        let policy = RetryPolicy {
+            breaker_threshold: 2,
+            breaker_cooldown: Duration::from_secs(60),
+            ..RetryPolicy::default()
+        };
+        let health = InnerTubeProfileHealth::default();
+        health.record_failure("ANDROID_MUSIC", &policy);
+        assert!(health.allows("ANDROID_MUSIC", &policy));
+        health.record_failure("ANDROID_MUSIC", &policy);
+        assert!(!health.allows("ANDROID_MUSIC", &policy));
+        assert!(health.allows("WEB_REMIX", &policy));
+
+        health.record_retry("ANDROID_MUSIC");
+        health.record_success("WEB_REMIX");
+        let snapshot = health.snapshot();
+        assert_eq!(snapshot[0].profile, "ANDROID_MUSIC");
+        assert!(snapshot[0].circuit_open);
+        assert_eq!(
+            (
+                snapshot[0].failures,
+                snapshot[0].retries,
+                snapshot[0].served
+            ),
+            (2, 1, 0)
+        );
+        assert_eq!(snapshot[1].profile, "WEB_REMIX");
+        assert_eq!(snapshot[1].served, 1);
+        assert!(snapshot[1].last_served_at.is_some());
+
+        health.record_success("ANDROID_MUSIC");
+        assert!(health.allows("ANDROID_MUSIC", &policy));
+        assert!(!health.snapshot()[0].circuit_open);
+    }
+
+    #[test]
+    fn half_open_breaker_lets_one_probe_through() {
+        let policy = RetryPolicy {
+            breaker_threshold: 1,
+            breaker_cooldown: Duration::ZERO,
+            ..RetryPolicy::default()
+        };
+        let health = InnerTubeProfileHealth::default();
+        health.record_failure("ANDROID_VR", &policy);
+        let waiting = RetryPolicy {
+            breaker_cooldown: Duration::from_secs(60),
+            ..policy.clone()
+        };
+        assert!(health.allows("ANDROID_VR", &waiting));
+        assert!(!health.allows("ANDROID_VR", &waiting));
+        assert!(!health.allows("ANDROID_VR", &waiting));
+
+        health.record_failure("ANDROID_VR", &waiting);
+        assert!(!health.allows("ANDROID_VR", &waiting));
+        assert!(health.snapshot()[0].circuit_open);
+    }
+}
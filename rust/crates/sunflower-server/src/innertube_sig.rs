@@ -0,0 +1,404 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use regex::Regex;
+
+use crate::{innertube::InnerTubeError, path_segment, query_param};
+
+/// How long the player hash read from `iframe_api` is trusted before it is
+/// looked up again. A rotated player shows up as a new hash and is parsed
+/// fresh; parsed scripts stay cached by hash.
+const CURRENT_PLAYER_TTL: Duration = Duration::from_secs(60 * 60);
+
+const NAME: &str = r"[a-zA-Z0-9$_]+";
+
+/// One step of a `base.js` signature or `n` transform.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SigOp {
+    Reverse,
+    /// `a.splice(0, b)`: drop the first `b` characters.
+    Splice(usize),
+    /// Swap the first character with the one at `b % len`.
+    Swap(usize),
+}
+
+/// Transforms extracted from one `base.js`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlayerScript {
+    /// Sent as `signatureTimestamp` so WEB clients get ciphers this script
+    /// can undo.
+    pub signature_timestamp: Option<u32>,
+    sig_ops: Vec<SigOp>,
+    /// `None` when the `n` function is not a plain op list; URLs keep their
+    /// original `n`, which plays but may be throttled.
+    n_ops: Option<Vec<SigOp>>,
+}
+
+impl PlayerScript {
+    pub fn parse(js: &str) -> Result<Self, InnerTubeError> {
+        let sig_name = find_signature_function_name(js)
+            .ok_or_else(|| InnerTubeError::new("base.js: signature function not found"))?;
+        let sig_ops = function_ops(js, &sig_name).ok_or_else(|| {
+            InnerTubeError::new(format!(
+                "base.js: cannot read signature function {sig_name}"
+            ))
+        })?;
+        let n_ops = find_n_function_name(js).and_then(|name| function_ops(js, &name));
+        Ok(Self {
+            signature_timestamp: capture(js, r"(?:signatureTimestamp|sts)\s*:\s*(\d{5})")
+                .and_then(|value| value.parse().ok()),
+            sig_ops,
+            n_ops,
+        })
+    }
+
+    pub fn decipher(&self, signature: &str) -> String {
+        apply_ops(&self.sig_ops, signature)
+    }
+
+    pub fn transform_n(&self, n: &str) -> Option<String> {
+        self.n_ops.as_ref().map(|ops| apply_ops(ops, n))
+    }
+
+    /// Builds a playable URL from a format's `signatureCipher` (`s`, `sp`,
+    /// `url`), with `n` transformed when possible.
+    pub fn resolve_cipher(&self, cipher: &str) -> Option<String> {
+        let url = query_param(cipher, "url").filter(|url| !url.is_empty())?;
+        let signature = query_param(cipher, "s").filter(|s| !s.is_empty())?;
+        let param = query_param(cipher, "sp")
+            .filter(|sp| !sp.is_empty())
+            .unwrap_or_else(|| "signature".into());
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let signed = format!(
+            "{url}{separator}{param}={}",
+            path_segment(&self.decipher(&signature))
+        );
+        Some(self.apply_n(&signed))
+    }
+
+    /// Rewrites the `n` query parameter of `url`, or returns it unchanged.
+    pub fn apply_n(&self, url: &str) -> String {
+        let Some((base, query)) = url.split_once('?') else {
+            return url.to_string();
+        };
+        let Some(n) = query_param(query, "n") else {
+            return url.to_string();
+        };
+        let Some(transformed) = self.transform_n(&n) else {
+            return url.to_string();
+        };
+        let query = query
+            .split('&')
+            .map(|pair| {
+                if pair.split_once('=').map_or(pair, |(key, _)| key) == "n" {
+                    format!("n={}", path_segment(&transformed))
+                } else {
+                    pair.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+        format!("{base}?{query}")
+    }
+}
+
+fn apply_ops(ops: &[SigOp], input: &str) -> String {
+    let mut chars = input.chars().collect::<Vec<_>>();
+    for op in ops {
+        match *op {
+            SigOp::Reverse => chars.reverse(),
+            SigOp::Splice(count) => {
+                chars.drain(..count.min(chars.len()));
+            }
+            SigOp::Swap(index) => {
+                if !chars.is_empty() {
+                    let index = index % chars.len();
+                    chars.swap(0, index);
+                }
+            }
+        }
+    }
+    chars.into_iter().collect()
+}
+
+fn capture(haystack: &str, pattern: &str) -> Option<String> {
+    Regex::new(pattern)
+        .ok()?
+        .captures(haystack)?
+        .get(1)
+        .map(|found| found.as_str().to_string())
+}
+
+fn find_signature_function_name(js: &str) -> Option<String> {
+    [
+        format!(r"\bc\s*&&\s*\(\s*c\s*=\s*({NAME})\(\s*decodeURIComponent\(\s*c\s*\)"),
+        format!(r#"\b[cs]\s*&&\s*[adf]\.set\([^,]+\s*,\s*encodeURIComponent\(\s*({NAME})\("#),
+        format!(r#"\b({NAME})\s*=\s*function\(\s*a\s*\)\s*\{{\s*a\s*=\s*a\.split\(\s*""\s*\)"#),
+    ]
+    .iter()
+    .find_map(|pattern| capture(js, pattern))
+}
+
+fn find_n_function_name(js: &str) -> Option<String> {
+    let pattern = Regex::new(&format!(
+        r#"\.get\("n"\)\)\s*&&\s*\(\s*b\s*=\s*({NAME})(?:\[(\d+)\])?\(\s*b\s*\)"#
+    ))
+    .ok()?;
+    let found = pattern.captures(js)?;
+    let name = found.get(1)?.as_str();
+    let Some(index) = found.get(2) else {
+        return Some(name.to_string());
+    };
+    // `b=Xma[0](b)`: the function is an element of an array variable.
+    let index = index.as_str().parse::<usize>().ok()?;
+    let array = capture(
+        js,
+        &format!(r"var\s+{}\s*=\s*\[([^\]]+)\]", regex::escape(name)),
+    )?;
+    array
+        .split(',')
+        .nth(index)
+        .map(|element| element.trim().to_string())
+}
+
+/// Reads a `function(a){a=a.split("");…;return a.join("")}` body whose
+/// statements all call methods on one helper object.
+fn function_ops(js: &str, name: &str) -> Option<Vec<SigOp>> {
+    let name = regex::escape(name);
+    let body = capture(
+        js,
+        &format!(
+            r#"(?s)(?:function\s+{name}|[{{;,\s]{name}\s*=\s*function)\s*\(\s*a\s*\)\s*\{{\s*a\s*=\s*a\.split\(\s*""\s*\)\s*;(.*?)return\s+a\.join\(\s*""\s*\)\s*\}}"#
+        ),
+    )?;
+    let call = Regex::new(&format!(
+        r#"^(?:a\s*=\s*)?({NAME})(?:\.({NAME})|\["({NAME})"\])\(\s*a\s*,\s*(\d+)\s*\)$"#
+    ))
+    .ok()?;
+    let mut helper = None::<String>;
+    let mut calls = Vec::new();
+    for statement in body.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+        let found = call.captures(statement)?;
+        let object = found.get(1)?.as_str();
+        if helper.get_or_insert_with(|| object.to_string()) != object {
+            return None;
+        }
+        let method = found.get(2).or_else(|| found.get(3))?.as_str().to_string();
+        calls.push((method, found.get(4)?.as_str().parse::<usize>().ok()?));
+    }
+    let methods = helper_methods(js, &helper?)?;
+    calls
+        .into_iter()
+        .map(|(method, arg)| match methods.get(&method)? {
+            HelperMethod::Reverse => Some(SigOp::Reverse),
+            HelperMethod::Splice => Some(SigOp::Splice(arg)),
+            HelperMethod::Swap => Some(SigOp::Swap(arg)),
+        })
+        .collect()
+}
+
+enum HelperMethod {
+    Reverse,
+    Splice,
+    Swap,
+}
+
+fn helper_methods(js: &str, object: &str) -> Option<HashMap<String, HelperMethod>> {
+    let body = capture(
+        js,
+        &format!(r"(?s)var\s+{}\s*=\s*\{{(.*?)\}}\s*;", regex::escape(object)),
+    )?;
+    let method = Regex::new(&format!(
+        r"({NAME})\s*:\s*function\s*\(\s*a(?:\s*,\s*b)?\s*\)\s*\{{([^}}]*)\}}"
+    ))
+    .ok()?;
+    let methods = method
+        .captures_iter(&body)
+        .filter_map(|found| {
+            let code = found.get(2)?.as_str();
+            let kind = if code.contains("reverse") {
+                HelperMethod::Reverse
+            } else if code.contains("splice") {
+                HelperMethod::Splice
+            } else if code.contains("a[0]") {
+                HelperMethod::Swap
+            } else {
+                return None;
+            };
+            Some((found.get(1)?.as_str().to_string(), kind))
+        })
+        .collect::<HashMap<_, _>>();
+    (!methods.is_empty()).then_some(methods)
+}
+
+/// The `abcd1234` in `/s/player/abcd1234/player_ias.vflset/en_US/base.js`.
+pub fn player_hash(url: &str) -> Option<&str> {
+    let rest = &url[url.find("/s/player/")? + "/s/player/".len()..];
+    let hash = rest.split('/').next()?;
+    (!hash.is_empty()).then_some(hash)
+}
+
+/// Reads the current player hash out of an `iframe_api` response, where the
+/// script path appears with escaped slashes.
+pub fn player_hash_from_iframe_api(body: &str) -> Option<String> {
+    capture(body, r"player\\?/([0-9a-fA-F]{8})\\?/")
+}
+
+pub fn player_js_url(base_url: &str, hash: &str) -> String {
+    format!("{base_url}/s/player/{hash}/player_ias.vflset/en_US/base.js")
+}
+
+/// Parsed scripts keyed by player hash, plus the hash currently served.
+#[derive(Default)]
+pub struct PlayerScriptCache {
+    scripts: Mutex<HashMap<String, Arc<PlayerScript>>>,
+    current: Mutex<Option<(Instant, String)>>,
+}
+
+impl PlayerScriptCache {
+    fn lock_scripts(&self) -> MutexGuard<'_, HashMap<String, Arc<PlayerScript>>> {
+        self.scripts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_current(&self) -> MutexGuard<'_, Option<(Instant, String)>> {
+        self.current
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn get(&self, hash: &str) -> Option<Arc<PlayerScript>> {
+        self.lock_scripts().get(hash).cloned()
+    }
+
+    pub fn insert(&self, hash: &str, script: PlayerScript) -> Arc<PlayerScript> {
+        let script = Arc::new(script);
+        self.lock_scripts().insert(hash.to_string(), script.clone());
+        self.set_current(hash);
+        script
+    }
+
+    /// The hash last read from `iframe_api`, while still fresh.
+    pub fn current_hash(&self) -> Option<String> {
+        self.lock_current()
+            .as_ref()
+            .filter(|(checked_at, _)| checked_at.elapsed() < CURRENT_PLAYER_TTL)
+            .map(|(_, hash)| hash.clone())
+    }
+
+    pub fn set_current(&self, hash: &str) {
+        *self.lock_current() = Some((Instant::now(), hash.to_string()));
+    }
+
+    /// The parsed script for the current player, if it has been fetched.
+    pub fn current(&self) -> Option<Arc<PlayerScript>> {
+        self.get(&self.current_hash()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNATURE: &str = "AOq0QJ8wRQIgF4yk0XGb2lHhP3fQOK8qvV0XYcbXx9T7vDcAE2Kf0pUCIQDz";
+
+    fn fixture(hash: &str) -> PlayerScript {
+        let path = format!(
+            "{}/testdata/innertube/player/base-{hash}.js",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        PlayerScript::parse(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parses_signature_and_n_ops_from_base_js() {
+        let script = fixture("3f2a1b7c");
+        assert_eq!(script.signature_timestamp, Some(20158));
+        assert_eq!(
+            script.sig_ops,
+            vec![
+                SigOp::Swap(47),
+                SigOp::Reverse,
+                SigOp::Splice(2),
+                SigOp::Swap(23),
+                SigOp::Reverse,
+                SigOp::Splice(1),
+            ]
+        );
+        assert_eq!(
+            script.decipher(SIGNATURE),
+            "Oq0QJ8wRQIgF4yk0XGb2lHhP3fQOK8qvVQXYcbXx9T7vDcAE2Kf0pUCI0"
+        );
+        assert_eq!(
+            script.transform_n("kP1xQdY3bGhL9w").as_deref(),
+            Some("LwGb3YdQx1Pk")
+        );
+    }
+
+    #[test]
+    fn unreadable_n_function_leaves_n_untouched() {
+        let script = fixture("9e8d7c6b");
+        assert_eq!(script.signature_timestamp, Some(20101));
+        assert_eq!(
+            script.decipher(SIGNATURE),
+            "ICUp0fK2EAcDv7T9xXbcYX0Vvq8KOQf3PhHl2bGX0ky4FgIARw8JQ0qOQ"
+        );
+        assert_eq!(script.transform_n("kP1xQdY3bGhL9w"), None);
+        let url = "https://rr1.googlevideo.com/videoplayback?itag=251&n=kP1xQdY3bGhL9w";
+        assert_eq!(script.apply_n(url), url);
+    }
+
+    #[test]
+    fn resolves_signature_cipher_urls() {
+        let script = fixture("3f2a1b7c");
+        let cipher = format!(
+            "s={SIGNATURE}&sp=sig&url={}",
+            path_segment(
+                "https://rr1.googlevideo.com/videoplayback?itag=251&n=kP1xQdY3bGhL9w&c=WEB_REMIX"
+            )
+        );
+        assert_eq!(
+            script.resolve_cipher(&cipher).as_deref(),
+            Some(
+                "https://rr1.googlevideo.com/videoplayback?itag=251&n=LwGb3YdQx1Pk&c=WEB_REMIX&sig=Oq0QJ8wRQIgF4yk0XGb2lHhP3fQOK8qvVQXYcbXx9T7vDcAE2Kf0pUCI0"
+            )
+        );
+        assert_eq!(
+            script.resolve_cipher("sp=sig&url=https%3A%2F%2Fexample"),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_rejects_scripts_without_a_signature_function() {
+        assert!(PlayerScript::parse("var a=1;").is_err());
+    }
+
+    #[test]
+    fn player_hashes_come_from_urls_and_iframe_api() {
+        assert_eq!(
+            player_hash(
+                "https://www.youtube.com/s/player/3f2a1b7c/player_ias.vflset/en_US/base.js"
+            ),
+            Some("3f2a1b7c")
+        );
+        assert_eq!(player_hash("https://example.com/base.js"), None);
+        assert_eq!(
+            player_hash_from_iframe_api(
+                r#"var scriptUrl = 'https:\/\/www.youtube.com\/s\/player\/9e8d7c6b\/www-widgetapi.vflset\/www-widgetapi.js';"#
+            )
+            .as_deref(),
+            Some("9e8d7c6b")
+        );
+
+        let cache = PlayerScriptCache::default();
+        assert!(cache.current().is_none());
+        cache.insert("3f2a1b7c", fixture("3f2a1b7c"));
+        assert_eq!(cache.current_hash().as_deref(), Some("3f2a1b7c"));
+        assert_eq!(cache.current().unwrap().signature_timestamp, Some(20158));
+    }
+}
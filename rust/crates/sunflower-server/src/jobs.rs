@@ -3,21 +3,35 @@ use std::{
     fs,
     io::Cursor,
     path::{Path, PathBuf},
-    sync::{Mutex, MutexGuard},
-    time::SystemTime,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime},
 };
 
 use chrono::{DateTime, Utc};
 use image::ImageFormat;
 use sha1::{Digest, Sha1};
-use sunflower_core::{JobResponse, legacy_rfc3339_nano};
-use sunflower_storage_postgres::{PostgresStore, ScannedLocalSong};
+use sunflower_core::{
+    JobResponse, LYRICS_SOURCE_EMBEDDED, LYRICS_SOURCE_LRC, Lyrics, LyricsLine,
+    legacy_rfc3339_nano, parse_lrc,
+};
+use sunflower_storage_postgres::{DownloadedYouTubeSong, PostgresStore, ScannedLocalSong};
+use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
 
+use crate::{
+    innertube::{InnerTubeBackend, PlayerResponse},
+    media_tags::{EmbeddedTags, embed_tags},
+    stream_proxy::allowed_host,
+};
+
 const STATUS_PENDING: &str = "pending";
 const STATUS_RUNNING: &str = "running";
 const STATUS_COMPLETED: &str = "completed";
 const STATUS_FAILED: &str = "failed";
+/// Range size for server-side YouTube downloads; googlevideo throttles
+/// requests for much more than this.
+const DOWNLOAD_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[derive(Clone)]
 struct JobRecord {
@@ -138,6 +152,18 @@ async fn scan_roots(
                     if let Err(err) = store.upsert_scanned_local_song(&song).await {
                         eprintln!("scan: failed to persist {}: {err}", path.display());
                     }
+                    // Lyrics of local songs only come from the scan, so a
+                    // sidecar or tag that is gone takes the stored copy with it.
+                    let lyrics_result = match &extracted.lyrics {
+                        Some(lyrics) => store.upsert_lyrics(&song.media_id, lyrics).await,
+                        None => store.delete_lyrics(&song.media_id).await,
+                    };
+                    if let Err(err) = lyrics_result {
+                        eprintln!(
+                            "scan: failed to persist lyrics for {}: {err}",
+                            path.display()
+                        );
+                    }
                     if let Some(cover) = extracted.cover_art {
                         let album_media_id = song.album_media_id.clone();
                         let data_dir = data_dir.clone();
@@ -177,6 +203,225 @@ async fn scan_roots(
     Ok(processed)
 }
 
+/// The client server-side YouTube downloads use unless one is injected.
+pub(crate) fn download_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+}
+
+pub async fn run_youtube_download_job(
+    registry: Arc<JobRegistry>,
+    store: PostgresStore,
+    yt: Arc<dyn InnerTubeBackend>,
+    client: reqwest::Client,
+    job_id: String,
+    media_id: String,
+    data_dir: String,
+) {
+    registry.update(&job_id, |job| job.status = STATUS_RUNNING.to_string());
+    let result = download_youtube_media(store, yt, &client, &media_id, data_dir).await;
+    match result {
+        Ok(()) => registry.update(&job_id, |job| {
+            job.status = STATUS_COMPLETED.to_string();
+            job.processed_files = 1;
+        }),
+        Err(err) => registry.update(&job_id, |job| {
+            job.status = STATUS_FAILED.to_string();
+            job.error = err;
+        }),
+    }
+}
+
+async fn download_youtube_media(
+    store: PostgresStore,
+    yt: Arc<dyn InnerTubeBackend>,
+    client: &reqwest::Client,
+    media_id: &str,
+    data_dir: String,
+) -> Result<(), String> {
+    let video_id =
+        youtube_video_id(media_id).ok_or_else(|| format!("not a youtube media id: {media_id}"))?;
+    let player = yt.player(video_id).await.map_err(|err| err.to_string())?;
+    let song = download_youtube_song(client, &player, media_id, &data_dir).await?;
+    store
+        .upsert_downloaded_youtube_song(&song)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// Saves `player`'s best audio stream under `<data_dir>/youtube/`, tagged
+/// with its title, artist and thumbnail, and the thumbnail as cover art for
+/// `media_id`.
+async fn download_youtube_song(
+    client: &reqwest::Client,
+    player: &PlayerResponse,
+    media_id: &str,
+    data_dir: &str,
+) -> Result<DownloadedYouTubeSong, String> {
+    let stream = &player.stream;
+    let url = reqwest::Url::parse(&stream.url).map_err(|_| "no playable audio stream")?;
+    if !url.host_str().is_some_and(allowed_host) {
+        return Err(format!("refusing to download from {url}"));
+    }
+    let dir = absolute_path(&Path::new(data_dir).join("youtube")).map_err(|err| err.to_string())?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|err| err.to_string())?;
+    let video_id =
+        youtube_video_id(media_id).ok_or_else(|| format!("not a youtube media id: {media_id}"))?;
+    let path = dir.join(format!("{video_id}.{}", audio_extension(&stream.mime_type)));
+    let partial = path.with_extension("part");
+    if let Err(err) = download_ranged(client, url, &partial, DOWNLOAD_CHUNK_SIZE).await {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(err);
+    }
+    tokio::fs::rename(&partial, &path)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let details = &player.details;
+    // Cover art and tags are best effort, like they are for scanned files.
+    let thumbnail = if details.thumbnail_url.is_empty() {
+        None
+    } else {
+        fetch_thumbnail(client, &details.thumbnail_url)
+            .await
+            .inspect_err(|err| {
+                eprintln!("download: failed to fetch cover art for {media_id}: {err}")
+            })
+            .ok()
+    };
+    let tags = EmbeddedTags {
+        title: details.title.clone(),
+        artist: details.author.clone(),
+        cover_jpeg: None,
+    };
+    let (tagged_path, album_media_id, art_dir) =
+        (path.clone(), media_id.to_string(), data_dir.to_string());
+    let tagged = tokio::task::spawn_blocking(move || {
+        let mut tags = tags;
+        if let Some(bytes) = &thumbnail {
+            if let Err(err) = save_cover_art(bytes, &album_media_id, &art_dir) {
+                eprintln!("download: failed to save cover art for {album_media_id}: {err}");
+            }
+            tags.cover_jpeg = jpeg_cover(bytes);
+        }
+        embed_tags(&tagged_path, &tags)
+    })
+    .await
+    .map_err(|err| err.to_string())
+    .and_then(|result| result);
+    if let Err(err) = tagged {
+        eprintln!("download: failed to tag {media_id}: {err}");
+    }
+    Ok(DownloadedYouTubeSong {
+        media_id: media_id.to_string(),
+        title: Some(details.title.clone())
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| "Untitled".to_string()),
+        artist: details.author.clone(),
+        artist_media_id: if details.channel_id.is_empty() {
+            String::new()
+        } else {
+            format!("yt:{}", details.channel_id)
+        },
+        duration_ms: Some(details.duration_ms).filter(|duration| *duration > 0),
+        thumbnail_url: details.thumbnail_url.clone(),
+        local_path: path.to_string_lossy().to_string(),
+    })
+}
+
+/// Fetches `url` into `dest` one `chunk_size` range at a time.
+async fn download_ranged(
+    client: &reqwest::Client,
+    url: reqwest::Url,
+    dest: &Path,
+    chunk_size: u64,
+) -> Result<(), String> {
+    let mut file = tokio::fs::File::create(dest)
+        .await
+        .map_err(|err| err.to_string())?;
+    let mut offset = 0;
+    loop {
+        let response = client
+            .get(url.clone())
+            .header(
+                reqwest::header::RANGE,
+                format!("bytes={offset}-{}", offset + chunk_size - 1),
+            )
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        let status = response.status();
+        let total = match status {
+            reqwest::StatusCode::PARTIAL_CONTENT => response
+                .headers()
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit_once('/'))
+                .and_then(|(_, total)| total.parse::<u64>().ok()),
+            // The whole body in one response.
+            reqwest::StatusCode::OK if offset == 0 => None,
+            status => return Err(format!("upstream status {status}")),
+        };
+        let bytes = response.bytes().await.map_err(|err| err.to_string())?;
+        file.write_all(&bytes)
+            .await
+            .map_err(|err| err.to_string())?;
+        offset += bytes.len() as u64;
+        match total {
+            Some(total) if offset < total && !bytes.is_empty() => continue,
+            Some(total) if offset < total => return Err("upstream sent no bytes".to_string()),
+            _ => break,
+        }
+    }
+    file.flush().await.map_err(|err| err.to_string())
+}
+
+async fn fetch_thumbnail(client: &reqwest::Client, thumbnail_url: &str) -> Result<Vec<u8>, String> {
+    let response = client
+        .get(thumbnail_url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|err| err.to_string())?;
+    let bytes = response.bytes().await.map_err(|err| err.to_string())?;
+    Ok(bytes.to_vec())
+}
+
+/// `bytes` as JPEG for embedding, re-encoding other image formats.
+fn jpeg_cover(bytes: &[u8]) -> Option<Vec<u8>> {
+    if image::guess_format(bytes).ok()? == ImageFormat::Jpeg {
+        return Some(bytes.to_vec());
+    }
+    let mut out = Cursor::new(Vec::new());
+    image::load_from_memory(bytes)
+        .ok()?
+        .write_to(&mut out, ImageFormat::Jpeg)
+        .ok()?;
+    Some(out.into_inner())
+}
+
+/// The video id of a `yt:` media id, if it is safe to use as a file name.
+fn youtube_video_id(media_id: &str) -> Option<&str> {
+    media_id.strip_prefix("yt:").filter(|id| {
+        !id.is_empty()
+            && id
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+    })
+}
+
+fn audio_extension(mime_type: &str) -> &'static str {
+    match mime_type.split(';').next().unwrap_or_default().trim() {
+        "audio/webm" => "webm",
+        "audio/mp4" => "m4a",
+        _ => "audio",
+    }
+}
+
 fn audio_files_under(root: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
     let mut out = Vec::new();
     collect_audio_files(root, &mut out)?;
@@ -223,6 +468,7 @@ fn local_media_id(input: &str) -> String {
 struct ExtractedTrack {
     song: ScannedLocalSong,
     cover_art: Option<Vec<u8>>,
+    lyrics: Option<Lyrics>,
 }
 
 fn extract_tags(path: &Path) -> Result<ExtractedTrack, String> {
@@ -236,7 +482,9 @@ fn extract_tags(path: &Path) -> Result<ExtractedTrack, String> {
     if extension != "mp3" {
         // Keep scan coverage aligned with the accepted audio extensions while
         // richer container-specific tag parsers are added.
-        return Ok(fallback_track_from_path(&path));
+        let mut track = fallback_track_from_path(&path);
+        track.lyrics = read_lrc_sidecar(&path);
+        return Ok(track);
     }
 
     let bytes = fs::read(&path).map_err(|err| err.to_string())?;
@@ -267,9 +515,21 @@ fn extract_tags(path: &Path) -> Result<ExtractedTrack, String> {
             artist,
             album,
             year: tags.year,
+            track_number: tags.track,
             local_path,
         },
         cover_art: tags.cover_art,
+        // A sidecar .lrc is usually hand-curated, so it wins over embedded frames.
+        lyrics: read_lrc_sidecar(&path).or(tags.lyrics),
+    })
+}
+
+fn read_lrc_sidecar(path: &Path) -> Option<Lyrics> {
+    let raw = fs::read(path.with_extension("lrc")).ok()?;
+    let lines = parse_lrc(&String::from_utf8_lossy(&raw));
+    (!lines.is_empty()).then(|| Lyrics {
+        source: LYRICS_SOURCE_LRC.to_string(),
+        lines,
     })
 }
 
@@ -293,9 +553,11 @@ fn fallback_track_from_path(path: &Path) -> ExtractedTrack {
             artist: String::new(),
             album: String::new(),
             year: None,
+            track_number: None,
             local_path,
         },
         cover_art: None,
+        lyrics: None,
     }
 }
 
@@ -326,6 +588,7 @@ struct Id3TextFrames {
     year: Option<i32>,
     track: Option<i32>,
     cover_art: Option<Vec<u8>>,
+    lyrics: Option<Lyrics>,
 }
 
 fn parse_id3v2_text_frames(bytes: &[u8]) -> Result<Id3TextFrames, String> {
@@ -372,6 +635,26 @@ fn parse_id3v2_text_frames(bytes: &[u8]) -> Result<Id3TextFrames, String> {
             b"APIC" if frames.cover_art.is_none() => {
                 frames.cover_art = parse_apic_frame(payload);
             }
+            // Synced SYLT beats unsynchronised USLT/TXXX:LYRICS text.
+            b"SYLT" if !frames.lyrics.as_ref().is_some_and(Lyrics::is_synced) => {
+                if let Some(lyrics) = parse_sylt_frame(payload) {
+                    frames.lyrics = Some(lyrics);
+                }
+            }
+            b"USLT" | b"TXXX" if frames.lyrics.is_none() => {
+                let text = if id == b"USLT" {
+                    parse_uslt_frame(payload)
+                } else {
+                    parse_txxx_lyrics_frame(payload)
+                };
+                let lines = text.map(|text| parse_lrc(&text)).unwrap_or_default();
+                if !lines.is_empty() {
+                    frames.lyrics = Some(Lyrics {
+                        source: LYRICS_SOURCE_EMBEDDED.to_string(),
+                        lines,
+                    });
+                }
+            }
             b"TIT2" | b"TPE1" | b"TALB" | b"TRCK" | b"TYER" | b"TDRC" => {
                 if let Ok(text) = decode_text_frame(payload) {
                     match id {
@@ -416,6 +699,64 @@ fn parse_apic_frame(payload: &[u8]) -> Option<Vec<u8>> {
     (description_end < rest.len()).then(|| rest[description_end..].to_vec())
 }
 
+fn parse_uslt_frame(payload: &[u8]) -> Option<String> {
+    let (&encoding, rest) = payload.split_first()?;
+    // Three-byte language code, then a terminated content descriptor.
+    let (_, text) = split_terminated(encoding, rest.get(3..)?)?;
+    Some(decode_text_bytes(encoding, text))
+}
+
+fn parse_txxx_lyrics_frame(payload: &[u8]) -> Option<String> {
+    let (&encoding, rest) = payload.split_first()?;
+    let (description, value) = split_terminated(encoding, rest)?;
+    let description = decode_text_bytes(encoding, description);
+    matches!(
+        description.to_ascii_uppercase().as_str(),
+        "LYRICS" | "UNSYNCEDLYRICS"
+    )
+    .then(|| decode_text_bytes(encoding, value))
+}
+
+fn parse_sylt_frame(payload: &[u8]) -> Option<Lyrics> {
+    let (&encoding, rest) = payload.split_first()?;
+    let timestamp_format = *rest.get(3)?;
+    // Format 1 counts MPEG frames, which needs the audio stream to convert.
+    if timestamp_format != 2 {
+        return None;
+    }
+    let (_, mut entries) = split_terminated(encoding, rest.get(5..)?)?;
+    let mut lines = Vec::new();
+    while !entries.is_empty() {
+        let (text, tail) = split_terminated(encoding, entries)?;
+        let stamp = tail.get(..4)?;
+        let start_ms = u32::from_be_bytes([stamp[0], stamp[1], stamp[2], stamp[3]]);
+        lines.push(LyricsLine {
+            start_ms: Some(i64::from(start_ms)),
+            text: decode_text_bytes(encoding, text).trim().to_string(),
+        });
+        entries = &tail[4..];
+    }
+    lines.sort_by_key(|line| line.start_ms);
+    (!lines.is_empty()).then(|| Lyrics {
+        source: LYRICS_SOURCE_EMBEDDED.to_string(),
+        lines,
+    })
+}
+
+/// Splits `data` at the first encoding-appropriate string terminator.
+fn split_terminated(encoding: u8, data: &[u8]) -> Option<(&[u8], &[u8])> {
+    match encoding {
+        1 | 2 => data
+            .chunks_exact(2)
+            .position(|pair| pair == [0, 0])
+            .map(|index| (&data[..index * 2], &data[index * 2 + 2..])),
+        _ => data
+            .iter()
+            .position(|byte| *byte == 0)
+            .map(|index| (&data[..index], &data[index + 1..])),
+    }
+}
+
 fn syncsafe_u32(bytes: &[u8]) -> Option<u32> {
     if bytes.len() != 4 || bytes.iter().any(|byte| byte & 0x80 != 0) {
         return None;
@@ -432,12 +773,16 @@ fn decode_text_frame(payload: &[u8]) -> Result<String, String> {
     let Some((&encoding, data)) = payload.split_first() else {
         return Ok(String::new());
     };
-    let text = match encoding {
+    let text = decode_text_bytes(encoding, data);
+    Ok(text.trim_matches(char::from(0)).trim().to_string())
+}
+
+fn decode_text_bytes(encoding: u8, data: &[u8]) -> String {
+    match encoding {
         0 | 3 => String::from_utf8_lossy(data).into_owned(),
         1 | 2 => decode_utf16(data),
         _ => String::new(),
-    };
-    Ok(text.trim_matches(char::from(0)).trim().to_string())
+    }
 }
 
 fn decode_utf16(data: &[u8]) -> String {
@@ -623,6 +968,240 @@ mod tests {
         let _ = fs::remove_dir_all(dir);
     }
 
+    #[test]
+    fn id3v23_sylt_lyrics_win_over_uslt() {
+        let mut uslt = vec![0];
+        uslt.extend_from_slice(b"eng\0First line\nSecond line");
+        let mut sylt = vec![0];
+        sylt.extend_from_slice(b"eng");
+        sylt.extend_from_slice(&[2, 1, 0]);
+        for (text, ms) in [("Second line", 4_200_u32), ("First line", 1_000)] {
+            sylt.extend_from_slice(text.as_bytes());
+            sylt.push(0);
+            sylt.extend_from_slice(&ms.to_be_bytes());
+        }
+        let mut frames = id3v23_frame("USLT", &uslt);
+        frames.extend(id3v23_frame("SYLT", &sylt));
+
+        let tags = parse_id3v2_text_frames(&id3v23_tag(&frames)).unwrap();
+        let lyrics = tags.lyrics.expect("lyrics");
+        assert_eq!(lyrics.source, LYRICS_SOURCE_EMBEDDED);
+        assert!(lyrics.is_synced());
+        assert_eq!(
+            lyrics
+                .lines
+                .iter()
+                .map(|line| (line.start_ms, line.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(Some(1_000), "First line"), (Some(4_200), "Second line")]
+        );
+
+        let tags = parse_id3v2_text_frames(&id3v23_tag(&id3v23_frame("USLT", &uslt))).unwrap();
+        let lyrics = tags.lyrics.expect("lyrics");
+        assert!(!lyrics.is_synced());
+        assert_eq!(lyrics.lines.len(), 2);
+    }
+
+    #[test]
+    fn id3v23_txxx_lyrics_frame_is_read() {
+        let mut txxx = vec![3];
+        txxx.extend_from_slice(b"LYRICS\0[00:01.00]Hello");
+        let tags = parse_id3v2_text_frames(&id3v23_tag(&id3v23_frame("TXXX", &txxx))).unwrap();
+        assert_eq!(
+            tags.lyrics.expect("lyrics").lines,
+            vec![LyricsLine {
+                start_ms: Some(1_000),
+                text: "Hello".into()
+            }]
+        );
+
+        let mut other = vec![3];
+        other.extend_from_slice(b"MOOD\0calm");
+        let tags = parse_id3v2_text_frames(&id3v23_tag(&id3v23_frame("TXXX", &other))).unwrap();
+        assert!(tags.lyrics.is_none());
+    }
+
+    #[test]
+    fn lrc_sidecar_is_read_for_every_audio_extension() {
+        let dir = std::env::temp_dir().join(format!("sunflower-lrc-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        for extension in ["mp3", "flac"] {
+            let path = dir.join(format!("Song {extension}.{extension}"));
+            let audio = if extension == "mp3" {
+                make_id3v23_mp3("Song", "Artist", "Album", 1, 2024)
+            } else {
+                b"flac".to_vec()
+            };
+            fs::write(&path, audio).unwrap();
+            fs::write(
+                path.with_extension("lrc"),
+                "[ti:Song]\n[00:02.50]Sidecar line\n",
+            )
+            .unwrap();
+
+            let lyrics = extract_tags(&path).unwrap().lyrics.expect("lyrics");
+            assert_eq!(lyrics.source, LYRICS_SOURCE_LRC, "{extension}");
+            assert_eq!(
+                lyrics.lines,
+                vec![LyricsLine {
+                    start_ms: Some(2_500),
+                    text: "Sidecar line".into()
+                }]
+            );
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    const AUDIO: &[u8] = b"0123456789abcdefghij";
+    /// An EBML header and an unknown-size segment holding one cluster.
+    const WEBM: &[u8] = b"\x1a\x45\xdf\xa3\x80\x18\x53\x80\x67\x01\xff\xff\xff\xff\xff\xff\xff\x1f\x43\xb6\x75\x83opu";
+
+    /// Serves `audio` at `/videoplayback` honoring ranges and `tiny_jpeg` at
+    /// `/thumb.jpg`, recording each audio range requested.
+    async fn fake_googlevideo(
+        audio: &'static [u8],
+    ) -> (std::net::SocketAddr, Arc<Mutex<Vec<String>>>) {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let seen = ranges.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 2048];
+                let n = socket.read(&mut buf).await.unwrap();
+                let req = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+                let (head, body) = if req.starts_with("get /thumb.jpg") {
+                    ("HTTP/1.1 200 OK\r\n".to_string(), tiny_jpeg())
+                } else {
+                    let range = req
+                        .lines()
+                        .find_map(|line| line.strip_prefix("range: bytes="))
+                        .unwrap()
+                        .trim()
+                        .to_string();
+                    seen.lock().unwrap().push(range.clone());
+                    let (start, end) = range.split_once('-').unwrap();
+                    let start: usize = start.parse().unwrap();
+                    let end = end.parse::<usize>().unwrap().min(audio.len() - 1);
+                    (
+                        format!(
+                            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {start}-{end}/{}\r\n",
+                            audio.len()
+                        ),
+                        audio[start..=end].to_vec(),
+                    )
+                };
+                let mut response = format!(
+                    "{head}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                )
+                .into_bytes();
+                response.extend_from_slice(&body);
+                socket.write_all(&response).await.unwrap();
+            }
+        });
+        (addr, ranges)
+    }
+
+    fn googlevideo_client(addr: std::net::SocketAddr) -> reqwest::Client {
+        reqwest::Client::builder()
+            .resolve("r1.googlevideo.com", addr)
+            .resolve("i.ytimg.com", addr)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn ranged_download_fetches_the_stream_chunk_by_chunk() {
+        let (addr, ranges) = fake_googlevideo(AUDIO).await;
+        let dir = std::env::temp_dir().join(format!("sunflower-yt-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("abc.part");
+
+        download_ranged(
+            &googlevideo_client(addr),
+            "http://r1.googlevideo.com/videoplayback".parse().unwrap(),
+            &dest,
+            8,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), AUDIO);
+        assert_eq!(*ranges.lock().unwrap(), ["0-7", "8-15", "16-23"]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn youtube_download_saves_tagged_audio_cover_art_and_metadata() {
+        let (addr, _) = fake_googlevideo(WEBM).await;
+        let dir = std::env::temp_dir().join(format!("sunflower-yt-{}", Uuid::new_v4()));
+        let data_dir = dir.to_string_lossy().to_string();
+        let player = PlayerResponse {
+            video_id: "abc".into(),
+            stream: crate::innertube::StreamUrl {
+                url: "http://r1.googlevideo.com/videoplayback?itag=251".into(),
+                itag: 251,
+                mime_type: "audio/webm; codecs=\"opus\"".into(),
+                bitrate: 160_000,
+                loudness: 0.0,
+            },
+            details: crate::innertube::VideoDetails {
+                title: "Song".into(),
+                author: "Artist".into(),
+                channel_id: "UC123".into(),
+                duration_ms: 215_000,
+                thumbnail_url: "http://i.ytimg.com/thumb.jpg".into(),
+            },
+            ..PlayerResponse::default()
+        };
+
+        let song = download_youtube_song(&googlevideo_client(addr), &player, "yt:abc", &data_dir)
+            .await
+            .unwrap();
+
+        let audio = dir.join("youtube").join("abc.webm");
+        assert_eq!(
+            song,
+            DownloadedYouTubeSong {
+                media_id: "yt:abc".into(),
+                title: "Song".into(),
+                artist: "Artist".into(),
+                artist_media_id: "yt:UC123".into(),
+                duration_ms: Some(215_000),
+                thumbnail_url: "http://i.ytimg.com/thumb.jpg".into(),
+                local_path: audio.to_string_lossy().to_string(),
+            }
+        );
+        let tagged = fs::read(&audio).unwrap();
+        assert!(tagged.starts_with(WEBM));
+        let cover = tiny_jpeg();
+        for needle in [&b"TITLE"[..], b"Song", b"ARTIST", b"Artist", &cover] {
+            assert!(tagged.windows(needle.len()).any(|window| window == needle));
+        }
+        assert!(!dir.join("youtube").join("abc.part").exists());
+        for size in [256, 512, 1024] {
+            assert!(
+                dir.join("art")
+                    .join("yt:abc")
+                    .join(format!("{size}.jpg"))
+                    .exists()
+            );
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn youtube_video_ids_must_be_safe_file_names() {
+        assert_eq!(youtube_video_id("yt:dQw4w9WgXcQ"), Some("dQw4w9WgXcQ"));
+        assert_eq!(youtube_video_id("yt:../x"), None);
+        assert_eq!(youtube_video_id("yt:"), None);
+        assert_eq!(youtube_video_id("local:abc"), None);
+    }
+
     fn make_id3v23_mp3(title: &str, artist: &str, album: &str, track: i32, year: i32) -> Vec<u8> {
         make_id3v23_mp3_with_cover(title, artist, album, track, year, &[])
     }
@@ -667,6 +1246,19 @@ mod tests {
         write_text_frame(&mut frames, "TRCK", &track.to_string());
         write_text_frame(&mut frames, "TYER", &year.to_string());
         write_apic_frame(&mut frames, cover);
+        id3v23_tag(&frames)
+    }
+
+    fn id3v23_frame(id: &str, data: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(10 + data.len());
+        frame.extend_from_slice(id.as_bytes());
+        frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(data);
+        frame
+    }
+
+    fn id3v23_tag(frames: &[u8]) -> Vec<u8> {
         let tag_size = frames.len();
         let mut out = Vec::with_capacity(10 + tag_size);
         out.extend_from_slice(b"ID3");
@@ -677,7 +1269,7 @@ mod tests {
             ((tag_size >> 7) & 0x7f) as u8,
             (tag_size & 0x7f) as u8,
         ]);
-        out.extend_from_slice(&frames);
+        out.extend_from_slice(frames);
         out
     }
 
@@ -329,6 +329,7 @@ pub(crate) fn legacy_allowed_methods_for_path(
         "/admin/now-playing" => Some(LEGACY_ALLOW_GET),
         "/admin/now-playing/command" => Some(LEGACY_ALLOW_POST),
         "/admin/audit" => Some(LEGACY_ALLOW_GET),
+        "/admin/diagnostics" => Some(LEGACY_ALLOW_GET),
         "/api/v1/setup/status" => Some(LEGACY_ALLOW_GET),
         "/api/v1/setup/owner" => Some(LEGACY_ALLOW_POST),
         "/api/v1/auth/register-device" => Some(LEGACY_ALLOW_POST),
@@ -341,14 +342,18 @@ pub(crate) fn legacy_allowed_methods_for_path(
         "/api/v1/admin/pairing-codes" => Some(LEGACY_ALLOW_POST),
         "/api/v1/admin/library/status" => Some(LEGACY_ALLOW_GET),
         "/api/v1/admin/library/scan" => Some(LEGACY_ALLOW_POST),
+        "/api/v1/admin/library/downloads" => Some(LEGACY_ALLOW_POST),
         "/api/v1/admin/cookies/youtube/status" => Some(LEGACY_ALLOW_GET),
         "/api/v1/admin/cookies/youtube" => Some(LEGACY_ALLOW_POST),
         "/api/v1/admin/cookies/youtube/probe" => Some(LEGACY_ALLOW_POST),
         "/api/v1/admin/cookies/youtube/clear" => Some(LEGACY_ALLOW_POST),
+        "/api/v1/admin/stream-proxy/rotate" => Some(LEGACY_ALLOW_POST),
         "/api/v1/admin/now-playing" => Some(LEGACY_ALLOW_GET),
         "/api/v1/admin/now-playing/command" => Some(LEGACY_ALLOW_POST),
         "/api/v1/admin/audit" => Some(LEGACY_ALLOW_GET),
+        "/api/v1/admin/diagnostics/innertube" => Some(LEGACY_ALLOW_GET),
         "/api/v1/queue/start" => Some(LEGACY_ALLOW_POST),
+        "/api/v1/queues" => Some(LEGACY_ALLOW_GET),
         "/api/v1/next" => Some(LEGACY_ALLOW_GET),
         "/api/v1/home" => Some(LEGACY_ALLOW_GET),
         "/api/v1/search" => Some(LEGACY_ALLOW_GET),
@@ -356,15 +361,18 @@ pub(crate) fn legacy_allowed_methods_for_path(
         "/api/v1/events" => Some(LEGACY_ALLOW_POST),
         "/api/v1/impressions" => Some(LEGACY_ALLOW_POST),
         "/api/v1/playlists" => Some(LEGACY_ALLOW_GET_POST),
+        "/api/v1/playlists/import" => Some(LEGACY_ALLOW_POST),
         "/api/v1/library/songs" => Some(LEGACY_ALLOW_GET),
         "/api/v1/library/albums" => Some(LEGACY_ALLOW_GET),
         "/api/v1/library/artists" => Some(LEGACY_ALLOW_GET),
         "/api/v1/library/scan" => Some(LEGACY_ALLOW_POST),
+        "/api/v1/library/downloads" => Some(LEGACY_ALLOW_POST),
         "/api/v1/cookies/youtube/status" => Some(LEGACY_ALLOW_GET),
         "/api/v1/cookies/youtube" => Some(LEGACY_ALLOW_POST),
         "/api/v1/ws/now-playing" => Some(LEGACY_ALLOW_GET),
         "/api/v1/streams/proxy" if legacy_routes.streams_proxy_enabled => Some(LEGACY_ALLOW_GET),
         "/api/v1/streams/resolve" => Some(LEGACY_ALLOW_POST),
+        "/api/v1/streams/refresh" => Some(LEGACY_ALLOW_POST),
         _ if path.starts_with("/admin/static/") => Some(LEGACY_ALLOW_GET),
         _ => LEGACY_DYNAMIC_ROUTES.iter().find_map(|(pattern, methods)| {
             legacy_route_pattern_matches(pattern, path).then_some(*methods)
@@ -394,14 +402,20 @@ pub(crate) fn legacy_idempotent_mutating_route_patterns() -> &'static [(&'static
     &[
         ("POST", "/api/v1/auth/register-device"),
         ("POST", "/api/v1/library/scan"),
+        ("POST", "/api/v1/library/downloads"),
         ("POST", "/api/v1/cookies/youtube"),
         ("POST", "/api/v1/queue/start"),
+        ("POST", "/api/v1/queue/:id/edit"),
+        ("POST", "/api/v1/queue/:id/mode"),
         ("POST", "/api/v1/streams/resolve"),
+        ("POST", "/api/v1/streams/refresh"),
         ("POST", "/api/v1/likes"),
         ("POST", "/api/v1/impressions"),
         ("POST", "/api/v1/playlists"),
+        ("POST", "/api/v1/playlists/import"),
         ("PATCH", "/api/v1/playlists/:id"),
         ("DELETE", "/api/v1/playlists/:id"),
+        ("POST", "/api/v1/playlists/:id/edit"),
         ("POST", "/api/v1/playlists/:id/items"),
         ("DELETE", "/api/v1/playlists/:id/items/:media_id"),
         ("POST", "/api/v1/devices/:id/downloads"),
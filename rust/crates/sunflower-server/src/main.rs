@@ -21,33 +21,53 @@ use axum::{
     routing::{delete, get, post},
 };
 use chrono::{DateTime, SecondsFormat, Utc};
-use futures_util::{future::BoxFuture, stream};
+use futures_util::{
+    StreamExt,
+    future::{BoxFuture, join_all},
+    stream,
+};
+use innertube_cache::{CachedInnerTube, InnerTubeCacheConfig};
+use innertube_fixtures::{RecordingInnerTube, ReplayInnerTube};
+use innertube_policy::{InnerTubeProfileHealth, RetryPolicy};
 use jobs::JobRegistry;
 use now_playing::NowPlayingHub;
+use parser_drift::ParserDriftRegistry;
+use proxy_cache::ChunkCache;
 use rand::RngCore;
 use serde_json::{Value, json};
 use sha2::{Digest, Sha256};
+use stream_cache::{AudioQuality, Freshness, ResolvedStreamCache, StreamCacheConfig};
 use stream_proxy::{ProxySigner, StreamProxy};
 use sunflower_core::{
-    AddPlaylistItemRequest, AdminAuditResponse, AdminDevicesResponse, AdminLibraryStatusResponse,
-    AdminLoginRequest, AdminLoginResponse, AdminMeResponse, AdminNowPlayingCommandRequest,
+    AddPlaylistItemRequest, AdminAuditResponse, AdminDevicesResponse,
+    AdminInnerTubeDiagnosticsResponse, AdminLibraryStatusResponse, AdminLoginRequest,
+    AdminLoginResponse, AdminMeResponse, AdminNowPlayingCommandRequest,
     AdminNowPlayingCommandResponse, AdminNowPlayingResponse, AdminPairingCodeRequest,
     AdminRevokeDeviceRequest, AdminStatusResponse, AdminUploadCookiesRequest, AlbumListResponse,
-    ArtistListResponse, DEFAULT_LOOKAHEAD_COUNT, DownloadListResponse, EventResultResponse,
-    EventsRequest, EventsResponse, HealthzResponse, HomeItemResponse, HomeResponse,
-    HomeSectionResponse, ImpressionsRequest, ImpressionsResponse, LegacyRequestError, LikeRequest,
-    LikeResponse, LocalRecommendationEngine, MediaId, NOW_PLAYING_CMD_PAUSE, NOW_PLAYING_CMD_PLAY,
-    NOW_PLAYING_CMD_SKIP_NEXT, NOW_PLAYING_CMD_SKIP_PREV, NOW_PLAYING_SUBPROTOCOL, NextQuery,
-    NextResponse, OwnerSetupRequest, PlaylistListResponse, PlaylistTitleRequest, QueueResponse,
-    RecommendationSource, RegisterDeviceRequest, RegisterDeviceResponse, RegisterDownloadRequest,
+    ArtistListResponse, ContentFlags, DEFAULT_LOOKAHEAD_COUNT, DownloadListResponse,
+    EventEntryRequest, EventResultResponse, EventsRequest, EventsResponse, ExpandedRadio,
+    HealthzResponse, HomeItemResponse, HomeResponse, HomeSectionResponse, ImpressionsRequest,
+    ImpressionsResponse, LYRICS_SOURCE_NONE, LegacyRequestError, LikeRequest, LikeResponse,
+    LocalRecommendationEngine, Lyrics, LyricsResponse, MediaId, NOW_PLAYING_CMD_PAUSE,
+    NOW_PLAYING_CMD_PLAY, NOW_PLAYING_CMD_SKIP_NEXT, NOW_PLAYING_CMD_SKIP_PREV,
+    NOW_PLAYING_SUBPROTOCOL, NextQuery, NextResponse, OwnerSetupRequest, PlaylistEdit,
+    PlaylistEditError, PlaylistEditRequest, PlaylistFile, PlaylistFileEntry, PlaylistFileFormat,
+    PlaylistFileTarget, PlaylistImportRequest, PlaylistImportResponse,
+    PlaylistImportUnmatchedResponse, PlaylistListResponse, PlaylistTitleRequest, QueueEditRequest,
+    QueueItem, QueueListResponse, QueueModeRequest, QueueResponse, QueueRules, QueueSession,
+    RecommendationSource, RefreshStreamFailure, RefreshStreamsRequest, RefreshStreamsResponse,
+    RegisterDeviceRequest, RegisterDeviceResponse, RegisterDownloadRequest, RepeatMode,
     ResolveStreamRequest, ResolvedStream, ResolvedStreamResponse, SearchAlbumResponse,
     SearchArtistResponse, SearchResponse, SearchSongResponse, SetupStatusResponse,
     SongHashResponse, SongListResponse, StartQueueRequest, StartScanRequest, StartScanResponse,
-    build_automix, next_window,
+    StartYouTubeDownloadRequest, StorageResult, apply_queue_rules, apply_queue_rules_after,
+    apply_session_edit, build_automix, common_directory, next_window, parse_playlist_file,
+    queue_play_order, relative_path, session_play_order, shuffle_queue_items, write_playlist_file,
 };
 use sunflower_storage_postgres::{
     AdminSession, AuthStoreError, AuthenticatedDevice, IdempotencyLogInsert, IdempotencyLogRecord,
-    PostgresStore, SongFileLookup, verify_admin_csrf,
+    PlaylistEditOutcome, PostgresStore, QueueSessionInsert, RemoteYouTubeSong, SongFileLookup,
+    verify_admin_csrf,
 };
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use uuid::Uuid;
@@ -69,6 +89,21 @@ const YT_HOME_BROWSE_ID: &str = "FEmusic_home";
 const YT_HOME_LIMIT: usize = 30;
 const COMMUNITY_PLAYLIST_LIMIT: usize = 15;
 const MIN_QUEUE_ITEMS: usize = 10;
+const AUTOMIX_SUGGESTIONS: usize = 3;
+const AUTOMIX_TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_UNAVAILABLE_SKIPS: usize = 10;
+const MAX_IMPORT_ENTRIES: usize = 5000;
+const MAX_IMPORT_YOUTUBE_LOOKUPS: usize = 50;
+const IMPORT_YOUTUBE_TIMEOUT: Duration = Duration::from_secs(5);
+const IMPORT_YOUTUBE_CONCURRENCY: usize = 4;
+const IMPORT_YOUTUBE_DEADLINE: Duration = Duration::from_secs(20);
+const IMPORTED_PLAYLIST_TITLE: &str = "Imported playlist";
+const LOCAL_RADIO_CANDIDATE_LIMIT: i64 = 200;
+const LOCAL_RADIO_ITEMS: usize = 50;
+const LOOKAHEAD_CONCURRENCY: usize = 4;
+const LOOKAHEAD_ITEM_TIMEOUT: Duration = Duration::from_secs(5);
+const LOOKAHEAD_DEADLINE: Duration = Duration::from_secs(8);
+const COOKIE_HEALTH_HISTORY_LIMIT: i64 = 24;
 const FILE_STREAM_CHUNK_SIZE: usize = 64 * 1024;
 const LEGACY_ALLOW_GET: &[&str] = &["GET"];
 const LEGACY_ALLOW_POST: &[&str] = &["POST"];
@@ -79,7 +114,12 @@ const LEGACY_DYNAMIC_ROUTES: &[(&str, &[&str])] = &[
     ("/admin/devices/:id/revoke", LEGACY_ALLOW_POST),
     ("/api/v1/admin/devices/:id/revoke", LEGACY_ALLOW_POST),
     ("/api/v1/queue/:id", LEGACY_ALLOW_GET),
+    ("/api/v1/queue/:id/edit", LEGACY_ALLOW_POST),
+    ("/api/v1/queue/:id/mode", LEGACY_ALLOW_POST),
+    ("/api/v1/queue/:id/resume", LEGACY_ALLOW_GET),
     ("/api/v1/playlists/:id", LEGACY_ALLOW_GET_PATCH_DELETE),
+    ("/api/v1/playlists/:id/edit", LEGACY_ALLOW_POST),
+    ("/api/v1/playlists/:id/export", LEGACY_ALLOW_GET),
     ("/api/v1/playlists/:id/items", LEGACY_ALLOW_POST),
     ("/api/v1/playlists/:id/items/:media_id", LEGACY_ALLOW_DELETE),
     ("/api/v1/jobs/:id", LEGACY_ALLOW_GET),
@@ -89,6 +129,7 @@ const LEGACY_DYNAMIC_ROUTES: &[(&str, &[&str])] = &[
     ),
     ("/api/v1/library/songs/:media_id/hash", LEGACY_ALLOW_GET),
     ("/api/v1/library/songs/:media_id/stream", LEGACY_ALLOW_GET),
+    ("/api/v1/lyrics/:media_id", LEGACY_ALLOW_GET),
     ("/api/v1/devices/:id/downloads", LEGACY_ALLOW_GET_POST),
     (
         "/api/v1/devices/:id/downloads/:media_id",
@@ -104,13 +145,21 @@ mod cookies;
 mod file_response;
 mod forms;
 mod innertube;
+mod innertube_cache;
+mod innertube_fixtures;
+mod innertube_policy;
+mod innertube_sig;
 mod jobs;
 mod legacy_http;
+mod media_tags;
 mod now_playing;
+mod parser_drift;
+mod proxy_cache;
 mod router;
 mod routes;
 mod runtime;
 mod state;
+mod stream_cache;
 mod stream_proxy;
 
 #[cfg(test)]
@@ -155,15 +204,45 @@ async fn main() -> anyhow::Result<()> {
     let cookie_file = configured_cookie_file();
     let cookies_configured = cookie_key.is_some() || cookie_file.is_some();
     let proxy_youtube = should_proxy_youtube(&stream_proxy_mode(), cookies_configured);
-    let stream_proxy = Arc::new(StreamProxy::new(ProxySigner::new(
+    let data_dir = configured_data_dir(env::var("DATA_DIR").ok());
+    let proxy_signer = ProxySigner::load(
+        FsPath::new(&data_dir).join("stream-proxy-keys.json"),
         parse_stream_proxy_key_env().context("parse SUNFLOWER_STREAM_PROXY_KEY")?,
-    )));
-    let yt = default_innertube_backend(store.clone(), cookie_key, cookie_file);
+    )
+    .context("load stream proxy keys")?;
+    let mut stream_proxy = StreamProxy::new(proxy_signer);
+    if let Some(max_bytes) =
+        configured_stream_proxy_cache_bytes(env::var("SUNFLOWER_STREAM_PROXY_CACHE_MB").ok())
+    {
+        stream_proxy = stream_proxy.with_cache(Arc::new(ChunkCache::new(
+            FsPath::new(&data_dir).join("stream-cache"),
+            max_bytes,
+        )));
+    }
+    let stream_proxy = Arc::new(stream_proxy);
+    let parser_drift = Arc::new(ParserDriftRegistry::default());
+    let innertube_profiles = Arc::new(InnerTubeProfileHealth::default());
+    let yt = match configured_innertube_replay_dir() {
+        Some(dir) => replay_innertube_backend(&dir),
+        None => {
+            let innertube_client = default_innertube_client(
+                store.clone(),
+                cookie_key,
+                cookie_file,
+                parser_drift.clone(),
+                innertube_profiles.clone(),
+            );
+            if let (Some(store), Some(client)) = (store.clone(), innertube_client.clone()) {
+                start_cookie_health_probe(store, client);
+            }
+            innertube_client.and_then(default_innertube_backend)
+        }
+    };
     let app = router_with_config(
         RouterBuildConfig::new(
             auth_mode,
             store,
-            configured_data_dir(env::var("DATA_DIR").ok()),
+            data_dir,
             runtime_setup_token().context("configure setup token")?,
             env::var("SUNFLOWER_PUBLIC_BASE_URL").unwrap_or_default(),
             cookie_key,
@@ -171,7 +250,12 @@ async fn main() -> anyhow::Result<()> {
         .with_hub(Some(Arc::new(NowPlayingHub::default())))
         .with_proxy(Some(stream_proxy))
         .with_proxy_youtube(proxy_youtube)
+        .with_playback_tracking(configured_playback_tracking(
+            env::var("SUNFLOWER_YT_PLAYBACK_TRACKING").ok(),
+        ))
         .with_yt(yt)
+        .with_parser_drift(parser_drift)
+        .with_innertube_profiles(innertube_profiles)
         .with_dev_open_registration(runtime_dev_open_registration()),
     );
 
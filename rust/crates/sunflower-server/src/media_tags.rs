@@ -0,0 +1,638 @@
+//! Writes title, artist and cover art into the containers YouTube serves
+//! audio in: WebM (Matroska) and M4A (MP4). Only what a downloaded track
+//! needs is written, and files laid out in ways these writers do not expect
+//! are refused rather than rewritten.
+
+use std::{fs, path::Path};
+
+const EBML_HEADER: u32 = 0x1A45_DFA3;
+const SEGMENT: u32 = 0x1853_8067;
+const SEEK_HEAD: u32 = 0x114D_9B74;
+const SEEK: u32 = 0x4DBB;
+const SEEK_ID: u32 = 0x53AB;
+const SEEK_POSITION: u32 = 0x53AC;
+const VOID: u32 = 0xEC;
+const TAGS: u32 = 0x1254_C367;
+const TAG: u32 = 0x7373;
+const TARGETS: u32 = 0x63C0;
+const TARGET_TYPE_VALUE: u32 = 0x68CA;
+const SIMPLE_TAG: u32 = 0x67C8;
+const TAG_NAME: u32 = 0x45A3;
+const TAG_STRING: u32 = 0x4487;
+const ATTACHMENTS: u32 = 0x1941_A469;
+const ATTACHED_FILE: u32 = 0x61A7;
+const FILE_NAME: u32 = 0x466E;
+const FILE_MIME_TYPE: u32 = 0x4660;
+const FILE_DATA: u32 = 0x465C;
+const FILE_UID: u32 = 0x46AE;
+
+/// Matroska target type of a whole track.
+const TARGET_TRACK: u64 = 30;
+/// iTunes `data` atom type of UTF-8 text and of JPEG images.
+const MP4_DATA_UTF8: u32 = 1;
+const MP4_DATA_JPEG: u32 = 13;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct EmbeddedTags {
+    pub(crate) title: String,
+    pub(crate) artist: String,
+    pub(crate) cover_jpeg: Option<Vec<u8>>,
+}
+
+/// Rewrites the `.webm` or `.m4a` file at `path` with `tags`.
+pub(crate) fn embed_tags(path: &Path, tags: &EmbeddedTags) -> Result<(), String> {
+    let bytes = fs::read(path).map_err(|err| err.to_string())?;
+    let tagged = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("webm") => tag_webm(&bytes, tags)?,
+        Some("m4a") => tag_m4a(&bytes, tags)?,
+        _ => return Err(format!("no tag writer for {}", path.display())),
+    };
+    let partial = path.with_extension("tagging");
+    fs::write(&partial, tagged).map_err(|err| err.to_string())?;
+    fs::rename(&partial, path).map_err(|err| err.to_string())
+}
+
+/// Appends `Tags` (and `Attachments` for the cover) to the segment, growing
+/// its size in place. The seek head learns about them when a `Void` right
+/// after it has room; cluster and cue positions never move.
+fn tag_webm(bytes: &[u8], tags: &EmbeddedTags) -> Result<Vec<u8>, String> {
+    let malformed = || "webm: unexpected layout".to_string();
+    let header = read_element(bytes, 0).ok_or_else(malformed)?;
+    if header.id != EBML_HEADER {
+        return Err(malformed());
+    }
+    let segment_at = header.end.ok_or_else(malformed)?;
+    let segment = read_element(bytes, segment_at).ok_or_else(malformed)?;
+    if segment.id != SEGMENT {
+        return Err(malformed());
+    }
+    if segment.end.is_some_and(|end| end != bytes.len()) {
+        return Err("webm: data after the segment".to_string());
+    }
+    let data_start = segment.body;
+
+    let mut seek_head = None;
+    let mut void_after_seek_head = None;
+    let mut pos = data_start;
+    while pos < bytes.len() {
+        let child = read_element(bytes, pos).ok_or_else(malformed)?;
+        // An unknown-size cluster runs to the end of the segment.
+        let Some(end) = child.end else {
+            break;
+        };
+        match child.id {
+            TAGS | ATTACHMENTS => return Err("webm: already tagged".to_string()),
+            SEEK_HEAD if seek_head.is_none() => seek_head = Some(child),
+            VOID if seek_head.as_ref().is_some_and(|head| head.end == Some(pos)) => {
+                void_after_seek_head = Some(end);
+            }
+            _ => {}
+        }
+        pos = end;
+    }
+
+    let mut tag_body = element(TARGETS, &uint_element(TARGET_TYPE_VALUE, TARGET_TRACK));
+    for (name, value) in [("TITLE", &tags.title), ("ARTIST", &tags.artist)] {
+        if !value.is_empty() {
+            let simple = [
+                element(TAG_NAME, name.as_bytes()),
+                element(TAG_STRING, value.as_bytes()),
+            ]
+            .concat();
+            tag_body.extend(element(SIMPLE_TAG, &simple));
+        }
+    }
+    let mut appended = element(TAGS, &element(TAG, &tag_body));
+    let mut seeks = vec![(TAGS, bytes.len() - data_start)];
+    if let Some(cover) = &tags.cover_jpeg {
+        seeks.push((ATTACHMENTS, bytes.len() - data_start + appended.len()));
+        let file = [
+            element(FILE_NAME, b"cover.jpg"),
+            element(FILE_MIME_TYPE, b"image/jpeg"),
+            element(FILE_DATA, cover),
+            uint_element(FILE_UID, rand::random::<u64>().max(1)),
+        ]
+        .concat();
+        appended.extend(element(ATTACHMENTS, &element(ATTACHED_FILE, &file)));
+    }
+
+    let mut out = bytes.to_vec();
+    if let Some(size) = segment.size {
+        let grown = size + appended.len() as u64;
+        let encoded = encode_size(grown, segment.body - segment.size_at)
+            .ok_or_else(|| "webm: segment size does not fit".to_string())?;
+        out[segment.size_at..segment.body].copy_from_slice(&encoded);
+    }
+    if let (Some(head), Some(void_end)) = (seek_head, void_after_seek_head) {
+        let mut body = bytes[head.body..head.end.unwrap_or(head.body)].to_vec();
+        for (id, position) in seeks {
+            let seek = [
+                element(SEEK_ID, &id_bytes(id)),
+                uint_element(SEEK_POSITION, position as u64),
+            ]
+            .concat();
+            body.extend(element(SEEK, &seek));
+        }
+        let grown = element(SEEK_HEAD, &body);
+        if let Some(filler) = void_element(void_end - head.start, grown.len()) {
+            out.splice(head.start..void_end, [grown, filler].concat());
+        }
+    }
+    out.extend(appended);
+    Ok(out)
+}
+
+struct Element {
+    id: u32,
+    start: usize,
+    size_at: usize,
+    body: usize,
+    /// `None` for an unknown size.
+    size: Option<u64>,
+    end: Option<usize>,
+}
+
+fn read_element(bytes: &[u8], start: usize) -> Option<Element> {
+    let first = *bytes.get(start)?;
+    let id_len = first.leading_zeros() as usize + 1;
+    if id_len > 4 {
+        return None;
+    }
+    let id = bytes
+        .get(start..start + id_len)?
+        .iter()
+        .fold(0u32, |id, byte| id << 8 | u32::from(*byte));
+    let size_at = start + id_len;
+    let first = *bytes.get(size_at)?;
+    let size_len = first.leading_zeros() as usize + 1;
+    if size_len > 8 {
+        return None;
+    }
+    let raw = bytes.get(size_at..size_at + size_len)?;
+    let value = raw[1..].iter().fold(
+        u64::from(first & (0xFF_u16 >> size_len) as u8),
+        |value, byte| value << 8 | u64::from(*byte),
+    );
+    let body = size_at + size_len;
+    let size = (value != (1u64 << (7 * size_len)) - 1).then_some(value);
+    let end = match size {
+        Some(size) => {
+            let end = body.checked_add(usize::try_from(size).ok()?)?;
+            if end > bytes.len() {
+                return None;
+            }
+            Some(end)
+        }
+        None => None,
+    };
+    Some(Element {
+        id,
+        start,
+        size_at,
+        body,
+        size,
+        end,
+    })
+}
+
+/// `size` as a `width`-byte EBML number; the all-ones value means unknown.
+fn encode_size(size: u64, width: usize) -> Option<Vec<u8>> {
+    if !(1..=8).contains(&width) || size >= (1u64 << (7 * width)) - 1 {
+        return None;
+    }
+    let mut out = size.to_be_bytes()[8 - width..].to_vec();
+    out[0] |= 0x80 >> (width - 1);
+    Some(out)
+}
+
+fn id_bytes(id: u32) -> Vec<u8> {
+    let bytes = id.to_be_bytes();
+    let first = bytes.iter().position(|byte| *byte != 0).unwrap_or(3);
+    bytes[first..].to_vec()
+}
+
+fn element(id: u32, body: &[u8]) -> Vec<u8> {
+    let size = (1..=8)
+        .find_map(|width| encode_size(body.len() as u64, width))
+        .unwrap_or_default();
+    [id_bytes(id), size, body.to_vec()].concat()
+}
+
+fn uint_element(id: u32, value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let first = bytes.iter().position(|byte| *byte != 0).unwrap_or(7);
+    element(id, &bytes[first..])
+}
+
+/// A `Void` filling what `used` bytes leave of `span`, if the rest fits one.
+fn void_element(span: usize, used: usize) -> Option<Vec<u8>> {
+    let rest = span.checked_sub(used)?;
+    if rest == 0 {
+        return Some(Vec::new());
+    }
+    (1..=8).find_map(|width| {
+        let body = rest.checked_sub(1 + width)?;
+        let size = encode_size(body as u64, width)?;
+        Some([vec![VOID as u8], size, vec![0; body]].concat())
+    })
+}
+
+/// Replaces `moov/udta` with an iTunes-style `ilst` and shifts the absolute
+/// offsets (`stco`/`co64`, `tfhd` base offsets) of media after `moov`.
+fn tag_m4a(bytes: &[u8], tags: &EmbeddedTags) -> Result<Vec<u8>, String> {
+    let top = mp4_children(bytes, 0, bytes.len()).ok_or("m4a: unexpected layout")?;
+    if top.iter().any(|child| &child.kind == b"mfra") {
+        // Its random-access table points at fragments by absolute offset.
+        return Err("m4a: fragment index is not supported".to_string());
+    }
+    let moov = top
+        .iter()
+        .find(|child| &child.kind == b"moov")
+        .ok_or("m4a: no moov box")?;
+
+    let mut items = Vec::new();
+    for (kind, value) in [(b"\xa9nam", &tags.title), (b"\xa9ART", &tags.artist)] {
+        if !value.is_empty() {
+            items.extend(ilst_item(kind, MP4_DATA_UTF8, value.as_bytes()));
+        }
+    }
+    if let Some(cover) = &tags.cover_jpeg {
+        items.extend(ilst_item(b"covr", MP4_DATA_JPEG, cover));
+    }
+    let handler = [&[0u8; 8][..], b"mdirappl", &[0u8; 9]].concat();
+    let meta = [
+        &[0u8; 4][..],
+        &mp4_box(b"hdlr", &handler),
+        &mp4_box(b"ilst", &items),
+    ]
+    .concat();
+    let udta = mp4_box(b"udta", &mp4_box(b"meta", &meta));
+
+    let children = mp4_children(bytes, moov.body, moov.end).ok_or("m4a: unexpected moov")?;
+    let mut moov_body = Vec::new();
+    for child in children.iter().filter(|child| &child.kind != b"udta") {
+        moov_body.extend_from_slice(&bytes[child.start..child.end]);
+    }
+    moov_body.extend(udta);
+    let mut new_moov = mp4_box(b"moov", &moov_body);
+    let delta = new_moov.len() as i64 - (moov.end - moov.start) as i64;
+    let shift = |offset: u64| -> Result<u64, String> {
+        if offset < moov.end as u64 {
+            return Ok(offset);
+        }
+        offset
+            .checked_add_signed(delta)
+            .ok_or_else(|| "m4a: offset out of range".to_string())
+    };
+    let new_moov_len = new_moov.len();
+    patch_offsets(&mut new_moov, 8, new_moov_len, &shift)?;
+
+    let mut out = Vec::with_capacity(bytes.len() + new_moov.len());
+    out.extend_from_slice(&bytes[..moov.start]);
+    out.extend(new_moov);
+    let rest_at = out.len();
+    out.extend_from_slice(&bytes[moov.end..]);
+    let out_len = out.len();
+    patch_offsets(&mut out, rest_at, out_len, &shift)?;
+    Ok(out)
+}
+
+struct Mp4Box {
+    kind: [u8; 4],
+    start: usize,
+    body: usize,
+    end: usize,
+}
+
+fn mp4_children(bytes: &[u8], start: usize, end: usize) -> Option<Vec<Mp4Box>> {
+    let mut children = Vec::new();
+    let mut pos = start;
+    while pos < end {
+        let size = u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?);
+        let kind: [u8; 4] = bytes.get(pos + 4..pos + 8)?.try_into().ok()?;
+        let (body, box_end) = match size {
+            0 => (pos + 8, end),
+            1 => {
+                let large = u64::from_be_bytes(bytes.get(pos + 8..pos + 16)?.try_into().ok()?);
+                (pos + 16, pos.checked_add(usize::try_from(large).ok()?)?)
+            }
+            size => (pos + 8, pos.checked_add(size as usize)?),
+        };
+        if box_end > end || box_end < body {
+            return None;
+        }
+        children.push(Mp4Box {
+            kind,
+            start: pos,
+            body,
+            end: box_end,
+        });
+        pos = box_end;
+    }
+    Some(children)
+}
+
+/// Rewrites absolute file offsets inside `buf[start..end]` with `shift`.
+fn patch_offsets(
+    buf: &mut [u8],
+    start: usize,
+    end: usize,
+    shift: &dyn Fn(u64) -> Result<u64, String>,
+) -> Result<(), String> {
+    let malformed = || "m4a: unexpected layout".to_string();
+    for child in mp4_children(buf, start, end).ok_or_else(malformed)? {
+        match &child.kind {
+            b"trak" | b"mdia" | b"minf" | b"stbl" | b"moof" | b"traf" => {
+                patch_offsets(buf, child.body, child.end, shift)?;
+            }
+            b"stco" | b"co64" => {
+                let width = if &child.kind == b"stco" { 4 } else { 8 };
+                let count = read_uint(buf, child.body + 4, 4).ok_or_else(malformed)?;
+                for index in 0..count as usize {
+                    let at = child.body + 8 + index * width;
+                    if at + width > child.end {
+                        return Err(malformed());
+                    }
+                    let offset = shift(read_uint(buf, at, width).ok_or_else(malformed)?)?;
+                    write_uint(buf, at, width, offset)?;
+                }
+            }
+            b"tfhd" => {
+                let flags = read_uint(buf, child.body, 4).ok_or_else(malformed)? & 0xFF_FFFF;
+                // base-data-offset-present
+                if flags & 1 != 0 {
+                    let at = child.body + 8;
+                    let offset = shift(read_uint(buf, at, 8).ok_or_else(malformed)?)?;
+                    write_uint(buf, at, 8, offset)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn read_uint(buf: &[u8], at: usize, width: usize) -> Option<u64> {
+    let bytes = buf.get(at..at + width)?;
+    Some(
+        bytes
+            .iter()
+            .fold(0u64, |value, byte| value << 8 | u64::from(*byte)),
+    )
+}
+
+fn write_uint(buf: &mut [u8], at: usize, width: usize, value: u64) -> Result<(), String> {
+    if width < 8 && value >> (width * 8) != 0 {
+        return Err("m4a: offset out of range".to_string());
+    }
+    buf[at..at + width].copy_from_slice(&value.to_be_bytes()[8 - width..]);
+    Ok(())
+}
+
+fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let size = u32::try_from(body.len() + 8).unwrap_or(u32::MAX);
+    [&size.to_be_bytes()[..], kind, body].concat()
+}
+
+fn ilst_item(kind: &[u8; 4], data_type: u32, payload: &[u8]) -> Vec<u8> {
+    let data = [&data_type.to_be_bytes()[..], &[0u8; 4], payload].concat();
+    mp4_box(kind, &mp4_box(b"data", &data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INFO: u32 = 0x1549_A966;
+    const CLUSTER: u32 = 0x1F43_B675;
+
+    fn tags() -> EmbeddedTags {
+        EmbeddedTags {
+            title: "Song".into(),
+            artist: "Artist".into(),
+            cover_jpeg: Some(vec![0xFF, 0xD8, 0xFF, 0xD9]),
+        }
+    }
+
+    /// EBML header, then a segment with an 8-byte size holding a seek head
+    /// pointing at `Info`, a `Void`, `Info` and one cluster.
+    fn sample_webm() -> Vec<u8> {
+        let seek_head = |position: u64| {
+            let seek = [
+                element(SEEK_ID, &id_bytes(INFO)),
+                uint_element(SEEK_POSITION, position),
+            ]
+            .concat();
+            element(SEEK_HEAD, &element(SEEK, &seek))
+        };
+        let info_at = seek_head(0).len() + 96;
+        let mut segment = [seek_head(info_at as u64), void_element(96, 0).unwrap()].concat();
+        segment.extend(element(INFO, &uint_element(0x2A_D7B1, 1_000_000)));
+        segment.extend(element(CLUSTER, b"opus frames"));
+        [
+            element(EBML_HEADER, &element(0x4282, b"webm")),
+            id_bytes(SEGMENT),
+            encode_size(segment.len() as u64, 8).unwrap(),
+            segment,
+        ]
+        .concat()
+    }
+
+    fn children(bytes: &[u8], start: usize, end: usize) -> Vec<Element> {
+        let mut out = Vec::new();
+        let mut pos = start;
+        while pos < end {
+            let child = read_element(bytes, pos).unwrap();
+            pos = child.end.unwrap();
+            out.push(child);
+        }
+        out
+    }
+
+    fn find(bytes: &[u8], elements: &[Element], id: u32) -> Vec<u8> {
+        let found = elements.iter().find(|element| element.id == id).unwrap();
+        bytes[found.body..found.end.unwrap()].to_vec()
+    }
+
+    #[test]
+    fn webm_tags_are_appended_and_indexed_by_the_seek_head() {
+        let original = sample_webm();
+        let tagged = tag_webm(&original, &tags()).unwrap();
+
+        let segment =
+            read_element(&tagged, read_element(&tagged, 0).unwrap().end.unwrap()).unwrap();
+        assert_eq!(segment.end, Some(tagged.len()));
+        let top = children(&tagged, segment.body, tagged.len());
+        let ids = top.iter().map(|element| element.id).collect::<Vec<_>>();
+        assert_eq!(ids, [SEEK_HEAD, VOID, INFO, CLUSTER, TAGS, ATTACHMENTS]);
+        // Nothing before the appended elements moved.
+        let cluster = top.iter().find(|element| element.id == CLUSTER).unwrap();
+        assert_eq!(
+            tagged[cluster.start..cluster.end.unwrap()],
+            original[cluster.start..cluster.end.unwrap()]
+        );
+
+        let seek_head = find(&tagged, &top, SEEK_HEAD);
+        let seeks = children(&seek_head, 0, seek_head.len())
+            .iter()
+            .map(|seek| {
+                let fields = children(&seek_head, seek.body, seek.end.unwrap());
+                let id = find(&seek_head, &fields, SEEK_ID);
+                let position = find(&seek_head, &fields, SEEK_POSITION);
+                (
+                    read_element(&[id, vec![0x80]].concat(), 0).unwrap().id,
+                    read_uint(&position, 0, position.len()).unwrap() as usize,
+                )
+            })
+            .collect::<Vec<_>>();
+        for (id, position) in &seeks {
+            let target = top.iter().find(|element| element.id == *id).unwrap();
+            assert_eq!(target.start - segment.body, *position);
+        }
+        assert_eq!(
+            seeks.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            [INFO, TAGS, ATTACHMENTS]
+        );
+
+        let tags_body = find(&tagged, &top, TAGS);
+        let tag = find(&tags_body, &children(&tags_body, 0, tags_body.len()), TAG);
+        let simple_tags = children(&tag, 0, tag.len())
+            .iter()
+            .filter(|element| element.id == SIMPLE_TAG)
+            .map(|element| {
+                let fields = children(&tag, element.body, element.end.unwrap());
+                (
+                    String::from_utf8(find(&tag, &fields, TAG_NAME)).unwrap(),
+                    String::from_utf8(find(&tag, &fields, TAG_STRING)).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            simple_tags,
+            [
+                ("TITLE".to_string(), "Song".to_string()),
+                ("ARTIST".to_string(), "Artist".to_string())
+            ]
+        );
+        assert_eq!(
+            tag_webm(&tagged, &tags()).unwrap_err(),
+            "webm: already tagged"
+        );
+    }
+
+    fn full_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        mp4_box(kind, &[&[0u8; 4][..], body].concat())
+    }
+
+    fn ilst_text(bytes: &[u8], kind: &[u8; 4]) -> Option<Vec<u8>> {
+        let top = mp4_children(bytes, 0, bytes.len())?;
+        let mut scope = top.into_iter().find(|child| &child.kind == b"moov")?;
+        for name in [b"udta", b"meta", b"ilst"] {
+            // `meta` is a full box: its children follow version and flags.
+            let skip = if &scope.kind == b"meta" { 4 } else { 0 };
+            scope = mp4_children(bytes, scope.body + skip, scope.end)?
+                .into_iter()
+                .find(|child| &child.kind == name)?;
+        }
+        let item = mp4_children(bytes, scope.body, scope.end)?
+            .into_iter()
+            .find(|child| &child.kind == kind)?;
+        Some(bytes[item.body + 16..item.end].to_vec())
+    }
+
+    #[test]
+    fn m4a_tags_go_into_moov_and_chunk_offsets_follow_the_media() {
+        let payload = b"aac frames";
+        let build = |offset: u32| {
+            let stco = full_box(
+                b"stco",
+                &[1u32.to_be_bytes(), offset.to_be_bytes()].concat(),
+            );
+            let trak = mp4_box(
+                b"trak",
+                &mp4_box(b"mdia", &mp4_box(b"minf", &mp4_box(b"stbl", &stco))),
+            );
+            let moov = mp4_box(
+                b"moov",
+                &[
+                    full_box(b"mvhd", &[0u8; 8]),
+                    trak,
+                    mp4_box(b"udta", b"old!"),
+                ]
+                .concat(),
+            );
+            [
+                mp4_box(b"ftyp", b"M4A \0\0\0\0"),
+                moov,
+                mp4_box(b"mdat", payload),
+            ]
+            .concat()
+        };
+        let offset = (build(0).len() - payload.len()) as u32;
+        let original = build(offset);
+
+        let tagged = tag_m4a(&original, &tags()).unwrap();
+
+        assert_eq!(
+            ilst_text(&tagged, b"\xa9nam").as_deref(),
+            Some(&b"Song"[..])
+        );
+        assert_eq!(
+            ilst_text(&tagged, b"\xa9ART").as_deref(),
+            Some(&b"Artist"[..])
+        );
+        assert_eq!(
+            ilst_text(&tagged, b"covr").as_deref(),
+            Some(&[0xFF, 0xD8, 0xFF, 0xD9][..])
+        );
+        let stco_at = tagged
+            .windows(4)
+            .position(|window| window == b"stco")
+            .unwrap();
+        let shifted = read_uint(&tagged, stco_at + 12, 4).unwrap() as usize;
+        assert_eq!(&tagged[shifted..shifted + payload.len()], payload);
+        assert!(!tagged.windows(4).any(|window| window == b"old!"));
+    }
+
+    #[test]
+    fn fragmented_m4a_keeps_explicit_base_offsets_pointing_at_their_data() {
+        let payload = b"fragment";
+        let build = |base: u64| {
+            let tfhd = mp4_box(
+                b"tfhd",
+                &[&[0, 0, 0, 1][..], &1u32.to_be_bytes(), &base.to_be_bytes()].concat(),
+            );
+            [
+                mp4_box(b"ftyp", b"dash\0\0\0\0"),
+                mp4_box(b"moov", &full_box(b"mvhd", &[0u8; 8])),
+                mp4_box(b"moof", &mp4_box(b"traf", &tfhd)),
+                mp4_box(b"mdat", payload),
+            ]
+            .concat()
+        };
+        let base = (build(0).len() - payload.len()) as u64;
+
+        let tagged = tag_m4a(&build(base), &tags()).unwrap();
+
+        let tfhd_at = tagged
+            .windows(4)
+            .position(|window| window == b"tfhd")
+            .unwrap();
+        let shifted = read_uint(&tagged, tfhd_at + 12, 8).unwrap() as usize;
+        assert_eq!(&tagged[shifted..shifted + payload.len()], payload);
+        assert_eq!(
+            ilst_text(&tagged, b"\xa9nam").as_deref(),
+            Some(&b"Song"[..])
+        );
+    }
+
+    #[test]
+    fn unknown_layouts_are_left_alone() {
+        assert_eq!(
+            tag_webm(b"not a webm", &tags()).unwrap_err(),
+            "webm: unexpected layout"
+        );
+        let with_index = [mp4_box(b"moov", &[]), mp4_box(b"mfra", &[])].concat();
+        assert!(tag_m4a(&with_index, &tags()).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use std::{
     collections::HashMap,
     sync::{Mutex, MutexGuard},
-    time::{Duration, SystemTime},
+    time::{Duration, Instant, SystemTime},
 };
 
 use axum::extract::ws::{Message, WebSocket};
@@ -11,11 +11,13 @@ use sunflower_core::{
     NOW_PLAYING_KIND_COMMAND, NowPlayingClientMessage, NowPlayingServerMessage,
     NowPlayingStateResponse,
 };
+use sunflower_storage_postgres::PostgresStore;
 use tokio::{sync::mpsc, time};
 use uuid::Uuid;
 
 const SEND_BUFFER: usize = 32;
 const PING_PERIOD: Duration = Duration::from_secs(54);
+const PROGRESS_SAVE_INTERVAL: Duration = Duration::from_secs(15);
 
 #[derive(Debug)]
 pub enum OutboundFrame {
@@ -162,10 +164,83 @@ impl NowPlayingHub {
     }
 }
 
+/// Saves a socket's ticks as queue progress, so another device can resume
+/// where this one is. While the same song plays, saves are spaced
+/// `PROGRESS_SAVE_INTERVAL` apart.
+pub struct QueueProgressRecorder {
+    store: PostgresStore,
+    user_id: Uuid,
+    device_id: Uuid,
+    last_saved: Option<SavedProgress>,
+}
+
+struct SavedProgress {
+    queue_id: String,
+    media_id: String,
+    at: Instant,
+}
+
+impl QueueProgressRecorder {
+    pub fn new(store: PostgresStore, user_id: Uuid, device_id: Uuid) -> Self {
+        Self {
+            store,
+            user_id,
+            device_id,
+            last_saved: None,
+        }
+    }
+
+    async fn record(&mut self, message: &NowPlayingClientMessage) {
+        let Ok(queue_id) = Uuid::parse_str(&message.queue_id) else {
+            return;
+        };
+        let now = Instant::now();
+        if message.media_id.is_empty() || !progress_save_due(self.last_saved.as_ref(), message, now)
+        {
+            return;
+        }
+        self.last_saved = Some(SavedProgress {
+            queue_id: message.queue_id.clone(),
+            media_id: message.media_id.clone(),
+            at: now,
+        });
+        if let Err(err) = self
+            .store
+            .record_queue_progress(
+                queue_id,
+                self.user_id,
+                self.device_id,
+                &message.media_id,
+                Some(message.position_ms),
+                Utc::now(),
+            )
+            .await
+        {
+            eprintln!("queue {queue_id}: record progress: {err}");
+        }
+    }
+}
+
+fn progress_save_due(
+    last: Option<&SavedProgress>,
+    message: &NowPlayingClientMessage,
+    now: Instant,
+) -> bool {
+    match last {
+        Some(last) => {
+            last.queue_id != message.queue_id
+                || last.media_id != message.media_id
+                || now.duration_since(last.at) >= PROGRESS_SAVE_INTERVAL
+        }
+        None => true,
+    }
+}
+
 pub async fn serve_socket(
     socket: WebSocket,
     hub: std::sync::Arc<NowPlayingHub>,
     device_id: String,
+    mut progress: Option<QueueProgressRecorder>,
 ) {
     let Registration {
         id,
@@ -218,6 +293,9 @@ pub async fn serve_socket(
         match message {
             Message::Text(raw) => {
                 if let Ok(message) = NowPlayingClientMessage::parse_json(&raw) {
+                    if let Some(progress) = progress.as_mut() {
+                        progress.record(&message).await;
+                    }
                     hub.on_client_message(id, &device_id, message);
                 }
             }
@@ -282,6 +360,44 @@ mod tests {
         hub.unregister(registration.id);
     }
 
+    #[test]
+    fn progress_saves_on_song_change_and_then_every_interval() {
+        let tick = |queue_id: &str, media_id: &str| NowPlayingClientMessage {
+            kind: NOW_PLAYING_KIND_TICK.to_string(),
+            queue_id: queue_id.into(),
+            media_id: media_id.into(),
+            ..NowPlayingClientMessage::default()
+        };
+        let start = Instant::now();
+        let last = SavedProgress {
+            queue_id: "q1".into(),
+            media_id: "yt:a".into(),
+            at: start,
+        };
+
+        assert!(progress_save_due(None, &tick("q1", "yt:a"), start));
+        assert!(!progress_save_due(
+            Some(&last),
+            &tick("q1", "yt:a"),
+            start + Duration::from_secs(5)
+        ));
+        assert!(progress_save_due(
+            Some(&last),
+            &tick("q1", "yt:b"),
+            start + Duration::from_secs(5)
+        ));
+        assert!(progress_save_due(
+            Some(&last),
+            &tick("q2", "yt:a"),
+            start + Duration::from_secs(5)
+        ));
+        assert!(progress_save_due(
+            Some(&last),
+            &tick("q1", "yt:a"),
+            start + PROGRESS_SAVE_INTERVAL
+        ));
+    }
+
     #[tokio::test]
     async fn hub_broadcasts_snapshot_and_commands_like_go() {
         let hub = NowPlayingHub::default();
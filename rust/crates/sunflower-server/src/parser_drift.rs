@@ -0,0 +1,172 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::Value;
+use sunflower_core::{AdminParserDriftEndpointResponse, AdminUnknownRendererResponse};
+
+/// What a single InnerTube parse noticed but could not use.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParserDrift {
+    pub unknown_renderers: Vec<String>,
+    pub failed: bool,
+}
+
+impl ParserDrift {
+    pub fn unknown(&mut self, item: &Value) {
+        let Some(key) = renderer_key(item) else {
+            return;
+        };
+        if !self.unknown_renderers.iter().any(|known| known == key) {
+            self.unknown_renderers.push(key.to_string());
+        }
+    }
+
+    pub fn fail(&mut self) {
+        self.failed = true;
+    }
+}
+
+/// Renderer wrappers are single-key objects like `{"musicShelfRenderer": {…}}`,
+/// sometimes with `trackingParams` alongside.
+fn renderer_key(item: &Value) -> Option<&str> {
+    let object = item.as_object()?;
+    object
+        .keys()
+        .find(|key| key.ends_with("Renderer"))
+        .or_else(|| object.keys().find(|key| *key != "trackingParams"))
+        .map(String::as_str)
+}
+
+struct UnknownRenderer {
+    count: u64,
+    last_seen_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct EndpointDrift {
+    parsed_responses: u64,
+    parse_failures: u64,
+    unknown_renderers: BTreeMap<String, UnknownRenderer>,
+}
+
+#[derive(Default)]
+pub struct ParserDriftRegistry {
+    endpoints: Mutex<BTreeMap<String, EndpointDrift>>,
+}
+
+impl ParserDriftRegistry {
+    fn lock_endpoints(&self) -> MutexGuard<'_, BTreeMap<String, EndpointDrift>> {
+        self.endpoints
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn record(&self, endpoint: &str, drift: ParserDrift) {
+        let now = Utc::now();
+        let mut endpoints = self.lock_endpoints();
+        let stats = endpoints.entry(endpoint.to_string()).or_default();
+        stats.parsed_responses += 1;
+        if drift.failed {
+            stats.parse_failures += 1;
+            eprintln!("innertube: {endpoint} response did not match any known layout");
+        }
+        for renderer in drift.unknown_renderers {
+            let seen = stats
+                .unknown_renderers
+                .entry(renderer.clone())
+                .or_insert_with(|| {
+                    eprintln!("innertube: unknown renderer {renderer} in {endpoint} response");
+                    UnknownRenderer {
+                        count: 0,
+                        last_seen_at: now,
+                    }
+                });
+            seen.count += 1;
+            seen.last_seen_at = now;
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<AdminParserDriftEndpointResponse> {
+        self.lock_endpoints()
+            .iter()
+            .map(|(endpoint, stats)| AdminParserDriftEndpointResponse {
+                endpoint: endpoint.clone(),
+                parsed_responses: stats.parsed_responses,
+                parse_failures: stats.parse_failures,
+                unknown_renderers: stats
+                    .unknown_renderers
+                    .iter()
+                    .map(|(renderer, seen)| AdminUnknownRendererResponse {
+                        renderer: renderer.clone(),
+                        count: seen.count,
+                        last_seen_at: seen.last_seen_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parser_drift_dedupes_renderer_keys_and_ignores_tracking_params() {
+        let mut drift = ParserDrift::default();
+        drift.unknown(&json!({"trackingParams": "x", "musicCardShelfRenderer": {}}));
+        drift.unknown(&json!({"musicCardShelfRenderer": {}}));
+        drift.unknown(&json!({"itemSection": {}}));
+        drift.unknown(&json!("not a renderer"));
+        assert_eq!(
+            drift.unknown_renderers,
+            vec![
+                "musicCardShelfRenderer".to_string(),
+                "itemSection".to_string()
+            ]
+        );
+        assert!(!drift.failed);
+    }
+
+    #[test]
+    fn registry_counts_parses_failures_and_unknown_renderers_per_endpoint() {
+        let registry = ParserDriftRegistry::default();
+        registry.record(
+            "search",
+            ParserDrift {
+                unknown_renderers: vec!["musicCardShelfRenderer".into()],
+                failed: false,
+            },
+        );
+        registry.record(
+            "search",
+            ParserDrift {
+                unknown_renderers: vec!["musicCardShelfRenderer".into()],
+                failed: true,
+            },
+        );
+        registry.record("browse", ParserDrift::default());
+
+        let snapshot = registry.snapshot();
+        assert_eq!(
+            snapshot
+                .iter()
+                .map(|endpoint| (
+                    endpoint.endpoint.as_str(),
+                    endpoint.parsed_responses,
+                    endpoint.parse_failures
+                ))
+                .collect::<Vec<_>>(),
+            vec![("browse", 1, 0), ("search", 2, 1)]
+        );
+        let unknown = &snapshot[1].unknown_renderers;
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(unknown[0].renderer, "musicCardShelfRenderer");
+        assert_eq!(unknown[0].count, 2);
+        assert!(unknown[0].last_seen_at.ends_with('Z'));
+    }
+}
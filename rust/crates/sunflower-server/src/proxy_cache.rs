@@ -0,0 +1,203 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use axum::body::Bytes;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(30);
+const META_FILE: &str = "meta.json";
+
+/// Which stream a set of cached chunks belongs to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkKey {
+    pub media_id: String,
+    pub itag: String,
+}
+
+impl ChunkKey {
+    /// The key for a googlevideo URL signed for `media_id`.
+    pub fn for_url(media_id: Option<&str>, url: &reqwest::Url) -> Option<Self> {
+        let media_id = media_id.filter(|value| !value.is_empty())?;
+        let itag = url
+            .query_pairs()
+            .find(|(key, _)| key == "itag")
+            .map(|(_, value)| value.into_owned())
+            .filter(|value| !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()))?;
+        Some(Self {
+            media_id: media_id.to_string(),
+            itag,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkMeta {
+    pub total: u64,
+    pub chunk_size: u64,
+    pub content_type: String,
+}
+
+impl ChunkMeta {
+    fn to_json(&self) -> Vec<u8> {
+        json!({
+            "total": self.total,
+            "chunk_size": self.chunk_size,
+            "content_type": self.content_type,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn from_json(raw: &[u8]) -> Option<Self> {
+        let value: Value = serde_json::from_slice(raw).ok()?;
+        Some(Self {
+            total: value.get("total")?.as_u64()?,
+            chunk_size: value.get("chunk_size")?.as_u64()?,
+            content_type: value
+                .get("content_type")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+}
+
+/// Proxied stream bytes on disk, in fixed-size chunks per media id and itag,
+/// so seeks and replays are served without going back to googlevideo.
+pub struct ChunkCache {
+    root: PathBuf,
+    max_bytes: u64,
+    last_prune: Mutex<Option<Instant>>,
+}
+
+impl ChunkCache {
+    pub fn new(root: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            root: root.into(),
+            max_bytes,
+            last_prune: Mutex::new(None),
+        }
+    }
+
+    pub async fn meta(&self, key: &ChunkKey, chunk_size: u64) -> Option<ChunkMeta> {
+        let raw = tokio::fs::read(self.dir(key).join(META_FILE)).await.ok()?;
+        ChunkMeta::from_json(&raw).filter(|meta| meta.chunk_size == chunk_size && meta.total > 0)
+    }
+
+    pub async fn read_chunk(&self, key: &ChunkKey, index: u64, len: u64) -> Option<Bytes> {
+        let bytes = tokio::fs::read(self.dir(key).join(chunk_file(index)))
+            .await
+            .ok()?;
+        (bytes.len() as u64 == len).then(|| Bytes::from(bytes))
+    }
+
+    pub async fn write_chunk(
+        self: &Arc<Self>,
+        key: &ChunkKey,
+        meta: &ChunkMeta,
+        index: u64,
+        bytes: &Bytes,
+    ) {
+        let dir = self.dir(key);
+        let result = async {
+            tokio::fs::create_dir_all(&dir).await?;
+            write_atomic(&dir.join(chunk_file(index)), bytes).await?;
+            write_atomic(&dir.join(META_FILE), &meta.to_json()).await
+        }
+        .await;
+        if let Err(err) = result {
+            eprintln!("stream proxy cache: write {}: {err}", dir.display());
+            return;
+        }
+        self.prune_soon();
+    }
+
+    fn dir(&self, key: &ChunkKey) -> PathBuf {
+        let digest = Sha256::digest(key.media_id.as_bytes());
+        let name = digest[..16]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        self.root.join(name).join(&key.itag)
+    }
+
+    /// Drops the least recently written streams once the cache is over
+    /// budget; at most one scan runs per interval.
+    fn prune_soon(self: &Arc<Self>) {
+        {
+            let mut last = self
+                .last_prune
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if last.is_some_and(|last| last.elapsed() < PRUNE_INTERVAL) {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+        let cache = self.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = cache.prune() {
+                eprintln!("stream proxy cache: prune: {err}");
+            }
+        });
+    }
+
+    fn prune(&self) -> std::io::Result<()> {
+        let mut streams = Vec::new();
+        let mut used = 0;
+        for media in std::fs::read_dir(&self.root)? {
+            let media = media?.path();
+            if !media.is_dir() {
+                continue;
+            }
+            for stream in std::fs::read_dir(&media)? {
+                let stream = stream?.path();
+                if !stream.is_dir() {
+                    continue;
+                }
+                let (bytes, modified) = dir_usage(&stream)?;
+                used += bytes;
+                streams.push((modified, bytes, stream));
+            }
+        }
+        streams.sort_by_key(|(modified, _, _)| *modified);
+        for (_, bytes, stream) in streams {
+            if used <= self.max_bytes {
+                break;
+            }
+            std::fs::remove_dir_all(&stream)?;
+            used = used.saturating_sub(bytes);
+            if let Some(media) = stream.parent() {
+                // Only succeeds once the last itag of the media is gone.
+                let _ = std::fs::remove_dir(media);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn chunk_file(index: u64) -> String {
+    format!("{index}.bin")
+}
+
+async fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    // Concurrent fetches of the same chunk must not share a temp file.
+    let tmp = path.with_extension(format!("{:08x}.tmp", rand::random::<u32>()));
+    tokio::fs::write(&tmp, bytes).await?;
+    tokio::fs::rename(&tmp, path).await
+}
+
+fn dir_usage(dir: &Path) -> std::io::Result<(u64, SystemTime)> {
+    let mut bytes = 0;
+    let mut newest = SystemTime::UNIX_EPOCH;
+    for entry in std::fs::read_dir(dir)? {
+        let metadata = entry?.metadata()?;
+        bytes += metadata.len();
+        newest = newest.max(metadata.modified()?);
+    }
+    Ok((bytes, newest))
+}
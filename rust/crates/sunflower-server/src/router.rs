@@ -4,25 +4,7 @@ pub(crate) fn router_with_config(config: RouterBuildConfig) -> Router {
     let legacy_routes = LegacyRouteConfig {
         streams_proxy_enabled: config.proxy.is_some(),
     };
-    let state = AppState {
-        auth_mode: config.auth_mode,
-        server_version: DEFAULT_SERVER_VERSION.to_string(),
-        setup_token: config.setup_token,
-        public_base_url: config.public_base_url,
-        cookie_key: config.cookie_key,
-        hub: config.hub,
-        proxy: config.proxy,
-        proxy_youtube: config.proxy_youtube,
-        yt: config.yt,
-        jobs: Arc::new(JobRegistry::default()),
-        started_at: SystemTime::now(),
-        data_dir: config.data_dir,
-        dev_open_registration: config.dev_open_registration,
-        setup_limiter: RateLimiter::new(10, Duration::from_secs(10 * 60)),
-        admin_login_limiter: RateLimiter::new(8, Duration::from_secs(5 * 60)),
-        pairing_limiter: RateLimiter::new(20, Duration::from_secs(10 * 60)),
-        store: config.store,
-    };
+    let state = app_state(config);
     let router = Router::new()
         .route("/healthz", get(healthz))
         .route("/admin/static/", get(admin_static_dir_listing))
@@ -55,6 +37,7 @@ pub(crate) fn router_with_config(config: RouterBuildConfig) -> Router {
             post(admin_now_playing_command_form),
         )
         .route("/admin/audit", get(admin_audit_page))
+        .route("/admin/diagnostics", get(admin_diagnostics_page))
         .route("/api/v1/setup/status", get(setup_status))
         .route("/api/v1/setup/owner", post(setup_owner))
         .route("/api/v1/auth/register-device", post(register_device))
@@ -71,6 +54,10 @@ pub(crate) fn router_with_config(config: RouterBuildConfig) -> Router {
         .route("/api/v1/admin/pairing-codes", post(admin_create_pairing))
         .route("/api/v1/admin/library/status", get(admin_library_status))
         .route("/api/v1/admin/library/scan", post(admin_start_scan))
+        .route(
+            "/api/v1/admin/library/downloads",
+            post(admin_start_youtube_download),
+        )
         .route(
             "/api/v1/admin/cookies/youtube/status",
             get(admin_cookies_youtube_status),
@@ -87,14 +74,26 @@ pub(crate) fn router_with_config(config: RouterBuildConfig) -> Router {
             "/api/v1/admin/cookies/youtube/clear",
             post(admin_clear_youtube_cookies),
         )
+        .route(
+            "/api/v1/admin/stream-proxy/rotate",
+            post(admin_rotate_stream_proxy_key),
+        )
         .route("/api/v1/admin/now-playing", get(admin_now_playing))
         .route(
             "/api/v1/admin/now-playing/command",
             post(admin_now_playing_command),
         )
         .route("/api/v1/admin/audit", get(admin_audit))
+        .route(
+            "/api/v1/admin/diagnostics/innertube",
+            get(admin_innertube_diagnostics),
+        )
         .route("/api/v1/queue/start", post(start_queue))
         .route("/api/v1/queue/:id", get(get_queue))
+        .route("/api/v1/queue/:id/edit", post(edit_queue))
+        .route("/api/v1/queue/:id/mode", post(set_queue_mode))
+        .route("/api/v1/queue/:id/resume", get(resume_queue))
+        .route("/api/v1/queues", get(list_queues))
         .route("/api/v1/next", get(get_next))
         .route("/api/v1/home", get(get_home))
         .route("/api/v1/search", get(search))
@@ -105,12 +104,15 @@ pub(crate) fn router_with_config(config: RouterBuildConfig) -> Router {
             "/api/v1/playlists",
             get(list_playlists).post(create_playlist),
         )
+        .route("/api/v1/playlists/import", post(import_playlist))
         .route(
             "/api/v1/playlists/:id",
             get(get_playlist)
                 .patch(update_playlist)
                 .delete(delete_playlist),
         )
+        .route("/api/v1/playlists/:id/edit", post(edit_playlist))
+        .route("/api/v1/playlists/:id/export", get(export_playlist))
         .route("/api/v1/playlists/:id/items", post(add_playlist_item))
         .route(
             "/api/v1/playlists/:id/items/:media_id",
@@ -120,6 +122,7 @@ pub(crate) fn router_with_config(config: RouterBuildConfig) -> Router {
         .route("/api/v1/library/albums", get(list_albums))
         .route("/api/v1/library/artists", get(list_artists))
         .route("/api/v1/library/scan", post(start_scan))
+        .route("/api/v1/library/downloads", post(start_youtube_download))
         .route("/api/v1/jobs/:id", get(get_job))
         .route(
             "/api/v1/library/albums/:album_media_id/art",
@@ -127,6 +130,7 @@ pub(crate) fn router_with_config(config: RouterBuildConfig) -> Router {
         )
         .route("/api/v1/library/songs/:media_id/hash", get(song_hash))
         .route("/api/v1/library/songs/:media_id/stream", get(stream_song))
+        .route("/api/v1/lyrics/:media_id", get(get_lyrics))
         .route(
             "/api/v1/cookies/youtube/status",
             get(device_youtube_cookie_status),
@@ -144,7 +148,8 @@ pub(crate) fn router_with_config(config: RouterBuildConfig) -> Router {
             delete(delete_download),
         )
         .route("/api/v1/ws/now-playing", get(ws_now_playing))
-        .route("/api/v1/streams/resolve", post(resolve_stream));
+        .route("/api/v1/streams/resolve", post(resolve_stream))
+        .route("/api/v1/streams/refresh", post(refresh_streams));
     let router = if legacy_routes.streams_proxy_enabled {
         router.route("/api/v1/streams/proxy", get(streams_proxy))
     } else {
@@ -160,3 +165,30 @@ pub(crate) fn router_with_config(config: RouterBuildConfig) -> Router {
             cors_middleware,
         ))
 }
+
+pub(crate) fn app_state(config: RouterBuildConfig) -> AppState {
+    AppState {
+        auth_mode: config.auth_mode,
+        server_version: DEFAULT_SERVER_VERSION.to_string(),
+        setup_token: config.setup_token,
+        public_base_url: config.public_base_url,
+        cookie_key: config.cookie_key,
+        hub: config.hub,
+        proxy: config.proxy,
+        proxy_youtube: config.proxy_youtube,
+        playback_tracking: config.playback_tracking,
+        yt: config.yt,
+        parser_drift: config.parser_drift,
+        innertube_profiles: config.innertube_profiles,
+        jobs: Arc::new(JobRegistry::default()),
+        download_client: config.download_client,
+        stream_cache: Arc::new(ResolvedStreamCache::new(StreamCacheConfig::default())),
+        started_at: SystemTime::now(),
+        data_dir: config.data_dir,
+        dev_open_registration: config.dev_open_registration,
+        setup_limiter: RateLimiter::new(10, Duration::from_secs(10 * 60)),
+        admin_login_limiter: RateLimiter::new(8, Duration::from_secs(5 * 60)),
+        pairing_limiter: RateLimiter::new(20, Duration::from_secs(10 * 60)),
+        store: config.store,
+    }
+}
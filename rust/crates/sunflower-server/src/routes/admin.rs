@@ -398,18 +398,45 @@ pub(crate) async fn admin_cookies_page(
     headers: HeaderMap,
     uri: Uri,
 ) -> Response {
-    let (_, csrf) = match admin_html_session_from_headers(&state, &headers, &Method::GET).await {
-        Ok(session) => session,
-        Err(response) => return response,
-    };
+    let (session, csrf) =
+        match admin_html_session_from_headers(&state, &headers, &Method::GET).await {
+            Ok(session) => session,
+            Err(response) => return response,
+        };
     let status = match &state.store {
-        Some(store) => store.admin_cookie_status().await.ok(),
+        Some(store) => store.admin_cookie_status(session.user_id).await.ok(),
         None => None,
     };
     let token_stored = match &state.store {
         Some(store) => store.has_youtube_innertube_token().await.unwrap_or(false),
         None => false,
     };
+    let history = match &state.store {
+        Some(store) => store
+            .youtube_cookie_health_history(session.user_id, COOKIE_HEALTH_HISTORY_LIMIT)
+            .await
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let history = if history.is_empty() {
+        "<li>No probes recorded yet.</li>".to_string()
+    } else {
+        history
+            .iter()
+            .map(|check| {
+                format!(
+                    "<li>{} &middot; {} &middot; {} &middot; {}</li>",
+                    check.checked_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+                    escape_html(&check.status),
+                    check
+                        .latency_ms
+                        .map(|latency| format!("{latency} ms"))
+                        .unwrap_or_else(|| "-".to_string()),
+                    escape_html(check.detail.as_deref().unwrap_or("")),
+                )
+            })
+            .collect::<String>()
+    };
     admin_html_page(
         "YouTube Cookies",
         csrf.as_deref(),
@@ -438,11 +465,20 @@ pub(crate) async fn admin_cookies_page(
     <button type="submit">Save InnerTube token</button>
   </form>
 </section>
+<section>
+  <h2>Health History</h2>
+  <ul>{}</ul>
+</section>
 "#,
             status
-                .map(|status| escape_html(&status.status))
+                .map(|status| match status.detail.as_deref() {
+                    Some(detail) if status.status == "degraded" =>
+                        format!("degraded, using guest mode ({})", escape_html(detail)),
+                    _ => escape_html(&status.status),
+                })
                 .unwrap_or_else(|| "unknown".to_string()),
-            if token_stored { "stored" } else { "not stored" }
+            if token_stored { "stored" } else { "not stored" },
+            history
         ),
     )
 }
@@ -525,14 +561,18 @@ pub(crate) async fn admin_probe_youtube_cookies_form(
     headers: HeaderMap,
     body: Bytes,
 ) -> Response {
-    let _session = match admin_action_session(&state, &headers, uri.query(), &body).await {
+    let session = match admin_action_session(&state, &headers, uri.query(), &body).await {
         Ok(session) => session,
         Err(response) => return *response,
     };
     let Some(store) = &state.store else {
         return admin_html_error(StatusCode::INTERNAL_SERVER_ERROR, "Could not probe cookies");
     };
-    if store.mark_youtube_cookie_probe_requested().await.is_err() {
+    if store
+        .mark_youtube_cookie_probe_requested(session.user_id)
+        .await
+        .is_err()
+    {
         return admin_html_error(StatusCode::INTERNAL_SERVER_ERROR, "Could not probe cookies");
     }
     redirect_found_post("/admin/cookies/youtube?flash=probe_requested")
@@ -623,6 +663,62 @@ pub(crate) async fn admin_audit_page(
     )
 }
 
+pub(crate) async fn admin_diagnostics_page(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    let (_, csrf) = match admin_html_session_from_headers(&state, &headers, &Method::GET).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let mut rows = String::new();
+    for endpoint in state.parser_drift.snapshot() {
+        let unknown = endpoint
+            .unknown_renderers
+            .iter()
+            .map(|renderer| {
+                format!(
+                    "<li><code>{}</code> &times; {} (last {})</li>",
+                    escape_html(&renderer.renderer),
+                    renderer.count,
+                    escape_html(&renderer.last_seen_at)
+                )
+            })
+            .collect::<String>();
+        rows.push_str(&format!(
+            "<section><h2>{}</h2><p>Parsed: {} &middot; Failures: {}</p><ul>{unknown}</ul></section>",
+            escape_html(&endpoint.endpoint),
+            endpoint.parsed_responses,
+            endpoint.parse_failures
+        ));
+    }
+    if rows.is_empty() {
+        rows.push_str("<p>No InnerTube responses parsed yet.</p>");
+    }
+    let profiles = state
+        .innertube_profiles
+        .snapshot()
+        .into_iter()
+        .map(|profile| {
+            format!(
+                "<li><code>{}</code> served {} &middot; failures {} &middot; retries {} &middot; breaker {} (last served {})</li>",
+                escape_html(&profile.profile),
+                profile.served,
+                profile.failures,
+                profile.retries,
+                if profile.circuit_open { "open" } else { "closed" },
+                escape_html(profile.last_served_at.as_deref().unwrap_or("never"))
+            )
+        })
+        .collect::<String>();
+    if !profiles.is_empty() {
+        rows.push_str(&format!(
+            "<section><h2>Client profiles</h2><ul>{profiles}</ul></section>"
+        ));
+    }
+    admin_html_page("Diagnostics", csrf.as_deref(), None, &rows)
+}
+
 pub(crate) async fn admin_me(State(state): State<AppState>, headers: HeaderMap) -> Response {
     let (session, csrf) = match admin_session_from_headers(&state, &headers).await {
         Ok(session) => session,
@@ -644,9 +740,10 @@ pub(crate) async fn admin_me(State(state): State<AppState>, headers: HeaderMap)
 }
 
 pub(crate) async fn admin_status(State(state): State<AppState>, headers: HeaderMap) -> Response {
-    if let Err(response) = admin_session_from_headers(&state, &headers).await {
-        return response;
-    }
+    let (session, _) = match admin_session_from_headers(&state, &headers).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
     let Some(store) = &state.store else {
         return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
     };
@@ -654,7 +751,7 @@ pub(crate) async fn admin_status(State(state): State<AppState>, headers: HeaderM
         Ok(counts) => counts,
         Err(_) => return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
     };
-    let cookie_status = match store.admin_cookie_status().await {
+    let cookie_status = match store.admin_cookie_status(session.user_id).await {
         Ok(status) => status,
         Err(_) => return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
     };
@@ -834,17 +931,44 @@ pub(crate) async fn admin_start_scan(
     Json(scan).into_response()
 }
 
-pub(crate) async fn admin_cookies_youtube_status(
+pub(crate) async fn admin_start_youtube_download(
     State(state): State<AppState>,
+    uri: Uri,
     headers: HeaderMap,
+    body: Bytes,
 ) -> Response {
-    if let Err(response) = admin_session_from_headers(&state, &headers).await {
-        return response;
+    let (session, _) = match admin_session_from_headers(&state, &headers).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let csrf = match require_admin_csrf(&session, &headers, uri.query(), Some(&body)) {
+        Ok(csrf) => csrf,
+        Err(response) => return *response,
+    };
+    let body = csrf.body_after_middleware(body);
+    let raw = String::from_utf8_lossy(&body);
+    let request = match StartYouTubeDownloadRequest::parse_json(&raw) {
+        Ok(request) => request,
+        Err(err) => return legacy_json_error(StatusCode::BAD_REQUEST, err.legacy_error_code()),
+    };
+    match enqueue_youtube_download_job(&state.for_user(session.user_id), request) {
+        Ok(download) => Json(download).into_response(),
+        Err(response) => *response,
     }
+}
+
+pub(crate) async fn admin_cookies_youtube_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    let (session, _) = match admin_session_from_headers(&state, &headers).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
     let Some(store) = &state.store else {
         return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
     };
-    match store.admin_cookie_status().await {
+    match store.admin_cookie_status(session.user_id).await {
         Ok(status) => Json(status).into_response(),
         Err(_) => legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
     }
@@ -945,6 +1069,38 @@ pub(crate) async fn admin_clear_youtube_cookies(
     }
 }
 
+/// Signs new proxy URLs with a fresh key; URLs already handed out keep
+/// working until their key leaves the ring.
+pub(crate) async fn admin_rotate_stream_proxy_key(
+    State(state): State<AppState>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let (session, _) = match admin_session_from_headers(&state, &headers).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    if let Err(response) = require_admin_csrf(&session, &headers, uri.query(), None) {
+        return *response;
+    }
+    let Some(proxy) = &state.proxy else {
+        return legacy_json_error(StatusCode::NOT_FOUND, "not_found");
+    };
+    let key_ids = match proxy.rotate_keys() {
+        Ok(key_ids) => key_ids,
+        Err(err) => {
+            eprintln!("stream proxy: rotate keys: {err}");
+            return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
+        }
+    };
+    if let Some(store) = &state.store {
+        let _ = store
+            .record_stream_proxy_key_rotated(&session, &key_ids[0])
+            .await;
+    }
+    Json(serde_json::json!({"key_id": key_ids[0], "key_ids": key_ids})).into_response()
+}
+
 pub(crate) async fn admin_now_playing(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -1027,6 +1183,20 @@ pub(crate) async fn admin_audit(
     }
 }
 
+pub(crate) async fn admin_innertube_diagnostics(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(response) = admin_session_from_headers(&state, &headers).await {
+        return response;
+    }
+    Json(AdminInnerTubeDiagnosticsResponse {
+        endpoints: state.parser_drift.snapshot(),
+        profiles: state.innertube_profiles.snapshot(),
+    })
+    .into_response()
+}
+
 pub(crate) fn innertube_token_upload_bytes(
     explicit_token: &str,
     cookie_export: &str,
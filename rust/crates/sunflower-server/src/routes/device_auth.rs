@@ -85,16 +85,17 @@ pub(crate) async fn device_youtube_cookie_status(
     uri: Uri,
     headers: HeaderMap,
 ) -> Response {
-    if let Err(response) = authorize(&headers, &uri, &state).await {
-        return response;
-    }
+    let auth = match authorize(&headers, &uri, &state).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
     if state.cookie_key.is_none() {
         return legacy_json_error(StatusCode::SERVICE_UNAVAILABLE, "cookies_disabled");
     }
     let Some(store) = &state.store else {
         return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
     };
-    match store.admin_cookie_status().await {
+    match store.admin_cookie_status(auth.user_id).await {
         Ok(status) => Json(status).into_response(),
         Err(_) => legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
     }
@@ -62,6 +62,10 @@ pub(crate) async fn post_events(
         Ok(auth) => auth,
         Err(response) => return response,
     };
+    let tracker = state
+        .playback_tracking
+        .then(|| state.for_user(auth.user_id).yt)
+        .flatten();
     run_idempotent(&state, &headers, &uri, "POST", &auth, async {
         let raw = String::from_utf8_lossy(&body);
         let request = match EventsRequest::parse_json(&raw) {
@@ -102,7 +106,21 @@ pub(crate) async fn post_events(
                 .insert_play_event(auth.user_id, auth.device_id, &event)
                 .await
             {
-                Ok(_) => {}
+                // Only first deliveries are reported; replays of an event id
+                // were already counted.
+                Ok(true) => {
+                    record_event_queue_progress(store, &auth, &event).await;
+                    if let (Some(yt), Some(video_id)) =
+                        (&tracker, youtube_tracking_video_id(&event))
+                    {
+                        report_youtube_playback(
+                            yt.clone(),
+                            video_id.to_string(),
+                            event.total_played_ms,
+                        );
+                    }
+                }
+                Ok(false) => {}
                 Err(_) => {
                     result.accepted = false;
                     result.reason = Some("internal".into());
@@ -116,6 +134,66 @@ pub(crate) async fn post_events(
     .await
 }
 
+/// Saves an event's song as the user's place in the queue it was played from,
+/// for resuming on another device. Events carry no offset, so a late one for
+/// the song already saved leaves the playback ticks' offset alone.
+async fn record_event_queue_progress(
+    store: &PostgresStore,
+    auth: &AuthenticatedDevice,
+    event: &EventEntryRequest,
+) {
+    let Ok(queue_id) = Uuid::parse_str(&event.queue_id) else {
+        return;
+    };
+    let observed_at = event
+        .occurred_at
+        .as_deref()
+        .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok())
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now);
+    if let Err(err) = store
+        .record_queue_progress(
+            queue_id,
+            auth.user_id,
+            auth.device_id,
+            &event.media_id,
+            None,
+            observed_at,
+        )
+        .await
+    {
+        eprintln!("queue {queue_id}: record progress: {err}");
+    }
+}
+
+/// The video to report for a scrobbled YouTube play.
+pub(crate) fn youtube_tracking_video_id(event: &EventEntryRequest) -> Option<&str> {
+    if event.kind != "play" {
+        return None;
+    }
+    event
+        .media_id
+        .strip_prefix("yt:")
+        .filter(|video_id| !video_id.is_empty())
+}
+
+/// Fires the tracking pings in the background so a slow or failing
+/// YouTube never holds up event ingestion.
+fn report_youtube_playback(
+    yt: Arc<dyn innertube::InnerTubeBackend>,
+    video_id: String,
+    played_ms: i32,
+) {
+    tokio::spawn(async move {
+        if let Err(err) = yt
+            .report_playback(&video_id, i64::from(played_ms), None)
+            .await
+        {
+            eprintln!("playback tracking {video_id}: {err}");
+        }
+    });
+}
+
 pub(crate) async fn post_impressions(
     State(state): State<AppState>,
     uri: Uri,
@@ -9,6 +9,7 @@ pub(crate) async fn get_home(
         Ok(auth) => auth,
         Err(response) => return response,
     };
+    let state = state.for_user(auth.user_id);
     let Some(store) = &state.store else {
         return legacy_json_error(StatusCode::SERVICE_UNAVAILABLE, "recs_unavailable");
     };
@@ -49,6 +49,62 @@ pub(crate) fn enqueue_scan_job(
     Ok(StartScanResponse { job_id: job.id })
 }
 
+pub(crate) async fn start_youtube_download(
+    State(state): State<AppState>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let auth = match authorize(&headers, &uri, &state).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
+    let state = state.for_user(auth.user_id);
+    run_idempotent(&state, &headers, &uri, "POST", &auth, async {
+        let raw = String::from_utf8_lossy(&body);
+        let request = match StartYouTubeDownloadRequest::parse_json(&raw) {
+            Ok(request) => request,
+            Err(err) => return legacy_json_error(StatusCode::BAD_REQUEST, err.legacy_error_code()),
+        };
+        match enqueue_youtube_download_job(&state, request) {
+            Ok(response) => Json(response).into_response(),
+            Err(response) => *response,
+        }
+    })
+    .await
+}
+
+/// Starts a job that keeps a server-side copy of a YouTube track, after
+/// which `resolve` serves it like a scanned file.
+pub(crate) fn enqueue_youtube_download_job(
+    state: &AppState,
+    request: StartYouTubeDownloadRequest,
+) -> ResponseResult<StartScanResponse> {
+    let Some(store) = &state.store else {
+        return Err(Box::new(legacy_json_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal",
+        )));
+    };
+    let Some(yt) = &state.yt else {
+        return Err(Box::new(legacy_json_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "unavailable",
+        )));
+    };
+    let job = state.jobs.create();
+    tokio::spawn(jobs::run_youtube_download_job(
+        state.jobs.clone(),
+        store.clone(),
+        yt.clone(),
+        state.download_client.clone(),
+        job.id.clone(),
+        request.media_id,
+        state.data_dir.clone(),
+    ));
+    Ok(StartScanResponse { job_id: job.id })
+}
+
 pub(crate) async fn get_job(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -0,0 +1,57 @@
+use crate::*;
+
+pub(crate) async fn get_lyrics(
+    State(state): State<AppState>,
+    Path(media_id): Path<String>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let auth = match authorize(&headers, &uri, &state).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
+    let state = state.for_user(auth.user_id);
+    let Some(store) = &state.store else {
+        return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
+    };
+    match store.get_lyrics(&media_id).await {
+        Ok(Some(lyrics)) if lyrics.source == LYRICS_SOURCE_NONE => {
+            return legacy_json_error(StatusCode::NOT_FOUND, "not_found");
+        }
+        Ok(Some(lyrics)) => {
+            return Json(LyricsResponse::from_lyrics(media_id, &lyrics)).into_response();
+        }
+        Ok(None) => {}
+        Err(_) => return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+    }
+
+    // Local lyrics only come from the scanner; YouTube lyrics are fetched on
+    // first request and kept so later plays skip the two InnerTube calls. A
+    // miss is kept too, as a `none` entry the store expires after a day.
+    let Some(video_id) = media_id.strip_prefix("yt:").filter(|id| !id.is_empty()) else {
+        return legacy_json_error(StatusCode::NOT_FOUND, "not_found");
+    };
+    let Some(yt) = &state.yt else {
+        return legacy_json_error(StatusCode::SERVICE_UNAVAILABLE, "yt_unavailable");
+    };
+    let lyrics = match tokio::time::timeout(Duration::from_secs(8), yt.lyrics(video_id)).await {
+        Ok(Ok(Some(lyrics))) => lyrics,
+        Ok(Ok(None)) => {
+            let missing = Lyrics {
+                source: LYRICS_SOURCE_NONE.to_string(),
+                lines: Vec::new(),
+            };
+            if let Err(err) = store.upsert_lyrics(&media_id, &missing).await {
+                eprintln!("lyrics: failed to persist {media_id}: {err}");
+            }
+            return legacy_json_error(StatusCode::NOT_FOUND, "not_found");
+        }
+        Ok(Err(_)) | Err(_) => {
+            return legacy_json_error(StatusCode::BAD_GATEWAY, "lyrics_unavailable");
+        }
+    };
+    if let Err(err) = store.upsert_lyrics(&media_id, &lyrics).await {
+        eprintln!("lyrics: failed to persist {media_id}: {err}");
+    }
+    Json(LyricsResponse::from_lyrics(media_id, &lyrics)).into_response()
+}
@@ -4,6 +4,7 @@ mod downloads;
 mod events;
 mod home;
 mod library;
+mod lyrics;
 mod now_playing;
 mod playlists;
 mod queue;
@@ -17,6 +18,7 @@ pub(crate) use downloads::*;
 pub(crate) use events::*;
 pub(crate) use home::*;
 pub(crate) use library::*;
+pub(crate) use lyrics::*;
 pub(crate) use now_playing::*;
 pub(crate) use playlists::*;
 pub(crate) use queue::*;
@@ -14,6 +14,10 @@ pub(crate) async fn ws_now_playing(
         return legacy_json_error(StatusCode::SERVICE_UNAVAILABLE, "ws_unavailable");
     };
     let device_id = auth.device_id.to_string();
+    let progress = state
+        .store
+        .clone()
+        .map(|store| now_playing::QueueProgressRecorder::new(store, auth.user_id, auth.device_id));
     ws.protocols([NOW_PLAYING_SUBPROTOCOL])
-        .on_upgrade(move |socket| now_playing::serve_socket(socket, hub, device_id))
+        .on_upgrade(move |socket| now_playing::serve_socket(socket, hub, device_id, progress))
 }
@@ -132,6 +132,56 @@ pub(crate) async fn delete_playlist(
     .await
 }
 
+pub(crate) async fn edit_playlist(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let auth = match authorize(&headers, &uri, &state).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
+    run_idempotent(&state, &headers, &uri, "POST", &auth, async {
+        let playlist_id = match parse_playlist_id(&id) {
+            Ok(id) => id,
+            Err(response) => return *response,
+        };
+        let raw = String::from_utf8_lossy(&body);
+        let (version, edit) = match PlaylistEditRequest::parse_json(&raw) {
+            Ok(parsed) => parsed,
+            Err(err) => return legacy_json_error(StatusCode::BAD_REQUEST, err.legacy_error_code()),
+        };
+        let Some(store) = &state.store else {
+            return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
+        };
+        match store
+            .edit_playlist(auth.user_id, auth.device_id, playlist_id, version, edit)
+            .await
+        {
+            Ok(PlaylistEditOutcome::Edited(playlist)) => Json(playlist).into_response(),
+            Ok(PlaylistEditOutcome::NotFound) => {
+                legacy_json_error(StatusCode::NOT_FOUND, "not_found")
+            }
+            Ok(PlaylistEditOutcome::VersionConflict) => {
+                legacy_json_error(StatusCode::CONFLICT, "version_conflict")
+            }
+            Ok(PlaylistEditOutcome::Rejected(PlaylistEditError::PositionOutOfRange { .. })) => {
+                legacy_json_error(StatusCode::BAD_REQUEST, "position_out_of_range")
+            }
+            Ok(PlaylistEditOutcome::Rejected(PlaylistEditError::UnknownItem(_))) => {
+                legacy_json_error(StatusCode::NOT_FOUND, "item_not_found")
+            }
+            Ok(PlaylistEditOutcome::UnknownSong(_)) => {
+                legacy_json_error(StatusCode::UNPROCESSABLE_ENTITY, "unknown_song")
+            }
+            Err(_) => legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        }
+    })
+    .await
+}
+
 pub(crate) async fn add_playlist_item(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -200,3 +250,366 @@ pub(crate) async fn remove_playlist_item(
     })
     .await
 }
+
+pub(crate) async fn import_playlist(
+    State(state): State<AppState>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let auth = match authorize(&headers, &uri, &state).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
+    run_idempotent(&state, &headers, &uri, "POST", &auth, async {
+        let raw = String::from_utf8_lossy(&body);
+        let request = match PlaylistImportRequest::parse_json(&raw) {
+            Ok(request) => request,
+            Err(err) => return legacy_json_error(StatusCode::BAD_REQUEST, err.legacy_error_code()),
+        };
+        let file = match parse_playlist_file(request.file_format(), &request.content) {
+            Ok(file) => file,
+            Err(_) => return legacy_json_error(StatusCode::BAD_REQUEST, "invalid_playlist_file"),
+        };
+        if file.entries.len() > MAX_IMPORT_ENTRIES {
+            return legacy_json_error(StatusCode::PAYLOAD_TOO_LARGE, "too_many_entries");
+        }
+        let Some(store) = &state.store else {
+            return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
+        };
+        let yt = if request.youtube {
+            state.for_user(auth.user_id).yt
+        } else {
+            None
+        };
+
+        let found = match match_import_entries(store, yt.as_deref(), &file.entries).await {
+            Ok(found) => found,
+            Err(err) => {
+                eprintln!("playlist import: {err}");
+                return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
+            }
+        };
+        let mut media_ids = Vec::with_capacity(file.entries.len());
+        let mut matched = [0; 3];
+        let mut unmatched = Vec::new();
+        for (entry, found) in file.entries.iter().zip(found) {
+            match found {
+                Some((media_id, how)) => {
+                    matched[how as usize] += 1;
+                    media_ids.push(MediaId::new(media_id));
+                }
+                None => unmatched.push(PlaylistImportUnmatchedResponse {
+                    line: entry.line,
+                    location: entry.location.clone(),
+                    title: entry.title.clone(),
+                    artist: entry.artist.clone(),
+                }),
+            }
+        }
+
+        let title = [request.title.trim(), file.title.trim()]
+            .into_iter()
+            .find(|title| !title.is_empty())
+            .unwrap_or(IMPORTED_PLAYLIST_TITLE);
+        let playlist = match store.create_playlist(auth.user_id, title).await {
+            Ok(playlist) => playlist,
+            Err(_) => return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        };
+        let playlist = if media_ids.is_empty() {
+            playlist
+        } else {
+            let Ok(playlist_id) = Uuid::parse_str(&playlist.id) else {
+                return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
+            };
+            let edit = PlaylistEdit::Insert {
+                position: None,
+                media_ids,
+                allow_duplicates: true,
+            };
+            match store
+                .edit_playlist(auth.user_id, auth.device_id, playlist_id, None, edit)
+                .await
+            {
+                Ok(PlaylistEditOutcome::Edited(playlist)) => playlist,
+                outcome => {
+                    eprintln!("playlist {playlist_id}: import items: {outcome:?}");
+                    let _ = store.delete_playlist(auth.user_id, playlist_id).await;
+                    return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
+                }
+            }
+        };
+        Json(PlaylistImportResponse {
+            playlist,
+            matched_by_path: matched[ImportMatch::Path as usize],
+            matched_by_tags: matched[ImportMatch::Tags as usize],
+            matched_by_youtube: matched[ImportMatch::YouTube as usize],
+            unmatched,
+        })
+        .into_response()
+    })
+    .await
+}
+
+pub(crate) async fn export_playlist(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let auth = match authorize(&headers, &uri, &state).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
+    let playlist_id = match parse_playlist_id(&id) {
+        Ok(id) => id,
+        Err(response) => return *response,
+    };
+    let query = uri.query().unwrap_or_default();
+    let format = match query_param(query, "format") {
+        None => PlaylistFileFormat::M3u8,
+        Some(name) => match PlaylistFileFormat::from_name(&name) {
+            Some(format) => format,
+            None => return legacy_json_error(StatusCode::BAD_REQUEST, "invalid_request"),
+        },
+    };
+    let relative = match query_param(query, "paths").as_deref() {
+        None | Some("absolute") => false,
+        Some("relative") => true,
+        Some(_) => return legacy_json_error(StatusCode::BAD_REQUEST, "invalid_request"),
+    };
+    let base = query_param(query, "base");
+    if base.as_deref().is_some_and(|base| !base.starts_with('/')) {
+        return legacy_json_error(StatusCode::BAD_REQUEST, "invalid_request");
+    }
+    let Some(store) = &state.store else {
+        return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
+    };
+    let playlist = match store.get_playlist(auth.user_id, playlist_id).await {
+        Ok(Some(playlist)) => playlist,
+        Ok(None) => return legacy_json_error(StatusCode::NOT_FOUND, "not_found"),
+        Err(_) => return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+    };
+    let media_ids: Vec<&str> = playlist
+        .items
+        .iter()
+        .map(|item| item.media_id.as_str())
+        .collect();
+    let paths = match store.song_local_paths(&media_ids).await {
+        Ok(paths) => paths,
+        Err(_) => return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+    };
+    // Relative paths default to the directory all the files share, which is
+    // where the exported playlist is expected to be saved.
+    let base = base.unwrap_or_else(|| common_directory(paths.values().map(String::as_str)));
+
+    // Songs with neither a file nor a YouTube id have nothing to point at and
+    // are left out.
+    let entries = playlist
+        .items
+        .iter()
+        .filter_map(|item| {
+            let location = match paths.get(&item.media_id) {
+                Some(path) if relative => relative_path(path, &base),
+                Some(path) => path.clone(),
+                None => format!(
+                    "https://music.youtube.com/watch?v={}",
+                    item.media_id.strip_prefix("yt:")?
+                ),
+            };
+            Some(PlaylistFileEntry {
+                line: 0,
+                location,
+                title: item.title.clone(),
+                artist: item.artist_name.clone(),
+                duration_ms: (item.duration_ms > 0).then_some(item.duration_ms),
+            })
+        })
+        .collect();
+    let body = write_playlist_file(
+        format,
+        &PlaylistFile {
+            title: playlist.title.clone(),
+            entries,
+        },
+    );
+    let file_name: String = playlist
+        .title
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let file_name = match file_name.trim() {
+        "" => "playlist",
+        name => name,
+    };
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{file_name}.{}\"",
+                    format.extension()
+                ),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+#[derive(Clone, Copy)]
+enum ImportMatch {
+    Path,
+    Tags,
+    YouTube,
+}
+
+/// Finds the song each imported entry stands for, in entry order: a library
+/// file at its path, then a library song with its tags, then, given `yt`,
+/// YouTube's top search result for them. Each library step looks up every
+/// entry still unmatched in one query. The searches run
+/// `IMPORT_YOUTUBE_CONCURRENCY` at a time, and entries whose search has not
+/// answered by `IMPORT_YOUTUBE_DEADLINE` stay unmatched.
+async fn match_import_entries(
+    store: &PostgresStore,
+    yt: Option<&dyn innertube::InnerTubeBackend>,
+    entries: &[PlaylistFileEntry],
+) -> StorageResult<Vec<Option<(String, ImportMatch)>>> {
+    let targets: Vec<PlaylistFileTarget> = entries.iter().map(PlaylistFileEntry::target).collect();
+    let mut matches: Vec<Option<(String, ImportMatch)>> = vec![None; entries.len()];
+
+    for relative in [false, true] {
+        let (indexes, paths): (Vec<usize>, Vec<&str>) = targets
+            .iter()
+            .enumerate()
+            .filter_map(|(index, target)| match target {
+                PlaylistFileTarget::AbsolutePath(path) if !relative => Some((index, path.as_str())),
+                PlaylistFileTarget::RelativePath(path) if relative && !path.is_empty() => {
+                    Some((index, path.as_str()))
+                }
+                _ => None,
+            })
+            .unzip();
+        let found = store.local_songs_by_paths(&paths, relative).await?;
+        for (index, media_id) in indexes.into_iter().zip(found) {
+            matches[index] = media_id.map(|media_id| (media_id, ImportMatch::Path));
+        }
+    }
+
+    let videos: Vec<(usize, &str, String)> = targets
+        .iter()
+        .enumerate()
+        .filter_map(|(index, target)| match target {
+            PlaylistFileTarget::YouTube(video_id) => {
+                Some((index, video_id.as_str(), format!("yt:{video_id}")))
+            }
+            _ => None,
+        })
+        .collect();
+    let lookup: Vec<&str> = videos
+        .iter()
+        .map(|(_, _, media_id)| media_id.as_str())
+        .collect();
+    let known = store.known_song_ids(&lookup).await?;
+    for (index, video_id, media_id) in videos {
+        if known.contains(&media_id) {
+            matches[index] = Some((media_id, ImportMatch::Path));
+        } else if yt.is_some() {
+            let (title, artist) = entries[index].tags();
+            store
+                .ensure_youtube_song(&RemoteYouTubeSong {
+                    media_id: media_id.clone(),
+                    title: if title.is_empty() {
+                        video_id.to_string()
+                    } else {
+                        title
+                    },
+                    artist,
+                    duration_ms: entries[index].duration_ms,
+                    thumbnail_url: String::new(),
+                    explicit: false,
+                })
+                .await?;
+            matches[index] = Some((media_id, ImportMatch::YouTube));
+        }
+    }
+
+    let by_tags: Vec<(usize, (String, String))> = entries
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| {
+            matches[*index].is_none() && !matches!(targets[*index], PlaylistFileTarget::YouTube(_))
+        })
+        .map(|(index, entry)| (index, entry.tags()))
+        .filter(|(_, (title, _))| !title.is_empty())
+        .collect();
+    let tags: Vec<(&str, &str)> = by_tags
+        .iter()
+        .map(|(_, (title, artist))| (title.as_str(), artist.as_str()))
+        .collect();
+    let found = store.local_songs_by_tags(&tags).await?;
+    let mut searches = Vec::new();
+    for ((index, tags), media_id) in by_tags.into_iter().zip(found) {
+        match media_id {
+            Some(media_id) => matches[index] = Some((media_id, ImportMatch::Tags)),
+            None => searches.push((index, tags)),
+        }
+    }
+
+    let Some(yt) = yt else {
+        return Ok(matches);
+    };
+    searches.truncate(MAX_IMPORT_YOUTUBE_LOOKUPS);
+    let deadline = tokio::time::Instant::now() + IMPORT_YOUTUBE_DEADLINE;
+    let searches: Vec<BoxFuture<'_, (usize, Option<innertube::SongItem>)>> = searches
+        .into_iter()
+        .map(|(index, (title, artist))| {
+            Box::pin(async move {
+                let query = format!("{artist} {title}");
+                let query = query.trim();
+                let song =
+                    match tokio::time::timeout(IMPORT_YOUTUBE_TIMEOUT, yt.search(query)).await {
+                        Ok(Ok(page)) => page
+                            .songs
+                            .into_iter()
+                            .find(|song| !song.video_id.is_empty() && !song.title.is_empty()),
+                        Ok(Err(err)) => {
+                            eprintln!("playlist import: search {query:?}: {err}");
+                            None
+                        }
+                        Err(_) => None,
+                    };
+                (index, song)
+            }) as _
+        })
+        .collect();
+    let mut pending = stream::iter(searches).buffer_unordered(IMPORT_YOUTUBE_CONCURRENCY);
+    let mut found = Vec::new();
+    while let Ok(Some((index, song))) = tokio::time::timeout_at(deadline, pending.next()).await {
+        found.extend(song.map(|song| (index, song)));
+    }
+    drop(pending);
+
+    for (index, song) in found {
+        let media_id = format!("yt:{}", song.video_id.trim_start_matches("yt:"));
+        store
+            .ensure_youtube_song(&RemoteYouTubeSong {
+                media_id: media_id.clone(),
+                title: song.title,
+                artist: song.artists.join(", "),
+                duration_ms: (song.duration_ms > 0).then_some(song.duration_ms),
+                thumbnail_url: song.thumbnail_url,
+                explicit: song.is_explicit,
+            })
+            .await?;
+        matches[index] = Some((media_id, ImportMatch::YouTube));
+    }
+    Ok(matches)
+}
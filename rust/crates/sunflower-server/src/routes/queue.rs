@@ -10,6 +10,7 @@ pub(crate) async fn start_queue(
         Ok(auth) => auth,
         Err(response) => return response,
     };
+    let state = state.for_user(auth.user_id);
 
     run_idempotent(&state, &headers, &uri, "POST", &auth, async {
         let raw = String::from_utf8_lossy(&body);
@@ -18,87 +19,204 @@ pub(crate) async fn start_queue(
             Err(err) => return legacy_json_error(StatusCode::BAD_REQUEST, err.legacy_error_code()),
         };
 
-        match request.seed_kind.as_str() {
-            "song" => {
-                let video_id = match request.song_seed_video_id() {
-                    Ok(video_id) => video_id,
-                    Err(err) => {
-                        return legacy_json_error(StatusCode::BAD_GATEWAY, err.legacy_error_code());
-                    }
-                };
-                let Some(yt) = &state.yt else {
-                    return legacy_json_error(
-                        StatusCode::BAD_GATEWAY,
-                        LegacyRequestError::SeedUnavailable.legacy_error_code(),
-                    );
-                };
-                let items =
-                    match innertube::expand_radio(yt.as_ref(), video_id, MIN_QUEUE_ITEMS).await {
-                        Ok(items) => items,
-                        Err(_) => {
-                            return legacy_json_error(
-                                StatusCode::BAD_GATEWAY,
-                                LegacyRequestError::SeedUnavailable.legacy_error_code(),
-                            );
-                        }
-                    };
-                if items.is_empty() {
-                    return legacy_json_error(StatusCode::UNPROCESSABLE_ENTITY, "empty_queue");
-                }
-                let Some(store) = &state.store else {
-                    return legacy_json_error(StatusCode::UNPROCESSABLE_ENTITY, "empty_queue");
-                };
-                match store
-                    .create_queue(
-                        auth.user_id,
-                        auth.device_id,
-                        &request.seed_kind,
-                        &request.seed_id,
-                        &request.title,
-                        &items,
-                    )
-                    .await
-                {
-                    Ok(session) => Json(QueueResponse::from(&session)).into_response(),
-                    Err(_) => legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
-                }
-            }
-            "shuffle_liked" => {
-                let Some(store) = &state.store else {
-                    return legacy_json_error(StatusCode::UNPROCESSABLE_ENTITY, "empty_queue");
-                };
-                let liked = match store.list_liked_songs(auth.user_id).await {
-                    Ok(liked) => liked,
-                    Err(_) => {
-                        return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
-                    }
-                };
-                let items = build_automix(&liked, shuffle_seed());
-                if items.is_empty() {
-                    return legacy_json_error(StatusCode::UNPROCESSABLE_ENTITY, "empty_queue");
-                }
-                match store
-                    .create_queue(
-                        auth.user_id,
-                        auth.device_id,
-                        &request.seed_kind,
-                        &request.seed_id,
-                        &request.title,
-                        &items,
-                    )
-                    .await
-                {
-                    Ok(session) => Json(QueueResponse::from(&session)).into_response(),
-                    Err(_) => legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
-                }
-            }
-            _ => legacy_json_error(
+        let seeded = match seed_queue_items(&state, &auth, &request).await {
+            Ok(seeded) => seeded,
+            Err(response) => return *response,
+        };
+        let Some(store) = &state.store else {
+            return legacy_json_error(StatusCode::UNPROCESSABLE_ENTITY, "empty_queue");
+        };
+        let rules = request.queue_rules();
+        let flags = match queue_content_flags(store, &seeded.items, seeded.flags, rules).await {
+            Ok(flags) => flags,
+            Err(_) => return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        };
+        let (items, continuation) = (seeded.items, seeded.continuation);
+        let items = apply_queue_rules(items, &flags, rules);
+        if items.is_empty() {
+            return legacy_json_error(StatusCode::UNPROCESSABLE_ENTITY, "empty_queue");
+        }
+        let seed = request.shuffle.then(shuffle_seed);
+        let shuffle_order = if seed.is_some() {
+            queue_play_order(items.len(), seed)
+        } else {
+            vec![]
+        };
+        match store
+            .create_queue(QueueSessionInsert {
+                user_id: auth.user_id,
+                device_id: auth.device_id,
+                seed_kind: &request.seed_kind,
+                seed_id: &request.seed_id,
+                title: &request.title,
+                items: &items,
+                continuation: continuation.as_deref(),
+                shuffle_seed: seed,
+                shuffle_order: &shuffle_order,
+                repeat: request.repeat_mode().unwrap_or_default(),
+                rules,
+            })
+            .await
+        {
+            Ok(session) => Json(QueueResponse::from(&session)).into_response(),
+            Err(_) => legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        }
+    })
+    .await
+}
+
+/// Materializes the items for a queue seed, plus the radio continuation for
+/// seeds that can keep growing and the content flags InnerTube reported for
+/// its songs.
+async fn seed_queue_items(
+    state: &AppState,
+    auth: &AuthenticatedDevice,
+    request: &StartQueueRequest,
+) -> ResponseResult<ExpandedRadio> {
+    let seed_unavailable = || {
+        Box::new(legacy_json_error(
+            StatusCode::BAD_GATEWAY,
+            LegacyRequestError::SeedUnavailable.legacy_error_code(),
+        ))
+    };
+    let internal = |_| {
+        Box::new(legacy_json_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal",
+        ))
+    };
+
+    if request.seed_kind == "song" {
+        let video_id = request
+            .song_seed_video_id()
+            .map_err(|_| seed_unavailable())?;
+        let yt = state.yt.as_ref().ok_or_else(seed_unavailable)?;
+        return innertube::expand_radio(yt.as_ref(), video_id, MIN_QUEUE_ITEMS)
+            .await
+            .map_err(|_| seed_unavailable());
+    }
+    if request.seed_kind == "yt_playlist" {
+        let browse_id = request.yt_playlist_browse_id().map_err(|err| {
+            Box::new(legacy_json_error(
+                StatusCode::BAD_REQUEST,
+                err.legacy_error_code(),
+            ))
+        })?;
+        let yt = state.yt.as_ref().ok_or_else(seed_unavailable)?;
+        return innertube::playlist_items(yt.as_ref(), &browse_id)
+            .await
+            .map_err(|_| seed_unavailable());
+    }
+
+    // The remaining seeds come from the library; without a store there is
+    // nothing to queue.
+    let Some(store) = &state.store else {
+        return Ok(ExpandedRadio::default());
+    };
+    let items = match request.seed_kind.as_str() {
+        "shuffle_liked" => {
+            let liked = store
+                .list_liked_songs(auth.user_id)
+                .await
+                .map_err(internal)?;
+            build_automix(&liked, shuffle_seed())
+        }
+        "album" => store
+            .list_album_queue_items(&request.seed_id)
+            .await
+            .map_err(internal)?,
+        "artist" => {
+            let items = store
+                .list_artist_queue_items(&request.seed_id)
+                .await
+                .map_err(internal)?;
+            shuffle_queue_items(items, shuffle_seed())
+        }
+        "playlist" => {
+            let playlist_id = Uuid::parse_str(&request.seed_id).map_err(|_| {
+                Box::new(legacy_json_error(
+                    StatusCode::BAD_REQUEST,
+                    LegacyRequestError::InvalidRequest.legacy_error_code(),
+                ))
+            })?;
+            let playlist = store
+                .get_playlist(auth.user_id, playlist_id)
+                .await
+                .map_err(internal)?
+                .ok_or_else(|| Box::new(legacy_json_error(StatusCode::NOT_FOUND, "not_found")))?;
+            playlist
+                .items
+                .into_iter()
+                .map(|item| QueueItem {
+                    media_id: MediaId::new(item.media_id),
+                    title: item.title,
+                    artists: (!item.artist_name.is_empty())
+                        .then_some(vec![item.artist_name])
+                        .unwrap_or_default(),
+                    duration_ms: item.duration_ms,
+                    unavailable: false,
+                })
+                .collect()
+        }
+        "local_radio" => {
+            let (candidates, stats) = store
+                .local_home_inputs(
+                    auth.user_id,
+                    auth.device_id,
+                    LOCAL_RADIO_CANDIDATE_LIMIT,
+                    false,
+                    false,
+                )
+                .await
+                .map_err(internal)?;
+            let local: Vec<_> = candidates
+                .into_iter()
+                .filter(|candidate| candidate.media_id.source() == "local")
+                .collect();
+            LocalRecommendationEngine::default()
+                .rank(&local, &stats, LOCAL_RADIO_ITEMS)
+                .into_iter()
+                .map(|candidate| QueueItem {
+                    media_id: candidate.media_id,
+                    title: candidate.title,
+                    artists: candidate.artists,
+                    duration_ms: candidate.duration_ms,
+                    unavailable: false,
+                })
+                .collect()
+        }
+        _ => {
+            return Err(Box::new(legacy_json_error(
                 StatusCode::BAD_REQUEST,
                 LegacyRequestError::InvalidSeedKind.legacy_error_code(),
-            ),
+            )));
         }
+    };
+    Ok(ExpandedRadio {
+        items,
+        ..ExpandedRadio::default()
     })
-    .await
+}
+
+/// The flags the hide rules check `items` against: what the seed's source
+/// `reported`, plus what the library knows. Empty when no rule hides
+/// anything.
+async fn queue_content_flags(
+    store: &PostgresStore,
+    items: &[QueueItem],
+    reported: HashMap<MediaId, ContentFlags>,
+    rules: QueueRules,
+) -> StorageResult<HashMap<MediaId, ContentFlags>> {
+    if !rules.hide_explicit && !rules.hide_video {
+        return Ok(HashMap::new());
+    }
+    let media_ids: Vec<MediaId> = items.iter().map(|item| item.media_id.clone()).collect();
+    let mut flags = reported;
+    for (media_id, stored) in store.content_flags(&media_ids).await? {
+        let merged = flags.get(&media_id).copied().unwrap_or_default();
+        flags.insert(media_id, merged.union(stored));
+    }
+    Ok(flags)
 }
 
 pub(crate) async fn get_queue(
@@ -125,6 +243,132 @@ pub(crate) async fn get_queue(
     }
 }
 
+pub(crate) async fn edit_queue(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let auth = match authorize(&headers, &uri, &state).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
+    let state = state.for_user(auth.user_id);
+
+    run_idempotent(&state, &headers, &uri, "POST", &auth, async {
+        let queue_id = match Uuid::parse_str(&id) {
+            Ok(id) => id,
+            Err(_) => return legacy_json_error(StatusCode::BAD_REQUEST, "invalid_id"),
+        };
+        let raw = String::from_utf8_lossy(&body);
+        let (version, edit) = match QueueEditRequest::parse_json(&raw) {
+            Ok(parsed) => parsed,
+            Err(err) => return legacy_json_error(StatusCode::BAD_REQUEST, err.legacy_error_code()),
+        };
+        let Some(store) = &state.store else {
+            return legacy_json_error(StatusCode::NOT_FOUND, "not_found");
+        };
+        let mut session = match store.get_queue(queue_id, auth.user_id).await {
+            Ok(Some(session)) => session,
+            Ok(None) => return legacy_json_error(StatusCode::NOT_FOUND, "not_found"),
+            Err(_) => return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        };
+        if session.version != version {
+            return legacy_json_error(StatusCode::CONFLICT, "version_conflict");
+        }
+        // A shuffled queue plays inserted items right after the current one.
+        let current = if session.shuffle_seed.is_some() {
+            match store.get_queue_progress(queue_id, auth.user_id).await {
+                Ok(progress) => progress.unwrap_or_default().position,
+                Err(_) => return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+            }
+        } else {
+            0
+        };
+        if apply_session_edit(&mut session, edit, current).is_err() {
+            return legacy_json_error(StatusCode::BAD_REQUEST, "position_out_of_range");
+        }
+        match store
+            .replace_queue_items(
+                queue_id,
+                auth.user_id,
+                version,
+                &session.items,
+                &session.shuffle_order,
+            )
+            .await
+        {
+            Ok(Some(session)) => Json(QueueResponse::from(&session)).into_response(),
+            // Another edit landed between the read and the write.
+            Ok(None) => legacy_json_error(StatusCode::CONFLICT, "version_conflict"),
+            Err(_) => legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        }
+    })
+    .await
+}
+
+pub(crate) async fn set_queue_mode(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let auth = match authorize(&headers, &uri, &state).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
+    let state = state.for_user(auth.user_id);
+
+    run_idempotent(&state, &headers, &uri, "POST", &auth, async {
+        let queue_id = match Uuid::parse_str(&id) {
+            Ok(id) => id,
+            Err(_) => return legacy_json_error(StatusCode::BAD_REQUEST, "invalid_id"),
+        };
+        let raw = String::from_utf8_lossy(&body);
+        let (shuffle, repeat) = match QueueModeRequest::parse_json(&raw) {
+            Ok(parsed) => parsed,
+            Err(err) => return legacy_json_error(StatusCode::BAD_REQUEST, err.legacy_error_code()),
+        };
+        let Some(store) = &state.store else {
+            return legacy_json_error(StatusCode::NOT_FOUND, "not_found");
+        };
+        let session = match store.get_queue(queue_id, auth.user_id).await {
+            Ok(Some(session)) => session,
+            Ok(None) => return legacy_json_error(StatusCode::NOT_FOUND, "not_found"),
+            Err(_) => return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        };
+        // Turning shuffle on for an already shuffled queue keeps its order.
+        let (seed, order) = match (shuffle, session.shuffle_seed) {
+            (Some(true) | None, Some(seed)) => (Some(seed), session_play_order(&session)),
+            (Some(true), None) => {
+                let seed = shuffle_seed();
+                (
+                    Some(seed),
+                    queue_play_order(session.items.len(), Some(seed)),
+                )
+            }
+            (Some(false), _) | (None, None) => (None, vec![]),
+        };
+        match store
+            .set_queue_mode(
+                queue_id,
+                auth.user_id,
+                seed,
+                &order,
+                repeat.unwrap_or(session.repeat),
+            )
+            .await
+        {
+            Ok(Some(session)) => Json(QueueResponse::from(&session)).into_response(),
+            Ok(None) => legacy_json_error(StatusCode::NOT_FOUND, "not_found"),
+            Err(_) => legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        }
+    })
+    .await
+}
+
 pub(crate) async fn get_next(
     State(state): State<AppState>,
     uri: Uri,
@@ -134,6 +378,7 @@ pub(crate) async fn get_next(
         Ok(auth) => auth,
         Err(response) => return response,
     };
+    let state = state.for_user(auth.user_id);
     let query = uri.query().unwrap_or_default();
     let queue_id_raw = query_param(query, "queue_id");
     let position_raw = query_param(query, "position");
@@ -149,38 +394,355 @@ pub(crate) async fn get_next(
         Ok(None) => return legacy_json_error(StatusCode::NOT_FOUND, "not_found"),
         Err(_) => return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
     };
-    if next_query.position >= session.items.len() {
-        return legacy_json_error(StatusCode::NOT_FOUND, "position_out_of_range");
+    let quality = AudioQuality::parse(
+        query_param(query, "audio_quality")
+            .as_deref()
+            .unwrap_or_default(),
+    );
+    let skip_unavailable = bool_param(uri.query(), "skip_unavailable");
+    match next_at_position(
+        &state,
+        store,
+        &auth,
+        session,
+        next_query.position,
+        quality,
+        skip_unavailable,
+    )
+    .await
+    {
+        Ok(next) => Json(next).into_response(),
+        Err(response) => *response,
     }
-    let current_item = &session.items[next_query.position];
-    let current = match resolve_queue_item(&state, current_item, false).await {
-        Ok(current) => current,
-        Err(ResolveMediaError::Unavailable) => {
-            return legacy_json_error(StatusCode::GONE, "current_unavailable");
-        }
-        Err(ResolveMediaError::Failed) => {
-            return legacy_json_error(StatusCode::BAD_GATEWAY, "resolve_failed");
-        }
+}
+
+pub(crate) async fn resume_queue(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let auth = match authorize(&headers, &uri, &state).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
+    let state = state.for_user(auth.user_id);
+    let queue_id = match Uuid::parse_str(&id) {
+        Ok(id) => id,
+        Err(_) => return legacy_json_error(StatusCode::BAD_REQUEST, "invalid_id"),
+    };
+    let Some(store) = &state.store else {
+        return legacy_json_error(StatusCode::NOT_FOUND, "not_found");
+    };
+    let (session, progress) = match tokio::try_join!(
+        store.get_queue(queue_id, auth.user_id),
+        store.get_queue_progress(queue_id, auth.user_id),
+    ) {
+        Ok((Some(session), progress)) => (session, progress.unwrap_or_default()),
+        Ok((None, _)) => return legacy_json_error(StatusCode::NOT_FOUND, "not_found"),
+        Err(_) => return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+    };
+    // Edits may have removed the saved item; fall back to the last one left.
+    let (position, position_ms) = if progress.position < session.items.len() {
+        (progress.position, progress.position_ms)
+    } else {
+        (session.items.len().saturating_sub(1), 0)
+    };
+    let quality = AudioQuality::parse(
+        query_param(uri.query().unwrap_or_default(), "audio_quality")
+            .as_deref()
+            .unwrap_or_default(),
+    );
+    let skip_unavailable = bool_param(uri.query(), "skip_unavailable");
+    match next_at_position(
+        &state,
+        store,
+        &auth,
+        session,
+        position,
+        quality,
+        skip_unavailable,
+    )
+    .await
+    {
+        Ok(next) => Json(NextResponse {
+            position_ms,
+            ..next
+        })
+        .into_response(),
+        Err(response) => *response,
+    }
+}
+
+pub(crate) async fn list_queues(
+    State(state): State<AppState>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let auth = match authorize(&headers, &uri, &state).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
+    let Some(store) = &state.store else {
+        return legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal");
+    };
+    let (limit, offset) = pagination(uri.query());
+    match store.list_queues(auth.user_id, limit, offset).await {
+        Ok(queues) => Json(QueueListResponse { queues }).into_response(),
+        Err(_) => legacy_json_error(StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+    }
+}
+
+/// Builds the `/next` window for the item at `position`, first extending a
+/// radio queue whose window is about to run out. With `skip_unavailable` an
+/// item that no longer resolves moves the window on to the next one that does.
+async fn next_at_position(
+    state: &AppState,
+    store: &PostgresStore,
+    auth: &AuthenticatedDevice,
+    mut session: QueueSession,
+    position: usize,
+    quality: AudioQuality,
+    skip_unavailable: bool,
+) -> ResponseResult<NextResponse> {
+    // Radio only grows a queue that plays straight through; shuffled and
+    // repeating queues play the items they already have.
+    if session.shuffle_seed.is_none()
+        && session.repeat == RepeatMode::Off
+        && position + DEFAULT_LOOKAHEAD_COUNT >= session.items.len()
+    {
+        extend_radio_queue(state, store, auth.user_id, &mut session).await;
+    }
+    let position_out_of_range = || {
+        Box::new(legacy_json_error(
+            StatusCode::NOT_FOUND,
+            "position_out_of_range",
+        ))
+    };
+    let Some(current_item) = session.items.get(position) else {
+        return Err(position_out_of_range());
+    };
+    let (position, current) = if skip_unavailable {
+        resolve_first_available(state, store, auth.user_id, &mut session, position, quality).await?
+    } else {
+        let current = resolve_queue_item(state, current_item, false, quality)
+            .await
+            .map_err(current_resolve_error)?;
+        (position, current)
     };
     let current_core = resolved_response_to_core(&current);
-    let decision = match next_window(
+    let mut decision = next_window(
         &session,
-        next_query.position,
+        position,
         current_core,
         DEFAULT_LOOKAHEAD_COUNT,
         RecommendationSource::Remote,
-    ) {
-        Ok(decision) => decision,
-        Err(_) => return legacy_json_error(StatusCode::NOT_FOUND, "position_out_of_range"),
-    };
-    let lookahead = resolve_lookahead_items(&state, &decision.lookahead).await;
-    Json(NextResponse::from_decision_with_streams(
+    )
+    .map_err(|_| position_out_of_range())?;
+    let (lookahead, automix) = tokio::join!(
+        resolve_lookahead_items(state, &decision.lookahead, quality),
+        automix_suggestions(state, store, auth, &session, position),
+    );
+    decision.automix = automix;
+    Ok(NextResponse::from_decision_with_streams(
         &decision,
         Some(current),
         lookahead,
     ))
-    .into_response()
 }
+
+fn current_resolve_error(err: ResolveMediaError) -> Box<Response> {
+    Box::new(match err {
+        ResolveMediaError::Unavailable => {
+            legacy_json_error(StatusCode::GONE, "current_unavailable")
+        }
+        ResolveMediaError::Failed => legacy_json_error(StatusCode::BAD_GATEWAY, "resolve_failed"),
+    })
+}
+
+/// Resolves the first playable item from `position` on, following the play
+/// order, and marks the items passed over as unavailable in the queue. Items
+/// already marked are passed over without resolving; after
+/// `MAX_UNAVAILABLE_SKIPS` failed resolves the request ends as
+/// `current_unavailable`. A resolve that fails for any other reason stops the
+/// walk as it would without skipping.
+async fn resolve_first_available(
+    state: &AppState,
+    store: &PostgresStore,
+    user_id: Uuid,
+    session: &mut QueueSession,
+    position: usize,
+    quality: AudioQuality,
+) -> ResponseResult<(usize, ResolvedStreamResponse)> {
+    let order = session_play_order(session);
+    let at = order
+        .iter()
+        .position(|&index| index == position)
+        .unwrap_or(position);
+    let candidates: Vec<usize> = match session.repeat {
+        RepeatMode::All => order
+            .iter()
+            .cycle()
+            .skip(at)
+            .take(order.len())
+            .copied()
+            .collect(),
+        RepeatMode::Off | RepeatMode::One => order[at..].to_vec(),
+    };
+
+    let mut skipped = vec![];
+    let mut outcome = Err(ResolveMediaError::Unavailable);
+    for candidate in candidates {
+        let item = &session.items[candidate];
+        if item.unavailable {
+            continue;
+        }
+        if skipped.len() == MAX_UNAVAILABLE_SKIPS {
+            break;
+        }
+        match resolve_queue_item(state, item, false, quality).await {
+            Ok(current) => {
+                outcome = Ok((candidate, current));
+                break;
+            }
+            Err(ResolveMediaError::Unavailable) => skipped.push(candidate),
+            Err(err) => {
+                outcome = Err(err);
+                break;
+            }
+        }
+    }
+
+    if !skipped.is_empty() {
+        match store
+            .mark_queue_items_unavailable(session.id, user_id, session.version, &skipped)
+            .await
+        {
+            Ok(Some(version)) => {
+                session.version = version;
+                for &index in &skipped {
+                    session.items[index].unavailable = true;
+                }
+            }
+            // An edit landed meanwhile; the positions may point elsewhere now.
+            Ok(None) => {}
+            Err(err) => eprintln!("queue {}: mark unavailable: {err}", session.id),
+        }
+    }
+    outcome.map_err(current_resolve_error)
+}
+
+/// Songs like the item at `position` that are not queued yet, for clients to
+/// offer as "add similar". Each source is best effort: one that fails or
+/// misses `AUTOMIX_TIMEOUT` contributes nothing.
+async fn automix_suggestions(
+    state: &AppState,
+    store: &PostgresStore,
+    auth: &AuthenticatedDevice,
+    session: &QueueSession,
+    position: usize,
+) -> Vec<QueueItem> {
+    let current = &session.items[position];
+    let related = async {
+        let (Some(yt), Some(video_id)) = (&state.yt, current.media_id.0.strip_prefix("yt:")) else {
+            return vec![];
+        };
+        match tokio::time::timeout(
+            AUTOMIX_TIMEOUT,
+            innertube::related_songs(yt.as_ref(), video_id),
+        )
+        .await
+        {
+            Ok(Ok(items)) => items,
+            Ok(Err(_)) | Err(_) => vec![],
+        }
+    };
+    let local = store.local_home_inputs(
+        auth.user_id,
+        auth.device_id,
+        HOME_LOCAL_CANDIDATE_LIMIT,
+        false,
+        false,
+    );
+    let (related, local) = tokio::join!(related, local);
+    let (candidates, stats) = local.unwrap_or_default();
+    LocalRecommendationEngine::default().automix(
+        current,
+        &related,
+        &candidates,
+        &stats,
+        &session.items,
+        AUTOMIX_SUGGESTIONS,
+    )
+}
+
+/// Appends the next radio pages to a queue whose window is about to run past
+/// its end, under the rules the queue was started with. Failures leave the
+/// queue as it was; the continuation stays stored and the next request tries
+/// again.
+async fn extend_radio_queue(
+    state: &AppState,
+    store: &PostgresStore,
+    user_id: Uuid,
+    session: &mut QueueSession,
+) {
+    let (Some(yt), Some(continuation)) = (&state.yt, session.continuation.clone()) else {
+        return;
+    };
+    let seed_video_id = session
+        .seed_id
+        .strip_prefix("yt:")
+        .unwrap_or(&session.seed_id)
+        .to_string();
+    let more = match innertube::continue_radio(
+        yt.as_ref(),
+        &seed_video_id,
+        &continuation,
+        &session.items,
+        MIN_QUEUE_ITEMS,
+    )
+    .await
+    {
+        Ok(more) => more,
+        Err(err) => {
+            eprintln!("queue {}: extend radio: {err}", session.id);
+            return;
+        }
+    };
+    let rules = session.rules;
+    let flags = match queue_content_flags(store, &more.items, more.flags, rules).await {
+        Ok(flags) => flags,
+        Err(err) => {
+            eprintln!("queue {}: extend radio: {err}", session.id);
+            return;
+        }
+    };
+    let items = apply_queue_rules_after(&session.items, more.items, &flags, rules);
+    match store
+        .append_queue_items(
+            session.id,
+            user_id,
+            session.version,
+            &items,
+            more.continuation.as_deref(),
+        )
+        .await
+    {
+        Ok(Some(version)) => {
+            session.version = version;
+            session.items.extend(items);
+            session.continuation = more.continuation;
+        }
+        // Someone else edited or extended the queue first; serve theirs.
+        Ok(None) => {
+            if let Ok(Some(latest)) = store.get_queue(session.id, user_id).await {
+                *session = latest;
+            }
+        }
+        Err(err) => eprintln!("queue {}: extend radio: {err}", session.id),
+    }
+}
+
 pub(crate) fn shuffle_seed() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -5,9 +5,11 @@ pub(crate) async fn search(
     uri: Uri,
     headers: HeaderMap,
 ) -> Response {
-    if let Err(response) = authorize(&headers, &uri, &state).await {
-        return response;
-    }
+    let auth = match authorize(&headers, &uri, &state).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
+    let state = state.for_user(auth.user_id);
     let query_raw = uri.query().unwrap_or_default();
     let query = decoded_query_param(query_raw, "q")
         .unwrap_or_default()
@@ -26,30 +26,100 @@ pub(crate) async fn resolve_stream(
         Ok(auth) => auth,
         Err(response) => return response,
     };
+    let state = state.for_user(auth.user_id);
 
     run_idempotent(&state, &headers, &uri, "POST", &auth, async {
         let raw = String::from_utf8_lossy(&body);
-        match ResolveStreamRequest::parse_json(&raw) {
-            Ok(request) => match resolve_media_id(&state, &request.media_id, request.proxy).await {
-                Ok(resolved) => Json(ResolvedStreamResponse::from(&resolved)).into_response(),
-                Err(ResolveMediaError::Unavailable) => {
-                    legacy_json_error(StatusCode::GONE, "unavailable")
-                }
-                Err(ResolveMediaError::Failed) => {
-                    legacy_json_error(StatusCode::BAD_GATEWAY, "resolve_failed")
-                }
-            },
-            Err(err) => legacy_json_error(StatusCode::BAD_REQUEST, err.legacy_error_code()),
+        let request = match ResolveStreamRequest::parse_json(&raw) {
+            Ok(request) => request,
+            Err(err) => return legacy_json_error(StatusCode::BAD_REQUEST, err.legacy_error_code()),
+        };
+        // A 403 means the cached URL is dead; a client about to hit expiry
+        // wants one that lasts, so neither may be served from refresh-ahead.
+        let freshness = match request.reason.as_str() {
+            "http_403" => Freshness::Reload,
+            "near_expiry" => Freshness::Fresh,
+            _ => Freshness::RefreshAhead,
+        };
+        match resolve_media_id(
+            &state,
+            &request.media_id,
+            request.proxy,
+            AudioQuality::parse(&request.audio_quality),
+            freshness,
+        )
+        .await
+        {
+            Ok(resolved) => Json(ResolvedStreamResponse::from(&resolved)).into_response(),
+            Err(ResolveMediaError::Unavailable) => {
+                legacy_json_error(StatusCode::GONE, "unavailable")
+            }
+            Err(ResolveMediaError::Failed) => {
+                legacy_json_error(StatusCode::BAD_GATEWAY, "resolve_failed")
+            }
+        }
+    })
+    .await
+}
+
+pub(crate) async fn refresh_streams(
+    State(state): State<AppState>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let auth = match authorize(&headers, &uri, &state).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
+    let state = state.for_user(auth.user_id);
+
+    run_idempotent(&state, &headers, &uri, "POST", &auth, async {
+        let raw = String::from_utf8_lossy(&body);
+        let request = match RefreshStreamsRequest::parse_json(&raw) {
+            Ok(request) => request,
+            Err(err) => return legacy_json_error(StatusCode::BAD_REQUEST, err.legacy_error_code()),
+        };
+        let quality = AudioQuality::parse(&request.audio_quality);
+        let results = join_all(request.media_ids.iter().map(|media_id| {
+            resolve_media_id(&state, media_id, request.proxy, quality, Freshness::Fresh)
+        }))
+        .await;
+        let mut response = RefreshStreamsResponse::default();
+        for (media_id, result) in request.media_ids.iter().zip(results) {
+            match result {
+                Ok(resolved) => response
+                    .streams
+                    .push(ResolvedStreamResponse::from(&resolved)),
+                Err(err) => response.failed.push(RefreshStreamFailure {
+                    media_id: media_id.clone(),
+                    error: match err {
+                        ResolveMediaError::Unavailable => "unavailable",
+                        ResolveMediaError::Failed => "resolve_failed",
+                    }
+                    .to_string(),
+                }),
+            }
         }
+        Json(response).into_response()
     })
     .await
 }
+
 pub(crate) async fn resolve_queue_item(
     state: &AppState,
     item: &sunflower_core::QueueItem,
     prefer_proxy: bool,
+    quality: AudioQuality,
 ) -> Result<ResolvedStreamResponse, ResolveMediaError> {
-    let resolved = resolve_media_id(state, &item.media_id.0, prefer_proxy).await?;
+    let resolved = resolve_media_id(
+        state,
+        &item.media_id.0,
+        prefer_proxy,
+        quality,
+        Freshness::RefreshAhead,
+    )
+    .await?;
     let mut response = ResolvedStreamResponse::from(&resolved);
     response.title = item.title.clone();
     response.artists = item.artists.clone();
@@ -57,18 +127,66 @@ pub(crate) async fn resolve_queue_item(
     Ok(response)
 }
 
+/// Limits on resolving a lookahead window; whatever misses them goes out
+/// metadata-only and the client re-resolves it before playing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct LookaheadBudget {
+    pub(crate) concurrency: usize,
+    pub(crate) item_timeout: Duration,
+    pub(crate) deadline: Duration,
+}
+
+impl Default for LookaheadBudget {
+    fn default() -> Self {
+        Self {
+            concurrency: LOOKAHEAD_CONCURRENCY,
+            item_timeout: LOOKAHEAD_ITEM_TIMEOUT,
+            deadline: LOOKAHEAD_DEADLINE,
+        }
+    }
+}
+
 pub(crate) async fn resolve_lookahead_items(
     state: &AppState,
     items: &[sunflower_core::QueueItem],
+    quality: AudioQuality,
 ) -> Vec<ResolvedStreamResponse> {
-    let mut resolved = Vec::with_capacity(items.len());
-    for item in items {
-        match resolve_queue_item(state, item, false).await {
-            Ok(stream) => resolved.push(stream),
-            Err(_) => resolved.push(ResolvedStreamResponse::from(item)),
-        }
+    resolve_lookahead_items_within(state, items, quality, LookaheadBudget::default()).await
+}
+
+pub(crate) async fn resolve_lookahead_items_within(
+    state: &AppState,
+    items: &[sunflower_core::QueueItem],
+    quality: AudioQuality,
+    budget: LookaheadBudget,
+) -> Vec<ResolvedStreamResponse> {
+    let deadline = tokio::time::Instant::now() + budget.deadline;
+    let mut resolved = vec![None; items.len()];
+    // Boxed up front: a lazily mapped stream over borrowed items trips the
+    // handler's `Send` check.
+    let lookups: Vec<BoxFuture<'_, _>> = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            Box::pin(async move {
+                let stream = tokio::time::timeout(
+                    budget.item_timeout,
+                    resolve_queue_item(state, item, false, quality),
+                )
+                .await;
+                (index, stream.ok().and_then(Result::ok))
+            }) as _
+        })
+        .collect();
+    let mut pending = stream::iter(lookups).buffer_unordered(budget.concurrency.max(1));
+    while let Ok(Some((index, stream))) = tokio::time::timeout_at(deadline, pending.next()).await {
+        resolved[index] = stream;
     }
-    resolved
+    items
+        .iter()
+        .zip(resolved)
+        .map(|(item, stream)| stream.unwrap_or_else(|| ResolvedStreamResponse::from(item)))
+        .collect()
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -81,6 +199,8 @@ pub(crate) async fn resolve_media_id(
     state: &AppState,
     media_id: &str,
     prefer_proxy: bool,
+    quality: AudioQuality,
+    freshness: Freshness,
 ) -> Result<ResolvedStream, ResolveMediaError> {
     let Some((source, external_id)) = media_id.split_once(':') else {
         return Err(ResolveMediaError::Failed);
@@ -89,48 +209,81 @@ pub(crate) async fn resolve_media_id(
         return Err(ResolveMediaError::Failed);
     }
     match source {
-        "local" => Ok(ResolvedStream {
-            media_id: MediaId::new(media_id),
-            source: "local".to_string(),
-            stream_url: format!("/api/v1/library/songs/{}/stream", path_segment(media_id)),
-            stream_expires_at: None,
-            mime_type: None,
-            content_length: None,
-            loudness_db: None,
-            playback_tracking_url: None,
-            metadata: Value::Null,
-        }),
-        "yt" => resolve_youtube_media_id(state, media_id, external_id, prefer_proxy).await,
+        "local" => Ok(local_stream(media_id)),
+        "yt" => {
+            if has_downloaded_copy(state, media_id).await {
+                return Ok(local_stream(media_id));
+            }
+            resolve_youtube_media_id(
+                state,
+                media_id,
+                external_id,
+                prefer_proxy,
+                quality,
+                freshness,
+            )
+            .await
+        }
         _ => Err(ResolveMediaError::Failed),
     }
 }
 
+fn local_stream(media_id: &str) -> ResolvedStream {
+    ResolvedStream {
+        media_id: MediaId::new(media_id),
+        source: "local".to_string(),
+        stream_url: format!("/api/v1/library/songs/{}/stream", path_segment(media_id)),
+        stream_expires_at: None,
+        mime_type: None,
+        content_length: None,
+        loudness_db: None,
+        playback_tracking_url: None,
+        metadata: Value::Null,
+    }
+}
+
+/// Whether a download job left a copy of `media_id` on disk; such tracks
+/// play from it and keep working while YouTube is unreachable.
+async fn has_downloaded_copy(state: &AppState, media_id: &str) -> bool {
+    let Some(store) = &state.store else {
+        return false;
+    };
+    let Ok(Some(path)) = store.song_stream_path(media_id).await else {
+        return false;
+    };
+    tokio::fs::metadata(path)
+        .await
+        .is_ok_and(|metadata| metadata.is_file())
+}
+
 pub(crate) async fn resolve_youtube_media_id(
     state: &AppState,
     media_id: &str,
     video_id: &str,
     prefer_proxy: bool,
+    quality: AudioQuality,
+    freshness: Freshness,
 ) -> Result<ResolvedStream, ResolveMediaError> {
     let Some(yt) = &state.yt else {
         return Err(ResolveMediaError::Unavailable);
     };
-    let player = yt
-        .player(video_id)
+    let stream = state
+        .stream_cache
+        .youtube_stream(yt.clone(), video_id, quality, freshness)
         .await
-        .map_err(|_| ResolveMediaError::Failed)?;
-    let stream = player.stream;
-    if stream.url.is_empty() {
-        return Err(ResolveMediaError::Unavailable);
-    }
+        .map_err(|_| ResolveMediaError::Failed)?
+        .ok_or(ResolveMediaError::Unavailable)?;
     let expires_at = innertube::expiry_from_url(&stream.url);
     let (source, stream_url) = if prefer_proxy || state.proxy_youtube {
         match &state.proxy {
             Some(proxy) => {
                 let token = match expires_at {
-                    Some(expires_at) => {
-                        proxy.sign_until(&stream.url, system_time_from_utc(expires_at))
-                    }
-                    None => proxy.sign(&stream.url),
+                    Some(expires_at) => proxy.sign_media_until(
+                        &stream.url,
+                        media_id,
+                        system_time_from_utc(expires_at),
+                    ),
+                    None => proxy.sign_media(&stream.url, media_id),
                 };
                 (
                     "proxy".to_string(),
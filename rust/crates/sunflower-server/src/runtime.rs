@@ -12,6 +12,68 @@ pub(crate) fn start_idempotency_gc(store: PostgresStore) {
     });
 }
 
+/// A long-lived, always-available video keeps the probe's `next` call cheap.
+const COOKIE_PROBE_VIDEO_ID: &str = "dQw4w9WgXcQ";
+
+pub(crate) fn start_cookie_health_probe(
+    store: PostgresStore,
+    client: innertube::HttpInnerTubeClient,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            run_cookie_health_probe(&store, &client).await;
+        }
+    });
+}
+
+/// Probes every account's stored cookies, recording each result under its
+/// own account.
+pub(crate) async fn run_cookie_health_probe(
+    store: &PostgresStore,
+    client: &innertube::HttpInnerTubeClient,
+) {
+    let accounts = match store.youtube_cookie_accounts().await {
+        Ok(accounts) => accounts,
+        Err(err) => {
+            eprintln!("cookie probe: list accounts: {err}");
+            return;
+        }
+    };
+    for user_id in accounts {
+        if let Some(client) = client.for_user_client(user_id) {
+            probe_account_cookies(store, &client, user_id).await;
+        }
+    }
+}
+
+async fn probe_account_cookies(
+    store: &PostgresStore,
+    client: &innertube::HttpInnerTubeClient,
+    user_id: Uuid,
+) {
+    let started = std::time::Instant::now();
+    let (status, detail) = match client.probe_cookies(COOKIE_PROBE_VIDEO_ID).await {
+        innertube::CookieProbe::NoCredentials => return,
+        innertube::CookieProbe::SignedIn => ("ok", "signed-in next call succeeded".to_string()),
+        innertube::CookieProbe::Rejected(detail) => ("degraded", detail),
+        innertube::CookieProbe::Failed(detail) => ("error", detail),
+    };
+    let latency_ms = i32::try_from(started.elapsed().as_millis()).ok();
+    match store
+        .record_youtube_cookie_probe(user_id, status, latency_ms, &detail)
+        .await
+    {
+        Ok(previous) if previous.as_deref() != Some(status) => eprintln!(
+            "cookie probe: youtube cookies of {user_id} are now {status} (was {})",
+            previous.as_deref().unwrap_or("unknown")
+        ),
+        Ok(_) => {}
+        Err(err) => eprintln!("cookie probe: record result: {err}"),
+    }
+}
+
 pub(crate) fn configured_database_url(value: Option<String>) -> String {
     non_empty_or(value, DEFAULT_DATABASE_URL)
 }
@@ -79,7 +141,9 @@ pub(crate) fn non_empty_or(value: Option<String>, fallback: &str) -> String {
         .unwrap_or_else(|| fallback.to_string())
 }
 
-pub(crate) fn parse_stream_proxy_key_env() -> anyhow::Result<Vec<u8>> {
+/// The configured proxy signing secret, if any; the key ring in the data dir
+/// takes over when it is unset.
+pub(crate) fn parse_stream_proxy_key_env() -> anyhow::Result<Option<Vec<u8>>> {
     match env::var("SUNFLOWER_STREAM_PROXY_KEY") {
         Ok(raw) if !raw.is_empty() => {
             if raw.len() < 64 || raw.len() % 2 != 0 {
@@ -101,12 +165,13 @@ pub(crate) fn parse_stream_proxy_key_env() -> anyhow::Result<Vec<u8>> {
                 };
                 key.push((hi << 4) | lo);
             }
-            Ok(key)
+            Ok(Some(key))
         }
-        _ => Ok(random_stream_proxy_key()),
+        _ => Ok(None),
     }
 }
 
+#[cfg(test)]
 pub(crate) fn random_stream_proxy_key() -> Vec<u8> {
     let mut key = vec![0u8; 32];
     rand::thread_rng().fill_bytes(&mut key);
@@ -154,6 +219,30 @@ pub(crate) fn should_proxy_youtube(mode: &str, cookies_configured: bool) -> bool
     }
 }
 
+/// The on-disk proxy cache budget from `SUNFLOWER_STREAM_PROXY_CACHE_MB`;
+/// unset or zero leaves the cache off.
+pub(crate) fn configured_stream_proxy_cache_bytes(value: Option<String>) -> Option<u64> {
+    value?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|mb| *mb > 0)
+        .map(|mb| mb.saturating_mul(1024 * 1024))
+}
+
+/// Plays are reported to YouTube unless `SUNFLOWER_YT_PLAYBACK_TRACKING`
+/// turns it off.
+pub(crate) fn configured_playback_tracking(value: Option<String>) -> bool {
+    !matches!(
+        value
+            .as_deref()
+            .map(str::trim)
+            .map(str::to_ascii_lowercase)
+            .as_deref(),
+        Some("0" | "false" | "off" | "never")
+    )
+}
+
 pub(crate) fn api_rfc3339_seconds(time: DateTime<Utc>) -> String {
     time.to_rfc3339_opts(SecondsFormat::Secs, true)
 }
@@ -10,8 +10,12 @@ pub(crate) struct RouterBuildConfig {
     pub(crate) hub: Option<Arc<NowPlayingHub>>,
     pub(crate) proxy: Option<Arc<StreamProxy>>,
     pub(crate) proxy_youtube: bool,
+    pub(crate) playback_tracking: bool,
     pub(crate) yt: Option<Arc<dyn innertube::InnerTubeBackend>>,
+    pub(crate) parser_drift: Arc<ParserDriftRegistry>,
+    pub(crate) innertube_profiles: Arc<InnerTubeProfileHealth>,
     pub(crate) dev_open_registration: bool,
+    pub(crate) download_client: reqwest::Client,
 }
 
 impl RouterBuildConfig {
@@ -33,8 +37,12 @@ impl RouterBuildConfig {
             hub: None,
             proxy: None,
             proxy_youtube: false,
+            playback_tracking: false,
             yt: None,
+            parser_drift: Arc::new(ParserDriftRegistry::default()),
+            innertube_profiles: Arc::new(InnerTubeProfileHealth::default()),
             dev_open_registration: false,
+            download_client: jobs::download_client(),
         }
     }
 
@@ -53,15 +61,39 @@ impl RouterBuildConfig {
         self
     }
 
+    pub(crate) fn with_playback_tracking(mut self, playback_tracking: bool) -> Self {
+        self.playback_tracking = playback_tracking;
+        self
+    }
+
     pub(crate) fn with_yt(mut self, yt: Option<Arc<dyn innertube::InnerTubeBackend>>) -> Self {
         self.yt = yt;
         self
     }
 
+    pub(crate) fn with_parser_drift(mut self, parser_drift: Arc<ParserDriftRegistry>) -> Self {
+        self.parser_drift = parser_drift;
+        self
+    }
+
+    pub(crate) fn with_innertube_profiles(
+        mut self,
+        innertube_profiles: Arc<InnerTubeProfileHealth>,
+    ) -> Self {
+        self.innertube_profiles = innertube_profiles;
+        self
+    }
+
     pub(crate) fn with_dev_open_registration(mut self, dev_open_registration: bool) -> Self {
         self.dev_open_registration = dev_open_registration;
         self
     }
+
+    #[cfg(test)]
+    pub(crate) fn with_download_client(mut self, download_client: reqwest::Client) -> Self {
+        self.download_client = download_client;
+        self
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -82,8 +114,14 @@ pub(crate) struct AppState {
     pub(crate) hub: Option<Arc<NowPlayingHub>>,
     pub(crate) proxy: Option<Arc<StreamProxy>>,
     pub(crate) proxy_youtube: bool,
+    pub(crate) playback_tracking: bool,
     pub(crate) yt: Option<Arc<dyn innertube::InnerTubeBackend>>,
+    pub(crate) parser_drift: Arc<ParserDriftRegistry>,
+    pub(crate) innertube_profiles: Arc<InnerTubeProfileHealth>,
     pub(crate) jobs: Arc<JobRegistry>,
+    /// Fetches audio and thumbnails for server-side YouTube downloads.
+    pub(crate) download_client: reqwest::Client,
+    pub(crate) stream_cache: Arc<ResolvedStreamCache>,
     pub(crate) started_at: SystemTime,
     pub(crate) data_dir: String,
     pub(crate) dev_open_registration: bool,
@@ -93,6 +131,18 @@ pub(crate) struct AppState {
     pub(crate) store: Option<PostgresStore>,
 }
 
+impl AppState {
+    /// The same state with InnerTube calls made under `user_id`'s YouTube
+    /// account, falling back to the owner's and then to guest mode.
+    pub(crate) fn for_user(&self, user_id: Uuid) -> Self {
+        let mut state = self.clone();
+        if let Some(yt) = self.yt.as_ref().and_then(|yt| yt.for_user(user_id)) {
+            state.yt = Some(yt);
+        }
+        state
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct RateLimiter {
     limit: usize,
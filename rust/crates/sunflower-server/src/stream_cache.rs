@@ -0,0 +1,482 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::innertube::{self, InnerTubeBackend, InnerTubeError, StreamUrl};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AudioQuality {
+    #[default]
+    High,
+    Low,
+}
+
+impl AudioQuality {
+    /// `audio_quality` as sent by clients; anything but `low` means high.
+    pub fn parse(raw: &str) -> Self {
+        if raw.trim().eq_ignore_ascii_case("low") {
+            Self::Low
+        } else {
+            Self::High
+        }
+    }
+
+    fn pick(self, streams: &[StreamUrl]) -> Option<StreamUrl> {
+        let streams = streams.iter().filter(|stream| !stream.url.is_empty());
+        match self {
+            Self::High => streams.max_by_key(|stream| stream.bitrate),
+            Self::Low => streams.min_by_key(|stream| stream.bitrate),
+        }
+        .cloned()
+    }
+}
+
+/// How a lookup treats an entry that is close to expiring.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Freshness {
+    /// Serve it and re-resolve in the background.
+    RefreshAhead,
+    /// Re-resolve before answering; the caller wants a URL that lasts.
+    Fresh,
+    /// Drop the entry whatever its expiry, e.g. after the client got a 403.
+    Reload,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamCacheConfig {
+    /// Entries expiring sooner than this are refreshed.
+    pub refresh_ahead: Duration,
+    /// Entries expiring sooner than this are never served.
+    pub min_remaining: Duration,
+    /// Lifetime for URLs without an `expire` parameter.
+    pub ttl_without_expiry: Duration,
+    pub max_entries: usize,
+}
+
+impl Default for StreamCacheConfig {
+    fn default() -> Self {
+        Self {
+            refresh_ahead: Duration::from_secs(30 * 60),
+            min_remaining: Duration::from_secs(5 * 60),
+            ttl_without_expiry: Duration::from_secs(30 * 60),
+            max_entries: 512,
+        }
+    }
+}
+
+struct CacheEntry {
+    stream: StreamUrl,
+    expires_at: Option<DateTime<Utc>>,
+    fetched_at: Instant,
+    last_used: Instant,
+    refreshing: bool,
+}
+
+type Key = (String, AudioQuality);
+type Entries = Arc<Mutex<HashMap<Key, CacheEntry>>>;
+
+/// Resolved YouTube stream URLs by video id and quality, refreshed before
+/// they expire so lookahead entries stay playable.
+pub struct ResolvedStreamCache {
+    config: StreamCacheConfig,
+    entries: Entries,
+}
+
+impl ResolvedStreamCache {
+    pub fn new(config: StreamCacheConfig) -> Self {
+        Self {
+            config,
+            entries: Arc::default(),
+        }
+    }
+
+    /// The stream for `video_id`, or `None` when the video has no playable
+    /// audio.
+    pub async fn youtube_stream(
+        &self,
+        yt: Arc<dyn InnerTubeBackend>,
+        video_id: &str,
+        quality: AudioQuality,
+        freshness: Freshness,
+    ) -> Result<Option<StreamUrl>, InnerTubeError> {
+        let key = (video_id.to_string(), quality);
+        let mut refresh = false;
+        let hit = {
+            let mut entries = lock_entries(&self.entries);
+            if freshness == Freshness::Reload {
+                entries.remove(&key);
+            }
+            match entries.get_mut(&key) {
+                Some(entry) => {
+                    let remaining = self.remaining(entry);
+                    if remaining <= self.config.min_remaining
+                        || (remaining <= self.config.refresh_ahead
+                            && freshness != Freshness::RefreshAhead)
+                    {
+                        None
+                    } else {
+                        entry.last_used = Instant::now();
+                        if remaining <= self.config.refresh_ahead && !entry.refreshing {
+                            entry.refreshing = true;
+                            refresh = true;
+                        }
+                        Some(entry.stream.clone())
+                    }
+                }
+                None => None,
+            }
+        };
+        if let Some(stream) = hit {
+            if refresh {
+                let entries = self.entries.clone();
+                let max_entries = self.config.max_entries;
+                tokio::spawn(async move {
+                    let result = resolve(yt.as_ref(), &key.0, quality).await;
+                    let mut entries = lock_entries(&entries);
+                    match result {
+                        Ok(Some(stream)) => insert(&mut entries, key, stream, max_entries),
+                        Ok(None) => {
+                            entries.remove(&key);
+                        }
+                        Err(err) => {
+                            eprintln!("stream cache: refresh {} failed: {err}", key.0);
+                            if let Some(entry) = entries.get_mut(&key) {
+                                entry.refreshing = false;
+                            }
+                        }
+                    }
+                });
+            }
+            return Ok(Some(stream));
+        }
+
+        let stream = resolve(yt.as_ref(), video_id, quality).await?;
+        let mut entries = lock_entries(&self.entries);
+        match &stream {
+            Some(stream) => insert(&mut entries, key, stream.clone(), self.config.max_entries),
+            None => {
+                entries.remove(&key);
+            }
+        }
+        Ok(stream)
+    }
+
+    fn remaining(&self, entry: &CacheEntry) -> Duration {
+        match entry.expires_at {
+            Some(expires_at) => (expires_at - Utc::now()).to_std().unwrap_or_default(),
+            None => self
+                .config
+                .ttl_without_expiry
+                .saturating_sub(entry.fetched_at.elapsed()),
+        }
+    }
+}
+
+async fn resolve(
+    yt: &dyn InnerTubeBackend,
+    video_id: &str,
+    quality: AudioQuality,
+) -> Result<Option<StreamUrl>, InnerTubeError> {
+    let player = yt.player(video_id).await?;
+    if player.all_streams.is_empty() {
+        return Ok(Some(player.stream).filter(|stream| !stream.url.is_empty()));
+    }
+    Ok(quality.pick(&player.all_streams))
+}
+
+fn lock_entries(entries: &Entries) -> MutexGuard<'_, HashMap<Key, CacheEntry>> {
+    entries
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn insert(entries: &mut HashMap<Key, CacheEntry>, key: Key, stream: StreamUrl, max: usize) {
+    let now = Instant::now();
+    entries.insert(
+        key,
+        CacheEntry {
+            expires_at: innertube::expiry_from_url(&stream.url),
+            stream,
+            fetched_at: now,
+            last_used: now,
+            refreshing: false,
+        },
+    );
+    while entries.len() > max {
+        let Some(oldest) = entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone())
+        else {
+            break;
+        };
+        entries.remove(&oldest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+
+    use futures_util::future::BoxFuture;
+    use sunflower_core::Lyrics;
+
+    use super::*;
+    use crate::innertube::{HomePage, NextPage, PlayerResponse, SearchPage};
+
+    /// Answers `player` with two audio formats expiring `expires_in` seconds
+    /// from the call; a negative `expires_in` means the video is unplayable.
+    struct ExpiringBackend {
+        players: AtomicUsize,
+        expires_in: AtomicI64,
+    }
+
+    impl ExpiringBackend {
+        fn new(expires_in: i64) -> Arc<Self> {
+            Arc::new(Self {
+                players: AtomicUsize::new(0),
+                expires_in: AtomicI64::new(expires_in),
+            })
+        }
+
+        fn calls(&self) -> usize {
+            self.players.load(Ordering::SeqCst)
+        }
+    }
+
+    impl InnerTubeBackend for ExpiringBackend {
+        fn browse<'a>(
+            &'a self,
+            _browse_id: &'a str,
+            _continuation: Option<&'a str>,
+        ) -> BoxFuture<'a, Result<HomePage, InnerTubeError>> {
+            Box::pin(async { Ok(HomePage::default()) })
+        }
+
+        fn search<'a>(
+            &'a self,
+            _query: &'a str,
+        ) -> BoxFuture<'a, Result<SearchPage, InnerTubeError>> {
+            Box::pin(async { Ok(SearchPage::default()) })
+        }
+
+        fn next<'a>(
+            &'a self,
+            _video_id: &'a str,
+            _continuation: Option<&'a str>,
+        ) -> BoxFuture<'a, Result<NextPage, InnerTubeError>> {
+            Box::pin(async { Ok(NextPage::default()) })
+        }
+
+        fn player<'a>(
+            &'a self,
+            video_id: &'a str,
+        ) -> BoxFuture<'a, Result<PlayerResponse, InnerTubeError>> {
+            let call = self.players.fetch_add(1, Ordering::SeqCst) + 1;
+            let expires_in = self.expires_in.load(Ordering::SeqCst);
+            Box::pin(async move {
+                if expires_in < 0 {
+                    return Ok(PlayerResponse::default());
+                }
+                let expire = Utc::now().timestamp() + expires_in;
+                let stream = |itag, bitrate| StreamUrl {
+                    url: format!(
+                        "https://r1.googlevideo.com/videoplayback?id={video_id}&itag={itag}&expire={expire}&call={call}"
+                    ),
+                    itag,
+                    mime_type: "audio/webm".into(),
+                    bitrate,
+                    loudness: 0.0,
+                };
+                Ok(PlayerResponse {
+                    video_id: video_id.to_string(),
+                    stream: stream(251, 160_000),
+                    all_streams: vec![stream(249, 48_000), stream(251, 160_000)],
+                    ..PlayerResponse::default()
+                })
+            })
+        }
+
+        fn lyrics<'a>(
+            &'a self,
+            _video_id: &'a str,
+        ) -> BoxFuture<'a, Result<Option<Lyrics>, InnerTubeError>> {
+            Box::pin(async { Ok(None) })
+        }
+    }
+
+    #[test]
+    fn audio_quality_defaults_to_high() {
+        assert_eq!(AudioQuality::parse("low"), AudioQuality::Low);
+        assert_eq!(AudioQuality::parse(" LOW "), AudioQuality::Low);
+        assert_eq!(AudioQuality::parse("high"), AudioQuality::High);
+        assert_eq!(AudioQuality::parse(""), AudioQuality::High);
+        assert_eq!(AudioQuality::parse("lossless"), AudioQuality::High);
+    }
+
+    #[tokio::test]
+    async fn long_lived_urls_are_cached_per_quality() {
+        let backend = ExpiringBackend::new(6 * 60 * 60);
+        let cache = ResolvedStreamCache::new(StreamCacheConfig::default());
+        let yt: Arc<dyn InnerTubeBackend> = backend.clone();
+
+        let high = cache
+            .youtube_stream(yt.clone(), "abc", AudioQuality::High, Freshness::Fresh)
+            .await
+            .unwrap()
+            .unwrap();
+        let again = cache
+            .youtube_stream(yt.clone(), "abc", AudioQuality::High, Freshness::Fresh)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(high.itag, 251);
+        assert_eq!(high, again);
+        assert_eq!(backend.calls(), 1);
+
+        let low = cache
+            .youtube_stream(
+                yt.clone(),
+                "abc",
+                AudioQuality::Low,
+                Freshness::RefreshAhead,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(low.itag, 249);
+        assert_eq!(backend.calls(), 2);
+
+        let reloaded = cache
+            .youtube_stream(yt, "abc", AudioQuality::High, Freshness::Reload)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(reloaded.url, high.url);
+        assert_eq!(backend.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn expiring_urls_are_refreshed_ahead_in_the_background() {
+        // Inside the 30 minute refresh window but well above the 5 minute floor.
+        let backend = ExpiringBackend::new(20 * 60);
+        let cache = ResolvedStreamCache::new(StreamCacheConfig::default());
+        let yt: Arc<dyn InnerTubeBackend> = backend.clone();
+
+        let first = cache
+            .youtube_stream(
+                yt.clone(),
+                "abc",
+                AudioQuality::High,
+                Freshness::RefreshAhead,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        backend.expires_in.store(6 * 60 * 60, Ordering::SeqCst);
+        let stale = cache
+            .youtube_stream(
+                yt.clone(),
+                "abc",
+                AudioQuality::High,
+                Freshness::RefreshAhead,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stale, first);
+
+        for _ in 0..100 {
+            if backend.calls() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(backend.calls(), 2);
+        let refreshed = cache
+            .youtube_stream(yt, "abc", AudioQuality::High, Freshness::Fresh)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(refreshed, first);
+        assert_eq!(backend.calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn fresh_lookups_never_return_urls_inside_the_refresh_window() {
+        let backend = ExpiringBackend::new(20 * 60);
+        let cache = ResolvedStreamCache::new(StreamCacheConfig::default());
+        let yt: Arc<dyn InnerTubeBackend> = backend.clone();
+
+        cache
+            .youtube_stream(yt.clone(), "abc", AudioQuality::High, Freshness::Fresh)
+            .await
+            .unwrap();
+        cache
+            .youtube_stream(yt.clone(), "abc", AudioQuality::High, Freshness::Fresh)
+            .await
+            .unwrap();
+        assert_eq!(backend.calls(), 2);
+
+        // Below the floor even refresh-ahead lookups wait for a new URL.
+        backend.expires_in.store(60, Ordering::SeqCst);
+        cache
+            .youtube_stream(yt.clone(), "short", AudioQuality::High, Freshness::Fresh)
+            .await
+            .unwrap();
+        cache
+            .youtube_stream(yt, "short", AudioQuality::High, Freshness::RefreshAhead)
+            .await
+            .unwrap();
+        assert_eq!(backend.calls(), 4);
+    }
+
+    #[tokio::test]
+    async fn unplayable_videos_are_not_cached_and_entries_are_bounded() {
+        let backend = ExpiringBackend::new(-1);
+        let cache = ResolvedStreamCache::new(StreamCacheConfig {
+            max_entries: 2,
+            ..StreamCacheConfig::default()
+        });
+        let yt: Arc<dyn InnerTubeBackend> = backend.clone();
+        for _ in 0..2 {
+            let stream = cache
+                .youtube_stream(
+                    yt.clone(),
+                    "gone",
+                    AudioQuality::High,
+                    Freshness::RefreshAhead,
+                )
+                .await
+                .unwrap();
+            assert!(stream.is_none());
+        }
+        assert_eq!(backend.calls(), 2);
+
+        backend.expires_in.store(6 * 60 * 60, Ordering::SeqCst);
+        for video_id in ["a", "b", "a", "c", "a"] {
+            cache
+                .youtube_stream(
+                    yt.clone(),
+                    video_id,
+                    AudioQuality::High,
+                    Freshness::RefreshAhead,
+                )
+                .await
+                .unwrap();
+        }
+        // "b" was least recently used when "c" arrived.
+        assert_eq!(backend.calls(), 5);
+        assert_eq!(lock_entries(&cache.entries).len(), 2);
+        cache
+            .youtube_stream(yt, "b", AudioQuality::High, Freshness::RefreshAhead)
+            .await
+            .unwrap();
+        assert_eq!(backend.calls(), 6);
+    }
+}
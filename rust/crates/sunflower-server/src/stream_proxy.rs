@@ -1,31 +1,132 @@
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use axum::{
-    body::Body,
+    body::{Body, Bytes},
     http::{HeaderMap, StatusCode, header},
     response::{IntoResponse, Response},
 };
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
-use futures_util::TryStreamExt;
+use futures_util::{TryStreamExt, stream};
 use sha2::{Digest, Sha256};
 
+use crate::proxy_cache::{ChunkCache, ChunkKey, ChunkMeta};
+
 const DEFAULT_TTL: Duration = Duration::from_secs(15 * 60);
 const HMAC_BLOCK_SIZE: usize = 64;
+/// googlevideo throttles long ranges, so upstream requests stay this small.
+const DEFAULT_CHUNK_SIZE: u64 = 1024 * 1024;
+/// The signing key plus the previous ones still accepted after rotations.
+/// Resolved googlevideo URLs live for hours, so a rotation must not cut off
+/// the tokens minted just before it.
+const MAX_RING_KEYS: usize = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ProxyKey {
+    /// Named in each token's `"k"`; empty for a bare key, whose tokens keep
+    /// the original `{"u","e"}` payload.
+    id: String,
+    secret: Vec<u8>,
+}
+
+impl ProxyKey {
+    fn generate() -> Self {
+        let mut secret = vec![0u8; 32];
+        rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut secret);
+        Self {
+            id: hex(&rand::random::<[u8; 4]>()),
+            secret,
+        }
+    }
+
+    /// A configured secret gets an id derived from it, so restarts with the
+    /// same secret agree on it.
+    fn configured(secret: Vec<u8>) -> Self {
+        Self {
+            id: hex(&Sha256::digest(&secret)[..4]),
+            secret,
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct ProxySigner {
-    key: Vec<u8>,
+    /// Newest first; the first key signs.
+    keys: Arc<RwLock<Vec<ProxyKey>>>,
+    key_file: Option<PathBuf>,
     ttl: Duration,
 }
 
 impl ProxySigner {
+    #[cfg(test)]
     pub fn new(key: Vec<u8>) -> Self {
         Self {
-            key,
+            keys: Arc::new(RwLock::new(vec![ProxyKey {
+                id: String::new(),
+                secret: key,
+            }])),
+            key_file: None,
             ttl: DEFAULT_TTL,
         }
     }
 
+    /// Loads the key ring persisted at `key_file`, so proxy URLs survive
+    /// restarts. Without a file the ring starts from the configured secret
+    /// (or a random key) and is written out; once the file exists it is the
+    /// only source, so a configured key that was rotated out stays out.
+    pub fn load(
+        key_file: impl Into<PathBuf>,
+        configured: Option<Vec<u8>>,
+    ) -> std::io::Result<Self> {
+        let key_file = key_file.into();
+        let keys = match std::fs::read(&key_file) {
+            Ok(raw) => parse_key_ring(&raw).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("invalid stream proxy key ring {}", key_file.display()),
+                )
+            })?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                let keys = vec![configured.map_or_else(ProxyKey::generate, ProxyKey::configured)];
+                write_key_ring(&key_file, &keys)?;
+                keys
+            }
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            keys: Arc::new(RwLock::new(keys)),
+            key_file: Some(key_file),
+            ttl: DEFAULT_TTL,
+        })
+    }
+
+    /// Starts signing with a fresh key. Earlier keys keep verifying until
+    /// they fall off the ring. Returns the key ids, newest first.
+    pub fn rotate(&self) -> std::io::Result<Vec<String>> {
+        let mut keys = self
+            .keys
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut rotated = keys.clone();
+        rotated.insert(0, ProxyKey::generate());
+        rotated.truncate(MAX_RING_KEYS);
+        if let Some(key_file) = &self.key_file {
+            write_key_ring(key_file, &rotated)?;
+        }
+        *keys = rotated;
+        Ok(keys.iter().map(|key| key.id.clone()).collect())
+    }
+
+    fn read_keys(&self) -> std::sync::RwLockReadGuard<'_, Vec<ProxyKey>> {
+        self.keys
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    #[cfg(test)]
     pub fn sign(&self, target: &str) -> String {
         self.sign_until(
             target,
@@ -35,28 +136,73 @@ impl ProxySigner {
         )
     }
 
+    #[cfg(test)]
     pub fn sign_until(&self, target: &str, exp: SystemTime) -> String {
+        self.sign_payload(target, None, exp)
+    }
+
+    pub fn sign_media(&self, target: &str, media_id: &str) -> String {
+        self.sign_media_until(
+            target,
+            media_id,
+            SystemTime::now()
+                .checked_add(self.ttl)
+                .unwrap_or(SystemTime::now()),
+        )
+    }
+
+    /// Like `sign_until`, also naming the media so its bytes can be cached.
+    pub fn sign_media_until(&self, target: &str, media_id: &str, exp: SystemTime) -> String {
+        self.sign_payload(target, Some(media_id), exp)
+    }
+
+    fn sign_payload(&self, target: &str, media_id: Option<&str>, exp: SystemTime) -> String {
         let exp = exp
             .duration_since(UNIX_EPOCH)
             .map(|duration| duration.as_secs() as i64)
             .unwrap_or_default();
-        let payload = format!("{{\"u\":{},\"e\":{exp}}}", go_json_string(target));
+        let media = media_id
+            .map(|media_id| format!(",\"m\":{}", go_json_string(media_id)))
+            .unwrap_or_default();
+        let keys = self.read_keys();
+        let key = &keys[0];
+        let key_id = if key.id.is_empty() {
+            String::new()
+        } else {
+            format!(",\"k\":{}", go_json_string(&key.id))
+        };
+        let payload = format!(
+            "{{\"u\":{},\"e\":{exp}{media}{key_id}}}",
+            go_json_string(target)
+        );
         let body = URL_SAFE_NO_PAD.encode(payload.as_bytes());
-        format!("{body}.{}", self.mac(&body))
+        format!("{body}.{}", mac(&key.secret, &body))
     }
 
+    #[cfg(test)]
     pub fn verify(&self, token: &str) -> Result<String, ProxyTokenError> {
+        self.verify_target(token).map(|target| target.url)
+    }
+
+    pub fn verify_target(&self, token: &str) -> Result<ProxyTarget, ProxyTokenError> {
         let (body, sig) = token.split_once('.').ok_or(ProxyTokenError)?;
-        let expected = self.mac(body);
-        if body.is_empty()
-            || sig.is_empty()
-            || !constant_time_eq(sig.as_bytes(), expected.as_bytes())
-        {
+        if body.is_empty() || sig.is_empty() {
             return Err(ProxyTokenError);
         }
         let raw = URL_SAFE_NO_PAD.decode(body).map_err(|_| ProxyTokenError)?;
         let payload: serde_json::Value =
             serde_json::from_slice(&raw).map_err(|_| ProxyTokenError)?;
+        // The key id only picks the key; the signature still has to match.
+        // Tokens without one predate key ids and may come from any key.
+        let key_id = payload.get("k").and_then(|value| value.as_str());
+        let signed = self
+            .read_keys()
+            .iter()
+            .filter(|key| key_id.is_none_or(|key_id| key.id == key_id))
+            .any(|key| constant_time_eq(sig.as_bytes(), mac(&key.secret, body).as_bytes()));
+        if !signed {
+            return Err(ProxyTokenError);
+        }
         let url = payload
             .get("u")
             .and_then(|value| value.as_str())
@@ -73,21 +219,94 @@ impl ProxySigner {
         if now > exp {
             return Err(ProxyTokenError);
         }
-        Ok(url.to_string())
+        Ok(ProxyTarget {
+            url: url.to_string(),
+            media_id: payload
+                .get("m")
+                .and_then(|value| value.as_str())
+                .filter(|value| !value.is_empty())
+                .map(ToOwned::to_owned),
+        })
     }
+}
+
+fn mac(secret: &[u8], body: &str) -> String {
+    URL_SAFE_NO_PAD.encode(hmac_sha256(secret, body.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
 
-    fn mac(&self, body: &str) -> String {
-        URL_SAFE_NO_PAD.encode(hmac_sha256(&self.key, body.as_bytes()))
+fn unhex(raw: &str) -> Option<Vec<u8>> {
+    if !raw.len().is_multiple_of(2) {
+        return None;
     }
+    (0..raw.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(raw.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// `{"keys":[{"id":"…","secret":"<hex>"}, …]}`, newest first.
+fn parse_key_ring(raw: &[u8]) -> Option<Vec<ProxyKey>> {
+    let value: serde_json::Value = serde_json::from_slice(raw).ok()?;
+    value
+        .get("keys")?
+        .as_array()?
+        .iter()
+        .map(|key| {
+            Some(ProxyKey {
+                id: key
+                    .get("id")?
+                    .as_str()
+                    .filter(|id| !id.is_empty())?
+                    .to_string(),
+                secret: unhex(key.get("secret")?.as_str()?).filter(|secret| !secret.is_empty())?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|keys| !keys.is_empty())
+}
+
+fn write_key_ring(path: &Path, keys: &[ProxyKey]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let ring = serde_json::json!({
+        "keys": keys
+            .iter()
+            .map(|key| serde_json::json!({"id": key.id, "secret": hex(&key.secret)}))
+            .collect::<Vec<_>>(),
+    });
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&tmp)?;
+    file.write_all(ring.to_string().as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ProxyTokenError;
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProxyTarget {
+    pub url: String,
+    pub media_id: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct StreamProxy {
     signer: ProxySigner,
     client: reqwest::Client,
+    chunk_size: u64,
+    cache: Option<Arc<ChunkCache>>,
 }
 
 impl StreamProxy {
@@ -97,9 +316,16 @@ impl StreamProxy {
             client: Self::client_builder()
                 .build()
                 .expect("stream proxy reqwest client"),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            cache: None,
         }
     }
 
+    pub fn with_cache(mut self, cache: Arc<ChunkCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     fn client_builder() -> reqwest::ClientBuilder {
         reqwest::Client::builder().redirect(reqwest::redirect::Policy::custom(|attempt| {
             let Some(next) = attempt.url().host_str() else {
@@ -113,20 +339,24 @@ impl StreamProxy {
         }))
     }
 
-    pub fn sign(&self, target: &str) -> String {
-        self.signer.sign(target)
+    pub fn sign_media(&self, target: &str, media_id: &str) -> String {
+        self.signer.sign_media(target, media_id)
     }
 
-    pub fn sign_until(&self, target: &str, exp: SystemTime) -> String {
-        self.signer.sign_until(target, exp)
+    pub fn rotate_keys(&self) -> std::io::Result<Vec<String>> {
+        self.signer.rotate()
+    }
+
+    pub fn sign_media_until(&self, target: &str, media_id: &str, exp: SystemTime) -> String {
+        self.signer.sign_media_until(target, media_id, exp)
     }
 
     pub async fn serve(&self, token: Option<&str>, headers: &HeaderMap) -> Response {
-        let target = match token.and_then(|token| self.signer.verify(token).ok()) {
+        let target = match token.and_then(|token| self.signer.verify_target(token).ok()) {
             Some(target) => target,
             None => return plain_json_error(StatusCode::FORBIDDEN, "invalid_token"),
         };
-        let Ok(url) = reqwest::Url::parse(&target) else {
+        let Ok(url) = reqwest::Url::parse(&target.url) else {
             return plain_json_error(StatusCode::FORBIDDEN, "forbidden_target");
         };
         let scheme_allowed = matches!(url.scheme(), "http" | "https");
@@ -135,11 +365,31 @@ impl StreamProxy {
             return plain_json_error(StatusCode::FORBIDDEN, "forbidden_target");
         }
 
-        let mut request = self.client.get(url);
-        if let Some(range) = headers
+        let range = headers
             .get(header::RANGE)
-            .and_then(|value| value.to_str().ok())
-        {
+            .and_then(|value| value.to_str().ok());
+        let requested = match range {
+            Some(raw) => ByteRange::parse(raw),
+            None => Some(ByteRange::From(0, None)),
+        };
+        let Some(requested) = requested else {
+            return self.forward(url, range).await;
+        };
+        let key = ChunkKey::for_url(target.media_id.as_deref(), &url);
+        let fetcher = ChunkFetcher {
+            client: self.client.clone(),
+            url,
+            chunk_size: self.chunk_size,
+            cache: self.cache.clone().zip(key),
+        };
+        fetcher.serve(requested, range.is_some()).await
+    }
+
+    /// Relays one upstream request as is; used for ranges the chunked path
+    /// does not split, such as multi-range requests.
+    async fn forward(&self, url: reqwest::Url, range: Option<&str>) -> Response {
+        let mut request = self.client.get(url);
+        if let Some(range) = range {
             request = request.header(header::RANGE, range);
         }
         let upstream = match request.send().await {
@@ -168,6 +418,272 @@ impl StreamProxy {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ByteRange {
+    /// `bytes=start-` or `bytes=start-end`.
+    From(u64, Option<u64>),
+    /// `bytes=-len`: the last `len` bytes.
+    Suffix(u64),
+}
+
+impl ByteRange {
+    fn parse(raw: &str) -> Option<Self> {
+        let spec = raw.trim().strip_prefix("bytes=")?;
+        if spec.contains(',') {
+            return None;
+        }
+        let (start, end) = spec.split_once('-')?;
+        let (start, end) = (start.trim(), end.trim());
+        if start.is_empty() {
+            return end.parse().ok().filter(|len| *len > 0).map(Self::Suffix);
+        }
+        let start = start.parse().ok()?;
+        let end = match end {
+            "" => None,
+            end => Some(end.parse().ok().filter(|end| *end >= start)?),
+        };
+        Some(Self::From(start, end))
+    }
+
+    fn start(self) -> u64 {
+        match self {
+            Self::From(start, _) => start,
+            Self::Suffix(_) => 0,
+        }
+    }
+
+    fn end(self) -> Option<u64> {
+        match self {
+            Self::From(_, end) => end,
+            Self::Suffix(_) => None,
+        }
+    }
+
+    /// Inclusive byte bounds within a `total`-byte body, if satisfiable.
+    fn resolve(self, total: u64) -> Option<(u64, u64)> {
+        let last = total.checked_sub(1)?;
+        match self {
+            Self::From(start, end) => {
+                (start <= last).then(|| (start, end.map_or(last, |end| end.min(last))))
+            }
+            Self::Suffix(len) => Some((total.saturating_sub(len), last)),
+        }
+    }
+}
+
+enum FetchError {
+    /// googlevideo answered with this status; a 403 tells the client to
+    /// re-resolve, so it is passed through.
+    Status(StatusCode),
+    Failed(String),
+}
+
+/// A run of bytes starting at `from` in a `total`-byte stream.
+struct Piece {
+    from: u64,
+    bytes: Bytes,
+    total: u64,
+    content_type: String,
+}
+
+impl Piece {
+    fn last(&self) -> u64 {
+        self.from + self.bytes.len() as u64 - 1
+    }
+
+    fn covers(&self, offset: u64) -> bool {
+        offset >= self.from && offset <= self.last()
+    }
+}
+
+/// Serves one client range as a sequence of bounded upstream requests.
+/// With a cache the requests are aligned to chunk boundaries so each one can
+/// be stored and reused by later, differently shaped ranges.
+struct ChunkFetcher {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    chunk_size: u64,
+    cache: Option<(Arc<ChunkCache>, ChunkKey)>,
+}
+
+impl ChunkFetcher {
+    async fn serve(self, requested: ByteRange, partial: bool) -> Response {
+        let meta = match &self.cache {
+            Some((cache, key)) => cache.meta(key, self.chunk_size).await,
+            None => None,
+        };
+        // Without cached metadata the length is only known once the first
+        // piece is back.
+        let (first, total, content_type) = match meta {
+            Some(meta) => (None, meta.total, meta.content_type),
+            None => match self.piece(requested.start(), requested.end()).await {
+                Ok(piece) => {
+                    let (total, content_type) = (piece.total, piece.content_type.clone());
+                    (Some(piece), total, content_type)
+                }
+                Err(FetchError::Status(status)) => return status.into_response(),
+                Err(FetchError::Failed(_)) => {
+                    return plain_json_error(StatusCode::BAD_GATEWAY, "upstream_error");
+                }
+            },
+        };
+        let Some((start, end)) = requested.resolve(total) else {
+            return (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{total}"))],
+            )
+                .into_response();
+        };
+
+        let mut builder = Response::builder()
+            .status(if partial {
+                StatusCode::PARTIAL_CONTENT
+            } else {
+                StatusCode::OK
+            })
+            .header(header::CONTENT_LENGTH, end - start + 1)
+            .header(header::ACCEPT_RANGES, "bytes");
+        if !content_type.is_empty() {
+            builder = builder.header(header::CONTENT_TYPE, content_type);
+        }
+        if partial {
+            builder = builder.header(
+                header::CONTENT_RANGE,
+                format!("bytes {start}-{end}/{total}"),
+            );
+        }
+        let body = stream::try_unfold(
+            (self, first, start),
+            move |(fetcher, mut first, next)| async move {
+                if next > end {
+                    return Ok(None);
+                }
+                let piece = match first.take().filter(|piece| piece.covers(next)) {
+                    Some(piece) => piece,
+                    None => fetcher
+                        .piece(next, Some(end))
+                        .await
+                        .map_err(FetchError::into_io)?,
+                };
+                if piece.total != total || !piece.covers(next) {
+                    return Err(std::io::Error::other(
+                        "streamproxy: upstream length changed",
+                    ));
+                }
+                let until = piece.last().min(end);
+                let bytes = piece
+                    .bytes
+                    .slice((next - piece.from) as usize..=(until - piece.from) as usize);
+                Ok(Some((bytes, (fetcher, first, until + 1))))
+            },
+        );
+        builder
+            .body(Body::from_stream(body))
+            .unwrap_or_else(|_| plain_json_error(StatusCode::BAD_GATEWAY, "upstream_error"))
+    }
+
+    /// The piece holding `offset`: a cached chunk, a fetched (and then
+    /// cached) chunk, or without a cache up to one chunk from `offset`.
+    async fn piece(&self, offset: u64, end: Option<u64>) -> Result<Piece, FetchError> {
+        let size = self.chunk_size;
+        let Some((cache, key)) = &self.cache else {
+            let to = offset.saturating_add(size - 1).min(end.unwrap_or(u64::MAX));
+            return self.fetch(offset, to).await;
+        };
+
+        let index = offset / size;
+        let from = index * size;
+        if let Some(meta) = cache.meta(key, size).await {
+            let len = meta.total.saturating_sub(from).min(size);
+            if let Some(bytes) = cache.read_chunk(key, index, len).await {
+                return Ok(Piece {
+                    from,
+                    bytes,
+                    total: meta.total,
+                    content_type: meta.content_type,
+                });
+            }
+        }
+        let piece = self.fetch(from, from + size - 1).await?;
+        if piece.from == from && piece.bytes.len() as u64 == size.min(piece.total - from) {
+            let meta = ChunkMeta {
+                total: piece.total,
+                chunk_size: size,
+                content_type: piece.content_type.clone(),
+            };
+            let (cache, key, bytes) = (cache.clone(), key.clone(), piece.bytes.clone());
+            tokio::spawn(async move { cache.write_chunk(&key, &meta, index, &bytes).await });
+        }
+        Ok(piece)
+    }
+
+    async fn fetch(&self, from: u64, to: u64) -> Result<Piece, FetchError> {
+        let response = self
+            .client
+            .get(self.url.clone())
+            .header(header::RANGE, format!("bytes={from}-{to}"))
+            .send()
+            .await
+            .map_err(|err| FetchError::Failed(err.to_string()))?;
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let range = match status {
+            reqwest::StatusCode::PARTIAL_CONTENT => Some(
+                response
+                    .headers()
+                    .get(header::CONTENT_RANGE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_content_range)
+                    .ok_or_else(|| FetchError::Failed("upstream content-range".into()))?,
+            ),
+            // The upstream ignored the range and sent the whole body.
+            reqwest::StatusCode::OK => None,
+            status => {
+                return Err(FetchError::Status(
+                    StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::BAD_GATEWAY),
+                ));
+            }
+        };
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| FetchError::Failed(err.to_string()))?;
+        if bytes.is_empty() {
+            return Err(FetchError::Failed("upstream sent no bytes".into()));
+        }
+        let (from, total) = range.unwrap_or((0, bytes.len() as u64));
+        Ok(Piece {
+            from,
+            bytes,
+            total,
+            content_type,
+        })
+    }
+}
+
+impl FetchError {
+    fn into_io(self) -> std::io::Error {
+        match self {
+            Self::Status(status) => {
+                std::io::Error::other(format!("streamproxy: upstream status {status}"))
+            }
+            Self::Failed(detail) => std::io::Error::other(format!("streamproxy: {detail}")),
+        }
+    }
+}
+
+/// `(start, total)` from `bytes start-end/total`.
+fn parse_content_range(raw: &str) -> Option<(u64, u64)> {
+    let (range, total) = raw.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()?))
+}
+
 pub fn allowed_host(host: &str) -> bool {
     let host = host.to_ascii_lowercase();
     host == "googlevideo.com"
@@ -290,6 +806,142 @@ mod tests {
         assert_eq!(signer.verify(&token).unwrap(), target);
     }
 
+    #[test]
+    fn signer_carries_media_id_after_url_and_expiry() {
+        let signer = ProxySigner::new(b"key".to_vec());
+        let target = "https://r1.googlevideo.com/videoplayback?itag=251";
+        let exp = UNIX_EPOCH + Duration::from_secs(2_000_000_001);
+        let token = signer.sign_media_until(target, "yt:abc", exp);
+        let (body, _) = token.split_once('.').unwrap();
+        let raw = URL_SAFE_NO_PAD.decode(body).unwrap();
+        assert_eq!(
+            String::from_utf8(raw).unwrap(),
+            r#"{"u":"https://r1.googlevideo.com/videoplayback?itag=251","e":2000000001,"m":"yt:abc"}"#
+        );
+        assert_eq!(
+            signer.verify_target(&token).unwrap(),
+            ProxyTarget {
+                url: target.to_string(),
+                media_id: Some("yt:abc".to_string()),
+            }
+        );
+        let plain = signer.sign_until(target, exp);
+        assert_eq!(signer.verify_target(&plain).unwrap().media_id, None);
+    }
+
+    #[test]
+    fn key_ring_persists_across_restarts_and_rotation_keeps_recent_tokens() {
+        let dir =
+            std::env::temp_dir().join(format!("sunflower-proxy-keys-{}", uuid::Uuid::new_v4()));
+        let key_file = dir.join("stream-proxy-keys.json");
+        let target = "https://r1.googlevideo.com/videoplayback";
+
+        let signer = ProxySigner::load(&key_file, None).unwrap();
+        let first = signer.sign(target);
+        let restarted = ProxySigner::load(&key_file, None).unwrap();
+        assert_eq!(restarted.verify(&first).unwrap(), target);
+
+        let key_ids = restarted.rotate().unwrap();
+        assert_eq!(key_ids.len(), 2);
+        let second = restarted.sign(target);
+        let (body, _) = second.split_once('.').unwrap();
+        let payload: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(body).unwrap()).unwrap();
+        assert_eq!(payload["k"], key_ids[0]);
+        assert_eq!(restarted.verify(&first).unwrap(), target);
+        // The rotation was persisted: a signer loaded before it lacks the
+        // new key, one loaded after has it.
+        assert!(signer.verify(&second).is_err());
+        assert_eq!(
+            ProxySigner::load(&key_file, None)
+                .unwrap()
+                .verify(&second)
+                .unwrap(),
+            target
+        );
+
+        restarted.rotate().unwrap();
+        assert_eq!(restarted.rotate().unwrap().len(), MAX_RING_KEYS);
+        assert!(restarted.verify(&first).is_err());
+        assert_eq!(restarted.verify(&second).unwrap(), target);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn configured_key_only_seeds_a_new_ring() {
+        let dir =
+            std::env::temp_dir().join(format!("sunflower-proxy-keys-{}", uuid::Uuid::new_v4()));
+        let key_file = dir.join("stream-proxy-keys.json");
+        let target = "https://r1.googlevideo.com/videoplayback";
+        let exp = UNIX_EPOCH + Duration::from_secs(2_000_000_001);
+
+        let configured = ProxySigner::load(&key_file, Some(vec![7; 32])).unwrap();
+        let token = configured.sign(target);
+        assert_eq!(
+            configured.read_keys()[0].id,
+            ProxyKey::configured(vec![7; 32]).id
+        );
+
+        let again = ProxySigner::load(&key_file, Some(vec![7; 32])).unwrap();
+        assert_eq!(
+            again.sign_until(target, exp),
+            configured.sign_until(target, exp)
+        );
+        assert_eq!(again.verify(&token).unwrap(), target);
+
+        let changed = ProxySigner::load(&key_file, Some(vec![8; 32])).unwrap();
+        assert_eq!(*changed.read_keys(), *configured.read_keys());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn restart_after_rotations_keeps_the_configured_key_retired() {
+        let dir =
+            std::env::temp_dir().join(format!("sunflower-proxy-keys-{}", uuid::Uuid::new_v4()));
+        let key_file = dir.join("stream-proxy-keys.json");
+        let target = "https://r1.googlevideo.com/videoplayback";
+
+        let signer = ProxySigner::load(&key_file, Some(vec![7; 32])).unwrap();
+        let seeded = signer.sign(target);
+        for _ in 0..MAX_RING_KEYS {
+            signer.rotate().unwrap();
+        }
+        let latest = signer.sign(target);
+        assert!(signer.verify(&seeded).is_err());
+
+        let restarted = ProxySigner::load(&key_file, Some(vec![7; 32])).unwrap();
+        assert_eq!(*restarted.read_keys(), *signer.read_keys());
+        assert!(restarted.verify(&seeded).is_err());
+        let token = restarted.sign(target);
+        let (body, _) = token.split_once('.').unwrap();
+        let (latest_body, _) = latest.split_once('.').unwrap();
+        let key_id = |body: &str| {
+            serde_json::from_slice::<serde_json::Value>(&URL_SAFE_NO_PAD.decode(body).unwrap())
+                .unwrap()["k"]
+                .clone()
+        };
+        assert_eq!(key_id(body), key_id(latest_body));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn byte_ranges_parse_and_resolve_against_the_length() {
+        assert_eq!(
+            ByteRange::parse("bytes=2-5"),
+            Some(ByteRange::From(2, Some(5)))
+        );
+        assert_eq!(ByteRange::parse("bytes=7-"), Some(ByteRange::From(7, None)));
+        assert_eq!(ByteRange::parse("bytes=-4"), Some(ByteRange::Suffix(4)));
+        assert_eq!(ByteRange::parse("bytes=5-2"), None);
+        assert_eq!(ByteRange::parse("bytes=0-1,4-5"), None);
+        assert_eq!(ByteRange::parse("items=0-1"), None);
+
+        assert_eq!(ByteRange::From(2, Some(50)).resolve(20), Some((2, 19)));
+        assert_eq!(ByteRange::From(20, None).resolve(20), None);
+        assert_eq!(ByteRange::Suffix(4).resolve(20), Some((16, 19)));
+        assert_eq!(ByteRange::Suffix(40).resolve(20), Some((0, 19)));
+    }
+
     #[test]
     fn signer_rejects_wrong_key() {
         let token =
@@ -345,11 +997,11 @@ mod tests {
         let signer = ProxySigner::new(b"k".to_vec());
         let token = signer.sign("http://r1.googlevideo.com/videoplayback");
         let proxy = StreamProxy {
-            signer,
             client: reqwest::Client::builder()
                 .resolve("r1.googlevideo.com", addr)
                 .build()
                 .unwrap(),
+            ..StreamProxy::new(signer)
         };
         let mut headers = HeaderMap::new();
         headers.insert(header::RANGE, "bytes=2-5".parse().unwrap());
@@ -386,11 +1038,11 @@ mod tests {
         let signer = ProxySigner::new(b"k".to_vec());
         let token = signer.sign("http://r1.googlevideo.com/videoplayback");
         let proxy = StreamProxy {
-            signer,
             client: StreamProxy::client_builder()
                 .resolve("r1.googlevideo.com", addr)
                 .build()
                 .unwrap(),
+            ..StreamProxy::new(signer)
         };
 
         let response = proxy.serve(Some(&token), &HeaderMap::new()).await;
@@ -398,6 +1050,163 @@ mod tests {
         server.await.unwrap();
     }
 
+    const BODY: &[u8] = b"0123456789abcdefghij";
+
+    /// Serves `BODY` honoring single ranges and records each requested range.
+    async fn range_upstream() -> (std::net::SocketAddr, Arc<std::sync::Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let ranges = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = ranges.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 2048];
+                let n = socket.read(&mut buf).await.unwrap();
+                let req = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+                let range = req
+                    .lines()
+                    .find_map(|line| line.strip_prefix("range: "))
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string();
+                seen.lock().unwrap().push(range.clone());
+                let (start, end) = match ByteRange::parse(&range).unwrap() {
+                    ByteRange::From(start, end) => (start as usize, end.unwrap() as usize),
+                    ByteRange::Suffix(_) => unreachable!(),
+                };
+                if start >= BODY.len() {
+                    let response = "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                    continue;
+                }
+                let end = end.min(BODY.len() - 1);
+                let mut response = format!(
+                    "HTTP/1.1 206 Partial Content\r\nContent-Type: audio/webm\r\nContent-Length: {}\r\nContent-Range: bytes {start}-{end}/{}\r\nConnection: close\r\n\r\n",
+                    end - start + 1,
+                    BODY.len()
+                )
+                .into_bytes();
+                response.extend_from_slice(&BODY[start..=end]);
+                socket.write_all(&response).await.unwrap();
+            }
+        });
+        (addr, ranges)
+    }
+
+    fn chunked_proxy(signer: ProxySigner, addr: std::net::SocketAddr) -> StreamProxy {
+        StreamProxy {
+            client: reqwest::Client::builder()
+                .resolve("r1.googlevideo.com", addr)
+                .build()
+                .unwrap(),
+            chunk_size: 4,
+            ..StreamProxy::new(signer)
+        }
+    }
+
+    async fn body_of(response: Response) -> Vec<u8> {
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    #[tokio::test]
+    async fn proxy_splits_long_ranges_into_bounded_upstream_requests() {
+        let (addr, ranges) = range_upstream().await;
+        let signer = ProxySigner::new(b"k".to_vec());
+        let token = signer.sign("http://r1.googlevideo.com/videoplayback?itag=251");
+        let proxy = chunked_proxy(signer, addr);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, "bytes=3-12".parse().unwrap());
+        let response = proxy.serve(Some(&token), &headers).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes 3-12/20"
+        );
+        assert_eq!(
+            response.headers().get(header::CONTENT_LENGTH).unwrap(),
+            "10"
+        );
+        assert_eq!(body_of(response).await, b"3456789abc");
+        assert_eq!(
+            *ranges.lock().unwrap(),
+            ["bytes=3-6", "bytes=7-10", "bytes=11-12"]
+        );
+
+        let response = proxy.serve(Some(&token), &HeaderMap::new()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "audio/webm"
+        );
+        assert_eq!(body_of(response).await, BODY);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, "bytes=30-".parse().unwrap());
+        let response = proxy.serve(Some(&token), &headers).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    }
+
+    #[tokio::test]
+    async fn proxy_serves_repeated_ranges_from_the_disk_cache() {
+        let (addr, ranges) = range_upstream().await;
+        let dir =
+            std::env::temp_dir().join(format!("sunflower-proxy-cache-{}", uuid::Uuid::new_v4()));
+        let cache = Arc::new(ChunkCache::new(&dir, 1 << 20));
+        let signer = ProxySigner::new(b"k".to_vec());
+        let target = "http://r1.googlevideo.com/videoplayback?itag=251";
+        let exp = SystemTime::now() + Duration::from_secs(60);
+        let token = signer.sign_media_until(target, "yt:abc", exp);
+        let proxy = chunked_proxy(signer, addr).with_cache(cache.clone());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, "bytes=5-13".parse().unwrap());
+        let response = proxy.serve(Some(&token), &headers).await;
+        assert_eq!(body_of(response).await, b"56789abcd");
+        assert_eq!(
+            *ranges.lock().unwrap(),
+            ["bytes=4-7", "bytes=8-11", "bytes=12-15"]
+        );
+
+        // Chunks are written in the background.
+        let key = ChunkKey::for_url(Some("yt:abc"), &target.parse().unwrap()).unwrap();
+        for _ in 0..100 {
+            if cache.read_chunk(&key, 3, 4).await.is_some() && cache.meta(&key, 4).await.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        headers.insert(header::RANGE, "bytes=6-15".parse().unwrap());
+        let response = proxy.serve(Some(&token), &headers).await;
+        assert_eq!(
+            response.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes 6-15/20"
+        );
+        assert_eq!(body_of(response).await, b"6789abcdef");
+        assert_eq!(ranges.lock().unwrap().len(), 3);
+
+        headers.insert(header::RANGE, "bytes=14-".parse().unwrap());
+        let response = proxy.serve(Some(&token), &headers).await;
+        assert_eq!(body_of(response).await, b"efghij");
+        assert_eq!(ranges.lock().unwrap().len(), 4);
+
+        // Known lengths answer unsatisfiable ranges without asking upstream.
+        headers.insert(header::RANGE, "bytes=30-".parse().unwrap());
+        let response = proxy.serve(Some(&token), &headers).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            response.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes */20"
+        );
+        assert_eq!(ranges.lock().unwrap().len(), 4);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
     async fn assert_plain_json_error(response: Response, status: StatusCode, code: &str) {
         assert_eq!(response.status(), status);
         assert_eq!(
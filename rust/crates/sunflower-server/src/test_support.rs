@@ -87,6 +87,13 @@ pub(crate) fn test_router_config(
     RouterBuildConfig::new(auth_mode, store, "./data", DEFAULT_SETUP_TOKEN, "", None)
 }
 
+/// InnerTube backend serving the recorded session in `testdata/innertube/replay`.
+#[cfg(test)]
+pub(crate) fn replay_innertube() -> Arc<dyn innertube::InnerTubeBackend> {
+    let dir = FsPath::new(env!("CARGO_MANIFEST_DIR")).join("testdata/innertube/replay");
+    Arc::new(ReplayInnerTube::from_dir(dir).unwrap())
+}
+
 pub(crate) struct FakeInnerTube {
     pub(crate) home_page: innertube::HomePage,
     pub(crate) search_page: innertube::SearchPage,
@@ -94,17 +101,6 @@ pub(crate) struct FakeInnerTube {
     pub(crate) player: innertube::PlayerResponse,
 }
 
-impl FakeInnerTube {
-    pub(crate) fn with_player(player: innertube::PlayerResponse) -> Self {
-        Self {
-            home_page: innertube::HomePage::default(),
-            search_page: innertube::SearchPage::default(),
-            next_pages: Mutex::new(vec![]),
-            player,
-        }
-    }
-}
-
 impl innertube::InnerTubeBackend for FakeInnerTube {
     fn browse<'a>(
         &'a self,
@@ -128,7 +124,12 @@ impl innertube::InnerTubeBackend for FakeInnerTube {
         _video_id: &'a str,
         _continuation: Option<&'a str>,
     ) -> BoxFuture<'a, Result<innertube::NextPage, innertube::InnerTubeError>> {
-        let page = self.next_pages.lock().unwrap().remove(0);
+        let mut pages = self.next_pages.lock().unwrap();
+        let page = if pages.is_empty() {
+            innertube::NextPage::default()
+        } else {
+            pages.remove(0)
+        };
         Box::pin(async move { Ok(page) })
     }
 
@@ -139,4 +140,11 @@ impl innertube::InnerTubeBackend for FakeInnerTube {
         let player = self.player.clone();
         Box::pin(async move { Ok(player) })
     }
+
+    fn lyrics<'a>(
+        &'a self,
+        _video_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<sunflower_core::Lyrics>, innertube::InnerTubeError>> {
+        Box::pin(async { Ok(None) })
+    }
 }
@@ -28,20 +28,23 @@ use tower::util::ServiceExt;
 use uuid::Uuid;
 
 use super::{
-    ADMIN_CSS, ADMIN_JS, ADMIN_STATIC_DIR_LISTING, AdminApiCsrfToken, AdminCsrfCheck, AuthMode,
-    DEFAULT_DATABASE_URL, DEFAULT_LISTEN_ADDR, DEFAULT_SETUP_TOKEN, FakeInnerTube,
-    LegacyRouteConfig, ProxySigner, StreamProxy, admin_api_csrf_token, admin_audit_limit,
-    admin_cookie, admin_form_csrf_token, album_art_size, append_legacy_json_newline, bool_param,
-    clear_admin_cookie, configured_cookie_file_from, configured_data_dir, configured_database_url,
-    configured_dev_open_registration, configured_listen_addr, configured_setup_token, cookie_value,
-    decoded_query_param, form_value, go_wildcard_socket_addr, healthz, hex_lower_bytes, innertube,
-    is_legacy_idempotent_mutation, legacy_allowed_methods_for_path,
-    legacy_idempotent_mutating_route_patterns, legacy_json_response, legacy_url_path,
-    legacy_wire_body_for_hash, pagination, parse_form, parse_request_form,
-    parse_youtube_cookie_header, path_segment, query_param, query_token, rate_limit_key,
-    require_admin_csrf, router_with_auth, router_with_config, router_with_state_and_config,
+    ADMIN_CSS, ADMIN_JS, ADMIN_STATIC_DIR_LISTING, AdminApiCsrfToken, AdminCsrfCheck, AudioQuality,
+    AuthMode, DEFAULT_DATABASE_URL, DEFAULT_LISTEN_ADDR, DEFAULT_SETUP_TOKEN, FakeInnerTube,
+    LegacyRouteConfig, LookaheadBudget, ProxySigner, RouterBuildConfig, StreamProxy,
+    admin_api_csrf_token, admin_audit_limit, admin_cookie, admin_form_csrf_token, album_art_size,
+    app_state, append_legacy_json_newline, bool_param, clear_admin_cookie,
+    configured_cookie_file_from, configured_data_dir, configured_database_url,
+    configured_dev_open_registration, configured_listen_addr, configured_playback_tracking,
+    configured_setup_token, cookie_value, decoded_query_param, form_value, go_wildcard_socket_addr,
+    healthz, hex_lower_bytes, innertube, is_legacy_idempotent_mutation,
+    legacy_allowed_methods_for_path, legacy_idempotent_mutating_route_patterns,
+    legacy_json_response, legacy_url_path, legacy_wire_body_for_hash, pagination, parse_form,
+    parse_request_form, parse_youtube_cookie_header, path_segment, query_param, query_token,
+    rate_limit_key, replay_innertube, require_admin_csrf, resolve_lookahead_items_within,
+    router_with_auth, router_with_config, router_with_state_and_config,
     router_with_state_and_config_and_hub, router_with_state_and_data_dir, router_with_store,
     scrobble_qualifies, search_limit, serve_local_file, should_proxy_youtube, test_router_config,
+    youtube_tracking_video_id,
 };
 
 static PG_TEST_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
@@ -420,6 +423,7 @@ fn legacy_allowed_methods_table_matches_go_router_contract() {
         ("/admin/now-playing", &["GET"]),
         ("/admin/now-playing/command", &["POST"]),
         ("/admin/audit", &["GET"]),
+        ("/admin/diagnostics", &["GET"]),
         ("/api/v1/setup/status", &["GET"]),
         ("/api/v1/setup/owner", &["POST"]),
         ("/api/v1/auth/register-device", &["POST"]),
@@ -436,18 +440,34 @@ fn legacy_allowed_methods_table_matches_go_router_contract() {
         ("/api/v1/admin/pairing-codes", &["POST"]),
         ("/api/v1/admin/library/status", &["GET"]),
         ("/api/v1/admin/library/scan", &["POST"]),
+        ("/api/v1/admin/library/downloads", &["POST"]),
         ("/api/v1/admin/cookies/youtube/status", &["GET"]),
         ("/api/v1/admin/cookies/youtube", &["POST"]),
         ("/api/v1/admin/cookies/youtube/probe", &["POST"]),
         ("/api/v1/admin/cookies/youtube/clear", &["POST"]),
+        ("/api/v1/admin/stream-proxy/rotate", &["POST"]),
         ("/api/v1/admin/now-playing", &["GET"]),
         ("/api/v1/admin/now-playing/command", &["POST"]),
         ("/api/v1/admin/audit", &["GET"]),
+        ("/api/v1/admin/diagnostics/innertube", &["GET"]),
         ("/api/v1/queue/start", &["POST"]),
         (
             "/api/v1/queue/018f3f27-0000-7000-8000-000000000010",
             &["GET"],
         ),
+        (
+            "/api/v1/queue/018f3f27-0000-7000-8000-000000000010/edit",
+            &["POST"],
+        ),
+        (
+            "/api/v1/queue/018f3f27-0000-7000-8000-000000000010/mode",
+            &["POST"],
+        ),
+        (
+            "/api/v1/queue/018f3f27-0000-7000-8000-000000000010/resume",
+            &["GET"],
+        ),
+        ("/api/v1/queues", &["GET"]),
         ("/api/v1/next", &["GET"]),
         ("/api/v1/home", &["GET"]),
         ("/api/v1/search", &["GET"]),
@@ -455,10 +475,19 @@ fn legacy_allowed_methods_table_matches_go_router_contract() {
         ("/api/v1/events", &["POST"]),
         ("/api/v1/impressions", &["POST"]),
         ("/api/v1/playlists", &["GET", "POST"]),
+        ("/api/v1/playlists/import", &["POST"]),
         (
             "/api/v1/playlists/018f3f27-0000-7000-8000-000000000020",
             &["GET", "PATCH", "DELETE"],
         ),
+        (
+            "/api/v1/playlists/018f3f27-0000-7000-8000-000000000020/edit",
+            &["POST"],
+        ),
+        (
+            "/api/v1/playlists/018f3f27-0000-7000-8000-000000000020/export",
+            &["GET"],
+        ),
         (
             "/api/v1/playlists/018f3f27-0000-7000-8000-000000000020/items",
             &["POST"],
@@ -471,10 +500,12 @@ fn legacy_allowed_methods_table_matches_go_router_contract() {
         ("/api/v1/library/albums", &["GET"]),
         ("/api/v1/library/artists", &["GET"]),
         ("/api/v1/library/scan", &["POST"]),
+        ("/api/v1/library/downloads", &["POST"]),
         ("/api/v1/jobs/scan-1", &["GET"]),
         ("/api/v1/library/albums/local:album/art", &["GET"]),
         ("/api/v1/library/songs/local:track/hash", &["GET"]),
         ("/api/v1/library/songs/local:track/stream", &["GET"]),
+        ("/api/v1/lyrics/local:track", &["GET"]),
         ("/api/v1/cookies/youtube/status", &["GET"]),
         ("/api/v1/cookies/youtube", &["POST"]),
         (
@@ -487,6 +518,7 @@ fn legacy_allowed_methods_table_matches_go_router_contract() {
         ),
         ("/api/v1/ws/now-playing", &["GET"]),
         ("/api/v1/streams/resolve", &["POST"]),
+        ("/api/v1/streams/refresh", &["POST"]),
     ];
 
     for (path, methods) in expected {
@@ -517,14 +549,20 @@ fn legacy_idempotent_mutating_routes_match_go_router_contract() {
         &[
             ("POST", "/api/v1/auth/register-device"),
             ("POST", "/api/v1/library/scan"),
+            ("POST", "/api/v1/library/downloads"),
             ("POST", "/api/v1/cookies/youtube"),
             ("POST", "/api/v1/queue/start"),
+            ("POST", "/api/v1/queue/:id/edit"),
+            ("POST", "/api/v1/queue/:id/mode"),
             ("POST", "/api/v1/streams/resolve"),
+            ("POST", "/api/v1/streams/refresh"),
             ("POST", "/api/v1/likes"),
             ("POST", "/api/v1/impressions"),
             ("POST", "/api/v1/playlists"),
+            ("POST", "/api/v1/playlists/import"),
             ("PATCH", "/api/v1/playlists/:id"),
             ("DELETE", "/api/v1/playlists/:id"),
+            ("POST", "/api/v1/playlists/:id/edit"),
             ("POST", "/api/v1/playlists/:id/items"),
             ("DELETE", "/api/v1/playlists/:id/items/:media_id"),
             ("POST", "/api/v1/devices/:id/downloads"),
@@ -536,12 +574,23 @@ fn legacy_idempotent_mutating_routes_match_go_router_contract() {
     for (method, path) in [
         ("POST", "/api/v1/auth/register-device"),
         ("POST", "/api/v1/library/scan"),
+        ("POST", "/api/v1/library/downloads"),
         ("POST", "/api/v1/cookies/youtube"),
         ("POST", "/api/v1/queue/start"),
+        (
+            "POST",
+            "/api/v1/queue/018f3f27-0000-7000-8000-000000000010/edit",
+        ),
+        (
+            "POST",
+            "/api/v1/queue/018f3f27-0000-7000-8000-000000000010/mode",
+        ),
         ("POST", "/api/v1/streams/resolve"),
+        ("POST", "/api/v1/streams/refresh"),
         ("POST", "/api/v1/likes"),
         ("POST", "/api/v1/impressions"),
         ("POST", "/api/v1/playlists"),
+        ("POST", "/api/v1/playlists/import"),
         (
             "PATCH",
             "/api/v1/playlists/018f3f27-0000-7000-8000-000000000020",
@@ -550,6 +599,10 @@ fn legacy_idempotent_mutating_routes_match_go_router_contract() {
             "DELETE",
             "/api/v1/playlists/018f3f27-0000-7000-8000-000000000020",
         ),
+        (
+            "POST",
+            "/api/v1/playlists/018f3f27-0000-7000-8000-000000000020/edit",
+        ),
         (
             "POST",
             "/api/v1/playlists/018f3f27-0000-7000-8000-000000000020/items",
@@ -578,6 +631,7 @@ fn legacy_idempotent_mutating_routes_match_go_router_contract() {
         ("POST", "/api/v1/setup/owner"),
         ("POST", "/api/v1/admin/auth/login"),
         ("POST", "/api/v1/admin/library/scan"),
+        ("POST", "/api/v1/admin/library/downloads"),
         ("GET", "/api/v1/home"),
         ("GET", "/api/v1/playlists"),
         ("POST", "/api/v1/unknown"),
@@ -965,6 +1019,29 @@ fn stream_proxy_policy_matches_legacy_go_contract() {
     assert!(!should_proxy_youtube("", false));
 }
 
+#[test]
+fn playback_tracking_is_on_unless_disabled_and_only_for_youtube_plays() {
+    assert!(configured_playback_tracking(None));
+    assert!(configured_playback_tracking(Some(String::new())));
+    assert!(configured_playback_tracking(Some("1".into())));
+    for off in ["0", "false", " OFF ", "never"] {
+        assert!(!configured_playback_tracking(Some(off.into())), "{off}");
+    }
+
+    let event = |kind: &str, media_id: &str| sunflower_core::EventEntryRequest {
+        kind: kind.into(),
+        media_id: media_id.into(),
+        ..sunflower_core::EventEntryRequest::default()
+    };
+    assert_eq!(
+        youtube_tracking_video_id(&event("play", "yt:abc")),
+        Some("abc")
+    );
+    assert_eq!(youtube_tracking_video_id(&event("skip", "yt:abc")), None);
+    assert_eq!(youtube_tracking_video_id(&event("play", "local:abc")), None);
+    assert_eq!(youtube_tracking_video_id(&event("play", "yt:")), None);
+}
+
 #[test]
 fn runtime_config_defaults_match_legacy_go_contract() {
     assert_eq!(
@@ -1482,6 +1559,14 @@ async fn postgres_setup_owner_matches_legacy_enrollment_contract_when_enabled()
         .execute(&pool)
         .await
         .unwrap();
+    sqlx::query("DELETE FROM cookie_health_checks WHERE provider = 'youtube'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM audit_events WHERE event = 'youtube_cookie_health_changed'")
+        .execute(&pool)
+        .await
+        .unwrap();
     sqlx::query("DELETE FROM users WHERE display_name = 'Rust Owner'")
         .execute(&pool)
         .await
@@ -2246,12 +2331,13 @@ async fn postgres_setup_owner_matches_legacy_enrollment_contract_when_enabled()
 
     sqlx::query(
         r#"
-        INSERT INTO cookie_health (provider, status, checked_at, detail)
-        VALUES ('youtube', 'ok', now(), 'probe ok')
-        ON CONFLICT (provider) DO UPDATE
+        INSERT INTO cookie_health (user_id, provider, status, checked_at, detail)
+        VALUES ($1, 'youtube', 'ok', now(), 'probe ok')
+        ON CONFLICT (user_id, provider) DO UPDATE
         SET status = 'ok', checked_at = now(), detail = 'probe ok'
         "#,
     )
+    .bind(user_id)
     .execute(&pool)
     .await
     .unwrap();
@@ -2273,6 +2359,61 @@ async fn postgres_setup_owner_matches_legacy_enrollment_contract_when_enabled()
     assert_eq!(cookie_status_value["detail"], "probe ok");
     assert!(cookie_status_value["checked_at"].as_str().is_some());
 
+    PostgresStore::new(pool.clone())
+        .mark_youtube_cookies_degraded(user_id, "innertube post /youtubei/v1/search: status 403")
+        .await
+        .unwrap();
+    let degraded_status = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri("/api/v1/admin/cookies/youtube/status")
+                .header(header::COOKIE, &cookie_header)
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let degraded_status_value = response_json(degraded_status).await;
+    assert_eq!(degraded_status_value["status"], "degraded");
+    assert_eq!(
+        degraded_status_value["detail"],
+        "innertube post /youtubei/v1/search: status 403"
+    );
+
+    let probe_store = PostgresStore::new(pool.clone());
+    assert_eq!(
+        probe_store
+            .record_youtube_cookie_probe(user_id, "ok", Some(120), "signed-in next call succeeded")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("degraded")
+    );
+    probe_store
+        .record_youtube_cookie_probe(user_id, "ok", Some(95), "signed-in next call succeeded")
+        .await
+        .unwrap();
+    let history = probe_store
+        .youtube_cookie_health_history(user_id, 24)
+        .await
+        .unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|check| check.latency_ms)
+            .collect::<Vec<_>>(),
+        vec![Some(95), Some(120)]
+    );
+    let health_transitions: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM audit_events WHERE event = 'youtube_cookie_health_changed'",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(health_transitions, 1);
+
     let upload_disabled = app
         .clone()
         .oneshot(
@@ -2392,6 +2533,89 @@ async fn postgres_setup_owner_matches_legacy_enrollment_contract_when_enabled()
         Some("SID=secret")
     );
 
+    let member_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO users (id, display_name) VALUES ($1, $2)")
+        .bind(member_id)
+        .bind("Rust Household Member Test")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let cookie_store = PostgresStore::new(pool.clone());
+    let member_fallback = cookie_store
+        .load_youtube_cookies_for_user(Some(member_id), [7u8; 32])
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        parse_youtube_cookie_header(&member_fallback).as_deref(),
+        Some("SID=secret")
+    );
+    assert_eq!(
+        cookie_store
+            .load_own_youtube_cookies(member_id, [7u8; 32])
+            .await
+            .unwrap(),
+        None
+    );
+    cookie_store
+        .store_youtube_cookies_for_user(member_id, [7u8; 32], b"SID=member")
+        .await
+        .unwrap();
+    for (user, expected) in [
+        (Some(member_id), "SID=member"),
+        (Some(user_id), "SID=secret"),
+        (None, "SID=secret"),
+    ] {
+        let raw = cookie_store
+            .load_youtube_cookies_for_user(user, [7u8; 32])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(parse_youtube_cookie_header(&raw).as_deref(), Some(expected));
+    }
+    assert_eq!(
+        cookie_store.youtube_cookie_accounts().await.unwrap(),
+        vec![user_id, member_id]
+    );
+
+    // A member's rejected cookies mark their own health, not the owner's.
+    let provider =
+        crate::YoutubeCookieProvider::new(Some(cookie_store.clone()), Some([7u8; 32]), None);
+    innertube::CookieProvider::report_guest_fallback(
+        &provider,
+        Some(member_id),
+        "next: status 403".into(),
+    )
+    .await;
+    assert_eq!(
+        cookie_store
+            .admin_cookie_status(member_id)
+            .await
+            .unwrap()
+            .status,
+        "degraded"
+    );
+    assert_eq!(
+        cookie_store
+            .admin_cookie_status(user_id)
+            .await
+            .unwrap()
+            .status,
+        "ok"
+    );
+    cookie_store
+        .record_youtube_cookie_probe(member_id, "ok", Some(80), "signed-in next call succeeded")
+        .await
+        .unwrap();
+    assert_eq!(
+        cookie_store
+            .youtube_cookie_health_history(user_id, 24)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+
     let token_upload = app_with_cookie_key
         .clone()
         .oneshot(
@@ -3689,45 +3913,101 @@ async fn protected_routes_accept_legacy_query_token_fallback() {
     assert_json_error(blank_bearer_response, "empty_queue").await;
 }
 
+#[tokio::test]
+async fn queue_resume_validates_id_before_touching_the_store() {
+    for (uri, status, code) in [
+        (
+            "/api/v1/queue/not-a-uuid/resume",
+            StatusCode::BAD_REQUEST,
+            "invalid_id",
+        ),
+        (
+            "/api/v1/queue/018f3f27-0000-7000-8000-000000000010/resume",
+            StatusCode::NOT_FOUND,
+            "not_found",
+        ),
+    ] {
+        let app = router_with_auth(AuthMode::AllowAllForContractTests);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::GET)
+                    .uri(uri)
+                    .header(header::AUTHORIZATION, "Bearer contract-test")
+                    .body(body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), status, "{uri}");
+        assert_json_error(response, code).await;
+    }
+}
+
+#[tokio::test]
+async fn queue_edit_validates_id_and_body_before_touching_the_store() {
+    for (uri, body_raw, status, code) in [
+        (
+            "/api/v1/queue/not-a-uuid/edit",
+            r#"{"version":1,"op":"remove","position":0}"#,
+            StatusCode::BAD_REQUEST,
+            "invalid_id",
+        ),
+        (
+            "/api/v1/queue/018f3f27-0000-7000-8000-000000000010/edit",
+            r#"{"op":"remove","position":0}"#,
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+        ),
+        (
+            "/api/v1/queue/018f3f27-0000-7000-8000-000000000010/edit",
+            r#"{"version":1,"op":"remove","position":0}"#,
+            StatusCode::NOT_FOUND,
+            "not_found",
+        ),
+        (
+            "/api/v1/queue/not-a-uuid/mode",
+            r#"{"shuffle":true}"#,
+            StatusCode::BAD_REQUEST,
+            "invalid_id",
+        ),
+        (
+            "/api/v1/queue/018f3f27-0000-7000-8000-000000000010/mode",
+            r#"{"repeat":"sometimes"}"#,
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+        ),
+        (
+            "/api/v1/queue/018f3f27-0000-7000-8000-000000000010/mode",
+            r#"{"repeat":"all"}"#,
+            StatusCode::NOT_FOUND,
+            "not_found",
+        ),
+    ] {
+        let app = router_with_auth(AuthMode::AllowAllForContractTests);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri(uri)
+                    .header(header::AUTHORIZATION, "Bearer contract-test")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header("idempotency-key", Uuid::now_v7().to_string())
+                    .body(body::Body::from(body_raw))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), status, "{uri} {body_raw}");
+        assert_json_error(response, code).await;
+    }
+}
+
 #[tokio::test]
 async fn search_uses_innertube_backend_like_legacy_handler() {
-    let yt: Arc<dyn innertube::InnerTubeBackend> = Arc::new(FakeInnerTube {
-        home_page: innertube::HomePage::default(),
-        search_page: innertube::SearchPage {
-            songs: vec![
-                innertube::SongItem {
-                    video_id: "song-a".into(),
-                    title: "Song A".into(),
-                    artists: vec!["Artist A".into()],
-                    duration_ms: 0,
-                    thumbnail_url: "https://img.example/song-a.jpg".into(),
-                    is_explicit: false,
-                },
-                innertube::SongItem {
-                    video_id: "song-b".into(),
-                    title: "Song B".into(),
-                    artists: vec![],
-                    duration_ms: 0,
-                    thumbnail_url: String::new(),
-                    is_explicit: false,
-                },
-            ],
-            albums: vec![innertube::AlbumItem {
-                browse_id: "album-a".into(),
-                title: "Album A".into(),
-                artists: vec!["Artist A".into()],
-                thumbnail_url: String::new(),
-            }],
-            artists: vec![innertube::ArtistItem {
-                browse_id: "artist-a".into(),
-                name: "Artist A".into(),
-                thumbnail_url: "https://img.example/artist-a.jpg".into(),
-            }],
-            continuation: Some("next-page".into()),
-        },
-        next_pages: Mutex::new(vec![]),
-        player: innertube::PlayerResponse::default(),
-    });
+    let yt = replay_innertube();
     let app = router_with_config(
         test_router_config(AuthMode::AllowAllForContractTests, None).with_yt(Some(yt)),
     );
@@ -3735,7 +4015,7 @@ async fn search_uses_innertube_backend_like_legacy_handler() {
         .oneshot(
             Request::builder()
                 .method(Method::GET)
-                .uri("/api/v1/search?q=Rick+Astley&limit=1")
+                .uri("/api/v1/search?q=rick+astley&limit=1")
                 .header(header::AUTHORIZATION, "Bearer contract-test")
                 .body(body::Body::empty())
                 .unwrap(),
@@ -3744,20 +4024,87 @@ async fn search_uses_innertube_backend_like_legacy_handler() {
         .unwrap();
     assert_eq!(response.status(), StatusCode::OK);
     let value = response_json(response).await;
-    assert_eq!(value["query"], "Rick Astley");
+    assert_eq!(value["query"], "rick astley");
     assert_eq!(value["songs"].as_array().unwrap().len(), 1);
-    assert_eq!(value["songs"][0]["media_id"], "yt:song-a");
+    assert_eq!(value["songs"][0]["media_id"], "yt:dQw4w9WgXcQ");
     assert_eq!(value["songs"][0]["source"], "yt");
-    assert_eq!(value["albums"].as_array().unwrap().len(), 1);
-    assert_eq!(value["artists"].as_array().unwrap().len(), 1);
-    assert_eq!(value["continuation"], "next-page");
+    assert_eq!(value["albums"][0]["title"], "Whenever You Need Somebody");
+    assert_eq!(value["artists"][0]["name"], "Rick Astley");
+}
+
+#[tokio::test]
+async fn replayed_innertube_session_serves_home_search_radio_and_streams() {
+    let yt = replay_innertube();
+    let home = yt.browse("FEmusic_home", None).await.unwrap();
+    assert!(!home.sections.is_empty());
+
+    let radio = innertube::expand_radio(yt.as_ref(), "dQw4w9WgXcQ", 25)
+        .await
+        .unwrap();
+    assert_eq!(
+        radio
+            .items
+            .iter()
+            .map(|item| item.media_id.0.as_str())
+            .collect::<Vec<_>>(),
+        vec!["yt:dQw4w9WgXcQ", "yt:abc123def456"]
+    );
+
+    let app = router_with_config(
+        test_router_config(AuthMode::AllowAllForContractTests, None).with_yt(Some(yt)),
+    );
+    let search = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri("/api/v1/search?q=rick+astley")
+                .header(header::AUTHORIZATION, "Bearer contract-test")
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(search.status(), StatusCode::OK);
+    let search = response_json(search).await;
+    assert_eq!(search["songs"][0]["media_id"], "yt:dQw4w9WgXcQ");
+
+    let resolved = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/v1/streams/resolve")
+                .header(header::AUTHORIZATION, "Bearer contract-test")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("idempotency-key", Uuid::now_v7().to_string())
+                .body(body::Body::from(r#"{"media_id":"yt:dQw4w9WgXcQ"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resolved.status(), StatusCode::OK);
+    let resolved = response_json(resolved).await;
+    assert_eq!(resolved["itag"], 251);
+    assert_eq!(resolved["mime_type"], "audio/webm; codecs=\"opus\"");
+
+    let missing = app
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri("/api/v1/search?q=not+recorded")
+                .header(header::AUTHORIZATION, "Bearer contract-test")
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), StatusCode::BAD_GATEWAY);
 }
 
 #[tokio::test]
 async fn search_query_rejects_malformed_escape_like_legacy_go_handler() {
-    let yt: Arc<dyn innertube::InnerTubeBackend> = Arc::new(FakeInnerTube::with_player(
-        innertube::PlayerResponse::default(),
-    ));
+    let yt = replay_innertube();
     let app = router_with_config(
         test_router_config(AuthMode::AllowAllForContractTests, None).with_yt(Some(yt)),
     );
@@ -3778,17 +4125,7 @@ async fn search_query_rejects_malformed_escape_like_legacy_go_handler() {
 
 #[tokio::test]
 async fn streams_resolve_uses_innertube_backend_and_proxy_flag() {
-    let yt: Arc<dyn innertube::InnerTubeBackend> =
-        Arc::new(FakeInnerTube::with_player(innertube::PlayerResponse {
-            stream: innertube::StreamUrl {
-                url: "https://r1.googlevideo.com/videoplayback?expire=2000000000".into(),
-                itag: 251,
-                mime_type: "audio/webm".into(),
-                bitrate: 160_000,
-                loudness: 0.0,
-            },
-            ..innertube::PlayerResponse::default()
-        }));
+    let yt = replay_innertube();
     let proxy = Arc::new(StreamProxy::new(ProxySigner::new(b"test-key".to_vec())));
     let app = router_with_config(
         test_router_config(AuthMode::AllowAllForContractTests, None)
@@ -3806,7 +4143,7 @@ async fn streams_resolve_uses_innertube_backend_and_proxy_flag() {
                 .header(header::CONTENT_TYPE, "application/json")
                 .header("idempotency-key", Uuid::now_v7().to_string())
                 .body(body::Body::from(
-                    r#"{"media_id":"yt:abc","audio_quality":"high","reason":"near_expiry"}"#,
+                    r#"{"media_id":"yt:dQw4w9WgXcQ","audio_quality":"high","reason":"near_expiry"}"#,
                 ))
                 .unwrap(),
         )
@@ -3814,15 +4151,15 @@ async fn streams_resolve_uses_innertube_backend_and_proxy_flag() {
         .unwrap();
     assert_eq!(direct.status(), StatusCode::OK);
     let direct_value = response_json(direct).await;
-    assert_eq!(direct_value["media_id"], "yt:abc");
+    assert_eq!(direct_value["media_id"], "yt:dQw4w9WgXcQ");
     assert_eq!(direct_value["source"], "youtube");
     assert_eq!(
         direct_value["stream_url"],
-        "https://r1.googlevideo.com/videoplayback?expire=2000000000"
+        "https://rr1---sn-replay.googlevideo.com/videoplayback?expire=2000000000&itag=251"
     );
     assert_eq!(direct_value["stream_expires_at"], "2033-05-18T03:33:20Z");
     assert_eq!(direct_value["itag"], 251);
-    assert_eq!(direct_value["mime_type"], "audio/webm");
+    assert_eq!(direct_value["mime_type"], "audio/webm; codecs=\"opus\"");
     assert_eq!(direct_value["metadata"]["bitrate"], 160_000);
 
     let proxied = app
@@ -3834,7 +4171,9 @@ async fn streams_resolve_uses_innertube_backend_and_proxy_flag() {
                 .header(header::AUTHORIZATION, "Bearer contract-test")
                 .header(header::CONTENT_TYPE, "application/json")
                 .header("idempotency-key", Uuid::now_v7().to_string())
-                .body(body::Body::from(r#"{"media_id":"yt:abc","proxy":true}"#))
+                .body(body::Body::from(
+                    r#"{"media_id":"yt:dQw4w9WgXcQ","proxy":true}"#,
+                ))
                 .unwrap(),
         )
         .await
@@ -3850,17 +4189,7 @@ async fn streams_resolve_uses_innertube_backend_and_proxy_flag() {
             .starts_with("/api/v1/streams/proxy?token=")
     );
 
-    let policy_yt: Arc<dyn innertube::InnerTubeBackend> =
-        Arc::new(FakeInnerTube::with_player(innertube::PlayerResponse {
-            stream: innertube::StreamUrl {
-                url: "https://r2.googlevideo.com/videoplayback?expire=2000000001".into(),
-                itag: 251,
-                mime_type: "audio/webm".into(),
-                bitrate: 160_000,
-                loudness: 0.0,
-            },
-            ..innertube::PlayerResponse::default()
-        }));
+    let policy_yt = replay_innertube();
     let policy_app = router_with_config(
         test_router_config(AuthMode::AllowAllForContractTests, None)
             .with_proxy(Some(Arc::new(StreamProxy::new(ProxySigner::new(
@@ -3877,7 +4206,7 @@ async fn streams_resolve_uses_innertube_backend_and_proxy_flag() {
                 .header(header::AUTHORIZATION, "Bearer contract-test")
                 .header(header::CONTENT_TYPE, "application/json")
                 .header("idempotency-key", Uuid::now_v7().to_string())
-                .body(body::Body::from(r#"{"media_id":"yt:def"}"#))
+                .body(body::Body::from(r#"{"media_id":"yt:dQw4w9WgXcQ"}"#))
                 .unwrap(),
         )
         .await
@@ -3910,12 +4239,245 @@ async fn streams_resolve_uses_innertube_backend_and_proxy_flag() {
 }
 
 #[tokio::test]
-async fn stream_proxy_route_uses_signed_token_not_device_auth() {
-    let app = router_with_auth(AuthMode::RejectAllTokens);
-    let response = app
-        .oneshot(
-            Request::builder()
-                .method(Method::GET)
+async fn streams_refresh_resolves_each_media_id_and_reports_failures() {
+    let yt = replay_innertube();
+    let proxy = Arc::new(StreamProxy::new(ProxySigner::new(b"test-key".to_vec())));
+    let app = router_with_config(
+        test_router_config(AuthMode::AllowAllForContractTests, None)
+            .with_proxy(Some(proxy))
+            .with_yt(Some(yt)),
+    );
+    let refresh = |body: &'static str| {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/streams/refresh")
+            .header(header::AUTHORIZATION, "Bearer contract-test")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("idempotency-key", Uuid::now_v7().to_string())
+            .body(body::Body::from(body))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(refresh(
+            r#"{"media_ids":["yt:dQw4w9WgXcQ","local:song-1","spotify:abc"],"proxy":true}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let value = response_json(response).await;
+    assert_eq!(value["streams"][0]["media_id"], "yt:dQw4w9WgXcQ");
+    assert_eq!(value["streams"][0]["source"], "proxy");
+    assert_eq!(
+        value["streams"][0]["stream_expires_at"],
+        "2033-05-18T03:33:20Z"
+    );
+    assert_eq!(value["streams"][1]["media_id"], "local:song-1");
+    assert_eq!(value["streams"][1]["source"], "local");
+    assert_eq!(
+        value["failed"],
+        json!([{ "media_id": "spotify:abc", "error": "resolve_failed" }])
+    );
+
+    let empty = app
+        .clone()
+        .oneshot(refresh(r#"{"media_ids":[]}"#))
+        .await
+        .unwrap();
+    assert_eq!(empty.status(), StatusCode::BAD_REQUEST);
+    assert_json_error(empty, "invalid_request").await;
+
+    let no_key = app
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/v1/streams/refresh")
+                .header(header::AUTHORIZATION, "Bearer contract-test")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body::Body::from(r#"{"media_ids":["yt:dQw4w9WgXcQ"]}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(no_key.status(), StatusCode::BAD_REQUEST);
+}
+
+/// Sleeps `delay_ms` from the video id (`slow-<ms>`) before answering and
+/// records the most `player` calls seen in flight at once.
+#[derive(Default)]
+struct DelayedPlayerInnerTube {
+    in_flight: std::sync::atomic::AtomicUsize,
+    max_in_flight: std::sync::atomic::AtomicUsize,
+}
+
+impl innertube::InnerTubeBackend for DelayedPlayerInnerTube {
+    fn browse<'a>(
+        &'a self,
+        _browse_id: &'a str,
+        _continuation: Option<&'a str>,
+    ) -> futures_util::future::BoxFuture<'a, Result<innertube::HomePage, innertube::InnerTubeError>>
+    {
+        Box::pin(async { Ok(innertube::HomePage::default()) })
+    }
+
+    fn search<'a>(
+        &'a self,
+        _query: &'a str,
+    ) -> futures_util::future::BoxFuture<'a, Result<innertube::SearchPage, innertube::InnerTubeError>>
+    {
+        Box::pin(async { Ok(innertube::SearchPage::default()) })
+    }
+
+    fn next<'a>(
+        &'a self,
+        _video_id: &'a str,
+        _continuation: Option<&'a str>,
+    ) -> futures_util::future::BoxFuture<'a, Result<innertube::NextPage, innertube::InnerTubeError>>
+    {
+        Box::pin(async { Ok(innertube::NextPage::default()) })
+    }
+
+    fn player<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> futures_util::future::BoxFuture<
+        'a,
+        Result<innertube::PlayerResponse, innertube::InnerTubeError>,
+    > {
+        use std::sync::atomic::Ordering;
+        Box::pin(async move {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+            let delay_ms = video_id
+                .strip_prefix("slow-")
+                .and_then(|ms| ms.parse().ok())
+                .unwrap_or(20);
+            // Timed-out calls are dropped mid-sleep, so count them out on drop.
+            let _in_flight = InFlight(&self.in_flight);
+            tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+            Ok(innertube::PlayerResponse {
+                video_id: video_id.to_string(),
+                stream: innertube::StreamUrl {
+                    url: format!("https://r1.googlevideo.com/videoplayback?id={video_id}"),
+                    itag: 251,
+                    mime_type: "audio/webm".into(),
+                    bitrate: 160_000,
+                    loudness: 0.0,
+                },
+                ..innertube::PlayerResponse::default()
+            })
+        })
+    }
+
+    fn lyrics<'a>(
+        &'a self,
+        _video_id: &'a str,
+    ) -> futures_util::future::BoxFuture<
+        'a,
+        Result<Option<sunflower_core::Lyrics>, innertube::InnerTubeError>,
+    > {
+        Box::pin(async { Ok(None) })
+    }
+}
+
+struct InFlight<'a>(&'a std::sync::atomic::AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn lookahead_resolution_is_bounded_and_returns_stragglers_metadata_only() {
+    let backend = Arc::new(DelayedPlayerInnerTube::default());
+    let state = app_state(
+        test_router_config(AuthMode::AllowAllForContractTests, None).with_yt(Some(backend.clone())),
+    );
+    let item = |video_id: &str| sunflower_core::QueueItem {
+        media_id: sunflower_core::MediaId::new(format!("yt:{video_id}")),
+        title: video_id.to_string(),
+        artists: vec!["Artist".to_string()],
+        duration_ms: 1000,
+        unavailable: false,
+    };
+    let items = [
+        item("a"),
+        item("slow-1000"),
+        item("b"),
+        item("slow-100"),
+        item("c"),
+        item("d"),
+    ];
+    let budget = LookaheadBudget {
+        concurrency: 2,
+        item_timeout: std::time::Duration::from_millis(150),
+        deadline: std::time::Duration::from_secs(5),
+    };
+
+    let started = std::time::Instant::now();
+    let resolved = resolve_lookahead_items_within(&state, &items, AudioQuality::High, budget).await;
+    assert!(started.elapsed() < std::time::Duration::from_millis(900));
+    assert_eq!(
+        backend
+            .max_in_flight
+            .load(std::sync::atomic::Ordering::SeqCst),
+        2
+    );
+
+    assert_eq!(
+        resolved
+            .iter()
+            .map(|stream| stream.media_id.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "yt:a",
+            "yt:slow-1000",
+            "yt:b",
+            "yt:slow-100",
+            "yt:c",
+            "yt:d"
+        ]
+    );
+    for (stream, resolved_url) in resolved.iter().zip([true, false, true, true, true, true]) {
+        assert_eq!(
+            !stream.stream_url.is_empty(),
+            resolved_url,
+            "{}",
+            stream.media_id
+        );
+        assert_eq!(stream.title, stream.media_id.trim_start_matches("yt:"));
+        assert_eq!(stream.artists, vec!["Artist"]);
+    }
+
+    // The total deadline cuts off items whose own timeout has not fired yet.
+    let patient = LookaheadBudget {
+        concurrency: 2,
+        item_timeout: std::time::Duration::from_secs(5),
+        deadline: std::time::Duration::from_millis(150),
+    };
+    let started = std::time::Instant::now();
+    let resolved = resolve_lookahead_items_within(
+        &state,
+        &[item("e"), item("slow-1001")],
+        AudioQuality::High,
+        patient,
+    )
+    .await;
+    assert!(started.elapsed() < std::time::Duration::from_millis(900));
+    assert!(!resolved[0].stream_url.is_empty());
+    assert!(resolved[1].stream_url.is_empty());
+    assert_eq!(resolved[1].media_id, "yt:slow-1001");
+}
+
+#[tokio::test]
+async fn stream_proxy_route_uses_signed_token_not_device_auth() {
+    let app = router_with_auth(AuthMode::RejectAllTokens);
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
                 .uri("/api/v1/streams/proxy?token=garbage")
                 .body(body::Body::empty())
                 .unwrap(),
@@ -4158,15 +4720,35 @@ async fn queue_start_error_paths_match_legacy_handler_contract() {
     let cases = [
         ("{", StatusCode::BAD_REQUEST, "invalid_request"),
         (
-            r#"{"seed_kind":"album"}"#,
+            r#"{"seed_kind":"mood"}"#,
             StatusCode::BAD_REQUEST,
             "invalid_seed_kind",
         ),
+        (
+            r#"{"seed_kind":"album"}"#,
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+        ),
         (
             r#"{"seed_kind":"song","seed_id":"yt:"}"#,
             StatusCode::BAD_GATEWAY,
             "seed_unavailable",
         ),
+        (
+            r#"{"seed_kind":"yt_playlist","seed_id":"PL../x"}"#,
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+        ),
+        (
+            r#"{"seed_kind":"yt_playlist","seed_id":"PLabc"}"#,
+            StatusCode::BAD_GATEWAY,
+            "seed_unavailable",
+        ),
+        (
+            r#"{"seed_kind":"local_radio"}"#,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "empty_queue",
+        ),
     ];
 
     for (body_raw, expected_status, expected_error) in cases {
@@ -4266,6 +4848,7 @@ async fn postgres_home_daily_discover_matches_legacy_related_section_when_enable
                         duration_ms: 0,
                         thumbnail_url: String::new(),
                         is_explicit: false,
+                        is_video: false,
                     },
                     innertube::SongItem {
                         video_id: "daily-a".into(),
@@ -4274,6 +4857,7 @@ async fn postgres_home_daily_discover_matches_legacy_related_section_when_enable
                         duration_ms: 0,
                         thumbnail_url: String::new(),
                         is_explicit: false,
+                        is_video: false,
                     },
                     innertube::SongItem {
                         video_id: "daily-dupe".into(),
@@ -4282,6 +4866,7 @@ async fn postgres_home_daily_discover_matches_legacy_related_section_when_enable
                         duration_ms: 0,
                         thumbnail_url: String::new(),
                         is_explicit: false,
+                        is_video: false,
                     },
                 ],
                 continuation: None,
@@ -4295,6 +4880,7 @@ async fn postgres_home_daily_discover_matches_legacy_related_section_when_enable
                         duration_ms: 0,
                         thumbnail_url: String::new(),
                         is_explicit: false,
+                        is_video: false,
                     },
                     innertube::SongItem {
                         video_id: "daily-b".into(),
@@ -4303,6 +4889,7 @@ async fn postgres_home_daily_discover_matches_legacy_related_section_when_enable
                         duration_ms: 0,
                         thumbnail_url: String::new(),
                         is_explicit: false,
+                        is_video: false,
                     },
                 ],
                 continuation: None,
@@ -4425,15 +5012,48 @@ async fn postgres_song_radio_queue_and_next_match_legacy_m4_when_enabled() {
             duration_ms: 0,
             thumbnail_url: String::new(),
             is_explicit: false,
+            is_video: false,
         })
         .collect();
     let yt: Arc<dyn innertube::InnerTubeBackend> = Arc::new(FakeInnerTube {
         home_page: innertube::HomePage::default(),
         search_page: innertube::SearchPage::default(),
-        next_pages: Mutex::new(vec![innertube::NextPage {
-            related,
-            continuation: None,
-        }]),
+        next_pages: Mutex::new(vec![
+            innertube::NextPage {
+                related,
+                continuation: Some("radio-more".into()),
+            },
+            // Songs related to the seed, read by the first /next for automix.
+            innertube::NextPage {
+                related: ["rel1", "auto1", "auto2", "auto3", "auto4"]
+                    .into_iter()
+                    .map(|video_id| innertube::SongItem {
+                        video_id: video_id.into(),
+                        title: format!("Related {video_id}"),
+                        artists: vec!["Radio Artist".into()],
+                        duration_ms: 0,
+                        thumbnail_url: String::new(),
+                        is_explicit: false,
+                        is_video: false,
+                    })
+                    .collect(),
+                continuation: None,
+            },
+            innertube::NextPage {
+                related: (5..16)
+                    .map(|index| innertube::SongItem {
+                        video_id: format!("rel{index}"),
+                        title: format!("Radio {index}"),
+                        artists: vec!["Radio Artist".into()],
+                        duration_ms: 0,
+                        thumbnail_url: String::new(),
+                        is_explicit: false,
+                        is_video: false,
+                    })
+                    .collect(),
+                continuation: None,
+            },
+        ]),
         player: innertube::PlayerResponse {
             stream: innertube::StreamUrl {
                 url: "https://r1.googlevideo.com/videoplayback?expire=2000000000".into(),
@@ -4477,6 +5097,7 @@ async fn postgres_song_radio_queue_and_next_match_legacy_m4_when_enabled() {
     let queue_id = start_value["queue_id"].as_str().unwrap();
 
     let next = app
+        .clone()
         .oneshot(
             Request::builder()
                 .method(Method::GET)
@@ -4504,6 +5125,51 @@ async fn postgres_song_radio_queue_and_next_match_legacy_m4_when_enabled() {
     assert_eq!(next_value["lookahead"][0]["itag"], 251);
     assert_eq!(next_value["queue_version"], start_value["version"]);
     assert_eq!(next_value["has_more"], true);
+    assert_eq!(next_value["continuation"], "radio-more");
+    let automix: Vec<_> = next_value["automix"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["media_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(automix, ["yt:auto1", "yt:auto2", "yt:auto3"]);
+
+    // Near the end of the stored items, /next pulls the next radio page.
+    let extended = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri(format!("/api/v1/next?queue_id={queue_id}&position=5"))
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(extended.status(), StatusCode::OK);
+    let extended_value = response_json(extended).await;
+    assert_eq!(extended_value["queue_version"], 2);
+    assert_eq!(extended_value["lookahead"][7]["media_id"], "yt:rel13");
+    assert_eq!(extended_value["has_more"], true);
+    assert_eq!(extended_value["continuation"], serde_json::Value::Null);
+
+    let tail = app
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri(format!("/api/v1/next?queue_id={queue_id}&position=10"))
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(tail.status(), StatusCode::OK);
+    let tail_value = response_json(tail).await;
+    assert_eq!(tail_value["queue_version"], 2);
+    assert_eq!(tail_value["lookahead"].as_array().unwrap().len(), 5);
+    assert_eq!(tail_value["has_more"], false);
 
     sqlx::query("DELETE FROM idempotency_log WHERE user_id = $1")
         .bind(user_id)
@@ -4527,6 +5193,118 @@ async fn postgres_song_radio_queue_and_next_match_legacy_m4_when_enabled() {
         .unwrap();
 }
 
+#[tokio::test]
+async fn postgres_song_radio_queue_hides_what_innertube_flags_when_enabled() {
+    if std::env::var("SUNFLOWER_RUN_PG_TESTS").ok().as_deref() != Some("1") {
+        return;
+    }
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        return;
+    };
+    let _pg_guard = PG_TEST_LOCK.lock().await;
+
+    let pool = sqlx::PgPool::connect(&database_url).await.unwrap();
+    cleanup_pg_test_users(&pool).await;
+    let store = PostgresStore::new(pool.clone());
+    let user_id = Uuid::new_v4();
+    let device_id = Uuid::new_v4();
+    let token = format!("sf_dev_test_{}", user_id.simple());
+
+    sqlx::query("INSERT INTO users (id, display_name) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind("Rust Radio Test")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO devices (id, user_id, name, platform, token_hash)
+        VALUES ($1, $2, 'test', 'rust', $3)
+        "#,
+    )
+    .bind(device_id)
+    .bind(user_id)
+    .bind(hash_token(&token).unwrap())
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // None of these songs are in the library; only InnerTube knows the flags.
+    let song = |video_id: &str, is_explicit: bool, is_video: bool| innertube::SongItem {
+        video_id: video_id.into(),
+        title: format!("Title {video_id}"),
+        artists: vec![format!("Artist {video_id}")],
+        duration_ms: 0,
+        thumbnail_url: String::new(),
+        is_explicit,
+        is_video,
+    };
+    let page = || innertube::NextPage {
+        related: vec![
+            song("flag-clean", false, false),
+            song("flag-explicit", true, false),
+            song("flag-video", false, true),
+        ],
+        continuation: None,
+    };
+    let yt: Arc<dyn innertube::InnerTubeBackend> = Arc::new(FakeInnerTube {
+        home_page: innertube::HomePage::default(),
+        search_page: innertube::SearchPage::default(),
+        next_pages: Mutex::new(vec![page(), page()]),
+        player: innertube::PlayerResponse::default(),
+    });
+    let app =
+        router_with_config(test_router_config(AuthMode::Database, Some(store)).with_yt(Some(yt)));
+    let start = |body: serde_json::Value| {
+        app.clone().oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/v1/queue/start")
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("idempotency-key", Uuid::now_v7().to_string())
+                .body(body::Body::from(body.to_string()))
+                .unwrap(),
+        )
+    };
+    let queue_media_ids = |value: &serde_json::Value| {
+        value["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["media_id"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let clean = start(json!({
+        "seed_kind": "song",
+        "seed_id": "yt:flag-seed",
+        "hide_explicit": true,
+    }))
+    .await
+    .unwrap();
+    assert_eq!(clean.status(), StatusCode::OK);
+    assert_eq!(
+        queue_media_ids(&response_json(clean).await),
+        ["yt:flag-clean", "yt:flag-video"]
+    );
+
+    let songs_only = start(json!({
+        "seed_kind": "song",
+        "seed_id": "yt:flag-seed",
+        "hide_video": true,
+    }))
+    .await
+    .unwrap();
+    assert_eq!(songs_only.status(), StatusCode::OK);
+    assert_eq!(
+        queue_media_ids(&response_json(songs_only).await),
+        ["yt:flag-clean", "yt:flag-explicit"]
+    );
+
+    cleanup_pg_test_users(&pool).await;
+}
+
 #[tokio::test]
 async fn postgres_library_songs_list_matches_legacy_shape_when_enabled() {
     if std::env::var("SUNFLOWER_RUN_PG_TESTS").ok().as_deref() != Some("1") {
@@ -4764,7 +5542,7 @@ async fn postgres_library_songs_list_matches_legacy_shape_when_enabled() {
 }
 
 #[tokio::test]
-async fn postgres_home_similar_artist_matches_legacy_top_artist_section_when_enabled() {
+async fn postgres_library_queue_seeds_follow_album_artist_and_playlist_order_when_enabled() {
     if std::env::var("SUNFLOWER_RUN_PG_TESTS").ok().as_deref() != Some("1") {
         return;
     }
@@ -4778,20 +5556,24 @@ async fn postgres_home_similar_artist_matches_legacy_top_artist_section_when_ena
     let store = PostgresStore::new(pool.clone());
     let user_id = Uuid::new_v4();
     let device_id = Uuid::new_v4();
+    let playlist_id = Uuid::new_v4();
     let token = format!("sf_dev_test_{}", user_id.simple());
-    let artist_id = "yt:artist-alpha";
-    let song_id = "yt:artist-alpha-song";
+    let artist_id = format!("local:artist-{}", Uuid::new_v4().simple());
+    let album_id = format!("local:album-{}", Uuid::new_v4().simple());
+    let first_track = format!("local:song-{}", Uuid::new_v4().simple());
+    let second_track = format!("local:song-{}", Uuid::new_v4().simple());
+    let single = format!("local:song-{}", Uuid::new_v4().simple());
 
     sqlx::query("INSERT INTO users (id, display_name) VALUES ($1, $2)")
         .bind(user_id)
-        .bind("Rust Similar Artist Test")
+        .bind("Rust Library Test")
         .execute(&pool)
         .await
         .unwrap();
     sqlx::query(
         r#"
         INSERT INTO devices (id, user_id, name, platform, token_hash)
-        VALUES ($1, $2, 'similar', 'rust', $3)
+        VALUES ($1, $2, 'test', 'rust', $3)
         "#,
     )
     .bind(device_id)
@@ -4803,45 +5585,366 @@ async fn postgres_home_similar_artist_matches_legacy_top_artist_section_when_ena
     sqlx::query(
         r#"
         INSERT INTO artists (media_id, source_type, name)
-        VALUES ($1, 'yt', 'Artist Alpha')
+        VALUES ($1, 'local', 'Seed Artist')
         "#,
     )
-    .bind(artist_id)
+    .bind(&artist_id)
     .execute(&pool)
     .await
     .unwrap();
     sqlx::query(
         r#"
-        INSERT INTO songs (media_id, source_type, title, primary_artist_id, available)
-        VALUES ($1, 'yt', 'Artist Alpha Song', $2, true)
+        INSERT INTO albums (media_id, source_type, title, primary_artist_id)
+        VALUES ($1, 'local', 'Seed Album', $2)
         "#,
     )
-    .bind(song_id)
-    .bind(artist_id)
+    .bind(&album_id)
+    .bind(&artist_id)
     .execute(&pool)
     .await
     .unwrap();
-    for idx in 0..4 {
+    for (media_id, title, album, track_number) in [
+        (&second_track, "A Second Track", Some(&album_id), Some(2)),
+        (&first_track, "B First Track", Some(&album_id), Some(1)),
+        (&single, "Seed Single", None, None),
+    ] {
         sqlx::query(
             r#"
-            INSERT INTO play_events
-                (user_id, device_id, song_media_id, kind, occurred_at)
-            VALUES ($1, $2, $3, 'play', now() - (($4::int) * interval '1 minute'))
+            INSERT INTO songs
+                (media_id, source_type, title, duration_ms, album_id, primary_artist_id,
+                 available, raw_metadata)
+            VALUES ($1, 'local', $2, 180000, $3, $4, true,
+                    jsonb_strip_nulls(jsonb_build_object('track_number', $5::int)))
             "#,
         )
-        .bind(user_id)
-        .bind(device_id)
-        .bind(song_id)
-        .bind(idx)
+        .bind(media_id)
+        .bind(title)
+        .bind(album)
+        .bind(&artist_id)
+        .bind(track_number)
         .execute(&pool)
         .await
         .unwrap();
     }
-
-    let yt: Arc<dyn innertube::InnerTubeBackend> = Arc::new(FakeInnerTube {
-        home_page: innertube::HomePage {
-            sections: vec![innertube::HomeSection {
-                title: "Related".into(),
+    sqlx::query(
+        r#"
+        INSERT INTO playlists (id, user_id, title, source_type)
+        VALUES ($1, $2, 'Seed Playlist', 'local')
+        "#,
+    )
+    .bind(playlist_id)
+    .bind(user_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    for (position, media_id) in [(0, &single), (1, &second_track)] {
+        sqlx::query(
+            r#"
+            INSERT INTO playlist_items (playlist_id, position, song_media_id)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(playlist_id)
+        .bind(position)
+        .bind(media_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let app = router_with_store(Some(store));
+    let start = |body: serde_json::Value| {
+        app.clone().oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/v1/queue/start")
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("idempotency-key", Uuid::now_v7().to_string())
+                .body(body::Body::from(body.to_string()))
+                .unwrap(),
+        )
+    };
+    let queue_media_ids = |value: &serde_json::Value| {
+        value["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["media_id"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let album_response = start(json!({"seed_kind": "album", "seed_id": album_id}))
+        .await
+        .unwrap();
+    assert_eq!(album_response.status(), StatusCode::OK);
+    let album_queue = response_json(album_response).await;
+    assert_eq!(
+        queue_media_ids(&album_queue),
+        vec![first_track.clone(), second_track.clone()]
+    );
+
+    let artist_response = start(json!({"seed_kind": "artist", "seed_id": artist_id}))
+        .await
+        .unwrap();
+    assert_eq!(artist_response.status(), StatusCode::OK);
+    let mut artist_songs = queue_media_ids(&response_json(artist_response).await);
+    artist_songs.sort();
+    let mut expected_artist_songs = vec![first_track.clone(), second_track.clone(), single.clone()];
+    expected_artist_songs.sort();
+    assert_eq!(artist_songs, expected_artist_songs);
+
+    let playlist_response = start(json!({"seed_kind": "playlist", "seed_id": playlist_id}))
+        .await
+        .unwrap();
+    assert_eq!(playlist_response.status(), StatusCode::OK);
+    assert_eq!(
+        queue_media_ids(&response_json(playlist_response).await),
+        vec![single.clone(), second_track.clone()]
+    );
+
+    sqlx::query("UPDATE songs SET explicit = true WHERE media_id = $1")
+        .bind(&single)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let clean_playlist = start(json!({
+        "seed_kind": "playlist",
+        "seed_id": playlist_id,
+        "hide_explicit": true,
+    }))
+    .await
+    .unwrap();
+    assert_eq!(clean_playlist.status(), StatusCode::OK);
+    assert_eq!(
+        queue_media_ids(&response_json(clean_playlist).await),
+        vec![second_track.clone()]
+    );
+
+    let missing_playlist = start(json!({"seed_kind": "playlist", "seed_id": Uuid::new_v4()}))
+        .await
+        .unwrap();
+    assert_eq!(missing_playlist.status(), StatusCode::NOT_FOUND);
+    assert_json_error(missing_playlist, "not_found").await;
+
+    let radio_response = start(json!({"seed_kind": "local_radio"})).await.unwrap();
+    assert_eq!(radio_response.status(), StatusCode::OK);
+    let radio_songs = queue_media_ids(&response_json(radio_response).await);
+    assert!(!radio_songs.is_empty());
+    assert!(
+        radio_songs
+            .iter()
+            .all(|media_id| media_id.starts_with("local:"))
+    );
+
+    cleanup_pg_test_users(&pool).await;
+    sqlx::query("DELETE FROM songs WHERE media_id = ANY($1)")
+        .bind(vec![first_track, second_track, single])
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM albums WHERE media_id = $1")
+        .bind(album_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM artists WHERE media_id = $1")
+        .bind(artist_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn postgres_next_skips_unavailable_items_when_asked_when_enabled() {
+    if std::env::var("SUNFLOWER_RUN_PG_TESTS").ok().as_deref() != Some("1") {
+        return;
+    }
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        return;
+    };
+    let _pg_guard = PG_TEST_LOCK.lock().await;
+
+    let pool = sqlx::PgPool::connect(&database_url).await.unwrap();
+    cleanup_pg_test_users(&pool).await;
+    let store = PostgresStore::new(pool.clone());
+    let user_id = Uuid::new_v4();
+    let device_id = Uuid::new_v4();
+    let token = format!("sf_dev_test_{}", user_id.simple());
+
+    sqlx::query("INSERT INTO users (id, display_name) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind("Rust Queue Test")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO devices (id, user_id, name, platform, token_hash)
+        VALUES ($1, $2, 'test', 'rust', $3)
+        "#,
+    )
+    .bind(device_id)
+    .bind(user_id)
+    .bind(hash_token(&token).unwrap())
+    .execute(&pool)
+    .await
+    .unwrap();
+    // Without an InnerTube backend the YouTube items cannot resolve.
+    let item = |media_id: &str| sunflower_core::QueueItem {
+        media_id: sunflower_core::MediaId::new(media_id),
+        title: media_id.to_string(),
+        artists: vec![],
+        duration_ms: 1000,
+        unavailable: false,
+    };
+    let session = store
+        .create_queue(sunflower_storage_postgres::QueueSessionInsert {
+            user_id,
+            device_id,
+            seed_kind: "playlist",
+            seed_id: "skip-test",
+            title: "Skip Test",
+            items: &[item("yt:gone-a"), item("yt:gone-b"), item("local:kept")],
+            continuation: None,
+            shuffle_seed: None,
+            shuffle_order: &[],
+            repeat: sunflower_core::RepeatMode::Off,
+            rules: sunflower_core::QueueRules::default(),
+        })
+        .await
+        .unwrap();
+
+    let app = router_with_store(Some(store));
+    let get = |uri: String| {
+        app.clone().oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+    };
+
+    let stuck = get(format!("/api/v1/next?queue_id={}", session.id))
+        .await
+        .unwrap();
+    assert_eq!(stuck.status(), StatusCode::GONE);
+    assert_json_error(stuck, "current_unavailable").await;
+
+    let skipped = get(format!(
+        "/api/v1/next?queue_id={}&skip_unavailable=1",
+        session.id
+    ))
+    .await
+    .unwrap();
+    assert_eq!(skipped.status(), StatusCode::OK);
+    let skipped_value = response_json(skipped).await;
+    assert_eq!(skipped_value["position"], 2);
+    assert_eq!(skipped_value["current"]["media_id"], "local:kept");
+    assert_eq!(skipped_value["queue_version"], session.version + 1);
+
+    let queue = get(format!("/api/v1/queue/{}", session.id)).await.unwrap();
+    let queue_value = response_json(queue).await;
+    assert_eq!(queue_value["version"], session.version + 1);
+    assert_eq!(queue_value["items"][0]["unavailable"], true);
+    assert_eq!(queue_value["items"][1]["unavailable"], true);
+    assert!(queue_value["items"][2].get("unavailable").is_none());
+
+    // Items already marked are passed over without another version bump.
+    let again = get(format!(
+        "/api/v1/next?queue_id={}&position=1&skip_unavailable=true",
+        session.id
+    ))
+    .await
+    .unwrap();
+    let again_value = response_json(again).await;
+    assert_eq!(again_value["position"], 2);
+    assert_eq!(again_value["queue_version"], session.version + 1);
+
+    cleanup_pg_test_users(&pool).await;
+}
+
+#[tokio::test]
+async fn postgres_home_similar_artist_matches_legacy_top_artist_section_when_enabled() {
+    if std::env::var("SUNFLOWER_RUN_PG_TESTS").ok().as_deref() != Some("1") {
+        return;
+    }
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        return;
+    };
+    let _pg_guard = PG_TEST_LOCK.lock().await;
+
+    let pool = sqlx::PgPool::connect(&database_url).await.unwrap();
+    cleanup_pg_test_users(&pool).await;
+    let store = PostgresStore::new(pool.clone());
+    let user_id = Uuid::new_v4();
+    let device_id = Uuid::new_v4();
+    let token = format!("sf_dev_test_{}", user_id.simple());
+    let artist_id = "yt:artist-alpha";
+    let song_id = "yt:artist-alpha-song";
+
+    sqlx::query("INSERT INTO users (id, display_name) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind("Rust Similar Artist Test")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO devices (id, user_id, name, platform, token_hash)
+        VALUES ($1, $2, 'similar', 'rust', $3)
+        "#,
+    )
+    .bind(device_id)
+    .bind(user_id)
+    .bind(hash_token(&token).unwrap())
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO artists (media_id, source_type, name)
+        VALUES ($1, 'yt', 'Artist Alpha')
+        "#,
+    )
+    .bind(artist_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO songs (media_id, source_type, title, primary_artist_id, available)
+        VALUES ($1, 'yt', 'Artist Alpha Song', $2, true)
+        "#,
+    )
+    .bind(song_id)
+    .bind(artist_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    for idx in 0..4 {
+        sqlx::query(
+            r#"
+            INSERT INTO play_events
+                (user_id, device_id, song_media_id, kind, occurred_at)
+            VALUES ($1, $2, $3, 'play', now() - (($4::int) * interval '1 minute'))
+            "#,
+        )
+        .bind(user_id)
+        .bind(device_id)
+        .bind(song_id)
+        .bind(idx)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let yt: Arc<dyn innertube::InnerTubeBackend> = Arc::new(FakeInnerTube {
+        home_page: innertube::HomePage {
+            sections: vec![innertube::HomeSection {
+                title: "Related".into(),
                 songs: vec![
                     innertube::SongItem {
                         video_id: "similar-a".into(),
@@ -4850,6 +5953,7 @@ async fn postgres_home_similar_artist_matches_legacy_top_artist_section_when_ena
                         duration_ms: 180_000,
                         thumbnail_url: "https://img.example/similar-a.jpg".into(),
                         is_explicit: false,
+                        is_video: false,
                     },
                     innertube::SongItem {
                         video_id: "similar-b".into(),
@@ -4858,6 +5962,7 @@ async fn postgres_home_similar_artist_matches_legacy_top_artist_section_when_ena
                         duration_ms: 181_000,
                         thumbnail_url: String::new(),
                         is_explicit: false,
+                        is_video: false,
                     },
                 ],
             }],
@@ -4978,6 +6083,7 @@ async fn postgres_home_community_playlists_matches_legacy_search_section_when_en
             duration_ms: 180_000 + idx,
             thumbnail_url: format!("https://img.example/community-{idx}.jpg"),
             is_explicit: false,
+            is_video: false,
         })
         .collect::<Vec<_>>();
     songs.push(innertube::SongItem {
@@ -4987,6 +6093,7 @@ async fn postgres_home_community_playlists_matches_legacy_search_section_when_en
         duration_ms: 180_000,
         thumbnail_url: String::new(),
         is_explicit: false,
+        is_video: false,
     });
     songs.push(innertube::SongItem {
         video_id: String::new(),
@@ -4995,6 +6102,7 @@ async fn postgres_home_community_playlists_matches_legacy_search_section_when_en
         duration_ms: 180_000,
         thumbnail_url: String::new(),
         is_explicit: false,
+        is_video: false,
     });
 
     let yt: Arc<dyn innertube::InnerTubeBackend> = Arc::new(FakeInnerTube {
@@ -5168,6 +6276,7 @@ async fn postgres_shuffle_liked_queue_http_round_trip_when_enabled() {
                         duration_ms: 0,
                         thumbnail_url: "https://img.example/yt-home-a.jpg".into(),
                         is_explicit: false,
+                        is_video: false,
                     },
                     innertube::SongItem {
                         video_id: "yt-home-b".into(),
@@ -5176,6 +6285,7 @@ async fn postgres_shuffle_liked_queue_http_round_trip_when_enabled() {
                         duration_ms: 0,
                         thumbnail_url: String::new(),
                         is_explicit: false,
+                        is_video: false,
                     },
                 ],
             }],
@@ -5354,43 +6464,221 @@ async fn postgres_shuffle_liked_queue_http_round_trip_when_enabled() {
         )
     );
 
-    let full_stream = app
+    let stale_edit = app
         .clone()
         .oneshot(
             Request::builder()
-                .method(Method::GET)
-                .uri(stream_url)
+                .method(Method::POST)
+                .uri(format!("/api/v1/queue/{queue_id}/edit"))
                 .header(header::AUTHORIZATION, format!("Bearer {token}"))
-                .body(body::Body::empty())
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("idempotency-key", Uuid::now_v7().to_string())
+                .body(body::Body::from(
+                    r#"{"version":7,"op":"move","from":1,"to":0}"#,
+                ))
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(full_stream.status(), StatusCode::OK);
-    assert_eq!(
-        full_stream.headers().get(header::ACCEPT_RANGES).unwrap(),
-        "bytes"
-    );
-    assert!(matches!(
-        full_stream
-            .headers()
-            .get(header::CONTENT_TYPE)
-            .and_then(|value| value.to_str().ok()),
-        Some("audio/mpeg") | Some("audio/flac")
-    ));
-    let full_body = body::to_bytes(full_stream.into_body(), usize::MAX)
+    assert_eq!(stale_edit.status(), StatusCode::CONFLICT);
+    assert_json_error(stale_edit, "version_conflict").await;
+
+    let edit = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/v1/queue/{queue_id}/edit"))
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("idempotency-key", Uuid::now_v7().to_string())
+                .body(body::Body::from(
+                    r#"{"version":1,"op":"move","from":1,"to":0}"#,
+                ))
+                .unwrap(),
+        )
         .await
         .unwrap();
-    assert_eq!(full_body.len(), 16);
+    assert_eq!(edit.status(), StatusCode::OK);
+    let edit_value = response_json(edit).await;
+    assert_eq!(edit_value["version"], 2);
+    assert_eq!(edit_value["items"][1]["media_id"], current_media_id);
 
-    let invalid_range = app
+    let edited_next = app
         .clone()
         .oneshot(
             Request::builder()
                 .method(Method::GET)
-                .uri(stream_url)
+                .uri(format!("/api/v1/next?queue_id={queue_id}&position=1"))
                 .header(header::AUTHORIZATION, format!("Bearer {token}"))
-                .header(header::RANGE, "bytes=999-1000")
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(edited_next.status(), StatusCode::OK);
+    let edited_next_value = response_json(edited_next).await;
+    assert_eq!(edited_next_value["queue_version"], 2);
+    assert_eq!(edited_next_value["current"]["media_id"], current_media_id);
+
+    let set_mode = |body_raw: &'static str| {
+        app.clone().oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/v1/queue/{queue_id}/mode"))
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("idempotency-key", Uuid::now_v7().to_string())
+                .body(body::Body::from(body_raw))
+                .unwrap(),
+        )
+    };
+    let repeat_all = set_mode(r#"{"repeat":"all"}"#).await.unwrap();
+    assert_eq!(repeat_all.status(), StatusCode::OK);
+    let repeat_all_value = response_json(repeat_all).await;
+    assert_eq!(repeat_all_value["version"], 3);
+    assert_eq!(repeat_all_value["repeat"], "all");
+    assert!(repeat_all_value.get("shuffle").is_none());
+
+    let wrapped_next = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri(format!("/api/v1/next?queue_id={queue_id}&position=1"))
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(wrapped_next.status(), StatusCode::OK);
+    let wrapped_next_value = response_json(wrapped_next).await;
+    assert_eq!(wrapped_next_value["queue_version"], 3);
+    assert_eq!(wrapped_next_value["has_more"], true);
+    assert_eq!(wrapped_next_value["repeat"], "all");
+    assert_eq!(wrapped_next_value["lookahead_positions"][0], 0);
+    assert_eq!(
+        wrapped_next_value["lookahead"][0]["media_id"],
+        edit_value["items"][0]["media_id"]
+    );
+
+    let shuffled = set_mode(r#"{"shuffle":true}"#).await.unwrap();
+    assert_eq!(shuffled.status(), StatusCode::OK);
+    let shuffled_value = response_json(shuffled).await;
+    assert_eq!(shuffled_value["version"], 4);
+    assert_eq!(shuffled_value["shuffle"], true);
+    assert_eq!(shuffled_value["repeat"], "all");
+    assert_eq!(shuffled_value["items"], edit_value["items"]);
+
+    let resumed_media_id = edit_value["items"][1]["media_id"].as_str().unwrap();
+    let event = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/v1/events")
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("idempotency-key", Uuid::now_v7().to_string())
+                .body(body::Body::from(
+                    json!({
+                        "events": [{
+                            "event_id": Uuid::now_v7().to_string(),
+                            "kind": "play",
+                            "media_id": resumed_media_id,
+                            "queue_id": queue_id,
+                            "total_played_ms": 60000,
+                            "duration_ms": 120000
+                        }]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(event.status(), StatusCode::OK);
+    assert_eq!(response_json(event).await["results"][0]["accepted"], true);
+
+    let queues = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri("/api/v1/queues?limit=5")
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(queues.status(), StatusCode::OK);
+    let queues_value = response_json(queues).await;
+    let listed = &queues_value["queues"][0];
+    assert_eq!(listed["queue_id"], queue_id);
+    assert_eq!(listed["seed_kind"], "shuffle_liked");
+    assert_eq!(listed["item_count"], 2);
+    assert_eq!(listed["position"], 1);
+    assert_eq!(listed["current_media_id"], resumed_media_id);
+    assert_eq!(listed["last_device_id"], device_id.to_string());
+    assert!(listed["last_played_at"].as_str().is_some());
+
+    let resume = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri(format!("/api/v1/queue/{queue_id}/resume"))
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resume.status(), StatusCode::OK);
+    let resume_value = response_json(resume).await;
+    assert_eq!(resume_value["position"], 1);
+    assert_eq!(resume_value["current"]["media_id"], resumed_media_id);
+    assert_eq!(resume_value["queue_version"], 4);
+
+    let full_stream = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri(stream_url)
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(full_stream.status(), StatusCode::OK);
+    assert_eq!(
+        full_stream.headers().get(header::ACCEPT_RANGES).unwrap(),
+        "bytes"
+    );
+    assert!(matches!(
+        full_stream
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok()),
+        Some("audio/mpeg") | Some("audio/flac")
+    ));
+    let full_body = body::to_bytes(full_stream.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(full_body.len(), 16);
+
+    let invalid_range = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri(stream_url)
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::RANGE, "bytes=999-1000")
                 .body(body::Body::empty())
                 .unwrap(),
         )
@@ -5973,6 +7261,116 @@ async fn postgres_shuffle_liked_queue_http_round_trip_when_enabled() {
     assert!(get_playlist_after_remove_value.get("items").is_none());
     assert_eq!(get_playlist_after_remove_value["version"], 4);
 
+    let edit_playlist = |body: serde_json::Value| {
+        app.clone().oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/v1/playlists/{playlist_id}/edit"))
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("idempotency-key", Uuid::now_v7().to_string())
+                .body(body::Body::from(body.to_string()))
+                .unwrap(),
+        )
+    };
+    let playlist_entries = |value: &serde_json::Value| {
+        value["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| {
+                (
+                    item["item_id"].as_str().unwrap().to_string(),
+                    item["media_id"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let bulk_add = edit_playlist(json!({
+        "version": 4,
+        "op": "insert",
+        "media_ids": [song_a, song_b, song_a],
+    }))
+    .await
+    .unwrap();
+    assert_eq!(bulk_add.status(), StatusCode::OK);
+    let bulk_add_value = response_json(bulk_add).await;
+    assert_eq!(bulk_add_value["version"], 5);
+    let entries = playlist_entries(&bulk_add_value);
+    assert_eq!(
+        entries
+            .iter()
+            .map(|(_, media_id)| media_id)
+            .collect::<Vec<_>>(),
+        [&song_a, &song_b, &song_a]
+    );
+    assert_ne!(entries[0].0, entries[2].0);
+
+    let no_duplicates = edit_playlist(json!({
+        "op": "insert",
+        "position": 0,
+        "media_ids": [song_b],
+        "allow_duplicates": false,
+    }))
+    .await
+    .unwrap();
+    assert_eq!(no_duplicates.status(), StatusCode::OK);
+    assert_eq!(response_json(no_duplicates).await["version"], 5);
+
+    let stale_move = edit_playlist(json!({
+        "version": 4,
+        "op": "move",
+        "item_id": entries[2].0,
+        "to": 0,
+    }))
+    .await
+    .unwrap();
+    assert_eq!(stale_move.status(), StatusCode::CONFLICT);
+    assert_json_error(stale_move, "version_conflict").await;
+
+    let moved = edit_playlist(json!({
+        "version": 5,
+        "op": "move",
+        "item_id": entries[2].0,
+        "to": 0,
+    }))
+    .await
+    .unwrap();
+    assert_eq!(moved.status(), StatusCode::OK);
+    let moved_value = response_json(moved).await;
+    assert_eq!(moved_value["version"], 6);
+    assert_eq!(
+        playlist_entries(&moved_value),
+        [entries[2].clone(), entries[0].clone(), entries[1].clone()]
+    );
+    assert_eq!(moved_value["items"][2]["position"], 2);
+
+    let bulk_remove = edit_playlist(json!({
+        "op": "remove",
+        "item_ids": [entries[0].0, entries[1].0],
+    }))
+    .await
+    .unwrap();
+    assert_eq!(bulk_remove.status(), StatusCode::OK);
+    let bulk_remove_value = response_json(bulk_remove).await;
+    assert_eq!(bulk_remove_value["version"], 7);
+    assert_eq!(playlist_entries(&bulk_remove_value), [entries[2].clone()]);
+
+    let unknown_song = edit_playlist(json!({"op": "insert", "media_ids": ["local:missing"]}))
+        .await
+        .unwrap();
+    assert_eq!(unknown_song.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_json_error(unknown_song, "unknown_song").await;
+    let unknown_item = edit_playlist(json!({
+        "op": "move",
+        "item_id": entries[0].0,
+        "to": 0,
+    }))
+    .await
+    .unwrap();
+    assert_eq!(unknown_item.status(), StatusCode::NOT_FOUND);
+    assert_json_error(unknown_item, "item_not_found").await;
+
     let delete_playlist_response = app
         .clone()
         .oneshot(
@@ -6206,6 +7604,628 @@ async fn assert_json_error(response: axum::response::Response, expected: &str) {
     assert_eq!(value, json!({ "error": expected }));
 }
 
+#[tokio::test]
+async fn postgres_rescan_drops_lyrics_whose_sidecar_was_removed_when_enabled() {
+    if std::env::var("SUNFLOWER_RUN_PG_TESTS").ok().as_deref() != Some("1") {
+        return;
+    }
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        return;
+    };
+    let _pg_guard = PG_TEST_LOCK.lock().await;
+
+    let pool = sqlx::PgPool::connect(&database_url).await.unwrap();
+    let store = PostgresStore::new(pool.clone());
+    let dir = std::env::temp_dir().join(format!("sunflower-rescan-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("Rescan.flac");
+    fs::write(&path, b"flac").unwrap();
+    fs::write(path.with_extension("lrc"), "[00:01.00]Gone soon\n").unwrap();
+    let roots = vec![dir.to_string_lossy().to_string()];
+    let data_dir = dir.join("data").to_string_lossy().to_string();
+    let registry = Arc::new(crate::jobs::JobRegistry::default());
+    let scan = || {
+        crate::jobs::run_scan_job(
+            registry.clone(),
+            store.clone(),
+            registry.create().id,
+            roots.clone(),
+            data_dir.clone(),
+        )
+    };
+
+    scan().await;
+    let media_id: String = sqlx::query_scalar("SELECT media_id FROM songs WHERE local_path = $1")
+        .bind(path.to_string_lossy().to_string())
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(store.get_lyrics(&media_id).await.unwrap().is_some());
+
+    fs::remove_file(path.with_extension("lrc")).unwrap();
+    scan().await;
+    assert_eq!(store.get_lyrics(&media_id).await.unwrap(), None);
+
+    // Other tests read every local song, so this one leaves none behind.
+    sqlx::query("DELETE FROM songs WHERE media_id = $1")
+        .bind(&media_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let _ = fs::remove_dir_all(dir);
+}
+
+/// Answers `lyrics` for ids starting with `sung-` and counts every lookup.
+#[derive(Default)]
+struct LyricsInnerTube {
+    lookups: std::sync::atomic::AtomicUsize,
+}
+
+impl innertube::InnerTubeBackend for LyricsInnerTube {
+    fn browse<'a>(
+        &'a self,
+        _browse_id: &'a str,
+        _continuation: Option<&'a str>,
+    ) -> futures_util::future::BoxFuture<'a, Result<innertube::HomePage, innertube::InnerTubeError>>
+    {
+        Box::pin(async { Ok(innertube::HomePage::default()) })
+    }
+
+    fn search<'a>(
+        &'a self,
+        _query: &'a str,
+    ) -> futures_util::future::BoxFuture<'a, Result<innertube::SearchPage, innertube::InnerTubeError>>
+    {
+        Box::pin(async { Ok(innertube::SearchPage::default()) })
+    }
+
+    fn next<'a>(
+        &'a self,
+        _video_id: &'a str,
+        _continuation: Option<&'a str>,
+    ) -> futures_util::future::BoxFuture<'a, Result<innertube::NextPage, innertube::InnerTubeError>>
+    {
+        Box::pin(async { Ok(innertube::NextPage::default()) })
+    }
+
+    fn player<'a>(
+        &'a self,
+        _video_id: &'a str,
+    ) -> futures_util::future::BoxFuture<
+        'a,
+        Result<innertube::PlayerResponse, innertube::InnerTubeError>,
+    > {
+        Box::pin(async { Ok(innertube::PlayerResponse::default()) })
+    }
+
+    fn lyrics<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> futures_util::future::BoxFuture<
+        'a,
+        Result<Option<sunflower_core::Lyrics>, innertube::InnerTubeError>,
+    > {
+        self.lookups
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let lyrics = video_id
+            .starts_with("sung-")
+            .then(|| sunflower_core::Lyrics {
+                source: sunflower_core::LYRICS_SOURCE_YOUTUBE.to_string(),
+                lines: vec![sunflower_core::LyricsLine {
+                    start_ms: None,
+                    text: "Fetched line".into(),
+                }],
+            });
+        Box::pin(async move { Ok(lyrics) })
+    }
+}
+
+#[tokio::test]
+async fn postgres_lyrics_serves_stored_fetches_youtube_and_caches_misses_when_enabled() {
+    if std::env::var("SUNFLOWER_RUN_PG_TESTS").ok().as_deref() != Some("1") {
+        return;
+    }
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        return;
+    };
+    let _pg_guard = PG_TEST_LOCK.lock().await;
+
+    let pool = sqlx::PgPool::connect(&database_url).await.unwrap();
+    cleanup_pg_test_users(&pool).await;
+    let store = PostgresStore::new(pool.clone());
+    let user_id = Uuid::new_v4();
+    let device_id = Uuid::new_v4();
+    let token = format!("sf_dev_test_{}", user_id.simple());
+    let suffix = Uuid::new_v4().simple().to_string();
+    let stored = format!("local:lyrics-{suffix}");
+    let sung = format!("yt:sung-{suffix}");
+    let silent = format!("yt:silent-{suffix}");
+
+    sqlx::query("INSERT INTO users (id, display_name) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind("Rust Library Test")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO devices (id, user_id, name, platform, token_hash)
+        VALUES ($1, $2, 'test', 'rust', $3)
+        "#,
+    )
+    .bind(device_id)
+    .bind(user_id)
+    .bind(hash_token(&token).unwrap())
+    .execute(&pool)
+    .await
+    .unwrap();
+    store
+        .upsert_lyrics(
+            &stored,
+            &sunflower_core::Lyrics {
+                source: sunflower_core::LYRICS_SOURCE_LRC.to_string(),
+                lines: vec![sunflower_core::LyricsLine {
+                    start_ms: Some(1_000),
+                    text: "Stored line".into(),
+                }],
+            },
+        )
+        .await
+        .unwrap();
+
+    let yt = Arc::new(LyricsInnerTube::default());
+    let app = router_with_config(
+        test_router_config(AuthMode::Database, Some(store.clone())).with_yt(Some(yt.clone())),
+    );
+    let get = |media_id: &str| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri(format!("/api/v1/lyrics/{media_id}"))
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+    };
+    let lookups = || yt.lookups.load(std::sync::atomic::Ordering::SeqCst);
+
+    let hit = get(&stored).await.unwrap();
+    assert_eq!(hit.status(), StatusCode::OK);
+    assert_eq!(
+        response_json(hit).await,
+        json!({
+            "media_id": stored,
+            "source": "lrc",
+            "synced": true,
+            "lines": [{ "start_ms": 1000, "text": "Stored line" }],
+        })
+    );
+    assert_eq!(lookups(), 0);
+
+    let fetched = get(&sung).await.unwrap();
+    assert_eq!(fetched.status(), StatusCode::OK);
+    assert_eq!(response_json(fetched).await["source"], "youtube");
+    assert_eq!(
+        store.get_lyrics(&sung).await.unwrap().unwrap().lines[0].text,
+        "Fetched line"
+    );
+    assert_eq!(get(&sung).await.unwrap().status(), StatusCode::OK);
+    assert_eq!(lookups(), 1);
+
+    let missing = get(&silent).await.unwrap();
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    assert_json_error(missing, "not_found").await;
+    let repeat = get(&silent).await.unwrap();
+    assert_eq!(repeat.status(), StatusCode::NOT_FOUND);
+    assert_eq!(lookups(), 2);
+
+    // A miss older than the TTL reads as absent, so the song is looked up again.
+    sqlx::query("UPDATE lyrics SET updated_at = now() - interval '2 days' WHERE media_id = $1")
+        .bind(&silent)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(store.get_lyrics(&silent).await.unwrap(), None);
+    assert_eq!(get(&silent).await.unwrap().status(), StatusCode::NOT_FOUND);
+    assert_eq!(lookups(), 3);
+
+    let unknown = get(&format!("local:missing-{suffix}")).await.unwrap();
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+    assert_json_error(unknown, "not_found").await;
+
+    for media_id in [&stored, &sung, &silent] {
+        store.delete_lyrics(media_id).await.unwrap();
+    }
+    cleanup_pg_test_users(&pool).await;
+}
+
+#[tokio::test]
+async fn postgres_youtube_download_is_served_locally_by_resolve_when_enabled() {
+    if std::env::var("SUNFLOWER_RUN_PG_TESTS").ok().as_deref() != Some("1") {
+        return;
+    }
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        return;
+    };
+    let _pg_guard = PG_TEST_LOCK.lock().await;
+
+    let pool = sqlx::PgPool::connect(&database_url).await.unwrap();
+    cleanup_pg_test_users(&pool).await;
+    let store = PostgresStore::new(pool.clone());
+    let user_id = Uuid::new_v4();
+    let device_id = Uuid::new_v4();
+    let token = format!("sf_dev_test_{}", user_id.simple());
+    let video_id = format!("dl{}", Uuid::new_v4().simple());
+    let media_id = format!("yt:{video_id}");
+    let data_dir = std::env::temp_dir().join(format!("sunflower-dl-{}", Uuid::new_v4()));
+
+    sqlx::query("INSERT INTO users (id, display_name) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind("Rust Library Test")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO devices (id, user_id, name, platform, token_hash)
+        VALUES ($1, $2, 'test', 'rust', $3)
+        "#,
+    )
+    .bind(device_id)
+    .bind(user_id)
+    .bind(hash_token(&token).unwrap())
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // googlevideo stand-in serving a one-cluster WebM in one response.
+    let googlevideo = axum::Router::new().route(
+        "/videoplayback",
+        axum::routing::get(|| async {
+            &b"\x1a\x45\xdf\xa3\x80\x18\x53\x80\x67\x01\xff\xff\xff\xff\xff\xff\xff\x1f\x43\xb6\x75\x83opu"[..]
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        axum::serve(listener, googlevideo).await.unwrap();
+    });
+    let yt: Arc<dyn innertube::InnerTubeBackend> = Arc::new(FakeInnerTube {
+        home_page: innertube::HomePage::default(),
+        search_page: innertube::SearchPage::default(),
+        next_pages: Mutex::new(Vec::new()),
+        player: innertube::PlayerResponse {
+            video_id: video_id.clone(),
+            stream: innertube::StreamUrl {
+                url: "http://r1.googlevideo.com/videoplayback?itag=251".into(),
+                itag: 251,
+                mime_type: "audio/webm; codecs=\"opus\"".into(),
+                bitrate: 160_000,
+                loudness: 0.0,
+            },
+            details: innertube::VideoDetails {
+                title: "Downloaded Song".into(),
+                author: "Downloaded Artist".into(),
+                duration_ms: 215_000,
+                ..innertube::VideoDetails::default()
+            },
+            ..innertube::PlayerResponse::default()
+        },
+    });
+    let app = router_with_config(
+        RouterBuildConfig::new(
+            AuthMode::Database,
+            Some(store.clone()),
+            data_dir.to_string_lossy(),
+            DEFAULT_SETUP_TOKEN,
+            "",
+            None,
+        )
+        .with_yt(Some(yt))
+        .with_download_client(
+            reqwest::Client::builder()
+                .resolve("r1.googlevideo.com", addr)
+                .build()
+                .unwrap(),
+        ),
+    );
+    let post = |uri: &str, body: String| {
+        app.clone().oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("idempotency-key", Uuid::now_v7().to_string())
+                .body(body::Body::from(body))
+                .unwrap(),
+        )
+    };
+    let media_body = json!({ "media_id": media_id }).to_string();
+
+    let streamed = post("/api/v1/streams/resolve", media_body.clone())
+        .await
+        .unwrap();
+    assert_eq!(streamed.status(), StatusCode::OK);
+    assert_ne!(response_json(streamed).await["source"], "local");
+
+    let started = post("/api/v1/library/downloads", media_body.clone())
+        .await
+        .unwrap();
+    assert_eq!(started.status(), StatusCode::OK);
+    let job_id = response_json(started).await["job_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let mut job = json!(null);
+    for _ in 0..100 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/v1/jobs/{job_id}"))
+                    .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                    .body(body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        job = response_json(response).await;
+        if job["status"] != "pending" && job["status"] != "running" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(job["status"], "completed", "{job}");
+
+    let resolved = post("/api/v1/streams/resolve", media_body).await.unwrap();
+    assert_eq!(resolved.status(), StatusCode::OK);
+    let resolved = response_json(resolved).await;
+    assert_eq!(resolved["source"], "local");
+    assert_eq!(
+        resolved["stream_url"],
+        format!("/api/v1/library/songs/{}/stream", path_segment(&media_id))
+    );
+    let local_path = store.song_stream_path(&media_id).await.unwrap().unwrap();
+    assert!(local_path.ends_with(&format!("{video_id}.webm")));
+    assert!(
+        fs::read(&local_path)
+            .unwrap()
+            .windows(b"Downloaded Song".len())
+            .any(|window| window == b"Downloaded Song")
+    );
+
+    server.abort();
+    sqlx::query("DELETE FROM songs WHERE media_id = $1")
+        .bind(&media_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let _ = fs::remove_dir_all(data_dir);
+    cleanup_pg_test_users(&pool).await;
+}
+
+#[tokio::test]
+async fn postgres_playlist_import_matches_paths_tags_and_youtube_and_exports_when_enabled() {
+    if std::env::var("SUNFLOWER_RUN_PG_TESTS").ok().as_deref() != Some("1") {
+        return;
+    }
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        return;
+    };
+    let _pg_guard = PG_TEST_LOCK.lock().await;
+
+    let pool = sqlx::PgPool::connect(&database_url).await.unwrap();
+    cleanup_pg_test_users(&pool).await;
+    let store = PostgresStore::new(pool.clone());
+    let user_id = Uuid::new_v4();
+    let device_id = Uuid::new_v4();
+    let token = format!("sf_dev_test_{}", user_id.simple());
+    let suffix = Uuid::new_v4().simple().to_string();
+    let dir = format!("/music/import-{suffix}");
+    let artist_id = format!("local:artist-{suffix}");
+    let song_a = format!("local:song-a-{suffix}");
+    let song_b = format!("local:song-b-{suffix}");
+    let video_id = format!("import-{suffix}");
+
+    sqlx::query("INSERT INTO users (id, display_name) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind("Rust Library Test")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO devices (id, user_id, name, platform, token_hash)
+        VALUES ($1, $2, 'test', 'rust', $3)
+        "#,
+    )
+    .bind(device_id)
+    .bind(user_id)
+    .bind(hash_token(&token).unwrap())
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO artists (media_id, source_type, name) VALUES ($1, 'local', 'Import Artist')",
+    )
+    .bind(&artist_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    for (media_id, title, path) in [
+        (&song_a, format!("Alpha {suffix}"), format!("{dir}/A.flac")),
+        (
+            &song_b,
+            format!("Beta {suffix}"),
+            format!("{dir}/sub/B.mp3"),
+        ),
+    ] {
+        sqlx::query(
+            r#"
+            INSERT INTO songs
+                (media_id, source_type, title, duration_ms, primary_artist_id, available, local_path)
+            VALUES ($1, 'local', $2, 180000, $3, true, $4)
+            "#,
+        )
+        .bind(media_id)
+        .bind(title)
+        .bind(&artist_id)
+        .bind(path)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let yt: Arc<dyn innertube::InnerTubeBackend> = Arc::new(FakeInnerTube {
+        home_page: innertube::HomePage::default(),
+        search_page: innertube::SearchPage {
+            songs: vec![innertube::SongItem {
+                video_id: video_id.clone(),
+                title: "Remote Hit".into(),
+                artists: vec!["Remote Artist".into()],
+                duration_ms: 200_000,
+                thumbnail_url: String::new(),
+                is_explicit: false,
+                is_video: false,
+            }],
+            ..Default::default()
+        },
+        next_pages: Mutex::new(vec![]),
+        player: innertube::PlayerResponse::default(),
+    });
+    let app =
+        router_with_config(test_router_config(AuthMode::Database, Some(store)).with_yt(Some(yt)));
+    let m3u = format!(
+        "#EXTM3U\n#PLAYLIST:Road Trip\n\
+         #EXTINF:180,Whoever - Not The Title\n../import-{suffix}/A.flac\n\
+         #EXTINF:200,import artist - BETA {suffix}\n/elsewhere/B.mp3\n\
+         #EXTINF:-1,Nobody - Remote {suffix}\nremote-{suffix}.mp3\n"
+    );
+    let import = |body: serde_json::Value| {
+        app.clone().oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/v1/playlists/import")
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("idempotency-key", Uuid::now_v7().to_string())
+                .body(body::Body::from(body.to_string()))
+                .unwrap(),
+        )
+    };
+
+    let local_only = import(json!({ "content": m3u })).await.unwrap();
+    assert_eq!(local_only.status(), StatusCode::OK);
+    let local_only = response_json(local_only).await;
+    assert_eq!(local_only["playlist"]["title"], "Road Trip");
+    assert_eq!(local_only["matched_by_path"], 1);
+    assert_eq!(local_only["matched_by_tags"], 1);
+    assert_eq!(local_only["matched_by_youtube"], 0);
+    assert_eq!(
+        local_only["unmatched"],
+        json!([{
+            "line": 8,
+            "location": format!("remote-{suffix}.mp3"),
+            "title": format!("Remote {suffix}"),
+            "artist": "Nobody",
+        }])
+    );
+    let items = local_only["playlist"]["items"].as_array().unwrap();
+    assert_eq!(items[0]["media_id"], song_a);
+    assert_eq!(items[1]["media_id"], song_b);
+
+    let with_youtube =
+        import(json!({ "title": "Mixed", "format": "m3u8", "content": m3u, "youtube": true }))
+            .await
+            .unwrap();
+    assert_eq!(with_youtube.status(), StatusCode::OK);
+    let with_youtube = response_json(with_youtube).await;
+    assert_eq!(with_youtube["playlist"]["title"], "Mixed");
+    assert_eq!(with_youtube["matched_by_youtube"], 1);
+    assert_eq!(with_youtube["unmatched"], json!([]));
+    let remote = &with_youtube["playlist"]["items"][2];
+    assert_eq!(remote["media_id"], format!("yt:{video_id}"));
+    assert_eq!(remote["title"], "Remote Hit");
+    assert_eq!(remote["artist_name"], "Remote Artist");
+
+    let invalid = import(json!({ "format": "xspf", "content": "not xml" }))
+        .await
+        .unwrap();
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    assert_json_error(invalid, "invalid_playlist_file").await;
+
+    let export = |playlist_id: &serde_json::Value, query: &str| {
+        app.clone().oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri(format!(
+                    "/api/v1/playlists/{}/export?{query}",
+                    playlist_id.as_str().unwrap()
+                ))
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(body::Body::empty())
+                .unwrap(),
+        )
+    };
+    let relative = export(&local_only["playlist"]["id"], "paths=relative")
+        .await
+        .unwrap();
+    assert_eq!(relative.status(), StatusCode::OK);
+    assert_eq!(
+        relative.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+        "attachment; filename=\"Road Trip.m3u8\""
+    );
+    let relative = body::to_bytes(relative.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(relative.to_vec()).unwrap(),
+        format!(
+            "#EXTM3U\n#PLAYLIST:Road Trip\n\
+             #EXTINF:180,Import Artist - Alpha {suffix}\nA.flac\n\
+             #EXTINF:180,Import Artist - Beta {suffix}\nsub/B.mp3\n"
+        )
+    );
+
+    let xspf = export(&with_youtube["playlist"]["id"], "format=xspf")
+        .await
+        .unwrap();
+    assert_eq!(xspf.status(), StatusCode::OK);
+    assert_eq!(
+        xspf.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/xspf+xml; charset=utf-8"
+    );
+    let xspf = body::to_bytes(xspf.into_body(), usize::MAX).await.unwrap();
+    let xspf = String::from_utf8(xspf.to_vec()).unwrap();
+    assert!(xspf.contains(&format!("<location>file://{dir}/A.flac</location>")));
+    assert!(xspf.contains(&format!(
+        "<location>https://music.youtube.com/watch?v={video_id}</location>"
+    )));
+
+    let bad_format = export(&local_only["playlist"]["id"], "format=pls")
+        .await
+        .unwrap();
+    assert_eq!(bad_format.status(), StatusCode::BAD_REQUEST);
+    assert_json_error(bad_format, "invalid_request").await;
+
+    // Other tests read every local song, so this one leaves none behind.
+    sqlx::query("DELETE FROM playlists WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM songs WHERE media_id = ANY($1)")
+        .bind(vec![song_a, song_b, format!("yt:{video_id}")])
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM artists WHERE media_id = $1")
+        .bind(&artist_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+}
+
 fn set_cookie_headers(response: &axum::response::Response) -> Vec<String> {
     response
         .headers()
@@ -6233,6 +8253,7 @@ async fn cleanup_pg_test_users(pool: &sqlx::PgPool) {
         "Rust Similar Artist Test",
         "Rust Community Playlists Test",
         "Rust Dev Registration Test",
+        "Rust Household Member Test",
     ];
     sqlx::query(
         r#"
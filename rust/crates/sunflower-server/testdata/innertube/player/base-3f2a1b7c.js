@@ -0,0 +1,21 @@
+var _yt_player={};(function(g){var window=this;/*
+ Hand-written player fixture, not a captured base.js: it only has the shapes
+ the signature and n parsers read (helper objects, op-list functions, call
+ sites), with n reached through an array variable.
+*/
+var Zx={Kp:function(a){a.reverse()},
+Vb:function(a,b){a.splice(0,b)},
+Ld:function(a,b){var c=a[0];a[0]=a[b%a.length];a[b%a.length]=c}};
+var Tma=function(a){a=a.split("");Zx.Ld(a,47);Zx.Kp(a,72);Zx.Vb(a,2);Zx.Ld(a,23);Zx.Kp(a,11);Zx.Vb(a,1);return a.join("")};
+var Qx={xR:function(a,b){var c=a[0];a[0]=a[b%a.length];a[b%a.length]=c},
+bK:function(a){a.reverse()},
+Uo:function(a,b){a.splice(0,b)}};
+var $ma=function(a){a=a.split("");Qx.bK(a,5);Qx["xR"](a,3);Qx.Uo(a,2);return a.join("")};
+var Xma=[$ma];
+g.zp=function(a,b,c){b=void 0===b?"":b;c=void 0===c?"":c;var d=new g.Nm(a);
+c&&(c=Tma(decodeURIComponent(c)),d.set(b,encodeURIComponent(c)));
+return d.toString()};
+g.Ap=function(a){var b;(b=a.get("n"))&&(b=Xma[0](b),a.set("n",b));return a};
+var yz={signatureTimestamp:20158,playerType:"html5"};
+g.Bp=function(){return yz};
+})(_yt_player);
@@ -0,0 +1,16 @@
+var _yt_player={};(function(g){var window=this;/*
+ Hand-written player fixture, not a captured base.js: the signature function
+ as a declaration, the timestamp under `sts`, and an n function that is not a
+ plain op list.
+*/
+var cy={Aa:function(a,b){a.splice(0,b)},
+Bb:function(a){a.reverse()},
+Cc:function(a,b){var c=a[0];a[0]=a[b%a.length];a[b%a.length]=c}};
+function Oqa(a){a=a.split("");cy.Cc(a,9);cy.Bb(a,30);cy.Aa(a,3);return a.join("")}
+var Rqa=function(a){var b=a.split(""),c=[function(d,e){e=(e%d.length+d.length)%d.length;d.splice(-e).reverse().forEach(function(f){d.unshift(f)})},-1418459887,b];c[0](c[2],c[1]);return b.join("")};
+g.zp=function(a,b,c){b=void 0===b?"":b;c=void 0===c?"":c;var d=new g.Nm(a);
+c&&(c=Oqa(decodeURIComponent(c)),d.set(b,encodeURIComponent(c)));
+return d.toString()};
+g.Ap=function(a){var b;(b=a.get("n"))&&(b=Rqa(b),a.set("n",b));return a};
+var yz={sts:20101,playerType:"html5"};
+})(_yt_player);
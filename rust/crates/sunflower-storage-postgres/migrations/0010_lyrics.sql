@@ -0,0 +1,17 @@
+-- +goose Up
+-- +goose StatementBegin
+
+CREATE TABLE lyrics (
+    media_id   text        NOT NULL PRIMARY KEY,
+    source     text        NOT NULL,
+    synced     boolean     NOT NULL DEFAULT false,
+    lines      jsonb       NOT NULL DEFAULT '[]',
+    updated_at timestamptz NOT NULL DEFAULT now()
+);
+
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP TABLE IF EXISTS lyrics;
+-- +goose StatementEnd
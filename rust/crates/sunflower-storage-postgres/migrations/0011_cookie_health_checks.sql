@@ -0,0 +1,24 @@
+-- +goose Up
+-- +goose StatementBegin
+
+ALTER TABLE cookie_health ADD COLUMN latency_ms integer;
+
+CREATE TABLE cookie_health_checks (
+    id          bigserial   PRIMARY KEY,
+    provider    text        NOT NULL,
+    status      text        NOT NULL,
+    latency_ms  integer,
+    detail      text,
+    checked_at  timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX cookie_health_checks_provider_checked_at_idx
+    ON cookie_health_checks (provider, checked_at DESC);
+
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP TABLE IF EXISTS cookie_health_checks;
+ALTER TABLE cookie_health DROP COLUMN IF EXISTS latency_ms;
+-- +goose StatementEnd
@@ -0,0 +1,9 @@
+-- +goose Up
+-- +goose StatementBegin
+ALTER TABLE queue_sessions ADD COLUMN continuation text;
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+ALTER TABLE queue_sessions DROP COLUMN continuation;
+-- +goose StatementEnd
@@ -0,0 +1,13 @@
+-- +goose Up
+-- +goose StatementBegin
+ALTER TABLE queue_sessions
+    ADD COLUMN shuffle_seed bigint,
+    ADD COLUMN repeat_mode  text NOT NULL DEFAULT 'off';
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+ALTER TABLE queue_sessions
+    DROP COLUMN shuffle_seed,
+    DROP COLUMN repeat_mode;
+-- +goose StatementEnd
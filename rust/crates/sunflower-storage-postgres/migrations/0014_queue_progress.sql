@@ -0,0 +1,21 @@
+-- +goose Up
+-- +goose StatementBegin
+ALTER TABLE queue_sessions
+    ADD COLUMN last_position    int,
+    ADD COLUMN last_position_ms int,
+    ADD COLUMN last_device_id   uuid REFERENCES devices (id) ON DELETE SET NULL,
+    ADD COLUMN last_played_at   timestamptz;
+
+CREATE INDEX queue_sessions_user_recent_idx
+    ON queue_sessions (user_id, (coalesce(last_played_at, created_at)) DESC);
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP INDEX IF EXISTS queue_sessions_user_recent_idx;
+ALTER TABLE queue_sessions
+    DROP COLUMN last_position,
+    DROP COLUMN last_position_ms,
+    DROP COLUMN last_device_id,
+    DROP COLUMN last_played_at;
+-- +goose StatementEnd
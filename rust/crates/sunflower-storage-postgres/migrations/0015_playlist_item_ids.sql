@@ -0,0 +1,13 @@
+-- +goose Up
+-- +goose StatementBegin
+ALTER TABLE playlist_items
+    ADD COLUMN id uuid NOT NULL DEFAULT gen_random_uuid();
+
+CREATE UNIQUE INDEX playlist_items_id_idx ON playlist_items (id);
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP INDEX IF EXISTS playlist_items_id_idx;
+ALTER TABLE playlist_items DROP COLUMN id;
+-- +goose StatementEnd
@@ -0,0 +1,10 @@
+-- +goose Up
+-- +goose StatementBegin
+ALTER TABLE queue_sessions
+    ADD COLUMN shuffle_order integer[] NOT NULL DEFAULT '{}';
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+ALTER TABLE queue_sessions DROP COLUMN shuffle_order;
+-- +goose StatementEnd
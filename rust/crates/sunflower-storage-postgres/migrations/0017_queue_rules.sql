@@ -0,0 +1,10 @@
+-- +goose Up
+-- +goose StatementBegin
+ALTER TABLE queue_sessions
+    ADD COLUMN queue_rules jsonb NOT NULL DEFAULT '{}';
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+ALTER TABLE queue_sessions DROP COLUMN queue_rules;
+-- +goose StatementEnd
@@ -0,0 +1,39 @@
+-- +goose Up
+-- +goose StatementBegin
+
+-- Health was one row per install; each account's stored cookies now get
+-- their own. Existing results belonged to the owner's cookies.
+ALTER TABLE cookie_health
+    ADD COLUMN user_id uuid REFERENCES users (id) ON DELETE CASCADE;
+UPDATE cookie_health
+SET user_id = (SELECT id FROM users ORDER BY created_at LIMIT 1);
+DELETE FROM cookie_health WHERE user_id IS NULL;
+ALTER TABLE cookie_health DROP CONSTRAINT cookie_health_pkey;
+ALTER TABLE cookie_health ALTER COLUMN user_id SET NOT NULL;
+ALTER TABLE cookie_health ADD PRIMARY KEY (user_id, provider);
+
+ALTER TABLE cookie_health_checks
+    ADD COLUMN user_id uuid REFERENCES users (id) ON DELETE CASCADE;
+UPDATE cookie_health_checks
+SET user_id = (SELECT id FROM users ORDER BY created_at LIMIT 1);
+DELETE FROM cookie_health_checks WHERE user_id IS NULL;
+ALTER TABLE cookie_health_checks ALTER COLUMN user_id SET NOT NULL;
+DROP INDEX cookie_health_checks_provider_checked_at_idx;
+CREATE INDEX cookie_health_checks_user_provider_checked_at_idx
+    ON cookie_health_checks (user_id, provider, checked_at DESC);
+
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP INDEX IF EXISTS cookie_health_checks_user_provider_checked_at_idx;
+ALTER TABLE cookie_health_checks DROP COLUMN user_id;
+CREATE INDEX cookie_health_checks_provider_checked_at_idx
+    ON cookie_health_checks (provider, checked_at DESC);
+
+DELETE FROM cookie_health
+WHERE user_id <> (SELECT id FROM users ORDER BY created_at LIMIT 1);
+ALTER TABLE cookie_health DROP CONSTRAINT cookie_health_pkey;
+ALTER TABLE cookie_health DROP COLUMN user_id;
+ALTER TABLE cookie_health ADD PRIMARY KEY (provider);
+-- +goose StatementEnd
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use async_trait::async_trait;
 use base64::Engine;
@@ -13,14 +13,16 @@ use sqlx::{PgPool, Postgres, Row, Transaction};
 use sunflower_core::{
     AdminAuditEventResponse, AdminCookieStatusResponse, AdminDeviceResponse,
     AdminLibraryCountsResponse, AdminPairingCodeResponse, AlbumListItemResponse,
-    ArtistListItemResponse, DownloadListItemResponse, EventEntryRequest, HomeResponse,
-    ImpressionEntryRequest, LikedSong, LocalStatsSnapshot, MediaId, MediaRepository,
-    OwnerSetupRequest, PlaylistItemResponse, PlaylistResponse, QueueItem, QueueSession,
-    RecommendationCandidate, RecommendationEvent, RecommendationEventRepository,
-    RecommendationSnapshot, RecommendationSnapshotRepository, RecommendationSource,
-    RegisterDeviceRequest, RegisterDeviceResponse, SearchAlbumResponse, SearchArtistResponse,
-    SearchResponse, SearchSongResponse, Song, SongListItemResponse, StorageError, StorageResult,
-    TrackStats, device_capabilities, legacy_rfc3339_nano,
+    ArtistListItemResponse, ContentFlags, DownloadListItemResponse, EventEntryRequest,
+    HomeResponse, ImpressionEntryRequest, LYRICS_SOURCE_NONE, LikedSong, LocalStatsSnapshot,
+    Lyrics, LyricsLine, MediaId, MediaRepository, OwnerSetupRequest, PlaylistEdit,
+    PlaylistEditError, PlaylistEntry, PlaylistItemResponse, PlaylistResponse, QueueItem,
+    QueueRules, QueueSession, QueueSummaryResponse, RecommendationCandidate, RecommendationEvent,
+    RecommendationEventRepository, RecommendationSnapshot, RecommendationSnapshotRepository,
+    RecommendationSource, RegisterDeviceRequest, RegisterDeviceResponse, RepeatMode,
+    SearchAlbumResponse, SearchArtistResponse, SearchResponse, SearchSongResponse, Song,
+    SongListItemResponse, StorageError, StorageResult, TrackStats, apply_playlist_edit,
+    device_capabilities, legacy_rfc3339_nano,
 };
 use thiserror::Error;
 use uuid::Uuid;
@@ -68,6 +70,29 @@ pub struct IdempotencyLogRecord {
     pub response_content_type: Option<String>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueueSessionInsert<'a> {
+    pub user_id: Uuid,
+    pub device_id: Uuid,
+    pub seed_kind: &'a str,
+    pub seed_id: &'a str,
+    pub title: &'a str,
+    pub items: &'a [QueueItem],
+    /// Radio cursor for extending the queue later, if the seed has one.
+    pub continuation: Option<&'a str>,
+    pub shuffle_seed: Option<u64>,
+    pub shuffle_order: &'a [usize],
+    pub repeat: RepeatMode,
+    pub rules: QueueRules,
+}
+
+/// Where the user last was in a queue, as reported by any of their devices.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueProgress {
+    pub position: usize,
+    pub position_ms: i32,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct IdempotencyLogInsert<'a> {
     pub key: Uuid,
@@ -87,6 +112,19 @@ pub enum SongFileLookup {
     Path(String),
 }
 
+/// How [`PostgresStore::edit_playlist`] went.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlaylistEditOutcome {
+    /// The playlist after the edit; an edit that changed nothing leaves the
+    /// version as it was.
+    Edited(PlaylistResponse),
+    NotFound,
+    VersionConflict,
+    Rejected(PlaylistEditError),
+    /// An inserted song is not in the library.
+    UnknownSong(MediaId),
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ScannedLocalSong {
     pub media_id: String,
@@ -96,9 +134,43 @@ pub struct ScannedLocalSong {
     pub album: String,
     pub album_media_id: String,
     pub year: Option<i32>,
+    /// Position on the album, kept in `raw_metadata.track_number`.
+    pub track_number: Option<i32>,
+    pub local_path: String,
+}
+
+/// A YouTube track whose audio the server keeps a copy of under its data dir.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DownloadedYouTubeSong {
+    pub media_id: String,
+    pub title: String,
+    pub artist: String,
+    /// `yt:<channel id>`, empty when the player response carried none.
+    pub artist_media_id: String,
+    pub duration_ms: Option<i32>,
+    pub thumbnail_url: String,
     pub local_path: String,
 }
 
+/// A YouTube track known from InnerTube metadata alone, with no copy on disk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemoteYouTubeSong {
+    pub media_id: String,
+    pub title: String,
+    pub artist: String,
+    pub duration_ms: Option<i32>,
+    pub thumbnail_url: String,
+    pub explicit: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CookieHealthCheck {
+    pub status: String,
+    pub latency_ms: Option<i32>,
+    pub detail: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct CachedHome {
     pub home: HomeResponse,
@@ -155,6 +227,7 @@ const ARGON_MEMORY: u32 = 64 * 1024;
 const ARGON_THREADS: u32 = 4;
 const ARGON_KEY_LEN: usize = 32;
 const ADMIN_SESSION_TTL_DAYS: i64 = 14;
+const MISSING_LYRICS_TTL_HOURS: i64 = 24;
 const DEFAULT_PAIRING_TTL_SECONDS: i64 = 10 * 60;
 const MAX_PAIRING_TTL_SECONDS: i64 = 60 * 60;
 const MIGRATION_ADVISORY_LOCK_KEY: i64 = 0x7375_6e66_6c6f_7765;
@@ -213,6 +286,51 @@ const EMBEDDED_MIGRATIONS: &[(i64, &str, &str)] = &[
         "0009_idempotency_response.sql",
         include_str!("../migrations/0009_idempotency_response.sql"),
     ),
+    (
+        10,
+        "0010_lyrics.sql",
+        include_str!("../migrations/0010_lyrics.sql"),
+    ),
+    (
+        11,
+        "0011_cookie_health_checks.sql",
+        include_str!("../migrations/0011_cookie_health_checks.sql"),
+    ),
+    (
+        12,
+        "0012_queue_continuation.sql",
+        include_str!("../migrations/0012_queue_continuation.sql"),
+    ),
+    (
+        13,
+        "0013_queue_modes.sql",
+        include_str!("../migrations/0013_queue_modes.sql"),
+    ),
+    (
+        14,
+        "0014_queue_progress.sql",
+        include_str!("../migrations/0014_queue_progress.sql"),
+    ),
+    (
+        15,
+        "0015_playlist_item_ids.sql",
+        include_str!("../migrations/0015_playlist_item_ids.sql"),
+    ),
+    (
+        16,
+        "0016_queue_shuffle_order.sql",
+        include_str!("../migrations/0016_queue_shuffle_order.sql"),
+    ),
+    (
+        17,
+        "0017_queue_rules.sql",
+        include_str!("../migrations/0017_queue_rules.sql"),
+    ),
+    (
+        18,
+        "0018_cookie_health_per_user.sql",
+        include_str!("../migrations/0018_cookie_health_per_user.sql"),
+    ),
 ];
 
 impl PostgresStore {
@@ -651,14 +769,19 @@ impl PostgresStore {
         })
     }
 
-    pub async fn admin_cookie_status(&self) -> StorageResult<AdminCookieStatusResponse> {
+    /// Health of `user_id`'s own stored YouTube cookies.
+    pub async fn admin_cookie_status(
+        &self,
+        user_id: Uuid,
+    ) -> StorageResult<AdminCookieStatusResponse> {
         let row = sqlx::query(
             r#"
             SELECT status, checked_at, detail
             FROM cookie_health
-            WHERE provider = 'youtube'
+            WHERE user_id = $1 AND provider = 'youtube'
             "#,
         )
+        .bind(user_id)
         .fetch_optional(&self.pool)
         .await
         .map_err(map_backend)?;
@@ -768,6 +891,24 @@ impl PostgresStore {
         .await
     }
 
+    pub async fn record_stream_proxy_key_rotated(
+        &self,
+        session: &AdminSession,
+        key_id: &str,
+    ) -> Result<(), AuthStoreError> {
+        let session_id_text = session.id.to_string();
+        self.write_audit_event(AuditEventInsert {
+            user_id: Some(session.user_id),
+            actor_type: "admin_session",
+            actor_id: &session_id_text,
+            event: "stream_proxy_key_rotated",
+            target_type: "stream_proxy_key",
+            target_id: key_id,
+            metadata: serde_json::json!({}),
+        })
+        .await
+    }
+
     pub async fn recent_audit_events(
         &self,
         limit: i64,
@@ -919,6 +1060,89 @@ impl PostgresStore {
             .await
     }
 
+    /// `user_id`'s YouTube cookies, falling back to the owner's (the earliest
+    /// account's). With no user, only the owner's are considered.
+    pub async fn load_youtube_cookies_for_user(
+        &self,
+        user_id: Option<Uuid>,
+        key: [u8; 32],
+    ) -> StorageResult<Option<Vec<u8>>> {
+        self.load_encrypted_secret_for_user("youtube", user_id, key)
+            .await
+    }
+
+    /// Only the cookies `user_id` stored, with no fallback to the owner's.
+    pub async fn load_own_youtube_cookies(
+        &self,
+        user_id: Uuid,
+        key: [u8; 32],
+    ) -> StorageResult<Option<Vec<u8>>> {
+        let Some(row) = sqlx::query(
+            r#"
+            SELECT ciphertext, nonce
+            FROM encrypted_cookies
+            WHERE provider = 'youtube' AND user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_backend)?
+        else {
+            return Ok(None);
+        };
+        decrypt_secret_row(&row, key).map(Some)
+    }
+
+    /// Same fallback as [`Self::load_youtube_cookies_for_user`].
+    pub async fn load_youtube_innertube_token_for_user(
+        &self,
+        user_id: Option<Uuid>,
+        key: [u8; 32],
+    ) -> StorageResult<Option<Vec<u8>>> {
+        self.load_encrypted_secret_for_user("youtube_innertube_token", user_id, key)
+            .await
+    }
+
+    /// The account whose stored YouTube cookies `user_id`'s requests send,
+    /// with the same fallback as [`Self::load_youtube_cookies_for_user`].
+    pub async fn youtube_cookies_owner(
+        &self,
+        user_id: Option<Uuid>,
+    ) -> StorageResult<Option<Uuid>> {
+        sqlx::query_scalar(
+            r#"
+            SELECT ec.user_id
+            FROM encrypted_cookies ec
+            WHERE ec.provider = 'youtube'
+              AND (ec.user_id = $1
+                   OR ec.user_id = (SELECT id FROM users ORDER BY created_at LIMIT 1))
+            ORDER BY ec.user_id IS NOT DISTINCT FROM $1 DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_backend)
+    }
+
+    /// Every account with stored YouTube cookies, owner first.
+    pub async fn youtube_cookie_accounts(&self) -> StorageResult<Vec<Uuid>> {
+        sqlx::query_scalar(
+            r#"
+            SELECT ec.user_id
+            FROM encrypted_cookies ec
+            JOIN users u ON u.id = ec.user_id
+            WHERE ec.provider = 'youtube'
+            ORDER BY u.created_at
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_backend)
+    }
+
     pub async fn has_youtube_innertube_token(&self) -> StorageResult<bool> {
         let count: i64 = sqlx::query_scalar(
             r#"
@@ -955,21 +1179,43 @@ impl PostgresStore {
         else {
             return Ok(None);
         };
+        decrypt_secret_row(&row, key).map(Some)
+    }
 
-        let ciphertext: Vec<u8> = row.try_get("ciphertext").map_err(map_backend)?;
-        let nonce: Vec<u8> = row.try_get("nonce").map_err(map_backend)?;
-        let cipher = XSalsa20Poly1305::new((&key).into());
-        let raw = cipher
-            .decrypt(nonce.as_slice().into(), ciphertext.as_slice())
-            .map_err(|err| StorageError::Backend(err.to_string()))?;
-        Ok(Some(raw))
+    async fn load_encrypted_secret_for_user(
+        &self,
+        provider: &str,
+        user_id: Option<Uuid>,
+        key: [u8; 32],
+    ) -> StorageResult<Option<Vec<u8>>> {
+        let Some(row) = sqlx::query(
+            r#"
+            SELECT ec.ciphertext, ec.nonce
+            FROM encrypted_cookies ec
+            WHERE ec.provider = $1
+              AND (ec.user_id = $2
+                   OR ec.user_id = (SELECT id FROM users ORDER BY created_at LIMIT 1))
+            ORDER BY ec.user_id IS NOT DISTINCT FROM $2 DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(provider)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_backend)?
+        else {
+            return Ok(None);
+        };
+        decrypt_secret_row(&row, key).map(Some)
     }
 
     pub async fn probe_youtube_cookies(
         &self,
         session: &AdminSession,
     ) -> Result<AdminCookieStatusResponse, AuthStoreError> {
-        self.mark_youtube_cookie_probe_requested().await?;
+        self.mark_youtube_cookie_probe_requested(session.user_id)
+            .await?;
 
         let session_id_text = session.id.to_string();
         self.write_audit_event(AuditEventInsert {
@@ -983,26 +1229,172 @@ impl PostgresStore {
         })
         .await?;
 
-        self.admin_cookie_status().await.map_err(map_auth_backend)
+        self.admin_cookie_status(session.user_id)
+            .await
+            .map_err(map_auth_backend)
     }
 
-    pub async fn mark_youtube_cookie_probe_requested(&self) -> Result<(), AuthStoreError> {
+    pub async fn mark_youtube_cookie_probe_requested(
+        &self,
+        user_id: Uuid,
+    ) -> Result<(), AuthStoreError> {
         sqlx::query(
             r#"
-            INSERT INTO cookie_health (provider, status, checked_at, detail)
-            VALUES ('youtube', 'unknown', now(), 'manual probe requested')
-            ON CONFLICT (provider) DO UPDATE
+            INSERT INTO cookie_health (user_id, provider, status, checked_at, detail)
+            VALUES ($1, 'youtube', 'unknown', now(), 'manual probe requested')
+            ON CONFLICT (user_id, provider) DO UPDATE
             SET status = 'unknown',
                 checked_at = now(),
                 detail = 'manual probe requested'
             "#,
         )
+        .bind(user_id)
         .execute(&self.pool)
         .await
         .map_err(map_auth_backend)?;
         Ok(())
     }
 
+    /// Stores a scheduled probe of `user_id`'s cookies in `cookie_health` and
+    /// its history, auditing status transitions. Returns the previous status.
+    pub async fn record_youtube_cookie_probe(
+        &self,
+        user_id: Uuid,
+        status: &str,
+        latency_ms: Option<i32>,
+        detail: &str,
+    ) -> StorageResult<Option<String>> {
+        let mut tx = self.pool.begin().await.map_err(map_backend)?;
+        let previous: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT status FROM cookie_health
+            WHERE user_id = $1 AND provider = 'youtube'
+            FOR UPDATE
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(map_backend)?;
+        sqlx::query(
+            r#"
+            INSERT INTO cookie_health (user_id, provider, status, checked_at, detail, latency_ms)
+            VALUES ($1, 'youtube', $2, now(), $3, $4)
+            ON CONFLICT (user_id, provider) DO UPDATE
+            SET status = EXCLUDED.status,
+                checked_at = EXCLUDED.checked_at,
+                detail = EXCLUDED.detail,
+                latency_ms = EXCLUDED.latency_ms
+            "#,
+        )
+        .bind(user_id)
+        .bind(status)
+        .bind(detail)
+        .bind(latency_ms)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_backend)?;
+        sqlx::query(
+            r#"
+            INSERT INTO cookie_health_checks (user_id, provider, status, latency_ms, detail)
+            VALUES ($1, 'youtube', $2, $3, $4)
+            "#,
+        )
+        .bind(user_id)
+        .bind(status)
+        .bind(latency_ms)
+        .bind(detail)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_backend)?;
+        sqlx::query(
+            r#"
+            DELETE FROM cookie_health_checks
+            WHERE provider = 'youtube' AND checked_at < now() - interval '30 days'
+            "#,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(map_backend)?;
+        tx.commit().await.map_err(map_backend)?;
+
+        if previous.as_deref() != Some(status) {
+            self.write_audit_event(AuditEventInsert {
+                user_id: Some(user_id),
+                actor_type: "system",
+                actor_id: "cookie_probe",
+                event: "youtube_cookie_health_changed",
+                target_type: "cookie_store",
+                target_id: "youtube",
+                metadata: serde_json::json!({
+                    "from": previous.as_deref().unwrap_or("unknown"),
+                    "to": status,
+                    "latency_ms": latency_ms,
+                    "detail": detail,
+                }),
+            })
+            .await
+            .map_err(map_backend)?;
+        }
+        Ok(previous)
+    }
+
+    pub async fn youtube_cookie_health_history(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+    ) -> StorageResult<Vec<CookieHealthCheck>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT status, latency_ms, detail, checked_at
+            FROM cookie_health_checks
+            WHERE user_id = $1 AND provider = 'youtube'
+            ORDER BY checked_at DESC, id DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(CookieHealthCheck {
+                    status: row.try_get("status").map_err(map_backend)?,
+                    latency_ms: row.try_get("latency_ms").map_err(map_backend)?,
+                    detail: row.try_get("detail").map_err(map_backend)?,
+                    checked_at: row.try_get("checked_at").map_err(map_backend)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Records that `user_id`'s stored cookies were rejected and InnerTube
+    /// fell back to guest mode.
+    pub async fn mark_youtube_cookies_degraded(
+        &self,
+        user_id: Uuid,
+        detail: &str,
+    ) -> StorageResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO cookie_health (user_id, provider, status, checked_at, detail)
+            VALUES ($1, 'youtube', 'degraded', now(), $2)
+            ON CONFLICT (user_id, provider) DO UPDATE
+            SET status = 'degraded',
+                checked_at = now(),
+                detail = EXCLUDED.detail
+            "#,
+        )
+        .bind(user_id)
+        .bind(detail)
+        .execute(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        Ok(())
+    }
+
     pub async fn clear_youtube_cookies(
         &self,
         session: &AdminSession,
@@ -1017,9 +1409,11 @@ impl PostgresStore {
         .execute(&self.pool)
         .await
         .map_err(map_auth_backend)?;
-        let _ = sqlx::query("DELETE FROM cookie_health WHERE provider = 'youtube'")
-            .execute(&self.pool)
-            .await;
+        let _ =
+            sqlx::query("DELETE FROM cookie_health WHERE user_id = $1 AND provider = 'youtube'")
+                .bind(session.user_id)
+                .execute(&self.pool)
+                .await;
 
         let session_id_text = session.id.to_string();
         self.write_audit_event(AuditEventInsert {
@@ -1338,20 +1732,9 @@ impl PostgresStore {
         Ok(out)
     }
 
-    pub async fn create_queue(
-        &self,
-        user_id: Uuid,
-        device_id: Uuid,
-        seed_kind: &str,
-        seed_id: &str,
-        title: &str,
-        items: &[QueueItem],
-    ) -> StorageResult<QueueSession> {
+    pub async fn create_queue(&self, queue: QueueSessionInsert<'_>) -> StorageResult<QueueSession> {
         let mut tx = self.pool.begin().await.map_err(map_backend)?;
-        let result = create_queue_tx(
-            &mut tx, user_id, device_id, seed_kind, seed_id, title, items,
-        )
-        .await;
+        let result = create_queue_tx(&mut tx, queue).await;
         match result {
             Ok(session) => {
                 tx.commit().await.map_err(map_backend)?;
@@ -1371,7 +1754,8 @@ impl PostgresStore {
     ) -> StorageResult<Option<QueueSession>> {
         let row = sqlx::query(
             r#"
-            SELECT id, seed_kind, seed_id, version, title
+            SELECT id, seed_kind, seed_id, version, title, continuation, shuffle_seed,
+                   shuffle_order, repeat_mode, queue_rules
             FROM queue_sessions
             WHERE id = $1 AND user_id = $2
             "#,
@@ -1387,38 +1771,384 @@ impl PostgresStore {
         };
 
         let id: Uuid = row.try_get("id").map_err(map_backend)?;
-        let seed_kind: Option<String> = row.try_get("seed_kind").map_err(map_backend)?;
-        let seed_id: Option<String> = row.try_get("seed_id").map_err(map_backend)?;
-        let title: Option<String> = row.try_get("title").map_err(map_backend)?;
-        let version: i64 = row.try_get("version").map_err(map_backend)?;
         let items = self.list_queue_items(id).await?;
-
-        Ok(Some(QueueSession {
-            id,
-            seed_kind: seed_kind.unwrap_or_default(),
-            seed_id: seed_id.unwrap_or_default(),
-            title: title.unwrap_or_default(),
-            version,
-            items,
-        }))
+        queue_session_from_row(id, &row, items).map(Some)
     }
 
-    async fn list_queue_items(&self, queue_id: Uuid) -> StorageResult<Vec<QueueItem>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT media_id, source_data
-            FROM queue_items
-            WHERE queue_id = $1
-            ORDER BY position
-            "#,
-        )
-        .bind(queue_id)
-        .fetch_all(&self.pool)
-        .await
-        .map_err(map_backend)?;
-
-        rows.into_iter()
-            .map(|row| {
+    /// Replaces the items of a queue, along with its shuffled play order, and
+    /// bumps its version, provided the stored version is still
+    /// `expected_version`. Returns `None` when the queue does not exist for the
+    /// user or was edited concurrently.
+    pub async fn replace_queue_items(
+        &self,
+        queue_id: Uuid,
+        user_id: Uuid,
+        expected_version: i64,
+        items: &[QueueItem],
+        shuffle_order: &[usize],
+    ) -> StorageResult<Option<QueueSession>> {
+        let items_json = serde_json::to_value(items).map_err(map_backend)?;
+        let mut tx = self.pool.begin().await.map_err(map_backend)?;
+        let result = async {
+            let row = sqlx::query(
+                r#"
+                UPDATE queue_sessions
+                SET items = $4, shuffle_order = $5, version = version + 1
+                WHERE id = $1 AND user_id = $2 AND version = $3
+                RETURNING seed_kind, seed_id, version, title, continuation, shuffle_seed,
+                          shuffle_order, repeat_mode, queue_rules
+                "#,
+            )
+            .bind(queue_id)
+            .bind(user_id)
+            .bind(expected_version)
+            .bind(items_json)
+            .bind(order_to_sql(shuffle_order))
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(map_backend)?;
+            let Some(row) = row else {
+                return Ok(None);
+            };
+            sqlx::query("DELETE FROM queue_items WHERE queue_id = $1")
+                .bind(queue_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(map_backend)?;
+            insert_queue_items_tx(&mut tx, queue_id, 0, items).await?;
+            queue_session_from_row(queue_id, &row, items.to_vec()).map(Some)
+        }
+        .await;
+        match result {
+            Ok(Some(session)) => {
+                tx.commit().await.map_err(map_backend)?;
+                Ok(Some(session))
+            }
+            Ok(None) => {
+                let _ = tx.rollback().await;
+                Ok(None)
+            }
+            Err(err) => {
+                let _ = tx.rollback().await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Appends radio items fetched from the stored continuation and replaces
+    /// the continuation with `continuation`, under the same version check as
+    /// [`Self::replace_queue_items`]. Returns the new version.
+    pub async fn append_queue_items(
+        &self,
+        queue_id: Uuid,
+        user_id: Uuid,
+        expected_version: i64,
+        items: &[QueueItem],
+        continuation: Option<&str>,
+    ) -> StorageResult<Option<i64>> {
+        let items_json = serde_json::to_value(items).map_err(map_backend)?;
+        let mut tx = self.pool.begin().await.map_err(map_backend)?;
+        let result = async {
+            let row = sqlx::query(
+                r#"
+                UPDATE queue_sessions
+                SET items = items || $4, continuation = $5, version = version + 1
+                WHERE id = $1 AND user_id = $2 AND version = $3
+                RETURNING version, jsonb_array_length(items) AS len
+                "#,
+            )
+            .bind(queue_id)
+            .bind(user_id)
+            .bind(expected_version)
+            .bind(items_json)
+            .bind(continuation)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(map_backend)?;
+            let Some(row) = row else {
+                return Ok(None);
+            };
+            let version: i64 = row.try_get("version").map_err(map_backend)?;
+            let len: i32 = row.try_get("len").map_err(map_backend)?;
+            let start = len as usize - items.len();
+            insert_queue_items_tx(&mut tx, queue_id, start, items).await?;
+            Ok(Some(version))
+        }
+        .await;
+        match result {
+            Ok(Some(version)) => {
+                tx.commit().await.map_err(map_backend)?;
+                Ok(Some(version))
+            }
+            Ok(None) => {
+                let _ = tx.rollback().await;
+                Ok(None)
+            }
+            Err(err) => {
+                let _ = tx.rollback().await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Flags the items at `positions` as unavailable, under the same version
+    /// check as [`Self::replace_queue_items`]. Returns the new version.
+    pub async fn mark_queue_items_unavailable(
+        &self,
+        queue_id: Uuid,
+        user_id: Uuid,
+        expected_version: i64,
+        positions: &[usize],
+    ) -> StorageResult<Option<i64>> {
+        let positions: Vec<i32> = positions.iter().map(|&position| position as i32).collect();
+        let mut tx = self.pool.begin().await.map_err(map_backend)?;
+        let result = async {
+            let row = sqlx::query(
+                r#"
+                UPDATE queue_sessions
+                SET items = coalesce((
+                        SELECT jsonb_agg(
+                            CASE WHEN t.ord - 1 = ANY($4)
+                                 THEN t.item || '{"unavailable": true}'
+                                 ELSE t.item END
+                            ORDER BY t.ord)
+                        FROM jsonb_array_elements(items) WITH ORDINALITY AS t(item, ord)
+                    ), '[]'),
+                    version = version + 1
+                WHERE id = $1 AND user_id = $2 AND version = $3
+                RETURNING version
+                "#,
+            )
+            .bind(queue_id)
+            .bind(user_id)
+            .bind(expected_version)
+            .bind(&positions)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(map_backend)?;
+            let Some(row) = row else {
+                return Ok(None);
+            };
+            sqlx::query(
+                r#"
+                UPDATE queue_items
+                SET source_data = source_data || '{"unavailable": true}'
+                WHERE queue_id = $1 AND position = ANY($2)
+                "#,
+            )
+            .bind(queue_id)
+            .bind(&positions)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_backend)?;
+            row.try_get("version").map(Some).map_err(map_backend)
+        }
+        .await;
+        match result {
+            Ok(Some(version)) => {
+                tx.commit().await.map_err(map_backend)?;
+                Ok(Some(version))
+            }
+            Ok(None) => {
+                let _ = tx.rollback().await;
+                Ok(None)
+            }
+            Err(err) => {
+                let _ = tx.rollback().await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Sets the shuffle seed, shuffled play order and repeat mode of a queue
+    /// and bumps its version, so clients holding an old lookahead refetch it.
+    /// Returns `None` when the queue does not exist for the user.
+    pub async fn set_queue_mode(
+        &self,
+        queue_id: Uuid,
+        user_id: Uuid,
+        shuffle_seed: Option<u64>,
+        shuffle_order: &[usize],
+        repeat: RepeatMode,
+    ) -> StorageResult<Option<QueueSession>> {
+        let row = sqlx::query(
+            r#"
+            UPDATE queue_sessions
+            SET shuffle_seed = $3, shuffle_order = $4, repeat_mode = $5, version = version + 1
+            WHERE id = $1 AND user_id = $2
+            RETURNING seed_kind, seed_id, version, title, continuation, shuffle_seed,
+                      shuffle_order, repeat_mode, queue_rules
+            "#,
+        )
+        .bind(queue_id)
+        .bind(user_id)
+        .bind(shuffle_seed.map(|seed| seed as i64))
+        .bind(order_to_sql(shuffle_order))
+        .bind(repeat.as_str())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let items = self.list_queue_items(queue_id).await?;
+        queue_session_from_row(queue_id, &row, items).map(Some)
+    }
+
+    /// The user's queues, most recently played (or created) first.
+    pub async fn list_queues(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> StorageResult<Vec<QueueSummaryResponse>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT qs.id, qs.seed_kind, qs.seed_id, qs.title, qs.version,
+                   (SELECT count(*) FROM queue_items qi WHERE qi.queue_id = qs.id) AS item_count,
+                   coalesce(qs.last_position, 0) AS position,
+                   coalesce(qs.last_position_ms, 0) AS position_ms,
+                   (SELECT qi.media_id FROM queue_items qi
+                    WHERE qi.queue_id = qs.id AND qi.position = coalesce(qs.last_position, 0))
+                       AS current_media_id,
+                   qs.last_device_id, qs.created_at, qs.last_played_at
+            FROM queue_sessions qs
+            WHERE qs.user_id = $1
+            ORDER BY coalesce(qs.last_played_at, qs.created_at) DESC, qs.id
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_backend)?;
+
+        rows.into_iter()
+            .map(|row| {
+                let id: Uuid = row.try_get("id").map_err(map_backend)?;
+                let seed_kind: Option<String> = row.try_get("seed_kind").map_err(map_backend)?;
+                let seed_id: Option<String> = row.try_get("seed_id").map_err(map_backend)?;
+                let title: Option<String> = row.try_get("title").map_err(map_backend)?;
+                let position: i32 = row.try_get("position").map_err(map_backend)?;
+                let last_device_id: Option<Uuid> =
+                    row.try_get("last_device_id").map_err(map_backend)?;
+                let created_at: DateTime<Utc> = row.try_get("created_at").map_err(map_backend)?;
+                let last_played_at: Option<DateTime<Utc>> =
+                    row.try_get("last_played_at").map_err(map_backend)?;
+                Ok(QueueSummaryResponse {
+                    queue_id: id.to_string(),
+                    seed_kind: seed_kind.unwrap_or_default(),
+                    seed_id: seed_id.unwrap_or_default(),
+                    title: title.unwrap_or_default(),
+                    version: row.try_get("version").map_err(map_backend)?,
+                    item_count: row.try_get("item_count").map_err(map_backend)?,
+                    position: i64::from(position),
+                    position_ms: row.try_get("position_ms").map_err(map_backend)?,
+                    current_media_id: row.try_get("current_media_id").map_err(map_backend)?,
+                    last_device_id: last_device_id.map(|id| id.to_string()),
+                    created_at: legacy_rfc3339_nano(created_at),
+                    last_played_at: last_played_at.map(legacy_rfc3339_nano),
+                })
+            })
+            .collect()
+    }
+
+    /// Saves `media_id` as the user's place in a queue. The position is the
+    /// first occurrence of the song at or after the previous one, so repeats
+    /// further down the queue do not jump back. Without `position_ms` (a
+    /// play event rather than a playback tick) the saved offset is kept while
+    /// the song stays the same and starts from 0 for a new one. Reports older
+    /// than the saved progress are ignored; returns whether anything was saved.
+    pub async fn record_queue_progress(
+        &self,
+        queue_id: Uuid,
+        user_id: Uuid,
+        device_id: Uuid,
+        media_id: &str,
+        position_ms: Option<i32>,
+        observed_at: DateTime<Utc>,
+    ) -> StorageResult<bool> {
+        let result = sqlx::query(
+            r#"
+            WITH target AS (
+                SELECT qi.position
+                FROM queue_items qi
+                JOIN queue_sessions qs ON qs.id = qi.queue_id
+                WHERE qi.queue_id = $1 AND qs.user_id = $2 AND qi.media_id = $4
+                ORDER BY qi.position < coalesce(qs.last_position, 0), qi.position
+                LIMIT 1
+            )
+            UPDATE queue_sessions
+            SET last_position = target.position,
+                last_position_ms = coalesce(
+                    $5,
+                    CASE WHEN last_position = target.position THEN last_position_ms ELSE 0 END
+                ),
+                last_device_id = $3,
+                last_played_at = $6
+            FROM target
+            WHERE id = $1 AND user_id = $2
+              AND (last_played_at IS NULL OR last_played_at <= $6)
+            "#,
+        )
+        .bind(queue_id)
+        .bind(user_id)
+        .bind((device_id != Uuid::nil()).then_some(device_id))
+        .bind(media_id)
+        .bind(position_ms.map(|position_ms| position_ms.max(0)))
+        .bind(observed_at)
+        .execute(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_queue_progress(
+        &self,
+        queue_id: Uuid,
+        user_id: Uuid,
+    ) -> StorageResult<Option<QueueProgress>> {
+        let row = sqlx::query(
+            r#"
+            SELECT coalesce(last_position, 0) AS position,
+                   coalesce(last_position_ms, 0) AS position_ms
+            FROM queue_sessions
+            WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(queue_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let position: i32 = row.try_get("position").map_err(map_backend)?;
+        Ok(Some(QueueProgress {
+            position: position.max(0) as usize,
+            position_ms: row.try_get("position_ms").map_err(map_backend)?,
+        }))
+    }
+
+    async fn list_queue_items(&self, queue_id: Uuid) -> StorageResult<Vec<QueueItem>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT media_id, source_data
+            FROM queue_items
+            WHERE queue_id = $1
+            ORDER BY position
+            "#,
+        )
+        .bind(queue_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_backend)?;
+
+        rows.into_iter()
+            .map(|row| {
                 let media_id: String = row.try_get("media_id").map_err(map_backend)?;
                 let value: serde_json::Value = row.try_get("source_data").map_err(map_backend)?;
                 match serde_json::from_value::<QueueItem>(value) {
@@ -1428,6 +2158,7 @@ impl PostgresStore {
                         title: String::new(),
                         artists: vec![],
                         duration_ms: 0,
+                        unavailable: false,
                     }),
                 }
             })
@@ -1566,33 +2297,333 @@ impl PostgresStore {
         .fetch_all(&self.pool)
         .await
         .map_err(map_backend)?;
-
-        rows.into_iter()
-            .map(|row| {
-                Ok(ArtistListItemResponse {
-                    media_id: row.try_get("media_id").map_err(map_backend)?,
-                    source_type: row.try_get("source_type").map_err(map_backend)?,
-                    name: row.try_get("name").map_err(map_backend)?,
-                    available: row.try_get("available").map_err(map_backend)?,
-                    raw_metadata: row.try_get("raw_metadata").map_err(map_backend)?,
-                    created_at: row.try_get("created_at").map_err(map_backend)?,
-                })
-            })
-            .collect()
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(ArtistListItemResponse {
+                    media_id: row.try_get("media_id").map_err(map_backend)?,
+                    source_type: row.try_get("source_type").map_err(map_backend)?,
+                    name: row.try_get("name").map_err(map_backend)?,
+                    available: row.try_get("available").map_err(map_backend)?,
+                    raw_metadata: row.try_get("raw_metadata").map_err(map_backend)?,
+                    created_at: row.try_get("created_at").map_err(map_backend)?,
+                })
+            })
+            .collect()
+    }
+
+    /// The flags of the listed songs the library marks explicit or
+    /// video-only; every other id is left out.
+    pub async fn content_flags(
+        &self,
+        media_ids: &[MediaId],
+    ) -> StorageResult<HashMap<MediaId, ContentFlags>> {
+        let ids: Vec<&str> = media_ids.iter().map(|id| id.0.as_str()).collect();
+        let rows = sqlx::query(
+            r#"
+            SELECT media_id, explicit, video_only
+            FROM songs
+            WHERE media_id = ANY($1) AND (explicit OR video_only)
+            "#,
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        rows.into_iter()
+            .map(|row| {
+                Ok((
+                    MediaId::new(row.try_get::<String, _>("media_id").map_err(map_backend)?),
+                    ContentFlags {
+                        explicit: row.try_get("explicit").map_err(map_backend)?,
+                        video_only: row.try_get("video_only").map_err(map_backend)?,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// The song whose file is at each of `paths`, in the same order. A
+    /// relative path matches any file ending in it, the shortest full path
+    /// first.
+    pub async fn local_songs_by_paths(
+        &self,
+        paths: &[&str],
+        relative: bool,
+    ) -> StorageResult<Vec<Option<String>>> {
+        if paths.is_empty() {
+            return Ok(vec![]);
+        }
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT ON (p.ord) p.ord, s.media_id
+            FROM unnest($1::text[]) WITH ORDINALITY AS p(path, ord)
+            JOIN songs s ON s.local_path = p.path
+                OR ($2 AND right(s.local_path, length(p.path) + 1) = '/' || p.path)
+            ORDER BY p.ord, s.local_path = p.path DESC, length(s.local_path), s.media_id
+            "#,
+        )
+        .bind(paths)
+        .bind(relative)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        matches_by_ordinality(paths.len(), rows)
+    }
+
+    /// For each `(title, artist)`, a song with a file on disk tagged with it,
+    /// compared without case; in the same order. An empty artist matches any.
+    pub async fn local_songs_by_tags(
+        &self,
+        tags: &[(&str, &str)],
+    ) -> StorageResult<Vec<Option<String>>> {
+        if tags.is_empty() {
+            return Ok(vec![]);
+        }
+        let (titles, artists): (Vec<&str>, Vec<&str>) = tags.iter().copied().unzip();
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT ON (t.ord) t.ord, s.media_id
+            FROM unnest($1::text[], $2::text[]) WITH ORDINALITY AS t(title, artist, ord)
+            JOIN songs s ON lower(s.title) = lower(t.title)
+            LEFT JOIN artists ar ON ar.media_id = s.primary_artist_id
+            WHERE s.local_path IS NOT NULL AND s.local_path <> ''
+                AND (t.artist = ''
+                    OR lower(COALESCE(ar.name, s.raw_metadata->>'artist', '')) = lower(t.artist))
+            ORDER BY t.ord, s.source_type = 'local' DESC, s.media_id
+            "#,
+        )
+        .bind(&titles)
+        .bind(&artists)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        matches_by_ordinality(tags.len(), rows)
+    }
+
+    /// The subset of `media_ids` the library has a song row for.
+    pub async fn known_song_ids(&self, media_ids: &[&str]) -> StorageResult<HashSet<String>> {
+        if media_ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let known: Vec<String> =
+            sqlx::query_scalar("SELECT media_id FROM songs WHERE media_id = ANY($1)")
+                .bind(media_ids)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(map_backend)?;
+        Ok(known.into_iter().collect())
+    }
+
+    /// Files on disk for the given songs, keyed by media id; songs without
+    /// one are left out.
+    pub async fn song_local_paths(
+        &self,
+        media_ids: &[&str],
+    ) -> StorageResult<HashMap<String, String>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT media_id, local_path
+            FROM songs
+            WHERE media_id = ANY($1) AND local_path IS NOT NULL AND local_path <> ''
+            "#,
+        )
+        .bind(media_ids)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        rows.into_iter()
+            .map(|row| {
+                Ok((
+                    row.try_get("media_id").map_err(map_backend)?,
+                    row.try_get("local_path").map_err(map_backend)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// An album's playable songs in track order; untagged tracks go last.
+    pub async fn list_album_queue_items(
+        &self,
+        album_media_id: &str,
+    ) -> StorageResult<Vec<QueueItem>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT s.media_id, s.title, COALESCE(ar.name, '') AS artist_name, s.duration_ms
+            FROM songs s
+            LEFT JOIN artists ar ON ar.media_id = s.primary_artist_id
+            WHERE s.album_id = $1 AND s.available = true
+            ORDER BY (s.raw_metadata->>'track_number')::int NULLS LAST, s.title
+            "#,
+        )
+        .bind(album_media_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        rows.into_iter().map(queue_item_from_row).collect()
+    }
+
+    /// Every playable song crediting the artist, primary or featured.
+    pub async fn list_artist_queue_items(
+        &self,
+        artist_media_id: &str,
+    ) -> StorageResult<Vec<QueueItem>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT s.media_id, s.title, COALESCE(ar.name, '') AS artist_name, s.duration_ms
+            FROM songs s
+            LEFT JOIN artists ar ON ar.media_id = s.primary_artist_id
+            WHERE s.available = true
+              AND (
+                s.primary_artist_id = $1
+                OR EXISTS (
+                    SELECT 1 FROM song_artists sa
+                    WHERE sa.song_media_id = s.media_id AND sa.artist_media_id = $1
+                )
+              )
+            ORDER BY s.title
+            LIMIT 500
+            "#,
+        )
+        .bind(artist_media_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        rows.into_iter().map(queue_item_from_row).collect()
+    }
+
+    pub async fn upsert_scanned_local_song(&self, song: &ScannedLocalSong) -> StorageResult<()> {
+        let mut tx = self.pool.begin().await.map_err(map_backend)?;
+        let artist_id = if song.artist.is_empty() {
+            None
+        } else {
+            sqlx::query(
+                r#"
+                INSERT INTO artists (media_id, source_type, name, raw_metadata)
+                VALUES ($1, 'local', $2, '{}'::jsonb)
+                ON CONFLICT (media_id) DO UPDATE SET
+                    name = excluded.name,
+                    raw_metadata = excluded.raw_metadata
+                "#,
+            )
+            .bind(&song.artist_media_id)
+            .bind(&song.artist)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_backend)?;
+            Some(song.artist_media_id.as_str())
+        };
+
+        let album_id = if song.album.is_empty() {
+            None
+        } else {
+            sqlx::query(
+                r#"
+                INSERT INTO albums
+                    (media_id, source_type, title, primary_artist_id, year, raw_metadata)
+                VALUES ($1, 'local', $2, $3, $4, '{}'::jsonb)
+                ON CONFLICT (media_id) DO UPDATE SET
+                    title = excluded.title,
+                    primary_artist_id = excluded.primary_artist_id,
+                    year = excluded.year,
+                    raw_metadata = excluded.raw_metadata
+                "#,
+            )
+            .bind(&song.album_media_id)
+            .bind(&song.album)
+            .bind(artist_id)
+            .bind(song.year)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_backend)?;
+            Some(song.album_media_id.as_str())
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO songs
+                (media_id, source_type, title, duration_ms, album_id,
+                 primary_artist_id, raw_metadata, local_path)
+            VALUES ($1, 'local', $2, NULL, $3, $4,
+                    jsonb_strip_nulls(jsonb_build_object('track_number', $6::int)), $5)
+            ON CONFLICT (media_id) DO UPDATE SET
+                title = excluded.title,
+                duration_ms = excluded.duration_ms,
+                album_id = excluded.album_id,
+                primary_artist_id = excluded.primary_artist_id,
+                raw_metadata = excluded.raw_metadata,
+                local_path = excluded.local_path
+            "#,
+        )
+        .bind(&song.media_id)
+        .bind(&song.title)
+        .bind(album_id)
+        .bind(artist_id)
+        .bind(&song.local_path)
+        .bind(song.track_number)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_backend)?;
+
+        if let Some(artist_id) = artist_id {
+            sqlx::query(
+                r#"
+                INSERT INTO song_artists (song_media_id, artist_media_id, position)
+                VALUES ($1, $2, 0)
+                ON CONFLICT (song_media_id, artist_media_id) DO UPDATE SET
+                    position = excluded.position
+                "#,
+            )
+            .bind(&song.media_id)
+            .bind(artist_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_backend)?;
+        }
+
+        tx.commit().await.map_err(map_backend)?;
+        Ok(())
+    }
+
+    /// Records a YouTube track so playlists can refer to it. A song already
+    /// in the library keeps what it has.
+    pub async fn ensure_youtube_song(&self, song: &RemoteYouTubeSong) -> StorageResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO songs
+                (media_id, source_type, title, duration_ms, explicit, raw_metadata)
+            VALUES ($1, 'yt', $2, $3, $4, $5)
+            ON CONFLICT (media_id) DO NOTHING
+            "#,
+        )
+        .bind(&song.media_id)
+        .bind(&song.title)
+        .bind(song.duration_ms)
+        .bind(song.explicit)
+        .bind(serde_json::json!({
+            "artist": song.artist,
+            "thumbnail_url": song.thumbnail_url,
+        }))
+        .execute(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        Ok(())
     }
 
-    pub async fn upsert_scanned_local_song(&self, song: &ScannedLocalSong) -> StorageResult<()> {
+    pub async fn upsert_downloaded_youtube_song(
+        &self,
+        song: &DownloadedYouTubeSong,
+    ) -> StorageResult<()> {
         let mut tx = self.pool.begin().await.map_err(map_backend)?;
-        let artist_id = if song.artist.is_empty() {
+        let artist_id = if song.artist.is_empty() || song.artist_media_id.is_empty() {
             None
         } else {
             sqlx::query(
                 r#"
                 INSERT INTO artists (media_id, source_type, name, raw_metadata)
-                VALUES ($1, 'local', $2, '{}'::jsonb)
+                VALUES ($1, 'yt', $2, '{}'::jsonb)
                 ON CONFLICT (media_id) DO UPDATE SET
-                    name = excluded.name,
-                    raw_metadata = excluded.raw_metadata
+                    name = excluded.name
                 "#,
             )
             .bind(&song.artist_media_id)
@@ -1603,50 +2634,31 @@ impl PostgresStore {
             Some(song.artist_media_id.as_str())
         };
 
-        let album_id = if song.album.is_empty() {
-            None
-        } else {
-            sqlx::query(
-                r#"
-                INSERT INTO albums
-                    (media_id, source_type, title, primary_artist_id, year, raw_metadata)
-                VALUES ($1, 'local', $2, $3, $4, '{}'::jsonb)
-                ON CONFLICT (media_id) DO UPDATE SET
-                    title = excluded.title,
-                    primary_artist_id = excluded.primary_artist_id,
-                    year = excluded.year,
-                    raw_metadata = excluded.raw_metadata
-                "#,
-            )
-            .bind(&song.album_media_id)
-            .bind(&song.album)
-            .bind(artist_id)
-            .bind(song.year)
-            .execute(&mut *tx)
-            .await
-            .map_err(map_backend)?;
-            Some(song.album_media_id.as_str())
-        };
-
+        // Metadata other paths recorded for the song is kept; only the
+        // download's own keys are overwritten.
         sqlx::query(
             r#"
             INSERT INTO songs
-                (media_id, source_type, title, duration_ms, album_id,
-                 primary_artist_id, raw_metadata, local_path)
-            VALUES ($1, 'local', $2, NULL, $3, $4, '{}'::jsonb, $5)
+                (media_id, source_type, title, duration_ms, primary_artist_id,
+                 raw_metadata, local_path)
+            VALUES ($1, 'yt', $2, $3, $4, $5, $6)
             ON CONFLICT (media_id) DO UPDATE SET
                 title = excluded.title,
-                duration_ms = excluded.duration_ms,
-                album_id = excluded.album_id,
-                primary_artist_id = excluded.primary_artist_id,
-                raw_metadata = excluded.raw_metadata,
-                local_path = excluded.local_path
+                duration_ms = COALESCE(excluded.duration_ms, songs.duration_ms),
+                primary_artist_id = COALESCE(excluded.primary_artist_id, songs.primary_artist_id),
+                raw_metadata = songs.raw_metadata || excluded.raw_metadata,
+                local_path = excluded.local_path,
+                available = true
             "#,
         )
         .bind(&song.media_id)
         .bind(&song.title)
-        .bind(album_id)
+        .bind(song.duration_ms)
         .bind(artist_id)
+        .bind(serde_json::json!({
+            "artist": song.artist,
+            "thumbnail_url": song.thumbnail_url,
+        }))
         .bind(&song.local_path)
         .execute(&mut *tx)
         .await
@@ -1672,6 +2684,66 @@ impl PostgresStore {
         Ok(())
     }
 
+    pub async fn upsert_lyrics(&self, media_id: &str, lyrics: &Lyrics) -> StorageResult<()> {
+        let lines = serde_json::to_value(&lyrics.lines).map_err(map_backend)?;
+        sqlx::query(
+            r#"
+            INSERT INTO lyrics (media_id, source, synced, lines, updated_at)
+            VALUES ($1, $2, $3, $4, now())
+            ON CONFLICT (media_id) DO UPDATE SET
+                source = excluded.source,
+                synced = excluded.synced,
+                lines = excluded.lines,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(media_id)
+        .bind(&lyrics.source)
+        .bind(lyrics.is_synced())
+        .bind(lines)
+        .execute(&self.pool)
+        .await
+        .map_err(map_backend)?;
+        Ok(())
+    }
+
+    pub async fn delete_lyrics(&self, media_id: &str) -> StorageResult<()> {
+        sqlx::query("DELETE FROM lyrics WHERE media_id = $1")
+            .bind(media_id)
+            .execute(&self.pool)
+            .await
+            .map_err(map_backend)?;
+        Ok(())
+    }
+
+    /// Returns stored lyrics, including a recent `none` marker; expired
+    /// markers read as absent so the caller looks the song up again.
+    pub async fn get_lyrics(&self, media_id: &str) -> StorageResult<Option<Lyrics>> {
+        let row = sqlx::query(
+            r#"
+            SELECT source, lines
+            FROM lyrics
+            WHERE media_id = $1
+              AND (source <> $2 OR updated_at > $3)
+            "#,
+        )
+        .bind(media_id)
+        .bind(LYRICS_SOURCE_NONE)
+        .bind(Utc::now() - Duration::hours(MISSING_LYRICS_TTL_HOURS))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_backend)?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let lines: serde_json::Value = row.try_get("lines").map_err(map_backend)?;
+        Ok(Some(Lyrics {
+            source: row.try_get("source").map_err(map_backend)?,
+            lines: serde_json::from_value::<Vec<LyricsLine>>(lines).map_err(map_backend)?,
+        }))
+    }
+
     pub async fn upsert_download(
         &self,
         device_id: Uuid,
@@ -2604,6 +3676,168 @@ impl PostgresStore {
         Ok(true)
     }
 
+    /// Applies `edit` to the playlist's items in one transaction, renumbering
+    /// positions from 0 and bumping the version when something changed.
+    /// `expected_version`, when given, must match the current version.
+    pub async fn edit_playlist(
+        &self,
+        user_id: Uuid,
+        device_id: Uuid,
+        playlist_id: Uuid,
+        expected_version: Option<i64>,
+        edit: PlaylistEdit,
+    ) -> StorageResult<PlaylistEditOutcome> {
+        let mut tx = self.pool.begin().await.map_err(map_backend)?;
+        let result = async {
+            let version: Option<i64> = sqlx::query_scalar(
+                r#"
+                SELECT version FROM playlists
+                WHERE id = $1 AND user_id = $2
+                FOR UPDATE
+                "#,
+            )
+            .bind(playlist_id)
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(map_backend)?;
+            let Some(version) = version else {
+                return Ok(Some(PlaylistEditOutcome::NotFound));
+            };
+            if expected_version.is_some_and(|expected| expected != version) {
+                return Ok(Some(PlaylistEditOutcome::VersionConflict));
+            }
+            if let PlaylistEdit::Insert { media_ids, .. } = &edit {
+                let ids: Vec<&str> = media_ids.iter().map(|id| id.0.as_str()).collect();
+                let known: HashSet<String> =
+                    sqlx::query_scalar("SELECT media_id FROM songs WHERE media_id = ANY($1)")
+                        .bind(&ids)
+                        .fetch_all(&mut *tx)
+                        .await
+                        .map_err(map_backend)?
+                        .into_iter()
+                        .collect();
+                if let Some(missing) = media_ids.iter().find(|id| !known.contains(&id.0)) {
+                    return Ok(Some(PlaylistEditOutcome::UnknownSong(missing.clone())));
+                }
+            }
+
+            let rows = sqlx::query(
+                r#"
+                SELECT id, song_media_id
+                FROM playlist_items
+                WHERE playlist_id = $1
+                ORDER BY position
+                "#,
+            )
+            .bind(playlist_id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(map_backend)?;
+            let mut entries = rows
+                .into_iter()
+                .map(|row| {
+                    Ok(PlaylistEntry {
+                        item_id: row.try_get("id").map_err(map_backend)?,
+                        media_id: MediaId::new(
+                            row.try_get::<String, _>("song_media_id")
+                                .map_err(map_backend)?,
+                        ),
+                    })
+                })
+                .collect::<StorageResult<Vec<_>>>()?;
+            let existing: HashSet<Uuid> = entries.iter().map(|entry| entry.item_id).collect();
+            match apply_playlist_edit(&mut entries, edit, Uuid::now_v7) {
+                Ok(true) => {}
+                Ok(false) => return Ok(None),
+                Err(err) => return Ok(Some(PlaylistEditOutcome::Rejected(err))),
+            }
+
+            let ids: Vec<Uuid> = entries.iter().map(|entry| entry.item_id).collect();
+            let positions: Vec<i32> = (0..entries.len() as i32).collect();
+            let media_ids: Vec<&str> = entries
+                .iter()
+                .map(|entry| entry.media_id.0.as_str())
+                .collect();
+            let is_new: Vec<bool> = ids.iter().map(|id| !existing.contains(id)).collect();
+            sqlx::query("DELETE FROM playlist_items WHERE playlist_id = $1 AND NOT (id = ANY($2))")
+                .bind(playlist_id)
+                .bind(&ids)
+                .execute(&mut *tx)
+                .await
+                .map_err(map_backend)?;
+            // Park the remaining rows on negative positions first so the
+            // renumbering never collides with the primary key.
+            sqlx::query(
+                r#"
+                UPDATE playlist_items SET position = -1 - position
+                WHERE playlist_id = $1
+                "#,
+            )
+            .bind(playlist_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_backend)?;
+            sqlx::query(
+                r#"
+                UPDATE playlist_items pi
+                SET position = t.pos
+                FROM UNNEST($2::uuid[], $3::int4[]) AS t(id, pos)
+                WHERE pi.playlist_id = $1 AND pi.id = t.id
+                "#,
+            )
+            .bind(playlist_id)
+            .bind(&ids)
+            .bind(&positions)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_backend)?;
+            sqlx::query(
+                r#"
+                INSERT INTO playlist_items
+                    (id, playlist_id, position, song_media_id, added_by_device_id)
+                SELECT t.id, $1, t.pos, t.mid, $6
+                FROM UNNEST($2::uuid[], $3::int4[], $4::text[], $5::bool[])
+                    AS t(id, pos, mid, is_new)
+                WHERE t.is_new
+                "#,
+            )
+            .bind(playlist_id)
+            .bind(&ids)
+            .bind(&positions)
+            .bind(&media_ids)
+            .bind(&is_new)
+            .bind((device_id != Uuid::nil()).then_some(device_id))
+            .execute(&mut *tx)
+            .await
+            .map_err(map_backend)?;
+            sqlx::query("UPDATE playlists SET version = version + 1 WHERE id = $1")
+                .bind(playlist_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(map_backend)?;
+            Ok(None)
+        }
+        .await;
+        match result {
+            Ok(None) => {
+                tx.commit().await.map_err(map_backend)?;
+                Ok(match self.get_playlist(user_id, playlist_id).await? {
+                    Some(playlist) => PlaylistEditOutcome::Edited(playlist),
+                    None => PlaylistEditOutcome::NotFound,
+                })
+            }
+            Ok(Some(outcome)) => {
+                let _ = tx.rollback().await;
+                Ok(outcome)
+            }
+            Err(err) => {
+                let _ = tx.rollback().await;
+                Err(err)
+            }
+        }
+    }
+
     async fn list_playlist_items(
         &self,
         playlist_id: Uuid,
@@ -2611,10 +3845,11 @@ impl PostgresStore {
         let rows = sqlx::query(
             r#"
             SELECT
+                pi.id,
                 pi.position,
                 pi.song_media_id,
                 COALESCE(s.title, '') AS title,
-                COALESCE(ar.name, '') AS artist_name,
+                COALESCE(ar.name, s.raw_metadata->>'artist', '') AS artist_name,
                 s.album_id,
                 s.duration_ms
             FROM playlist_items pi
@@ -2633,7 +3868,9 @@ impl PostgresStore {
             .map(|row| {
                 let album_id: Option<String> = row.try_get("album_id").map_err(map_backend)?;
                 let duration_ms: Option<i32> = row.try_get("duration_ms").map_err(map_backend)?;
+                let item_id: Uuid = row.try_get("id").map_err(map_backend)?;
                 Ok(PlaylistItemResponse {
+                    item_id: item_id.to_string(),
                     position: row.try_get("position").map_err(map_backend)?,
                     media_id: row.try_get("song_media_id").map_err(map_backend)?,
                     title: row.try_get("title").map_err(map_backend)?,
@@ -2646,6 +3883,21 @@ impl PostgresStore {
     }
 }
 
+fn queue_item_from_row(row: sqlx::postgres::PgRow) -> StorageResult<QueueItem> {
+    let media_id: String = row.try_get("media_id").map_err(map_backend)?;
+    let artist_name: String = row.try_get("artist_name").map_err(map_backend)?;
+    let duration_ms: Option<i32> = row.try_get("duration_ms").map_err(map_backend)?;
+    Ok(QueueItem {
+        media_id: MediaId::new(media_id),
+        title: row.try_get("title").map_err(map_backend)?,
+        artists: (!artist_name.is_empty())
+            .then_some(vec![artist_name])
+            .unwrap_or_default(),
+        duration_ms: duration_ms.unwrap_or_default(),
+        unavailable: false,
+    })
+}
+
 fn playlist_from_row(
     row: sqlx::postgres::PgRow,
     items: Vec<PlaylistItemResponse>,
@@ -2666,19 +3918,30 @@ fn non_empty_str(value: &str) -> Option<&str> {
 
 async fn create_queue_tx(
     tx: &mut Transaction<'_, Postgres>,
-    user_id: Uuid,
-    device_id: Uuid,
-    seed_kind: &str,
-    seed_id: &str,
-    title: &str,
-    items: &[QueueItem],
+    queue: QueueSessionInsert<'_>,
 ) -> StorageResult<QueueSession> {
+    let QueueSessionInsert {
+        user_id,
+        device_id,
+        seed_kind,
+        seed_id,
+        title,
+        items,
+        continuation,
+        shuffle_seed,
+        shuffle_order,
+        repeat,
+        rules,
+    } = queue;
     let items_json = serde_json::to_value(items).map_err(map_backend)?;
     let row = sqlx::query(
         r#"
-        INSERT INTO queue_sessions (user_id, device_id, seed_kind, seed_id, title, items)
-        VALUES ($1, $2, nullif($3,''), nullif($4,''), nullif($5,''), $6)
-        RETURNING id, seed_kind, seed_id, version, title
+        INSERT INTO queue_sessions
+            (user_id, device_id, seed_kind, seed_id, title, items, continuation, shuffle_seed,
+             shuffle_order, repeat_mode, queue_rules)
+        VALUES ($1, $2, nullif($3,''), nullif($4,''), nullif($5,''), $6, $7, $8, $9, $10, $11)
+        RETURNING id, seed_kind, seed_id, version, title, continuation, shuffle_seed,
+                  shuffle_order, repeat_mode, queue_rules
         "#,
     )
     .bind(user_id)
@@ -2687,18 +3950,88 @@ async fn create_queue_tx(
     .bind(seed_id)
     .bind(title)
     .bind(items_json)
+    .bind(continuation)
+    .bind(shuffle_seed.map(|seed| seed as i64))
+    .bind(order_to_sql(shuffle_order))
+    .bind(repeat.as_str())
+    .bind(serde_json::to_value(rules).map_err(map_backend)?)
     .fetch_one(&mut **tx)
     .await
     .map_err(map_backend)?;
 
     let queue_id: Uuid = row.try_get("id").map_err(map_backend)?;
+    insert_queue_items_tx(tx, queue_id, 0, items).await?;
+    queue_session_from_row(queue_id, &row, items.to_vec())
+}
+
+fn queue_session_from_row(
+    id: Uuid,
+    row: &sqlx::postgres::PgRow,
+    items: Vec<QueueItem>,
+) -> StorageResult<QueueSession> {
+    let seed_kind: Option<String> = row.try_get("seed_kind").map_err(map_backend)?;
+    let seed_id: Option<String> = row.try_get("seed_id").map_err(map_backend)?;
+    let title: Option<String> = row.try_get("title").map_err(map_backend)?;
+    let version: i64 = row.try_get("version").map_err(map_backend)?;
+    let continuation: Option<String> = row.try_get("continuation").map_err(map_backend)?;
+    let shuffle_seed: Option<i64> = row.try_get("shuffle_seed").map_err(map_backend)?;
+    let shuffle_order: Vec<i32> = row.try_get("shuffle_order").map_err(map_backend)?;
+    let repeat_mode: String = row.try_get("repeat_mode").map_err(map_backend)?;
+    let rules: serde_json::Value = row.try_get("queue_rules").map_err(map_backend)?;
+    Ok(QueueSession {
+        id,
+        seed_kind: seed_kind.unwrap_or_default(),
+        seed_id: seed_id.unwrap_or_default(),
+        title: title.unwrap_or_default(),
+        version,
+        items,
+        continuation,
+        shuffle_seed: shuffle_seed.map(|seed| seed as u64),
+        shuffle_order: shuffle_order
+            .into_iter()
+            .map(|index| index.max(0) as usize)
+            .collect(),
+        repeat: RepeatMode::parse(&repeat_mode).unwrap_or_default(),
+        rules: serde_json::from_value(rules).unwrap_or_default(),
+    })
+}
+
+/// Spreads `(ord, media_id)` rows from a `WITH ORDINALITY` lookup over `len`
+/// inputs; inputs without a row get `None`.
+fn matches_by_ordinality(
+    len: usize,
+    rows: Vec<sqlx::postgres::PgRow>,
+) -> StorageResult<Vec<Option<String>>> {
+    let mut matches = vec![None; len];
+    for row in rows {
+        let ord: i64 = row.try_get("ord").map_err(map_backend)?;
+        if let Some(slot) = usize::try_from(ord - 1)
+            .ok()
+            .and_then(|index| matches.get_mut(index))
+        {
+            *slot = Some(row.try_get("media_id").map_err(map_backend)?);
+        }
+    }
+    Ok(matches)
+}
+
+fn order_to_sql(order: &[usize]) -> Vec<i32> {
+    order.iter().map(|&index| index as i32).collect()
+}
+
+async fn insert_queue_items_tx(
+    tx: &mut Transaction<'_, Postgres>,
+    queue_id: Uuid,
+    start: usize,
+    items: &[QueueItem],
+) -> StorageResult<()> {
     if !items.is_empty() {
         // Collapse all per-item INSERTs into a single round-trip via UNNEST.
         let mut positions: Vec<i32> = Vec::with_capacity(items.len());
         let mut media_ids: Vec<&str> = Vec::with_capacity(items.len());
         let mut source_data: Vec<String> = Vec::with_capacity(items.len());
         for (position, item) in items.iter().enumerate() {
-            positions.push(position as i32);
+            positions.push((start + position) as i32);
             media_ids.push(&item.media_id.0);
             source_data.push(serde_json::to_string(item).map_err(map_backend)?);
         }
@@ -2720,20 +4053,7 @@ async fn create_queue_tx(
         .await
         .map_err(map_backend)?;
     }
-
-    let seed_kind: Option<String> = row.try_get("seed_kind").map_err(map_backend)?;
-    let seed_id: Option<String> = row.try_get("seed_id").map_err(map_backend)?;
-    let title: Option<String> = row.try_get("title").map_err(map_backend)?;
-    let version: i64 = row.try_get("version").map_err(map_backend)?;
-
-    Ok(QueueSession {
-        id: queue_id,
-        seed_kind: seed_kind.unwrap_or_default(),
-        seed_id: seed_id.unwrap_or_default(),
-        title: title.unwrap_or_default(),
-        version,
-        items: items.to_vec(),
-    })
+    Ok(())
 }
 
 async fn register_device_tx(
@@ -2836,6 +4156,15 @@ async fn register_device_tx(
     })
 }
 
+fn decrypt_secret_row(row: &sqlx::postgres::PgRow, key: [u8; 32]) -> StorageResult<Vec<u8>> {
+    let ciphertext: Vec<u8> = row.try_get("ciphertext").map_err(map_backend)?;
+    let nonce: Vec<u8> = row.try_get("nonce").map_err(map_backend)?;
+    let cipher = XSalsa20Poly1305::new((&key).into());
+    cipher
+        .decrypt(nonce.as_slice().into(), ciphertext.as_slice())
+        .map_err(|err| StorageError::Backend(err.to_string()))
+}
+
 async fn ensure_owner_user(pool: &PgPool, display_name: &str) -> Result<Uuid, AuthStoreError> {
     let existing: Option<Uuid> =
         sqlx::query_scalar("SELECT id FROM users ORDER BY created_at LIMIT 1")
@@ -3357,6 +4686,7 @@ fn hex_lower(bytes: &[u8]) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
     use sunflower_core::RegisterDeviceRequest;
 
     #[test]
@@ -4064,6 +5394,92 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn postgres_queue_progress_from_events_keeps_the_saved_offset_when_enabled() {
+        if std::env::var("SUNFLOWER_RUN_PG_TESTS").ok().as_deref() != Some("1") {
+            return;
+        }
+        let Ok(database_url) = std::env::var("DATABASE_URL") else {
+            return;
+        };
+
+        let store = PostgresStore::connect(&database_url).await.unwrap();
+        store.migrate().await.unwrap();
+
+        let user_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO users (id, display_name) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind("Rust Queue Progress Test")
+            .execute(&store.pool)
+            .await
+            .unwrap();
+        let item = |media_id: &str| QueueItem {
+            media_id: MediaId::new(media_id),
+            title: media_id.to_string(),
+            artists: vec![],
+            duration_ms: 180_000,
+            unavailable: false,
+        };
+        let queue = store
+            .create_queue(QueueSessionInsert {
+                user_id,
+                device_id: Uuid::nil(),
+                seed_kind: "playlist",
+                seed_id: "progress-test",
+                title: "Progress Test",
+                items: &[item("yt:first"), item("yt:second")],
+                continuation: None,
+                shuffle_seed: None,
+                shuffle_order: &[],
+                repeat: RepeatMode::Off,
+                rules: QueueRules::default(),
+            })
+            .await
+            .unwrap();
+        let at = |minute: u32| Utc.with_ymd_and_hms(2026, 7, 1, 0, minute, 0).unwrap();
+        let record = |media_id: &'static str, position_ms: Option<i32>, minute: u32| {
+            store.record_queue_progress(
+                queue.id,
+                user_id,
+                Uuid::nil(),
+                media_id,
+                position_ms,
+                at(minute),
+            )
+        };
+
+        assert!(record("yt:first", Some(90_000), 1).await.unwrap());
+        // A play event for the same song reported after the tick.
+        assert!(record("yt:first", None, 2).await.unwrap());
+        assert_eq!(
+            store.get_queue_progress(queue.id, user_id).await.unwrap(),
+            Some(QueueProgress {
+                position: 0,
+                position_ms: 90_000,
+            })
+        );
+
+        assert!(record("yt:second", None, 3).await.unwrap());
+        assert_eq!(
+            store.get_queue_progress(queue.id, user_id).await.unwrap(),
+            Some(QueueProgress {
+                position: 1,
+                position_ms: 0,
+            })
+        );
+
+        sqlx::query("DELETE FROM queue_sessions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&store.pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(user_id)
+            .execute(&store.pool)
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn postgres_migrate_bootstraps_legacy_and_rust_tables_when_enabled() {
         if std::env::var("SUNFLOWER_RUN_PG_TESTS").ok().as_deref() != Some("1") {
@@ -4092,6 +5508,8 @@ mod tests {
             "rust_ingested_events",
             "rust_recommendation_snapshots",
             "rust_like_tombstones",
+            "lyrics",
+            "cookie_health_checks",
         ] {
             let found: Option<String> = sqlx::query_scalar("SELECT to_regclass($1)::text")
                 .bind(table)